@@ -2,3 +2,4 @@
 extern crate log;
 
 pub mod client;
+pub mod peer_client;
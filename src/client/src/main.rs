@@ -3,9 +3,11 @@ extern crate log;
 
 mod client;
 
-use crate::client::{ClientObj, SyncState};
+use crate::client::ClientObj;
 use exonum_crypto::{Hash, PublicKey};
 use std::io;
+use std::sync::{Arc, Mutex};
+use utils::global_peer_data::GlobalData;
 use utils::logger::logger_init_from_yml;
 
 pub fn remove_trailing_newline(input: &mut String) {
@@ -113,7 +115,8 @@ pub fn get_bool_input(input: &mut String) -> Option<bool> {
 fn main() {
     logger_init_from_yml("client_log.yml");
     info!("Peer Client Bootstrapping");
-    let mut client: ClientObj = ClientObj::new();
+    let peers = Arc::new(Mutex::new(GlobalData::new()));
+    let mut client: ClientObj = ClientObj::new(peers.clone());
     let mut end_flag: bool = false;
     let mut invalid_opt_count: u8 = 0;
     while !end_flag {
@@ -126,15 +129,14 @@ fn main() {
         info!("5:) fetch block");
         info!("6:) fetch latest block");
         info!("7:) fetch blockchain length");
-        info!("8:) fetch sync states");
-        info!("9:) exit");
+        info!("8:) exit");
         let mut input = String::new();
         info!("Please select Option:");
         let is_string: bool = get_string_input(&mut input);
         if is_string {
             if input == String::from("1") {
                 invalid_opt_count = 0;
-                client = ClientObj::new();
+                client = ClientObj::new(peers.clone());
             } else if input == String::from("2") {
                 invalid_opt_count = 0;
                 info!("Enter transaction Hash");
@@ -187,10 +189,6 @@ fn main() {
                 info!("{:#?}", output);
                 invalid_opt_count = 0;
             } else if input == String::from("8") {
-                let output: SyncState = client.fetch_sync_state(0);
-                info!("{:?}", output);
-                invalid_opt_count = 0;
-            } else if input == String::from("9") {
                 end_flag = true;
             } else {
                 info!("invalid option");
@@ -0,0 +1,211 @@
+use exonum_crypto::Hash;
+use exonum_merkledb::ObjectHash;
+use libp2p::{identity::PublicKey, PeerId};
+use message_handler::peer_request::{PeerRequest, PeerRequests, PeerResponse};
+use schema::block::SignedBlock;
+use schema::signed_transaction::SignedTransaction;
+use schema::transaction_pool::{Pool, TxnPool, TxnPoolKeyType};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+fn peer_id_using_pk(pk: &String) -> Option<PeerId> {
+    match utils::keypair::PublicKey::from_string(pk) {
+        Some(public_key) => Some(PeerId::from_public_key(PublicKey::Ed25519(public_key))),
+        None => None,
+    }
+}
+
+#[derive(Debug)]
+pub struct SyncState {
+    pub index: u64,
+    pub block_map: HashMap<u64, SignedBlock>,
+    pub txn_map: HashMap<Hash, SignedTransaction>,
+}
+
+impl SyncState {
+    pub fn new() -> SyncState {
+        SyncState {
+            index: 0,
+            block_map: HashMap::new(),
+            txn_map: HashMap::new(),
+        }
+    }
+
+    pub fn new_from(
+        index: u64,
+        block_map: HashMap<u64, SignedBlock>,
+        txn_map: HashMap<Hash, SignedTransaction>,
+    ) -> SyncState {
+        SyncState {
+            index,
+            block_map,
+            txn_map,
+        }
+    }
+}
+
+/// PeerClient fetches blocks & transactions from other nodes over the p2p
+/// connection of a node, any connected peer answers requests without a
+/// peer_id. Fetched transactions go to the pool of the node.
+pub struct PeerClient {
+    peer_requests: Arc<PeerRequests>,
+    pool: Pool,
+}
+
+impl PeerClient {
+    pub fn new(peer_requests: Arc<PeerRequests>, pool: Pool) -> PeerClient {
+        PeerClient {
+            peer_requests,
+            pool,
+        }
+    }
+
+    pub fn fetch_block(&self, peer_id: Option<PeerId>, block_index: u64) -> Option<SignedBlock> {
+        match self
+            .peer_requests
+            .request(peer_id, PeerRequest::FetchBlock(block_index))
+        {
+            Some(PeerResponse::Block(block)) => block,
+            _ => None,
+        }
+    }
+
+    pub fn fetch_latest_block(&self, peer_id: Option<PeerId>) -> Option<SignedBlock> {
+        match self
+            .peer_requests
+            .request(peer_id, PeerRequest::FetchLatestBlock)
+        {
+            Some(PeerResponse::Block(block)) => block,
+            _ => None,
+        }
+    }
+
+    /// 0 if no peer answered
+    pub fn fetch_blockchain_length(&self, peer_id: Option<PeerId>) -> u64 {
+        match self
+            .peer_requests
+            .request(peer_id, PeerRequest::FetchBlockchainLength)
+        {
+            Some(PeerResponse::BlockchainLength(length)) => length,
+            _ => 0,
+        }
+    }
+
+    /// transaction of the pool or of the chain of the peer
+    pub fn fetch_transaction(
+        &self,
+        peer_id: Option<PeerId>,
+        transaction_hash: &Hash,
+    ) -> Option<SignedTransaction> {
+        match self
+            .peer_requests
+            .request(peer_id, PeerRequest::FetchTransaction(*transaction_hash))
+        {
+            Some(PeerResponse::Transaction(transaction)) => transaction,
+            _ => None,
+        }
+    }
+
+    /// transactions of the pool or of the chain of the peer in a single
+    /// request, None unless the peer sent the ones asked for in order
+    pub fn fetch_transactions(
+        &self,
+        peer_id: Option<PeerId>,
+        transaction_hashes: &Vec<Hash>,
+    ) -> Option<Vec<SignedTransaction>> {
+        let request = PeerRequest::FetchTransactions(transaction_hashes.clone());
+        let txns: Vec<SignedTransaction> = match self.peer_requests.request(peer_id, request) {
+            Some(PeerResponse::Transactions(txns)) => txns,
+            _ => return None,
+        };
+        let complete: bool = txns.len() == transaction_hashes.len()
+            && txns
+                .iter()
+                .zip(transaction_hashes.iter())
+                .all(|(txn, txn_hash)| txn.object_hash() == *txn_hash);
+        if complete {
+            Some(txns)
+        } else {
+            None
+        }
+    }
+
+    /// this function will sync blockchain state with other peers
+    pub fn fetch_sync_state(&self, current_length: u64) -> SyncState {
+        let mut block_pool: HashMap<u64, SignedBlock> = HashMap::new();
+        let mut txn_map: HashMap<Hash, SignedTransaction> = HashMap::new();
+        let mut own_chain_length = current_length;
+        info!("sync-state function called");
+        // blocks & transactions are fetched from the peer which told the length
+        let (peer_id, blockchain_length): (PeerId, u64) = match self
+            .peer_requests
+            .request_peer(None, PeerRequest::FetchBlockchainLength)
+        {
+            Some((peer_id, PeerResponse::BlockchainLength(length))) if length > 0 => {
+                (peer_id, length)
+            }
+            _ => return SyncState::new(),
+        };
+        while own_chain_length < blockchain_length {
+            match self.fetch_block(Some(peer_id.clone()), own_chain_length) {
+                Some(signed_block) => {
+                    block_pool.insert(own_chain_length.clone(), signed_block);
+                    own_chain_length = own_chain_length + 1;
+                }
+                // no point in fetching higher block since lower is missing.
+                None => own_chain_length = blockchain_length,
+            }
+        }
+        info!("Block fetched -> {:#?}", block_pool.len());
+        for (_key, value) in block_pool.iter() {
+            if value.block.txn_pool.is_empty() {
+                continue;
+            }
+            match self.fetch_transactions(Some(peer_id.clone()), &value.block.txn_pool) {
+                Some(txns) => {
+                    for txn in txns {
+                        txn_map.insert(txn.object_hash(), txn);
+                    }
+                }
+                None => break,
+            }
+        }
+        info!("Sync_State --All data fetched");
+        return SyncState::new_from(blockchain_length, block_pool, txn_map);
+    }
+
+    /// fetch the transactions of a block missing from the pool from the block
+    /// author (pk), in a single request. Without a running swarm nothing can be fetched and
+    /// the block validation has the final say.
+    pub fn sync_txn_pool(&self, pk: String, transaction_hash_vec: &Vec<Hash>) -> bool {
+        if !self.peer_requests.is_serving() {
+            return true;
+        }
+        let peer_id: PeerId = match peer_id_using_pk(&pk) {
+            Some(peer_id) => peer_id,
+            None => return false,
+        };
+        let missing: Vec<Hash> = transaction_hash_vec
+            .iter()
+            .filter(|txn_hash| self.pool.get(txn_hash).is_none())
+            .cloned()
+            .collect();
+        if missing.is_empty() {
+            return true;
+        }
+        let txns: Vec<SignedTransaction> = match self.fetch_transactions(Some(peer_id), &missing) {
+            Some(txns) => txns,
+            None => return false,
+        };
+        for txn in txns.iter() {
+            match txn.header.get(&String::from("timestamp")) {
+                Some(string) => match string.parse::<TxnPoolKeyType>() {
+                    Ok(timestamp) => self.pool.insert_op(&timestamp, txn),
+                    Err(_) => return false,
+                },
+                None => return false,
+            }
+        }
+        true
+    }
+}
@@ -4,23 +4,24 @@ extern crate schema;
 extern crate utils;
 
 use super::aura_message_sender::AuraMessageSender;
-use super::aura_messages::{AuraMessageTypes, AuthorBlock, BlockAcceptance, RoundOwner};
-use super::config::initialize_config;
+use super::aura_messages::{
+    validate_aura_msg, AuraMessageTypes, AuthorBlock, BlockAcceptance, RoundOwner,
+};
 use db_service::db_fork_ref::SchemaFork;
-use db_service::db_layer::{fork_db, patch_db, snapshot_db};
 use db_service::db_snapshot_ref::SchemaSnap;
+use db_service::node_context::NodeContext;
 use exonum_merkledb::{ObjectHash, Snapshot};
-use futures::{channel::mpsc::*, executor::*, future, prelude::*, task::*};
+use futures::{channel::mpsc::*, future, future::Either, prelude::*, task::*};
 
-use message_handler::messages::MessageTypes;
+use message_handler::queues::GossipSender;
+use message_handler::shutdown::Shutdown;
 use schema::block::SignedBlock;
-use schema::transaction_pool::{TxnPool, POOL};
+use schema::transaction_pool::TxnPool;
 use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
-use std::thread;
 use std::time::Duration;
 use std::time::SystemTime;
-use utils::configreader::Configuration;
+use tokio::task;
 use utils::keypair::KeypairType;
 use utils::serializer::{deserialize, serialize, Deserialize, Serialize};
 
@@ -37,6 +38,8 @@ pub struct Aura {
     force_sealing: bool,
     // last start time of consensus (in seconds)
     start_time: u64,
+    // node the consensus runs on
+    context: NodeContext,
 }
 
 /// WaitingBLocksQueue will store waiting block queue and
@@ -71,7 +74,7 @@ pub struct MetaData {
     // peer keypair
     kp: KeypairType,
     // data sender in P2P system
-    sender: Sender<Option<MessageTypes>>,
+    sender: GossipSender,
     // last start time of consensus (in seconds)
     start_time: u64,
     // round number at the time of last restart
@@ -82,6 +85,8 @@ pub struct MetaData {
     block_queue_size: usize,
     // peer public key
     public_key: String,
+    // node the consensus runs on
+    context: NodeContext,
 }
 
 // AURA consensus custom headers for the signed block
@@ -95,8 +100,8 @@ impl Aura {
     // init_state will create genesis block if predefined storage is empty
     // or if storage is not empty it will start from previous state
     // read genesis block details from config file (future work)
-    fn init_state(&mut self, _db_path: &String, _sender: &mut Sender<Option<MessageTypes>>) {
-        let fork = fork_db();
+    fn init_state(&mut self, _sender: &mut GossipSender) {
+        let fork = self.context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
             if schema.blockchain_length() == 0 {
@@ -109,7 +114,7 @@ impl Aura {
                     Err(_) => Vec::new(),
                 };
                 let genesis_signed_block =
-                    schema.initialize_db(custom_headers, self.start_time as u128);
+                    schema.initialize_db(&self.context, custom_headers, self.start_time as u128);
                 info!(
                     "genesis block created with hash {:?}",
                     genesis_signed_block.get_hash()
@@ -122,7 +127,7 @@ impl Aura {
                 )
             }
         }
-        patch_db(fork);
+        self.context.db.patch(fork);
     }
 
     // fn will compute what is the round number at present time
@@ -149,59 +154,52 @@ impl Aura {
         panic!("Should match current identity with anyone validator identity");
     }
 
-    // fn will process incoming AuthorBlockEnum data
+    // fn will process the block of an incoming AuthorBlockEnum, once rebuilt
     fn handle_author_block_enum(
-        author_block: AuthorBlock,
+        signed_block: SignedBlock,
         waiting_blocks_queue: &mut WaitingBLocksQueue,
         meta_data_obj: &mut MetaData,
     ) {
         let current_leader: String = Aura::primary_leader(&meta_data_obj);
         // only rightful auther should propose block
-        if current_leader != author_block.block.block.peer_id.clone() {
+        if current_leader != signed_block.block.peer_id.clone() {
             info!("malicious author proposing block!");
             return;
         }
 
-        // validate block signature
-        if !author_block.verify() {
-            warn!(
-                "malicious block proposed by author {:?}!",
-                author_block.block.block.peer_id
-            );
-            return;
-        }
+        // the block signature was checked when the block was rebuilt
         // validate block height & previous block hash
         match waiting_blocks_queue.queue.last() {
             Some(last_waiting_block) => {
                 let last_waiting_block: &SignedBlock = last_waiting_block;
-                // if last_waiting_block.block.id + 1 != author_block.block.block.id {
+                // if last_waiting_block.block.id + 1 != signed_block.block.id {
                 //     warn!(
                 //         "malicious block proposed by author {:?}!",
-                //         author_block.block.block.peer_id
+                //         signed_block.block.peer_id
                 //     );
                 //     warn!("malicious block proposed, invalid height compare to waiting block!");
                 //     warn!(
                 //         "block should proposed on height {:?}, but got block on height {:?}",
                 //         last_waiting_block.block.id + 1,
-                //         author_block.block.block.id
+                //         signed_block.block.id
                 //     );
                 //     return;
                 // }
-                // if last_waiting_block.get_hash() != author_block.block.block.prev_hash {
+                // if last_waiting_block.get_hash() != signed_block.block.prev_hash {
                 //     warn!(
                 //         "malicious block proposed by author {:?}!",
-                //         author_block.block.block.peer_id
+                //         signed_block.block.peer_id
                 //     );
                 //     warn!("malicious block proposed, invalid previous block hash compare to waiting block!");
                 //     warn!(
                 //         "previous_hash shuold be {:?}, but previous hash is {:?}",
                 //         last_waiting_block.get_hash(),
-                //         author_block.block.get_hash()
+                //         signed_block.get_hash()
                 //     );
                 //     return;
                 // }
                 let custom_header: CustomHeaders =
-                    match deserialize(&author_block.block.block.custom_headers) {
+                    match deserialize(&signed_block.block.custom_headers) {
                         Ok(value) => value,
                         Err(_) => {
                             warn!("block custom headers couldn't deserialized");
@@ -225,7 +223,7 @@ impl Aura {
                 if custom_header.round_number <= last_custom_header.round_number {
                     warn!(
                         "malicious block proposed by author {:?}!",
-                        author_block.block.block.peer_id
+                        signed_block.block.peer_id
                     );
                     warn!(
                         "malicious block proposed, invalid round number compare to waiting block!"
@@ -240,7 +238,7 @@ impl Aura {
                 if custom_header.timestamp <= last_custom_header.timestamp {
                     warn!(
                         "malicious block proposed by author {:?}!",
-                        author_block.block.block.peer_id
+                        signed_block.block.peer_id
                     );
                     warn!("malicious block proposed, invalid timestamp compare to waiting block!");
                     warn!(
@@ -252,37 +250,37 @@ impl Aura {
                 }
             }
             None => {
-                let snapshot: Box<dyn Snapshot> = snapshot_db();
+                let snapshot: Box<dyn Snapshot> = meta_data_obj.context.db.snapshot();
                 {
                     let schema = SchemaSnap::new(&snapshot);
-                    // if schema.get_blockchain_length() != author_block.block.block.id {
+                    // if schema.get_blockchain_length() != signed_block.block.id {
                     //     warn!(
                     //         "malicious block proposed by author {:?}!",
-                    //         author_block.block.block.peer_id
+                    //         signed_block.block.peer_id
                     //     );
                     //     warn!("malicious block proposed, invalid height from snapshot!");
                     //     warn!(
                     //         "block should proposed on height {:?}, but got block on height {:?}",
                     //         schema.get_blockchain_length(),
-                    //         author_block.block.block.id
+                    //         signed_block.block.id
                     //     );
                     //     return;
                     // }
-                    // if schema.get_root_block_hash() != author_block.block.block.prev_hash {
+                    // if schema.get_root_block_hash() != signed_block.block.prev_hash {
                     //     warn!(
                     //         "malicious block proposed by author {:?}!",
-                    //         author_block.block.block.peer_id
+                    //         signed_block.block.peer_id
                     //     );
                     //     warn!("malicious block proposed, invalid previous block hash compare to snapshot!");
                     //     warn!(
                     //         "previous_hash shuold be {:?}, but previous hash is {:?}",
                     //         schema.get_root_block_hash(),
-                    //         author_block.block.get_hash()
+                    //         signed_block.get_hash()
                     //     );
                     //     return;
                     // }
                     let custom_header: CustomHeaders =
-                        match deserialize(&author_block.block.block.custom_headers) {
+                        match deserialize(&signed_block.block.custom_headers) {
                             Ok(value) => value,
                             Err(_) => {
                                 warn!("block custom headers couldn't deserialized");
@@ -314,7 +312,7 @@ impl Aura {
                     if custom_header.round_number <= last_custom_header.round_number {
                         warn!(
                             "malicious block proposed by author {:?}!",
-                            author_block.block.block.peer_id
+                            signed_block.block.peer_id
                         );
                         warn!(
                             "malicious block proposed, invalid round number compare to snapshot!"
@@ -329,7 +327,7 @@ impl Aura {
                     if custom_header.timestamp <= last_custom_header.timestamp {
                         warn!(
                             "malicious block proposed by author {:?}!",
-                            author_block.block.block.peer_id
+                            signed_block.block.peer_id
                         );
                         warn!("malicious block proposed, invalid timestamp compare to snapshot!");
                         warn!(
@@ -346,23 +344,23 @@ impl Aura {
         // let author_block: AuthorBlock = AuthorBlock::create(signed_block.clone());
         // AuraMessageSender::send_author_block_msg(sender, author_block);
         let block_acceptance: BlockAcceptance =
-            BlockAcceptance::create(&meta_data_obj.kp, author_block.block.get_hash());
+            BlockAcceptance::create(&meta_data_obj.kp, signed_block.get_hash());
         AuraMessageSender::send_block_acceptance_msg(&mut meta_data_obj.sender, block_acceptance);
         info!(
             "block accepted, created by {:?} with id {:?}, & hash {:?}",
-            author_block.block.block.peer_id,
-            author_block.block.block.id,
-            author_block.block.get_hash().to_hex()
+            signed_block.block.peer_id,
+            signed_block.block.id,
+            signed_block.get_hash().to_hex()
         );
-        waiting_blocks_queue.last_block_hash = author_block.block.get_hash().to_hex();
+        waiting_blocks_queue.last_block_hash = signed_block.get_hash().to_hex();
         waiting_blocks_queue.last_block_acceptance.clear();
         waiting_blocks_queue
             .last_block_acceptance
             .insert(meta_data_obj.public_key.clone());
         waiting_blocks_queue
             .last_block_acceptance
-            .insert(author_block.block.block.peer_id.clone());
-        waiting_blocks_queue.queue.push(author_block.block);
+            .insert(signed_block.block.peer_id.clone());
+        waiting_blocks_queue.queue.push(signed_block);
     }
 
     // fn will process incoming BlockAcceptenceEnum data
@@ -462,24 +460,28 @@ impl Aura {
     }
 
     // fn will update waiting blocks in sequence to local db
-    fn process_blocks(blocks_count: usize, waiting_blocks_queue: &mut WaitingBLocksQueue) {
-        let fork = fork_db();
+    fn process_blocks(
+        blocks_count: usize,
+        waiting_blocks_queue: &mut WaitingBLocksQueue,
+        context: &NodeContext,
+    ) {
+        let fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
             for each in waiting_blocks_queue.queue.iter() {
-                if !schema.update_block(each) {
+                if !schema.update_block(context, each) {
                     return;
                 }
             }
         }
         let mut blocks_count = blocks_count;
-        let fork = fork_db();
+        let fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
             while blocks_count > 0 {
                 let signed_block: SignedBlock = waiting_blocks_queue.queue.remove(0);
-                if schema.update_block(&signed_block) {
-                    POOL.sync_pool(&signed_block.block.txn_pool);
+                if schema.update_block(context, &signed_block) {
+                    context.pool.sync_pool(&signed_block.block.txn_pool);
                     info!(
                         "block with id {} & hash {} added in database",
                         signed_block.block.id,
@@ -518,7 +520,7 @@ impl Aura {
             }
         }
         if blocks_count == 0 {
-            patch_db(fork);
+            context.db.patch(fork);
             info!("Blocks are updated in the database");
         }
     }
@@ -532,7 +534,11 @@ impl Aura {
         if queue_length > meta_data_obj.block_queue_size + 1 {
             info!("queue length {:?}", queue_length);
             let blocks_to_be_confirmed: usize = queue_length / 3 * 2;
-            Aura::process_blocks(blocks_to_be_confirmed, waiting_blocks_queue);
+            Aura::process_blocks(
+                blocks_to_be_confirmed,
+                waiting_blocks_queue,
+                &meta_data_obj.context,
+            );
             info!(
                 "after processing queue length {:?}",
                 waiting_blocks_queue.queue.len()
@@ -540,73 +546,101 @@ impl Aura {
         }
     }
 
-    // fn will listen incoming data from other peers via P2P system
-    fn aura_msg_receiver(
+    /// receive the aura messages of other validators until shutdown. Handling
+    /// them reads & writes the db and may fetch transactions, so each message
+    /// is handled on the blocking pool, one after the other.
+    async fn aura_msg_receiver(
         waiting_blocks_queue: Arc<Mutex<WaitingBLocksQueue>>,
         meta_data: Arc<Mutex<MetaData>>,
         rx: Arc<Mutex<Receiver<Option<Vec<u8>>>>>,
+        shutdown: Shutdown,
     ) {
-        thread::spawn(move || {
-            block_on(future::poll_fn(move |cx: &mut Context| {
-                loop {
-                    match rx.lock().unwrap().poll_next_unpin(cx) {
-                        Poll::Ready(Some(msg)) => match msg {
-                            None => info!("Empty msg received !"),
-                            Some(msgtype) => {
-                                if let Ok(msgtype) =
-                                    deserialize::<AuraMessageTypes>(msgtype.as_slice())
-                                {
-                                    match msgtype {
-                                        AuraMessageTypes::AuthorBlockEnum(data) => {
-                                            let author_block: AuthorBlock = data;
-                                            info!("AuthorBlock data received");
-                                            let mut waiting_blocks_queue_obj =
-                                                waiting_blocks_queue.lock().unwrap();
-                                            let mut meta_data_obj = meta_data.lock().unwrap();
-                                            Aura::handle_author_block_enum(
-                                                author_block,
-                                                &mut waiting_blocks_queue_obj,
-                                                &mut meta_data_obj,
-                                            );
-                                        }
-                                        AuraMessageTypes::BlockAcceptanceEnum(data) => {
-                                            let block_acceptance: BlockAcceptance = data;
-                                            info!("BlockAcceptance data received");
-                                            let mut waiting_blocks_queue_obj =
-                                                waiting_blocks_queue.lock().unwrap();
-                                            let meta_data_obj = meta_data.lock().unwrap();
-                                            Aura::handle_block_acceptence_enum(
-                                                block_acceptance,
-                                                &mut waiting_blocks_queue_obj,
-                                                &meta_data_obj,
-                                            );
-                                        }
-                                        AuraMessageTypes::RoundOwnerEnum(data) => {
-                                            let round_config: RoundOwner = data;
-                                            info!("RoundOwner data received");
-                                            let mut waiting_blocks_queue_obj =
-                                                waiting_blocks_queue.lock().unwrap();
-                                            let meta_data_obj = meta_data.lock().unwrap();
-                                            Aura::handle_round_owner_enum(
-                                                round_config,
-                                                &mut waiting_blocks_queue_obj,
-                                                &meta_data_obj,
-                                            );
-                                        }
-                                    }
-                                }
-                            }
-                        },
-                        Poll::Ready(None) => {
-                            info!("channel closed !");
-                            return Poll::Ready(1);
+        while !shutdown.is_triggered() {
+            let next_msg =
+                future::poll_fn(|cx: &mut Context| rx.lock().unwrap().poll_next_unpin(cx));
+            let msg: Option<Vec<u8>> = match future::select(next_msg, shutdown.wait()).await {
+                Either::Left((Some(msg), _)) => msg,
+                Either::Left((None, _)) => {
+                    info!("channel closed !");
+                    break;
+                }
+                Either::Right(_) => break,
+            };
+            let msgtype: Vec<u8> = match msg {
+                Some(msgtype) => msgtype,
+                None => {
+                    info!("Empty msg received !");
+                    continue;
+                }
+            };
+            let waiting_blocks_queue = waiting_blocks_queue.clone();
+            let meta_data = meta_data.clone();
+            let handled = task::spawn_blocking(move || {
+                Aura::handle_aura_msg(msgtype, &waiting_blocks_queue, &meta_data)
+            });
+            if let Err(error) = handled.await {
+                error!("aura message couldn't be handled: {:?}", error);
+            }
+        }
+        info!("aura messages no longer received");
+    }
+
+    // fn will process an incoming aura message
+    fn handle_aura_msg(
+        msgtype: Vec<u8>,
+        waiting_blocks_queue: &Arc<Mutex<WaitingBLocksQueue>>,
+        meta_data: &Arc<Mutex<MetaData>>,
+    ) {
+        if let Ok(msgtype) = deserialize::<AuraMessageTypes>(msgtype.as_slice()) {
+            match msgtype {
+                AuraMessageTypes::AuthorBlockEnum(data) => {
+                    let author_block: AuthorBlock = data;
+                    info!("AuthorBlock data received");
+                    // missing transactions are fetched before the locks are
+                    // taken
+                    let relay = meta_data.lock().unwrap().context.block_relay();
+                    let signed_block: SignedBlock = match author_block.rebuild(&relay) {
+                        Some(signed_block) => signed_block,
+                        None => {
+                            warn!(
+                                "author block {} couldn't be rebuilt",
+                                author_block.block.header.id
+                            );
+                            return;
                         }
-                        Poll::Pending => break,
-                    }
+                    };
+                    let mut waiting_blocks_queue_obj = waiting_blocks_queue.lock().unwrap();
+                    let mut meta_data_obj = meta_data.lock().unwrap();
+                    Aura::handle_author_block_enum(
+                        signed_block,
+                        &mut waiting_blocks_queue_obj,
+                        &mut meta_data_obj,
+                    );
                 }
-                Poll::Pending
-            }));
-        });
+                AuraMessageTypes::BlockAcceptanceEnum(data) => {
+                    let block_acceptance: BlockAcceptance = data;
+                    info!("BlockAcceptance data received");
+                    let mut waiting_blocks_queue_obj = waiting_blocks_queue.lock().unwrap();
+                    let meta_data_obj = meta_data.lock().unwrap();
+                    Aura::handle_block_acceptence_enum(
+                        block_acceptance,
+                        &mut waiting_blocks_queue_obj,
+                        &meta_data_obj,
+                    );
+                }
+                AuraMessageTypes::RoundOwnerEnum(data) => {
+                    let round_config: RoundOwner = data;
+                    info!("RoundOwner data received");
+                    let mut waiting_blocks_queue_obj = waiting_blocks_queue.lock().unwrap();
+                    let meta_data_obj = meta_data.lock().unwrap();
+                    Aura::handle_round_owner_enum(
+                        round_config,
+                        &mut waiting_blocks_queue_obj,
+                        &meta_data_obj,
+                    );
+                }
+            }
+        }
     }
 
     // fn will create new block to propose after processing waiting blocks
@@ -614,14 +648,14 @@ impl Aura {
         &self,
         waiting_blocks_queue: &mut WaitingBLocksQueue,
         meta_data: &MetaData,
-    ) -> SignedBlock {
+    ) -> Result<SignedBlock, String> {
         loop {
-            let fork = fork_db();
+            let fork = self.context.db.fork();
             let mut schema = SchemaFork::new(&fork);
             let mut update_success_flag: bool = true;
             for each_block in waiting_blocks_queue.queue.iter() {
                 debug!("blocks order {:?}", each_block.block.id);
-                if !schema.update_block(each_block) {
+                if !schema.update_block(&self.context, each_block) {
                     update_success_flag = false;
                     break;
                 }
@@ -641,22 +675,22 @@ impl Aura {
                     Err(_) => Vec::new(),
                 };
                 if self.force_sealing {
-                    return schema.create_block(&self.keypair, custom_headers);
+                    return schema.create_block(&self.context, &self.keypair, custom_headers);
                 } else {
                     let (_fork_instance, signed_block) =
-                        schema.forge_new_block(&self.keypair, custom_headers);
-                    return signed_block;
+                        schema.forge_new_block(&self.context, &self.keypair, custom_headers)?;
+                    return Ok(signed_block);
                 }
             } else {
                 // init sync_state_flag with false
                 #[allow(unused_assignments)]
                 let mut sync_state_flag: bool = false;
-                let fork_to_sync = fork_db();
+                let fork_to_sync = self.context.db.fork();
                 {
                     let mut sync_schema = SchemaFork::new(&fork_to_sync);
-                    sync_state_flag = sync_schema.sync_state();
+                    sync_state_flag = sync_schema.sync_state(&self.context);
                 }
-                patch_db(fork_to_sync);
+                self.context.db.patch(fork_to_sync);
                 // flush the waiting blocks queue
                 if sync_state_flag {
                     if waiting_blocks_queue.queue.len() > 0 {
@@ -670,89 +704,135 @@ impl Aura {
         }
     }
 
-    // fn will create new blocks periodically after checking round ownership
-    fn state_machine(
-        &mut self,
+    // fn will close the round this node owns and propose its block on top of
+    // the waiting blocks, it reads & writes the db
+    fn lead_round(
+        &self,
+        round_owner: RoundOwner,
+        waiting_blocks_queue: &Arc<Mutex<WaitingBLocksQueue>>,
+        meta_data: &Arc<Mutex<MetaData>>,
+        sender: &mut GossipSender,
+    ) {
+        let meta_data_obj = meta_data.lock().unwrap();
+        let mut waiting_blocks_queue_obj = waiting_blocks_queue.lock().unwrap();
+        Aura::handle_round_owner_enum(round_owner, &mut waiting_blocks_queue_obj, &meta_data_obj);
+        match self.propose_block(&mut waiting_blocks_queue_obj, &meta_data_obj) {
+            Ok(signed_block) => {
+                info!(
+                    "new block created.. id {},hash {}",
+                    signed_block.block.id,
+                    signed_block.object_hash()
+                );
+                let author_block: AuthorBlock =
+                    AuthorBlock::create(&self.context.block_relay(), &signed_block);
+                AuraMessageSender::send_author_block_msg(sender, author_block);
+                waiting_blocks_queue_obj.last_block_hash = signed_block.get_hash().to_hex();
+                waiting_blocks_queue_obj.queue.push(signed_block);
+                waiting_blocks_queue_obj.last_block_acceptance.clear();
+                waiting_blocks_queue_obj
+                    .last_block_acceptance
+                    .insert(meta_data_obj.public_key.clone());
+            }
+            Err(error) => error!("block couldn't be proposed: {}", error),
+        }
+    }
+
+    // fn will create new blocks periodically after checking round ownership,
+    // the rounds are timed by the runtime & the db work runs on the blocking
+    // pool
+    async fn state_machine(
+        self,
         waiting_blocks_queue: Arc<Mutex<WaitingBLocksQueue>>,
         meta_data: Arc<Mutex<MetaData>>,
-        sender: &mut Sender<Option<MessageTypes>>,
+        sender: GossipSender,
+        shutdown: Shutdown,
     ) {
-        let mut wait_till_one_round: u64 = (self.validator_mapping.len() * 10) as u64;
-        wait_till_one_round = wait_till_one_round * self.leader_epoch;
-        thread::sleep(Duration::from_millis(wait_till_one_round));
-        let fork = fork_db();
+        let aura: Arc<Aura> = Arc::new(self);
+        let mut wait_till_one_round: u64 = (aura.validator_mapping.len() * 10) as u64;
+        wait_till_one_round = wait_till_one_round * aura.leader_epoch;
+        if !shutdown
+            .pause(Duration::from_millis(wait_till_one_round))
+            .await
         {
-            let mut schema = SchemaFork::new(&fork);
-            schema.sync_state();
+            info!("consensus stopped");
+            return;
         }
-        patch_db(fork);
-        #[allow(unused_assignments)]
-        let mut leader_flag = false;
-        loop {
+        let context: NodeContext = aura.context.clone();
+        let synced = task::spawn_blocking(move || {
+            let fork = context.db.fork();
+            {
+                let mut schema = SchemaFork::new(&fork);
+                schema.sync_state(&context);
+            }
+            context.db.patch(fork);
+        });
+        if let Err(error) = synced.await {
+            error!("state couldn't be synced: {:?}", error);
+        }
+        while !shutdown.is_triggered() {
             /*
             calculate round number and find out who is the leader
             need to check continuously
             if node is the leader, propose block on top of waiting block queue
             */
-            {
-                let mut am_i_leader: bool = false;
+            let am_i_leader: bool = {
+                let meta_data_obj = meta_data.lock().unwrap();
+                Aura::primary_leader(&meta_data_obj) == aura.pk
+            };
+            let mut pause: u64 = aura.leader_epoch;
+            if am_i_leader {
+                info!("I'm the leader NOW!!");
+                let round_owner: RoundOwner = RoundOwner::create(&aura.keypair);
+                AuraMessageSender::send_round_owner_msg(&mut sender.clone(), round_owner.clone());
+                if !shutdown
+                    .pause(Duration::from_millis(aura.leader_epoch))
+                    .await
                 {
-                    let meta_data_obj = meta_data.lock().unwrap();
-                    let current_leader: String = Aura::primary_leader(&meta_data_obj);
-                    if current_leader == self.pk.clone() {
-                        am_i_leader = true;
-                    }
+                    break;
                 }
-
-                if am_i_leader {
-                    info!("I'm the leader NOW!!");
-                    let round_owner: RoundOwner = RoundOwner::create(&self.keypair);
-                    AuraMessageSender::send_round_owner_msg(sender, round_owner.clone());
-                    thread::sleep(Duration::from_millis(self.leader_epoch));
-                    {
-                        let meta_data_obj = meta_data.lock().unwrap();
-                        let mut waiting_blocks_queue_obj = waiting_blocks_queue.lock().unwrap();
-                        Aura::handle_round_owner_enum(
-                            round_owner,
-                            &mut waiting_blocks_queue_obj,
-                            &meta_data_obj,
-                        );
-                        let signed_block: SignedBlock =
-                            self.propose_block(&mut waiting_blocks_queue_obj, &meta_data_obj);
-                        info!(
-                            "new block created.. id {},hash {}",
-                            signed_block.block.id,
-                            signed_block.object_hash()
-                        );
-                        let author_block: AuthorBlock = AuthorBlock::create(signed_block.clone());
-                        AuraMessageSender::send_author_block_msg(sender, author_block);
-                        waiting_blocks_queue_obj.last_block_hash = signed_block.get_hash().to_hex();
-                        waiting_blocks_queue_obj.queue.push(signed_block);
-                        waiting_blocks_queue_obj.last_block_acceptance.clear();
-                        waiting_blocks_queue_obj
-                            .last_block_acceptance
-                            .insert(meta_data_obj.public_key.clone());
-                    }
-                    leader_flag = true;
+                let lead = {
+                    let aura = aura.clone();
+                    let waiting_blocks_queue = waiting_blocks_queue.clone();
+                    let meta_data = meta_data.clone();
+                    let mut sender = sender.clone();
+                    task::spawn_blocking(move || {
+                        aura.lead_round(round_owner, &waiting_blocks_queue, &meta_data, &mut sender)
+                    })
+                };
+                if let Err(error) = lead.await {
+                    error!("round couldn't be led: {:?}", error);
                 }
+                pause = aura.leader_epoch * 10;
             }
-            if leader_flag {
-                leader_flag = false;
-                thread::sleep(Duration::from_millis(self.leader_epoch * 10));
-            } else {
-                thread::sleep(Duration::from_millis(self.leader_epoch));
+            if !shutdown.pause(Duration::from_millis(pause)).await {
+                break;
             }
         }
+        info!("consensus stopped");
     }
 
-    pub fn init_aura_consensus(
-        config: &Configuration,
-        consensus_file_path: &str,
-        sender: &mut Sender<Option<MessageTypes>>,
-        msg_receiver: Arc<Mutex<Receiver<Option<Vec<u8>>>>>,
+    /// run aura consensus until shutdown, the message receiver included
+    pub async fn init_aura_consensus(
+        context: NodeContext,
+        consensus_file_path: String,
+        sender: GossipSender,
+        shutdown: Shutdown,
     ) {
-        initialize_config(consensus_file_path);
-        let aura_config: &crate::config::Configuration = &crate::config::AURA_CONFIG;
+        let aura_config: crate::config::Configuration =
+            match crate::config::Configuration::from_file(&consensus_file_path) {
+                Ok(aura_config) => aura_config,
+                Err(error) => {
+                    error!("{}, consensus not started", error);
+                    return;
+                }
+            };
+        let config = context.config.clone();
+        let validator_config = config.clone();
+        context
+            .dispatcher
+            .set_consensus_validator(Box::new(move |data: &Vec<u8>| {
+                validate_aura_msg(data, &validator_config.block_config)
+            }));
         let mut validator_mapping: HashMap<String, u64> = HashMap::new();
         for i in 0..aura_config.validator_set.len() {
             validator_mapping.insert(
@@ -768,6 +848,7 @@ impl Aura {
             leader_epoch: 100 * aura_config.step_time,
             force_sealing: aura_config.force_sealing,
             start_time: aura_config.start_time,
+            context: context.clone(),
         };
 
         let consensus_meta_data = MetaData {
@@ -780,25 +861,43 @@ impl Aura {
             round_number: aura_config.round_number,
             step_time: aura_config.step_time,
             block_queue_size: aura_config.block_list_size,
+            context: context.clone(),
         };
         let meta_data = Arc::new(Mutex::new(consensus_meta_data));
         let waiting_blocks_queue: Arc<Mutex<WaitingBLocksQueue>> =
             Arc::new(Mutex::new(WaitingBLocksQueue::new()));
-        Aura::aura_msg_receiver(
+        let receiver = Aura::aura_msg_receiver(
             waiting_blocks_queue.clone(),
             meta_data.clone(),
-            msg_receiver,
+            context.dispatcher.consensus_msg_receiver.clone(),
+            shutdown.clone(),
         );
-        if config.node.genesis_block {
-            aura_obj.init_state(&config.db.dbpath, &mut sender.clone());
-        } else {
-            let fork = fork_db();
-            {
-                let mut schema = SchemaFork::new(&fork);
-                schema.sync_state();
+        let engine = async move {
+            let genesis_block: bool = config.node.genesis_block;
+            let mut init_sender = sender.clone();
+            // creating the genesis block or catching up blocks
+            let init = task::spawn_blocking(move || {
+                if genesis_block {
+                    aura_obj.init_state(&mut init_sender);
+                } else {
+                    let fork = context.db.fork();
+                    {
+                        let mut schema = SchemaFork::new(&fork);
+                        schema.sync_state(&context);
+                    }
+                    context.db.patch(fork);
+                }
+                aura_obj
+            });
+            match init.await {
+                Ok(aura_obj) => {
+                    aura_obj
+                        .state_machine(waiting_blocks_queue, meta_data, sender, shutdown)
+                        .await
+                }
+                Err(error) => error!("consensus couldn't start: {:?}", error),
             }
-            patch_db(fork);
-        }
-        aura_obj.state_machine(waiting_blocks_queue.clone(), meta_data, sender);
+        };
+        future::join(receiver, engine).await;
     }
 }
@@ -1,12 +1,13 @@
 use crate::aura_messages::{AuraMessageTypes, AuthorBlock, BlockAcceptance, RoundOwner};
 use futures::channel::mpsc::*;
 use message_handler::messages::MessageTypes;
+use message_handler::queues::GossipSender;
 use utils::serializer::serialize;
 
 pub struct AuraMessageSender {}
 
 impl AuraMessageSender {
-    pub fn send_round_owner_msg(sender: &mut Sender<Option<MessageTypes>>, msg: RoundOwner) {
+    pub fn send_round_owner_msg(sender: &mut GossipSender, msg: RoundOwner) {
         if let Ok(serialize_msg) = serialize(&AuraMessageTypes::RoundOwnerEnum(msg)) {
             let data = Some(MessageTypes::ConsensusMsg(serialize_msg));
             let error: Result<(), TrySendError<Option<MessageTypes>>> = sender.try_send(data);
@@ -18,10 +19,7 @@ impl AuraMessageSender {
         }
     }
 
-    pub fn send_block_acceptance_msg(
-        sender: &mut Sender<Option<MessageTypes>>,
-        msg: BlockAcceptance,
-    ) {
+    pub fn send_block_acceptance_msg(sender: &mut GossipSender, msg: BlockAcceptance) {
         if let Ok(serialize_msg) = serialize(&AuraMessageTypes::BlockAcceptanceEnum(msg)) {
             let data = Some(MessageTypes::ConsensusMsg(serialize_msg));
             let error: Result<(), TrySendError<Option<MessageTypes>>> = sender.try_send(data);
@@ -33,7 +31,7 @@ impl AuraMessageSender {
         }
     }
 
-    pub fn send_author_block_msg(sender: &mut Sender<Option<MessageTypes>>, msg: AuthorBlock) {
+    pub fn send_author_block_msg(sender: &mut GossipSender, msg: AuthorBlock) {
         if let Ok(serialize_msg) = serialize(&AuraMessageTypes::AuthorBlockEnum(msg)) {
             let data = Some(MessageTypes::ConsensusMsg(serialize_msg));
             let error: Result<(), TrySendError<Option<MessageTypes>>> = sender.try_send(data);
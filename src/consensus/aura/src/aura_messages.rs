@@ -1,11 +1,14 @@
 use exonum_crypto::Hash;
-use libp2p::floodsub::Topic;
+use libp2p::gossipsub::Topic;
+use message_handler::block_relay::{check_compact_block, BlockRelay};
 use message_handler::constants;
 use message_handler::message_traits::Message;
 use schema::block::SignedBlock;
+use schema::compact_block::CompactBlock;
 use std::time::SystemTime;
+use utils::configreader::BlockConfig;
 use utils::keypair::{CryptoKeypair, Keypair, KeypairType, PublicKey, Verify};
-use utils::serializer::{serialize, Deserialize, Serialize};
+use utils::serializer::{deserialize, serialize, Deserialize, Serialize};
 
 pub const AURA_MSG_TOPIC_STR: &'static [&'static str] =
     &["RoundOwner", "BlockAcceptance", "AuthorBlock"];
@@ -31,6 +34,11 @@ impl RoundOwner {
         if stamp - self.round_details.unix_time > step_time {
             return false;
         }
+        self.verify_signature()
+    }
+
+    /// signature check only, the round may already be over
+    pub fn verify_signature(&self) -> bool {
         let ser_payload: Vec<u8> = match serialize(&self.round_details) {
             Result::Ok(value) => value,
             Result::Err(_) => return false,
@@ -100,18 +108,31 @@ impl BlockAcceptance {
     }
 }
 
+/// AuthorBlock announces the block of the round leader as a CompactBlock,
+/// validators rebuild it from their pool before accepting it
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthorBlock {
-    pub block: SignedBlock,
+    pub block: CompactBlock,
 }
 
 impl AuthorBlock {
-    pub fn verify(&self) -> bool {
-        self.block.validate()
+    /// the block signature is checked once the block is rebuilt
+    pub fn verify(&self, block_config: &BlockConfig) -> bool {
+        check_compact_block(&self.block, block_config).is_ok()
+    }
+
+    /// the transactions of block have to be in the pool still, validators
+    /// fetch the ones they miss from this node
+    pub fn create(relay: &BlockRelay, block: &SignedBlock) -> AuthorBlock {
+        AuthorBlock {
+            block: relay.compact_block(block),
+        }
     }
 
-    pub fn create(block: SignedBlock) -> AuthorBlock {
-        AuthorBlock { block }
+    /// the block rebuilt from the pool & the fetched transactions, None if it
+    /// couldn't be or doesn't match its signature
+    pub fn rebuild(&self, relay: &BlockRelay) -> Option<SignedBlock> {
+        relay.reconstruct_block(&self.block)
     }
 }
 
@@ -157,13 +178,35 @@ impl From<AuraMessageTypes> for Topic {
     }
 }
 
+/// gossip validation of aura messages, checks they decode and carry a valid
+/// signature. Round timing is left to the receiver, a late message is not
+/// an invalid one.
+pub fn validate_aura_msg(data: &Vec<u8>, block_config: &BlockConfig) -> Result<(), String> {
+    let valid: bool = match deserialize::<AuraMessageTypes>(data.as_slice())? {
+        AuraMessageTypes::RoundOwnerEnum(data) => data.verify_signature(),
+        AuraMessageTypes::BlockAcceptanceEnum(data) => data.verify(),
+        AuraMessageTypes::AuthorBlockEnum(data) => data.verify(block_config),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(String::from("aura message signature couldn't verify"))
+    }
+}
+
 #[cfg(test)]
 mod consensus_message_test {
 
     #[test]
     pub fn check_aura_messages_verification_process() {
         use super::*;
+        use message_handler::block_relay::RecentBlocks;
+        use message_handler::peer_request::PeerRequests;
         use schema::block::{Block, BlockTraits};
+        use schema::transaction_pool::Pool;
+        use std::sync::{Arc, Mutex};
+        use utils::configreader::Configuration;
+        use utils::queue_metrics::QueueMetrics;
 
         let kp: KeypairType = Keypair::generate();
         let pk: String = hex::encode(kp.public().encode());
@@ -188,9 +231,32 @@ mod consensus_message_test {
             BlockAcceptance::create(&kp, signed_block.get_hash());
         assert_eq!(block_acceptance.verify(), true);
 
-        let author_block: AuthorBlock = AuthorBlock {
-            block: signed_block,
-        };
-        assert_eq!(author_block.verify(), true);
+        // a block without transactions is rebuilt from the announcement alone
+        let config = Configuration::from_file("../../../config.toml").unwrap();
+        let queue_metrics = Arc::new(Mutex::new(QueueMetrics::new()));
+        let relay = BlockRelay::new(
+            Pool::new(),
+            Arc::new(PeerRequests::new(&queue_metrics)),
+            Arc::new(Mutex::new(RecentBlocks::new())),
+        );
+        let block: Block = Block::new_block(
+            1,
+            pk,
+            Hash::zero(),
+            Vec::new(),
+            [Hash::zero(), Hash::zero(), Hash::zero()],
+            Vec::new(),
+        );
+        let signed_block: SignedBlock =
+            SignedBlock::create_block(block.clone(), block.sign(&kp), Vec::new());
+        let author_block: AuthorBlock = AuthorBlock::create(&relay, &signed_block);
+        assert_eq!(author_block.verify(&config.block_config), true);
+        assert_eq!(
+            author_block.rebuild(&relay).map(|block| block.get_hash()),
+            Some(signed_block.get_hash())
+        );
+        let mut forged_block: AuthorBlock = AuthorBlock::create(&relay, &signed_block);
+        forged_block.block.header.id = 2;
+        assert_eq!(forged_block.rebuild(&relay).is_none(), true);
     }
 }
@@ -71,6 +71,18 @@ impl Configuration {
         let conf_data: Configuration = toml::from_str(&config_file_str).unwrap();
         conf_data
     }
+
+    /// Err if the consensus config at file_path can't be read
+    pub fn from_file(file_path: &str) -> Result<Configuration, String> {
+        let mut config_file_str = String::new();
+        File::open(file_path)
+            .and_then(|mut config_file| config_file.read_to_string(&mut config_file_str))
+            .map_err(|error| {
+                format!("consensus config {} couldn't be read: {}", file_path, error)
+            })?;
+        toml::from_str(&config_file_str)
+            .map_err(|error| format!("invalid consensus config {}: {}", file_path, error))
+    }
 }
 
 pub fn initialize_config(file_path: &str) {
@@ -3,36 +3,59 @@ extern crate message_handler;
 extern crate schema;
 extern crate utils;
 
-use super::poa_config::initialize_config;
+use super::poa_config;
 use super::poa_message_sender::ConsensusMessageSender;
 use super::poa_messages::{
-    ConsensusMessageTypes, ElectionPing, ElectionPong, LeaderElection, SignedLeaderElection,
+    validate_consensus_msg, ConsensusMessageTypes, ElectionPing, ElectionPong, LeaderElection,
+    SignedLeaderElection,
 };
 use db_service::db_fork_ref::SchemaFork;
-use db_service::db_layer::{fork_db, patch_db, snapshot_db};
 use db_service::db_snapshot_ref::SchemaSnap;
+use db_service::node_context::NodeContext;
 use exonum_merkledb::{Fork, ObjectHash};
-use futures::{channel::mpsc::*, executor::*, future, prelude::*, task::*};
+use futures::{channel::mpsc::*, future, future::Either, prelude::*, task::*};
 use message_handler::message_sender::MessageSender;
-use message_handler::messages::MessageTypes;
+use message_handler::queues::GossipSender;
+use message_handler::shutdown::Shutdown;
 use schema::block::SignedBlock;
-use schema::transaction_pool::{TxnPool, POOL};
+use schema::transaction_pool::TxnPool;
 use std::collections::{hash_map::DefaultHasher, BTreeMap};
 use std::hash::{Hash, Hasher};
 use std::sync::{Arc, Mutex};
-use std::thread;
 use std::time::Duration;
 use std::time::SystemTime;
-use utils::configreader::Configuration;
+use tokio::task;
 use utils::keypair::{CryptoKeypair, Keypair, KeypairType, PublicKey, Verify};
 use utils::serializer::{deserialize, serialize};
 
+/// time the other validators have to answer the election ping of the leader
+const ELECTION_PING_WAIT: Duration = Duration::from_millis(1000);
+/// the state machine steps at least this often, e.g. to catch up with blocks
+/// of future leaders
+const SYNC_INTERVAL: Duration = Duration::from_millis(2000);
+/// time the other validators have to announce themselves on startup
+const STARTUP_WAIT: Duration = Duration::from_millis(5000);
+
+/// what the state machine does after a step
+#[derive(Debug, PartialEq)]
+enum Step {
+    /// wait for a leader election or the sync interval
+    Wait,
+    /// the leader map changed, step again at once
+    Continue,
+    /// this node proposed a block, the next leader is elected once the other
+    /// validators answered the election ping
+    Elect,
+}
+
 pub struct Consensus {
     keypair: KeypairType,
     pk: String,
     round_number: u64,
     public_keys: Vec<String>,
     force_sealing: bool,
+    // node the consensus runs on
+    context: NodeContext,
 }
 
 pub struct LeaderMap {
@@ -43,17 +66,12 @@ pub struct MetaData {
     active_node: Vec<String>,
     public_keys: Vec<String>,
     kp: KeypairType,
-    sender: Sender<Option<MessageTypes>>,
+    sender: GossipSender,
 }
 
 impl Consensus {
-    fn init_state(
-        &mut self,
-        _db_path: &String,
-        leader_map: Arc<Mutex<LeaderMap>>,
-        sender: &mut Sender<Option<MessageTypes>>,
-    ) {
-        let fork = fork_db();
+    fn init_state(&mut self, leader_map: Arc<Mutex<LeaderMap>>, sender: &mut GossipSender) {
+        let fork = self.context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
             if schema.blockchain_length() == 0 {
@@ -62,12 +80,17 @@ impl Consensus {
                     .duration_since(SystemTime::UNIX_EPOCH)
                     .unwrap()
                     .as_micros();
-                let genesis_signed_block = schema.initialize_db(custom_headers, timestamp);
+                let genesis_signed_block =
+                    schema.initialize_db(&self.context, custom_headers, timestamp);
                 info!(
                     "genesis block created!, genesis block hash {:?}",
                     genesis_signed_block.get_hash()
                 );
-                MessageSender::send_block_msg(sender, genesis_signed_block);
+                MessageSender::send_block_msg(
+                    sender,
+                    &self.context.block_relay(),
+                    genesis_signed_block,
+                );
             } else {
                 self.round_number = schema.blockchain_length() - 1;
                 info!(
@@ -77,7 +100,7 @@ impl Consensus {
                 )
             }
         }
-        patch_db(fork);
+        self.context.db.patch(fork);
         let leader_payload: LeaderElection = LeaderElection {
             block_height: self.round_number + 1,
             old_leader: self.pk.clone(),
@@ -100,8 +123,7 @@ impl Consensus {
             .insert(self.round_number, self.pk.clone());
     }
 
-    fn select_leader(&self, meta_data: Arc<Mutex<MetaData>>) -> SignedLeaderElection {
-        thread::sleep(Duration::from_millis(1000));
+    fn select_leader(&self, meta_data: &Arc<Mutex<MetaData>>) -> SignedLeaderElection {
         let meta_data_locked = meta_data.lock().unwrap();
         let mut iter_vec_pk: usize = 0;
         #[allow(unused_assignments)]
@@ -154,7 +176,7 @@ impl Consensus {
         }
     }
 
-    fn propose_block(&self, fork: Fork) -> (Fork, SignedBlock) {
+    fn propose_block(&self, fork: Fork) -> Result<(Fork, SignedBlock), String> {
         let signed_block: SignedBlock;
         {
             let mut schema = SchemaFork::new(&fork);
@@ -162,268 +184,343 @@ impl Consensus {
             let custom_headers: Vec<u8> = Vec::new();
             if !self.force_sealing {
                 let (fork_instance, signed_block) =
-                    schema.forge_new_block(&self.keypair, custom_headers);
+                    schema.forge_new_block(&self.context, &self.keypair, custom_headers)?;
                 info!(
                     "new block created.. id {},hash {}",
                     signed_block.block.id,
                     signed_block.object_hash()
                 );
-                return (fork_instance, signed_block);
+                return Ok((fork_instance, signed_block));
             }
-            signed_block = schema.create_block(&self.keypair, custom_headers);
+            signed_block = schema.create_block(&self.context, &self.keypair, custom_headers)?;
             info!(
                 "new block created.. id {},hash {}",
                 signed_block.block.id,
                 signed_block.object_hash()
             );
         }
-        return (fork, signed_block);
+        Ok((fork, signed_block))
     }
 
-    fn validator(
-        &mut self,
-        sender: &mut Sender<Option<MessageTypes>>,
-        meta_data: Arc<Mutex<MetaData>>,
-    ) -> bool {
+    /// propose the block of this round & ping the validators which may lead
+    /// the next one, false if no block could be proposed
+    fn propose(&mut self, sender: &mut GossipSender, meta_data: &Arc<Mutex<MetaData>>) -> bool {
         // no polling machenism of txn_pool and create block need to implement or modified here
         // if one want to change the create_block and txn priority then change/ implment that part in
         // schema operations and p2p module
-        let mut fork = fork_db();
+        let fork = self.context.db.fork();
+        let (fork, signed_block) = match self.propose_block(fork) {
+            Ok(proposal) => proposal,
+            Err(error) => {
+                error!("block couldn't be proposed: {}", error);
+                return false;
+            }
+        };
+        self.round_number = signed_block.block.id;
+        // the compact block is built from the pooled transactions
+        MessageSender::send_block_msg(sender, &self.context.block_relay(), signed_block.clone());
+        self.context.pool.sync_pool(&signed_block.block.txn_pool);
         {
-            let (fork_instance, signed_block) = self.propose_block(fork);
-            fork = fork_instance;
-            POOL.sync_pool(&signed_block.block.txn_pool);
-            self.round_number = signed_block.block.id;
-            MessageSender::send_block_msg(sender, signed_block);
-
             let mut meta_data_locked = meta_data.lock().unwrap();
             meta_data_locked.active_node.clear();
             let msg: ElectionPing =
                 ElectionPing::create(&meta_data_locked.kp, self.round_number + 1);
             ConsensusMessageSender::send_election_ping_msg(sender, msg);
             info!("pinging for block number {}", self.round_number + 1);
-            thread::sleep(Duration::from_micros(1000));
         }
-        patch_db(fork);
+        self.context.db.patch(fork);
+        true
+    }
+
+    /// elect the leader of the next round among the validators which answered
+    /// the election ping, true if it is this node again
+    fn elect_leader(
+        &mut self,
+        sender: &mut GossipSender,
+        meta_data: &Arc<Mutex<MetaData>>,
+    ) -> bool {
         let signed_new_leader: SignedLeaderElection = self.select_leader(meta_data);
         self.round_number = self.round_number + 1;
         let flag: bool = signed_new_leader.leader_payload.new_leader.clone()
             == signed_new_leader.leader_payload.old_leader.clone();
         ConsensusMessageSender::send_leader_election_msg(sender, signed_new_leader);
-
-        return flag;
+        flag
     }
 
-    pub fn consensus_msg_receiver(
+    /// receive the consensus messages of other validators until shutdown
+    pub async fn consensus_msg_receiver(
         leader_map: Arc<Mutex<LeaderMap>>,
         meta_data: Arc<Mutex<MetaData>>,
         rx: Arc<Mutex<Receiver<Option<Vec<u8>>>>>,
+        mut leader_events: Sender<()>,
+        shutdown: Shutdown,
     ) {
-        thread::spawn(move || {
-            block_on(future::poll_fn(move |cx: &mut Context| {
-                loop {
-                    match rx.lock().unwrap().poll_next_unpin(cx) {
-                        Poll::Ready(Some(msg)) => {
-                            match msg {
-                                None => info!("Empty msg received !"),
-                                Some(msgtype) => {
-                                    if let Ok(msgtype) =
-                                        deserialize::<ConsensusMessageTypes>(msgtype.as_slice())
-                                    {
-                                        match msgtype {
-                                            ConsensusMessageTypes::LeaderElect(data) => {
-                                                let new_leader_obj: SignedLeaderElection = data;
-                                                let ser_leader_election: Vec<u8> =
-                                                    match serialize(&new_leader_obj.leader_payload)
-                                                    {
-                                                        Result::Ok(value) => value,
-                                                        Result::Err(_) => vec![0],
-                                                    };
-                                                if PublicKey::verify_from_encoded_pk(
-                                                    &new_leader_obj.leader_payload.old_leader,
-                                                    &ser_leader_election,
-                                                    &new_leader_obj.signature,
-                                                ) {
-                                                    let mut leader_map_locked =
-                                                        leader_map.lock().unwrap();
-                                                    leader_map_locked.map.insert(
-                                                        new_leader_obj.leader_payload.block_height,
-                                                        new_leader_obj
-                                                            .leader_payload
-                                                            .new_leader
-                                                            .clone(),
-                                                    );
-                                                    info!(
-                                                        "New Leader for block height {} -> {}",
-                                                        new_leader_obj.leader_payload.block_height,
-                                                        new_leader_obj.leader_payload.new_leader,
-                                                    );
-                                                }
-                                                // update leader selection process here.
-                                            }
-                                            ConsensusMessageTypes::ConsensusPing(data) => {
-                                                let election_ping: ElectionPing = data;
-                                                let mut meta_data_locked =
-                                                    meta_data.lock().unwrap();
-                                                if meta_data_locked
-                                                    .public_keys
-                                                    .contains(&election_ping.payload.public_key)
-                                                {
-                                                    if election_ping.verify() {
-                                                        let election_pong: ElectionPong =
-                                                            ElectionPong::create(
-                                                                &meta_data_locked.kp,
-                                                                &election_ping,
-                                                            );
-                                                        ConsensusMessageSender::send_election_pong_msg(
-                                                            &mut meta_data_locked.sender,
-                                                            election_pong,
-                                                        );
-                                                        debug!(
-                                                            "Ping message from  {} for height {} -> ",
-                                                            election_ping.payload.public_key,
-                                                            election_ping.payload.height,
-                                                        );
-                                                    } else {
-                                                        warn!(
-                                                            "Election Ping data tempered {}",
-                                                            election_ping.payload.height
-                                                        );
-                                                    }
-                                                } else {
-                                                    debug!(
-                                                        "public_keys {:?} key {:?}",
-                                                        meta_data_locked.public_keys,
-                                                        election_ping.payload.public_key
-                                                    );
-                                                    warn!("Election Ping data from malicious node");
-                                                }
-                                            }
-                                            ConsensusMessageTypes::ConsensusPong(data) => {
-                                                let election_pong: ElectionPong = data;
-                                                let mut meta_data_locked =
-                                                    meta_data.lock().unwrap();
-                                                if hex::encode(
-                                                    meta_data_locked.kp.public().encode(),
-                                                ) == election_pong.payload.current_leader
-                                                {
-                                                    if meta_data_locked.public_keys.contains(
-                                                        &election_pong.payload.may_be_leader,
-                                                    ) {
-                                                        if election_pong.verify() {
-                                                            meta_data_locked.active_node.push(
-                                                                election_pong
-                                                                    .payload
-                                                                    .may_be_leader
-                                                                    .clone(),
-                                                            );
-                                                            debug!(
-                                                            "Pong message received from  {} for height {} -> ",
-                                                            election_pong.payload.may_be_leader,
-                                                            election_pong.payload.height,
-                                                        );
-                                                        } else {
-                                                            warn!(
-                                                                "Election Ping data tempered {}",
-                                                                election_pong.payload.may_be_leader
-                                                            );
-                                                        }
-                                                    } else {
-                                                        warn!(
-                                                            "Election Pong data tempered {}",
-                                                            election_pong.payload.may_be_leader,
-                                                        );
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+        while !shutdown.is_triggered() {
+            let next_msg =
+                future::poll_fn(|cx: &mut Context| rx.lock().unwrap().poll_next_unpin(cx));
+            let msg: Option<Vec<u8>> = match future::select(next_msg, shutdown.wait()).await {
+                Either::Left((Some(msg), _)) => msg,
+                Either::Left((None, _)) => {
+                    info!("channel closed !");
+                    break;
+                }
+                Either::Right(_) => break,
+            };
+            match msg {
+                None => info!("Empty msg received !"),
+                Some(msgtype) => Consensus::handle_consensus_msg(
+                    msgtype,
+                    &leader_map,
+                    &meta_data,
+                    &mut leader_events,
+                ),
+            }
+        }
+        info!("consensus messages no longer received");
+    }
+
+    // fn will process an incoming consensus message, it doesn't block
+    fn handle_consensus_msg(
+        msgtype: Vec<u8>,
+        leader_map: &Arc<Mutex<LeaderMap>>,
+        meta_data: &Arc<Mutex<MetaData>>,
+        leader_events: &mut Sender<()>,
+    ) {
+        if let Ok(msgtype) = deserialize::<ConsensusMessageTypes>(msgtype.as_slice()) {
+            match msgtype {
+                ConsensusMessageTypes::LeaderElect(data) => {
+                    let new_leader_obj: SignedLeaderElection = data;
+                    let ser_leader_election: Vec<u8> =
+                        match serialize(&new_leader_obj.leader_payload) {
+                            Result::Ok(value) => value,
+                            Result::Err(_) => vec![0],
+                        };
+                    if PublicKey::verify_from_encoded_pk(
+                        &new_leader_obj.leader_payload.old_leader,
+                        &ser_leader_election,
+                        &new_leader_obj.signature,
+                    ) {
+                        let mut leader_map_locked = leader_map.lock().unwrap();
+                        leader_map_locked.map.insert(
+                            new_leader_obj.leader_payload.block_height,
+                            new_leader_obj.leader_payload.new_leader.clone(),
+                        );
+                        info!(
+                            "New Leader for block height {} -> {}",
+                            new_leader_obj.leader_payload.block_height,
+                            new_leader_obj.leader_payload.new_leader,
+                        );
+                    }
+                    // the state machine may lead or sync now
+                    let _ = leader_events.try_send(());
+                    // update leader selection process here.
+                }
+                ConsensusMessageTypes::ConsensusPing(data) => {
+                    let election_ping: ElectionPing = data;
+                    let mut meta_data_locked = meta_data.lock().unwrap();
+                    if meta_data_locked
+                        .public_keys
+                        .contains(&election_ping.payload.public_key)
+                    {
+                        if election_ping.verify() {
+                            let election_pong: ElectionPong =
+                                ElectionPong::create(&meta_data_locked.kp, &election_ping);
+                            ConsensusMessageSender::send_election_pong_msg(
+                                &mut meta_data_locked.sender,
+                                election_pong,
+                            );
+                            debug!(
+                                "Ping message from  {} for height {} -> ",
+                                election_ping.payload.public_key, election_ping.payload.height,
+                            );
+                        } else {
+                            warn!(
+                                "Election Ping data tempered {}",
+                                election_ping.payload.height
+                            );
                         }
-                        Poll::Ready(None) => {
-                            info!("channel closed !");
-                            return Poll::Ready(1);
+                    } else {
+                        debug!(
+                            "public_keys {:?} key {:?}",
+                            meta_data_locked.public_keys, election_ping.payload.public_key
+                        );
+                        warn!("Election Ping data from malicious node");
+                    }
+                }
+                ConsensusMessageTypes::ConsensusPong(data) => {
+                    let election_pong: ElectionPong = data;
+                    let mut meta_data_locked = meta_data.lock().unwrap();
+                    if hex::encode(meta_data_locked.kp.public().encode())
+                        == election_pong.payload.current_leader
+                    {
+                        if meta_data_locked
+                            .public_keys
+                            .contains(&election_pong.payload.may_be_leader)
+                        {
+                            if election_pong.verify() {
+                                meta_data_locked
+                                    .active_node
+                                    .push(election_pong.payload.may_be_leader.clone());
+                                debug!(
+                                    "Pong message received from  {} for height {} -> ",
+                                    election_pong.payload.may_be_leader,
+                                    election_pong.payload.height,
+                                );
+                            } else {
+                                warn!(
+                                    "Election Ping data tempered {}",
+                                    election_pong.payload.may_be_leader
+                                );
+                            }
+                        } else {
+                            warn!(
+                                "Election Pong data tempered {}",
+                                election_pong.payload.may_be_leader,
+                            );
                         }
-                        Poll::Pending => break,
                     }
                 }
-                Poll::Pending
-            }));
-        });
+            }
+        }
     }
 
-    fn state_machine(
+    /// one step of the state machine, it reads & writes the db so it runs on
+    /// the blocking pool
+    fn step(
         &mut self,
+        leader_map: &Arc<Mutex<LeaderMap>>,
+        meta_data: &Arc<Mutex<MetaData>>,
+        sender: &mut GossipSender,
+    ) -> Step {
+        /*
+        check leader map so you will get least rn and respective leader
+        now check in db the block count.
+        accordingly decide either leader map need cleanup or i'm the leader or other's are leader.
+        if leader_map_least_num < block_count  ---- cleanup
+        else you are the leader or other will be leader
+        */
+        let current_block_chain_length: u64 = {
+            let snapshot = self.context.db.snapshot();
+            let db_snapshot = SchemaSnap::new(&snapshot);
+            db_snapshot.get_blockchain_length()
+        };
+        let (key, value): (u64, String) = match leader_map.lock().unwrap().map.iter().next() {
+            Some((key, value)) => (*key, value.clone()),
+            None => return Step::Wait,
+        };
+        if key < current_block_chain_length {
+            // block already added so no need of previous leader
+            leader_map.lock().unwrap().map.remove(&key);
+            Step::Continue
+        } else if key > current_block_chain_length {
+            // future leader, catch up with its blocks & wait
+            let fork = self.context.db.fork();
+            {
+                let mut schema = SchemaFork::new(&fork);
+                schema.sync_state(&self.context);
+            }
+            self.context.db.patch(fork);
+            Step::Wait
+        } else if value != self.pk {
+            // someone else is the current leader.
+            leader_map.lock().unwrap().map.remove(&key);
+            Step::Continue
+        } else {
+            // i am the leader.
+            info!("I AM THE LEADER NOW!!!");
+            if self.propose(sender, meta_data) {
+                Step::Elect
+            } else {
+                Step::Wait
+            }
+        }
+    }
+
+    /// run the state machine until shutdown, it steps whenever a leader is
+    /// elected and at least every SYNC_INTERVAL
+    async fn state_machine(
+        self,
         leader_map: Arc<Mutex<LeaderMap>>,
         meta_data: Arc<Mutex<MetaData>>,
-        sender: &mut Sender<Option<MessageTypes>>,
+        sender: GossipSender,
+        mut leader_events: Receiver<()>,
+        shutdown: Shutdown,
     ) {
-        loop {
-            /*
-            check leader map so you will get least rn and respective leader
-            now check in db the block count.
-            accordingly decide either leader map need cleanup or i'm the leader or other's are leader.
-            if leader_map_least_num < block_count  ---- cleanup
-            else you are the leader or other will be leader
-            */
-            {
-                let snapshot = snapshot_db();
-                let db_snapshot = SchemaSnap::new(&snapshot);
-                let current_block_chain_length = db_snapshot.get_blockchain_length();
-                let mut leader_map_locked = leader_map.lock().unwrap();
-                if let Some((_key, _value)) = leader_map_locked.map.iter().next() {
-                    let key = _key.clone();
-                    if key < current_block_chain_length {
-                        // block already added so no need of previous leader
-                        leader_map_locked.map.remove(&key);
-                    } else if key > current_block_chain_length {
-                        // future leader
-                        // by pass this for now and wait
-                        let fork = fork_db();
-                        {
-                            let mut schema = SchemaFork::new(&fork);
-                            schema.sync_state();
-                        }
-                        patch_db(fork);
-                    } else {
-                        // current leader
-                        let value = _value.clone();
-                        if value != self.pk {
-                            // someone else is the current leader.
-                            leader_map_locked.map.remove(&key);
-                        } else {
-                            // i am the leader.
-                            info!("I AM THE LEADER NOW!!!");
-                            if self.validator(sender, meta_data.clone()) {
-                                leader_map_locked
-                                    .map
-                                    .insert(self.round_number, self.pk.clone());
-                            }
-                        }
+        let consensus: Arc<Mutex<Consensus>> = Arc::new(Mutex::new(self));
+        while !shutdown.is_triggered() {
+            let step = {
+                let consensus = consensus.clone();
+                let leader_map = leader_map.clone();
+                let meta_data = meta_data.clone();
+                let mut sender = sender.clone();
+                task::spawn_blocking(move || {
+                    consensus
+                        .lock()
+                        .unwrap()
+                        .step(&leader_map, &meta_data, &mut sender)
+                })
+            };
+            match step.await {
+                Ok(Step::Continue) => continue,
+                Ok(Step::Elect) => {
+                    if !shutdown.pause(ELECTION_PING_WAIT).await {
+                        break;
+                    }
+                    let mut consensus_locked = consensus.lock().unwrap();
+                    if consensus_locked.elect_leader(&mut sender.clone(), &meta_data) {
+                        leader_map
+                            .lock()
+                            .unwrap()
+                            .map
+                            .insert(consensus_locked.round_number, consensus_locked.pk.clone());
+                        continue;
                     }
-                } else {
+                }
+                Ok(Step::Wait) => {}
+                Err(error) => {
+                    error!("consensus step failed: {:?}", error);
+                    break;
                 }
             }
-            thread::sleep(Duration::from_millis(2000));
+            // an elected leader or the interval, whichever comes first
+            let leader_elected = leader_events.next();
+            match future::select(leader_elected, Box::pin(shutdown.pause(SYNC_INTERVAL))).await {
+                Either::Left((Some(_), _)) => {}
+                Either::Left((None, _)) => {
+                    shutdown.pause(SYNC_INTERVAL).await;
+                }
+                Either::Right(_) => {}
+            }
         }
+        info!("consensus stopped");
     }
 
-    pub fn init_poa_consensus(
-        config: &Configuration,
-        consensus_file_path: &str,
-        sender: &mut Sender<Option<MessageTypes>>,
-        msg_receiver: Arc<Mutex<Receiver<Option<Vec<u8>>>>>,
+    /// run poa consensus until shutdown, the message receiver included
+    pub async fn init_poa_consensus(
+        context: NodeContext,
+        consensus_file_path: String,
+        sender: GossipSender,
+        shutdown: Shutdown,
     ) {
-        initialize_config(consensus_file_path);
-        let consensus_configuration: &crate::poa_config::Configuration =
-            &crate::poa_config::POA_CONFIG;
+        let consensus_configuration: poa_config::Configuration =
+            match poa_config::Configuration::from_file(&consensus_file_path) {
+                Ok(consensus_configuration) => consensus_configuration,
+                Err(error) => {
+                    error!("{}, consensus not started", error);
+                    return;
+                }
+            };
+        let config = context.config.clone();
+        context
+            .dispatcher
+            .set_consensus_validator(Box::new(validate_consensus_msg));
         let mut consensus_obj = Consensus {
             keypair: config.node.keypair.clone(),
             pk: String::from(""),
             round_number: 0,
             public_keys: consensus_configuration.public_keys.clone(),
             force_sealing: consensus_configuration.force_sealing,
+            context: context.clone(),
         };
         consensus_obj.pk = hex::encode(consensus_obj.keypair.public().encode());
         let leader_map_obj = LeaderMap {
@@ -438,23 +535,50 @@ impl Consensus {
             sender: sender.clone(),
         };
         let meta_data = Arc::new(Mutex::new(consensus_meta_data));
-        Consensus::consensus_msg_receiver(leader_map.clone(), meta_data.clone(), msg_receiver);
-        thread::sleep(Duration::from_millis(5000));
-        if config.node.genesis_block {
-            {
-                let clone_leader_map = leader_map.clone();
-                let mut leader_map_locked = clone_leader_map.lock().unwrap();
-                leader_map_locked.map.insert(0, consensus_obj.pk.clone());
+        // a pending notification covers any number of elections
+        let (leader_notifier, leader_events) = channel::<()>(1);
+        let receiver = Consensus::consensus_msg_receiver(
+            leader_map.clone(),
+            meta_data.clone(),
+            context.dispatcher.consensus_msg_receiver.clone(),
+            leader_notifier,
+            shutdown.clone(),
+        );
+        let engine = async move {
+            if !shutdown.pause(STARTUP_WAIT).await {
+                return;
             }
-            consensus_obj.init_state(&config.db.dbpath, leader_map.clone(), &mut sender.clone());
-        } else {
-            let fork = fork_db();
-            {
-                let mut schema = SchemaFork::new(&fork);
-                schema.sync_state();
+            let genesis_block: bool = config.node.genesis_block;
+            let init_leader_map = leader_map.clone();
+            let mut init_sender = sender.clone();
+            // creating the genesis block or catching up blocks
+            let init = task::spawn_blocking(move || {
+                if genesis_block {
+                    init_leader_map
+                        .lock()
+                        .unwrap()
+                        .map
+                        .insert(0, consensus_obj.pk.clone());
+                    consensus_obj.init_state(init_leader_map, &mut init_sender);
+                } else {
+                    let fork = context.db.fork();
+                    {
+                        let mut schema = SchemaFork::new(&fork);
+                        schema.sync_state(&context);
+                    }
+                    context.db.patch(fork);
+                }
+                consensus_obj
+            });
+            match init.await {
+                Ok(consensus_obj) => {
+                    consensus_obj
+                        .state_machine(leader_map, meta_data, sender, leader_events, shutdown)
+                        .await
+                }
+                Err(error) => error!("consensus couldn't start: {:?}", error),
             }
-            patch_db(fork);
-        }
-        consensus_obj.state_machine(leader_map.clone(), meta_data.clone(), &mut sender.clone());
+        };
+        future::join(receiver, engine).await;
     }
 }
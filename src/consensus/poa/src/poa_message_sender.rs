@@ -3,12 +3,13 @@ use crate::poa_messages::{
 };
 use futures::channel::mpsc::*;
 use message_handler::messages::MessageTypes;
+use message_handler::queues::GossipSender;
 use utils::serializer::serialize;
 
 pub struct ConsensusMessageSender {}
 
 impl ConsensusMessageSender {
-    pub fn send_election_ping_msg(sender: &mut Sender<Option<MessageTypes>>, msg: ElectionPing) {
+    pub fn send_election_ping_msg(sender: &mut GossipSender, msg: ElectionPing) {
         if let Ok(serialize_msg) = serialize(&ConsensusMessageTypes::ConsensusPing(msg)) {
             let data = Some(MessageTypes::ConsensusMsg(serialize_msg));
             let error: Result<(), TrySendError<Option<MessageTypes>>> = sender.try_send(data);
@@ -18,7 +19,7 @@ impl ConsensusMessageSender {
         }
     }
 
-    pub fn send_election_pong_msg(sender: &mut Sender<Option<MessageTypes>>, msg: ElectionPong) {
+    pub fn send_election_pong_msg(sender: &mut GossipSender, msg: ElectionPong) {
         if let Ok(serialize_msg) = serialize(&ConsensusMessageTypes::ConsensusPong(msg)) {
             let data = Some(MessageTypes::ConsensusMsg(serialize_msg));
             let error: Result<(), TrySendError<Option<MessageTypes>>> = sender.try_send(data);
@@ -28,10 +29,7 @@ impl ConsensusMessageSender {
         }
     }
 
-    pub fn send_leader_election_msg(
-        sender: &mut Sender<Option<MessageTypes>>,
-        msg: SignedLeaderElection,
-    ) {
+    pub fn send_leader_election_msg(sender: &mut GossipSender, msg: SignedLeaderElection) {
         if let Ok(serialize_msg) = serialize(&ConsensusMessageTypes::LeaderElect(msg)) {
             let data = Some(MessageTypes::ConsensusMsg(serialize_msg));
             let error: Result<(), TrySendError<Option<MessageTypes>>> = sender.try_send(data);
@@ -1,9 +1,9 @@
-use libp2p::floodsub::Topic;
+use libp2p::gossipsub::Topic;
 use message_handler::constants;
 use message_handler::message_traits::Message;
 use std::hash::{Hash, Hasher};
 use utils::keypair::{CryptoKeypair, Keypair, KeypairType, PublicKey, Verify};
-use utils::serializer::{serialize, Deserialize, Serialize};
+use utils::serializer::{deserialize, serialize, Deserialize, Serialize};
 
 pub const CONSENSUS_MSG_TOPIC_STR: &'static [&'static str] =
     &["LeaderElection", "ElectionPing", "ElectionPong"];
@@ -29,6 +29,21 @@ pub struct SignedLeaderElection {
     pub signature: Vec<u8>,
 }
 
+impl SignedLeaderElection {
+    /// the election is signed by the old leader
+    pub fn verify(&self) -> bool {
+        let ser_payload: Vec<u8> = match serialize(&self.leader_payload) {
+            Result::Ok(value) => value,
+            Result::Err(_) => return false,
+        };
+        PublicKey::verify_from_encoded_pk(
+            &self.leader_payload.old_leader,
+            &ser_payload,
+            &self.signature.as_ref(),
+        )
+    }
+}
+
 impl Message for SignedLeaderElection {
     const TOPIC: &'static str = CONSENSUS_MSG_TOPIC_STR[0];
     const MODULE_TOPIC: &'static str = constants::CONSENSUS;
@@ -169,6 +184,21 @@ impl From<ConsensusMessageTypes> for Topic {
     }
 }
 
+/// gossip validation of poa consensus messages, checks they decode and
+/// carry a valid signature
+pub fn validate_consensus_msg(data: &Vec<u8>) -> Result<(), String> {
+    let valid: bool = match deserialize::<ConsensusMessageTypes>(data.as_slice())? {
+        ConsensusMessageTypes::LeaderElect(data) => data.verify(),
+        ConsensusMessageTypes::ConsensusPing(data) => data.verify(),
+        ConsensusMessageTypes::ConsensusPong(data) => data.verify(),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(String::from("consensus message signature couldn't verify"))
+    }
+}
+
 #[cfg(test)]
 mod consensus_message_test {
 
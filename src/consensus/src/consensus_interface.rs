@@ -1,40 +1,78 @@
 extern crate aura;
+extern crate db_service;
 extern crate message_handler;
-extern crate utils;
 
 use aura::aura_interface;
-use futures::channel::mpsc::*;
-use message_handler::messages::MessageTypes;
+use db_service::node_context::NodeContext;
+use message_handler::queues::GossipSender;
+use message_handler::shutdown::Shutdown;
 use poa::poa_interface;
-use std::sync::{Arc, Mutex};
-use utils::configreader::Configuration;
+
+/// ConsensusEngine is a consensus a validator can run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConsensusEngine {
+    Aura,
+    Poa,
+}
+
+impl ConsensusEngine {
+    /// engine of a consensus_name of config.toml
+    pub fn from_name(name: &str) -> Option<ConsensusEngine> {
+        match name {
+            "aura" => Some(ConsensusEngine::Aura),
+            "poa" => Some(ConsensusEngine::Poa),
+            _ => None,
+        }
+    }
+}
 
 pub struct Consensus {}
 
 impl Consensus {
-    pub fn init_consensus(
-        config: &Configuration,
-        consensus_file_path: &str,
-        sender: &mut Sender<Option<MessageTypes>>,
-        msg_receiver: Arc<Mutex<Receiver<Option<Vec<u8>>>>>,
+    /// run the engine named by the consensus_name of the node config until
+    /// shutdown, Err if the name is unknown
+    pub async fn init_consensus(
+        context: NodeContext,
+        consensus_file_path: String,
+        sender: GossipSender,
+        shutdown: Shutdown,
+    ) -> Result<(), String> {
+        let consensus_name: &String = &context.config.node.consensus_name;
+        let engine: ConsensusEngine = ConsensusEngine::from_name(consensus_name)
+            .ok_or_else(|| format!("unknown consensus {}", consensus_name))?;
+        Consensus::run_engine(engine, context, consensus_file_path, sender, shutdown).await;
+        Ok(())
+    }
+
+    /// run engine on the node of context until shutdown is triggered, the
+    /// returned future ends once the engine & its message receiver stopped
+    /// using the db
+    pub async fn run_engine(
+        engine: ConsensusEngine,
+        context: NodeContext,
+        consensus_file_path: String,
+        sender: GossipSender,
+        shutdown: Shutdown,
     ) {
-        if config.node.consensus_name == "aura" {
-            aura_interface::Aura::init_aura_consensus(
-                config,
-                &consensus_file_path,
-                sender,
-                msg_receiver,
-            );
-        } else if config.node.consensus_name == "poa" {
-            poa_interface::Consensus::init_poa_consensus(
-                config,
-                &consensus_file_path,
-                sender,
-                msg_receiver,
-            );
-        } else {
-            println!("kindly provide predefined consensus name string");
-            std::process::exit(1);
+        match engine {
+            ConsensusEngine::Aura => {
+                aura_interface::Aura::init_aura_consensus(
+                    context,
+                    consensus_file_path,
+                    sender,
+                    shutdown,
+                )
+                .await
+            }
+            ConsensusEngine::Poa => {
+                poa_interface::Consensus::init_poa_consensus(
+                    context,
+                    consensus_file_path,
+                    sender,
+                    shutdown,
+                )
+                .await
+            }
         }
     }
 }
@@ -1,16 +1,23 @@
 extern crate services;
 use actix_rt::System;
-use actix_web::{dev::Server, get, middleware, post, web, App, HttpResponse, HttpServer};
-use futures::channel::mpsc::*;
-use message_handler::messages::MessageTypes;
+use actix_web::{
+    dev::Server, get, middleware, post, web, App, HttpRequest, HttpResponse, HttpServer,
+};
+use db_service::node_context::NodeContext;
+use message_handler::queues::GossipSender;
+use p2plib::peer_manager::PeerManager;
+use services::admin_services::AdminServices;
 use services::client_services::ClientServices;
 use std::net::{SocketAddr, ToSocketAddrs};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
 
 pub trait Controller {
     //fn new() -> Self;
-    fn start_validator_controller(&mut self, sender: Sender<Option<MessageTypes>>) -> bool;
-    fn start_fullnode_controller(&mut self, sender: Sender<Option<MessageTypes>>) -> bool;
+    fn start_validator_controller(&mut self, sender: GossipSender) -> bool;
+    fn start_fullnode_controller(&mut self, sender: GossipSender) -> bool;
+    /// start serving on the actix System which is running already
+    fn run_validator_controller(&mut self, sender: GossipSender) -> bool;
+    fn run_fullnode_controller(&mut self, sender: GossipSender) -> bool;
     fn stop(&self);
 }
 
@@ -18,70 +25,140 @@ pub trait Controller {
 #[post("/client/submit_transaction")]
 async fn submit_transaction_controller(
     transaction: web::Bytes,
-    app_state: web::Data<Mutex<AppState>>,
+    app_state: web::Data<AppState>,
 ) -> HttpResponse {
     // call client service handler
     ClientServices::submit_transaction_service(
+        &app_state.context,
         transaction,
-        &mut app_state.lock().unwrap().txn_sender,
+        &mut app_state.txn_sender.lock().unwrap(),
     )
     // HttpResponse::Ok().body("txn added in the pool")
 }
 
 #[get("/client/fetch_pending_transaction")]
-async fn fetch_pending_transaction_controller(transaction_hash: web::Bytes) -> HttpResponse {
-    ClientServices::fetch_pending_transaction_service(transaction_hash)
+async fn fetch_pending_transaction_controller(
+    transaction_hash: web::Bytes,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    ClientServices::fetch_pending_transaction_service(&app_state.context, transaction_hash)
 }
 
 #[get("/client/fetch_confirm_transaction")]
-async fn fetch_confirm_transaction_controller(transaction_hash: web::Bytes) -> HttpResponse {
-    ClientServices::fetch_confirm_transaction_service(transaction_hash)
+async fn fetch_confirm_transaction_controller(
+    transaction_hash: web::Bytes,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    ClientServices::fetch_confirm_transaction_service(&app_state.context, transaction_hash)
 }
 
 #[get("/client/fetch_state")]
-async fn fetch_state_controller(address: web::Bytes) -> HttpResponse {
-    ClientServices::fetch_state_service(address)
+async fn fetch_state_controller(
+    address: web::Bytes,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    ClientServices::fetch_state_service(&app_state.context, address)
 }
 
-#[get("/peer/fetch_block")]
-async fn fetch_block_peer_controller(address: web::Bytes) -> HttpResponse {
-    ClientServices::fetch_block_peer_service(address)
+#[get("/client/fetch_app_state/{app_name}")]
+async fn fetch_app_state_controller(
+    app_name: web::Path<String>,
+    key: web::Bytes,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    ClientServices::fetch_app_state_service(&app_state.context, &app_name, key)
 }
 
-#[get("/peer/fetch_latest_block")]
-async fn fetch_latest_block_peer_controller() -> HttpResponse {
-    ClientServices::fetch_latest_block_peer_service()
+#[get("/client/query/{app_name}")]
+async fn query_controller(
+    app_name: web::Path<String>,
+    query: web::Bytes,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    ClientServices::query_service(&app_state.context, &app_name, query)
 }
 
 #[get("/client/fetch_block")]
-async fn fetch_block_controller(address: web::Bytes) -> HttpResponse {
-    ClientServices::fetch_block_service(address)
+async fn fetch_block_controller(
+    address: web::Bytes,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    ClientServices::fetch_block_service(&app_state.context, address)
 }
 
 #[get("/client/fetch_latest_block")]
-async fn fetch_latest_block_controller() -> HttpResponse {
-    ClientServices::fetch_latest_block_service()
+async fn fetch_latest_block_controller(app_state: web::Data<AppState>) -> HttpResponse {
+    ClientServices::fetch_latest_block_service(&app_state.context)
 }
 
-#[get("/peer/fetch_blockchain_length")]
-async fn fetch_blockchain_length_peer_controller() -> HttpResponse {
-    ClientServices::fetch_blockchain_length_peer_service()
+#[get("/client/fetch_blockchain_length")]
+async fn fetch_blockchain_length_controller(app_state: web::Data<AppState>) -> HttpResponse {
+    ClientServices::fetch_blockchain_length_service(&app_state.context)
 }
 
-#[get("/client/fetch_blockchain_length")]
-async fn fetch_blockchain_length_controller() -> HttpResponse {
-    ClientServices::fetch_blockchain_length_service()
+/// admin endpoints only answer requests of the node's own host
+fn is_local_request(request: &HttpRequest) -> bool {
+    match request.peer_addr() {
+        Some(addr) => addr.ip().is_loopback(),
+        None => false,
+    }
+}
+
+#[get("/admin/peers")]
+async fn list_peers_controller(
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    if !is_local_request(&request) {
+        return HttpResponse::Forbidden().body("admin api is only served locally");
+    }
+    AdminServices::list_peers_service(&app_state.peer_manager)
+}
+
+#[get("/admin/queues")]
+async fn list_queues_controller(
+    request: HttpRequest,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    if !is_local_request(&request) {
+        return HttpResponse::Forbidden().body("admin api is only served locally");
+    }
+    AdminServices::list_queues_service(&app_state.context.queue_metrics)
+}
+
+#[post("/admin/ban_peer")]
+async fn ban_peer_controller(
+    request: HttpRequest,
+    peer_id: web::Bytes,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    if !is_local_request(&request) {
+        return HttpResponse::Forbidden().body("admin api is only served locally");
+    }
+    AdminServices::ban_peer_service(
+        &app_state.peer_manager,
+        app_state.context.config.node.ban_duration,
+        peer_id,
+    )
 }
 
-#[get("/peer/fetch_transaction")]
-async fn fetch_transaction_peer_controller(transaction_hash: web::Bytes) -> HttpResponse {
-    ClientServices::fetch_transaction_peer_service(transaction_hash)
+#[post("/admin/unban_peer")]
+async fn unban_peer_controller(
+    request: HttpRequest,
+    peer_id: web::Bytes,
+    app_state: web::Data<AppState>,
+) -> HttpResponse {
+    if !is_local_request(&request) {
+        return HttpResponse::Forbidden().body("admin api is only served locally");
+    }
+    AdminServices::unban_peer_service(&app_state.peer_manager, peer_id)
 }
 
-//May be useful for storing forwarding/db_layer related info
-#[allow(dead_code)]
+/// the node state the routes answer from
 struct AppState {
-    txn_sender: Sender<Option<MessageTypes>>,
+    txn_sender: Mutex<GossipSender>,
+    context: NodeContext,
+    peer_manager: Arc<Mutex<PeerManager>>,
 }
 
 // pub static mut APP_STATE: AppState = AppState{ txn_sender: None};
@@ -89,10 +166,17 @@ struct AppState {
 pub struct ClientController {
     srvr_addr: SocketAddr,
     srvr: Option<Server>,
+    context: NodeContext,
+    peer_manager: Arc<Mutex<PeerManager>>,
 }
 
 impl ClientController {
-    pub fn new(host: &String, port: u32) -> Self {
+    pub fn new(
+        host: &String,
+        port: u32,
+        context: NodeContext,
+        peer_manager: Arc<Mutex<PeerManager>>,
+    ) -> Self {
         //to enable logging use below setting
         std::env::set_var("RUST_LOG", "actix_web=info,actix_server=trace");
         let addr = format!("{}:{}", host, port)
@@ -103,75 +187,110 @@ impl ClientController {
         ClientController {
             srvr_addr: addr,
             srvr: None,
+            context,
+            peer_manager,
         }
     }
 }
 
-impl Controller for ClientController {
-    fn start_validator_controller(&mut self, sender: Sender<Option<MessageTypes>>) -> bool {
-        let sys = System::new("TransactionService");
+/// routes every node serves
+fn node_routes(config: &mut web::ServiceConfig) {
+    config
+        .service(fetch_confirm_transaction_controller)
+        .service(fetch_state_controller)
+        .service(fetch_app_state_controller)
+        .service(query_controller)
+        .service(fetch_block_controller)
+        .service(fetch_latest_block_controller)
+        .service(fetch_blockchain_length_controller)
+        .service(list_peers_controller)
+        .service(list_queues_controller)
+        .service(ban_peer_controller)
+        .service(unban_peer_controller);
+}
+
+/// routes of validators, which take transactions of clients
+fn validator_routes(config: &mut web::ServiceConfig) {
+    config
+        .service(submit_transaction_controller)
+        .service(fetch_pending_transaction_controller);
+}
+
+impl ClientController {
+    /// the running server, stop(true) on it finishes the open requests first
+    pub fn server(&self) -> Option<Server> {
+        self.srvr.clone()
+    }
+
+    /// serve the node routes, and the validator ones for a validator, on the
+    /// actix System which is running already
+    fn run_controller(&mut self, sender: GossipSender, validator: bool) -> bool {
         info!("Starting api_service at {:?}", self.srvr_addr);
-        let app_data = web::Data::new(Mutex::new(AppState { txn_sender: sender }));
-        self.srvr = Some(
-            HttpServer::new(move || {
-                App::new()
-                    .app_data(app_data.clone())
-                    // enable logger
-                    .wrap(middleware::Logger::default())
-                    //.service(web::resource("/index.html").to(|| async { "Hello world!" }))
-                    .service(submit_transaction_controller)
-                    .service(fetch_confirm_transaction_controller)
-                    .service(fetch_pending_transaction_controller)
-                    .service(fetch_state_controller)
-                    .service(fetch_block_controller)
-                    .service(fetch_latest_block_controller)
-                    .service(fetch_block_peer_controller)
-                    .service(fetch_latest_block_peer_controller)
-                    .service(fetch_blockchain_length_peer_controller)
-                    .service(fetch_blockchain_length_controller)
-                    .service(fetch_transaction_peer_controller)
-            })
-            .bind(self.srvr_addr)
-            .unwrap()
-            .shutdown_timeout(5)
-            .run(),
-        );
-
-        if let Err(_) = sys.run() {
-            panic!("not able to start server on port 8089");
+        let max_message_size: usize = self.context.config.block_config.max_message_size as usize;
+        let app_data = web::Data::new(AppState {
+            txn_sender: Mutex::new(sender),
+            context: self.context.clone(),
+            peer_manager: self.peer_manager.clone(),
+        });
+        let server = HttpServer::new(move || {
+            App::new()
+                .app_data(app_data.clone())
+                // request bodies beyond max_message_size are refused unread
+                .app_data(web::PayloadConfig::new(max_message_size))
+                // enable logger
+                .wrap(middleware::Logger::default())
+                .configure(node_routes)
+                .configure(|config: &mut web::ServiceConfig| {
+                    if validator {
+                        validator_routes(config)
+                    }
+                })
+        })
+        // the node stops the server as part of its shutdown
+        .disable_signals()
+        .shutdown_timeout(5)
+        .bind(self.srvr_addr);
+        match server {
+            Ok(server) => {
+                self.srvr = Some(server.run());
+                true
+            }
+            Err(error) => {
+                error!("api_service couldn't bind {:?}: {}", self.srvr_addr, error);
+                false
+            }
         }
-        true
     }
 
-    fn start_fullnode_controller(&mut self, sender: Sender<Option<MessageTypes>>) -> bool {
+    /// run the controller on an actix System of its own until it stops
+    fn start_controller(&mut self, sender: GossipSender, validator: bool) -> bool {
         let sys = System::new("TransactionService");
-        info!("Starting api_service at {:?}", self.srvr_addr);
-        let app_data = web::Data::new(Mutex::new(AppState { txn_sender: sender }));
-        self.srvr = Some(
-            HttpServer::new(move || {
-                App::new()
-                    .app_data(app_data.clone())
-                    // enable logger
-                    .wrap(middleware::Logger::default())
-                    //.service(web::resource("/index.html").to(|| async { "Hello world!" }))
-                    .service(fetch_confirm_transaction_controller)
-                    .service(fetch_state_controller)
-                    .service(fetch_block_controller)
-                    .service(fetch_latest_block_controller)
-                    .service(fetch_block_peer_controller)
-                    .service(fetch_latest_block_peer_controller)
-                    .service(fetch_blockchain_length_peer_controller)
-                    .service(fetch_blockchain_length_controller)
-            })
-            .bind(self.srvr_addr)
-            .unwrap()
-            .shutdown_timeout(5)
-            .run(),
-        );
-
-        let _ = sys.run();
+        if !self.run_controller(sender, validator) {
+            return false;
+        }
+        if let Err(error) = sys.run() {
+            error!("api_service at {:?} failed: {}", self.srvr_addr, error);
+        }
         true
     }
+}
+
+impl Controller for ClientController {
+    fn start_validator_controller(&mut self, sender: GossipSender) -> bool {
+        self.start_controller(sender, true)
+    }
+
+    fn start_fullnode_controller(&mut self, sender: GossipSender) -> bool {
+        self.start_controller(sender, false)
+    }
+
+    fn run_validator_controller(&mut self, sender: GossipSender) -> bool {
+        self.run_controller(sender, true)
+    }
+
+    fn run_fullnode_controller(&mut self, sender: GossipSender) -> bool {
+        self.run_controller(sender, false)
+    }
 
     fn stop(&self) {
         System::new("").block_on(self.srvr.as_ref().unwrap().stop(true));
@@ -2,27 +2,25 @@
 mod test_controller_services {
     use crate::client_controller::*;
     use client::client::ClientObj;
-    use db_service::{
-        db_fork_ref::SchemaFork,
-        db_layer::{fork_db, patch_db},
-    };
+    use db_service::{db_fork_ref::SchemaFork, db_layer::Db, node_context::NodeContext};
     use exonum_crypto::Hash;
     use exonum_merkledb::ObjectHash;
-    use futures::channel::mpsc::*;
     use libp2p::core::{Multiaddr, PeerId};
     use libp2p::identity::PublicKey;
-    use message_handler::messages::MessageTypes;
+    use message_handler::queues::gossip_channel;
+    use p2plib::peer_manager::PeerManager;
+    use schema::appdata::AppData;
     use schema::signed_transaction::SignedTransaction;
     use schema::state::State;
-    use schema::transaction_pool::{TxnPool, TxnPoolKeyType, POOL};
-    use sdk::traits::StateContext;
+    use schema::transaction_pool::TxnPoolKeyType;
+    use sdk::testing::test_keypair;
+    use sdk::traits::{ReadOnlyStateContext, StateContext};
     use std::collections::HashMap;
     use std::net::IpAddr;
     use std::net::Ipv4Addr;
+    use std::sync::{Arc, Mutex};
     use std::time::SystemTime;
     use std::{thread, time::Duration};
-    use utils::configreader;
-    use utils::configreader::initialize_config;
     use utils::configreader::Configuration;
     use utils::global_peer_data::*;
     use utils::serializer::serialize;
@@ -31,12 +29,8 @@ mod test_controller_services {
         let mut header = HashMap::default();
         let time_stamp: TxnPoolKeyType = 6565656565;
         header.insert("timestamp".to_string(), time_stamp.to_string());
-        let signed_transaction: SignedTransaction = SignedTransaction {
-            txn: vec![0],
-            app_name: String::from("app_name"),
-            header,
-            signature: vec![0],
-        };
+        let signed_transaction: SignedTransaction =
+            SignedTransaction::create(&test_keypair(), "app_name", header, vec![0]);
         let transaction: Vec<u8> = serialize(&signed_transaction).unwrap();
         match client.submit_transaction(transaction) {
             Ok(value) => {
@@ -48,16 +42,12 @@ mod test_controller_services {
         };
     }
 
-    fn test_fetch_pending_transaction_controller(client: &ClientObj, pk: String) {
+    fn test_fetch_pending_transaction_controller(client: &ClientObj) {
         let time_stamp: TxnPoolKeyType = 6565656565;
         let mut header = HashMap::default();
         header.insert("timestamp".to_string(), time_stamp.to_string());
-        let signed_transaction: SignedTransaction = SignedTransaction {
-            txn: vec![0],
-            app_name: String::from("app_name"),
-            header,
-            signature: vec![0],
-        };
+        let signed_transaction: SignedTransaction =
+            SignedTransaction::create(&test_keypair(), "app_name", header, vec![0]);
         let txn_hash: Hash = signed_transaction.object_hash();
         match client.fetch_pending_transaction(&txn_hash) {
             Ok(is_value) => {
@@ -67,42 +57,26 @@ mod test_controller_services {
             }
             Err(_) => panic!("http_response not equal to 200"),
         };
-        let url = String::from("http://127.0.0.1:8089/");
-        let transaction_hash_vec: Vec<Hash> = vec![txn_hash];
-        assert_eq!(client.sync_txn_pool(pk, &transaction_hash_vec), true);
-        assert_eq!(POOL.get(&txn_hash).unwrap(), signed_transaction);
-        match client.fetch_transaction(&url, &txn_hash) {
-            Ok(is_value) => {
-                if None == is_value {
-                    panic!("error in fetch pending transaction process");
-                }
-            }
-            Err(_) => panic!("http_response not equal to 200"),
-        };
     }
 
-    fn test_fetch_confirm_transaction_controller(client: &ClientObj) {
+    fn test_fetch_confirm_transaction_controller(context: &NodeContext, client: &ClientObj) {
         let mut header = HashMap::default();
         let time_stamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
             .as_micros();
         header.insert("timestamp".to_string(), time_stamp.to_string());
-        let signed_transaction: SignedTransaction = SignedTransaction {
-            txn: vec![0],
-            app_name: String::from("app_name"),
-            header,
-            signature: vec![0],
-        };
+        let signed_transaction: SignedTransaction =
+            SignedTransaction::create(&test_keypair(), "app_name", header, vec![0]);
         let txn_hash: Hash = signed_transaction.object_hash();
-        let fork = fork_db();
+        let fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
             if !schema.contains_txn(&txn_hash) {
                 schema.put_txn(&txn_hash, signed_transaction.clone());
             }
         }
-        patch_db(fork);
+        context.db.patch(fork);
         match client.fetch_confirm_transaction(&txn_hash) {
             Ok(is_value) => {
                 if None == is_value {
@@ -111,28 +85,19 @@ mod test_controller_services {
             }
             Err(_) => panic!("http_response not equal to 200"),
         };
-        let url = String::from("http://127.0.0.1:8089/");
-        match client.fetch_transaction(&url, &txn_hash) {
-            Ok(is_value) => {
-                if None == is_value {
-                    panic!("error in fetch pending transaction process");
-                }
-            }
-            Err(_) => panic!("http_response not equal to 200"),
-        };
     }
 
-    fn test_fetch_state_controller(client: &ClientObj) {
+    fn test_fetch_state_controller(context: &NodeContext, client: &ClientObj) {
         let state_key: String = String::from("dkcnjsdcnosdvnvsfv");
         let state: State = State::new();
-        let fork = fork_db();
+        let fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
             if !schema.contains(&state_key) {
                 schema.put(&state_key, state.clone());
             }
         }
-        patch_db(fork);
+        context.db.patch(fork);
         match client.fetch_state(&state_key) {
             Ok(is_value) => {
                 if None == is_value {
@@ -143,13 +108,13 @@ mod test_controller_services {
         };
     }
 
-    fn test_fetch_block_controller(client: &ClientObj) {
-        let fork = fork_db();
+    fn test_fetch_block_controller(context: &NodeContext, client: &ClientObj) {
+        let fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
-            schema.initialize_db(Vec::new(), 0);
+            schema.initialize_db(context, Vec::new(), 0);
         }
-        patch_db(fork);
+        context.db.patch(fork);
         match client.fetch_block(&0) {
             Ok(is_value) => {
                 if None == is_value {
@@ -180,31 +145,34 @@ mod test_controller_services {
 
     #[test]
     fn test_controller_functionality() {
-        initialize_config("../../config.toml");
-        let config: &Configuration = &configreader::GLOBAL_CONFIG;
-        let pk: PublicKey = PublicKey::Ed25519(config.node.public.clone());
+        let config = Configuration::from_file("../../config.toml").unwrap();
+        let db = Db::open(&config.db.dbpath).unwrap();
+        let peer_manager = Arc::new(Mutex::new(PeerManager::from_config(&config.node)));
+        let context = NodeContext::new(Arc::new(config), db, AppData::new());
+        let pk: PublicKey = PublicKey::Ed25519(context.config.node.public.clone());
         let peer_id = PeerId::from_public_key(pk).to_string();
         let time_stamp: u128 = 123445;
         let addr: Multiaddr = Multiaddr::from(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)));
         let id: PeerId = PeerId::random();
         let peer_data: PeerData = PeerData::new(id, time_stamp, addr);
-        {
-            GLOBALDATA.lock().unwrap().peers.insert(peer_id, peer_data);
-        }
-        let (sender, _) = channel::<Option<MessageTypes>>(4194304);
+        let peers = Arc::new(Mutex::new(GlobalData::new()));
+        peers.lock().unwrap().peers.insert(peer_id, peer_data);
+        // submitted transactions are refused once nothing receives the gossip
+        let (sender, _receiver) = gossip_channel(&context.queue_metrics);
         let host: String = String::from("127.0.0.1");
-        let mut client_controller: ClientController = ClientController::new(&host, 8089);
+        let mut client_controller: ClientController =
+            ClientController::new(&host, 8089, context.clone(), peer_manager);
         thread::spawn(move || {
             client_controller.start_validator_controller(sender);
         });
         println!("thread for server started");
         thread::sleep(Duration::from_millis(2000));
-        let client: ClientObj = ClientObj::new();
+        let client: ClientObj = ClientObj::new(peers);
         test_submit_transaction_controller(&client);
-        test_fetch_pending_transaction_controller(&client, config.node.hex_public.clone());
-        test_fetch_confirm_transaction_controller(&client);
-        test_fetch_state_controller(&client);
-        test_fetch_block_controller(&client);
+        test_fetch_pending_transaction_controller(&client);
+        test_fetch_confirm_transaction_controller(&context, &client);
+        test_fetch_state_controller(&context, &client);
+        test_fetch_block_controller(&context, &client);
         test_fetch_latest_block_controller(&client);
         test_fetch_blockchain_length_controller(&client);
         std::process::exit(0);
@@ -2,25 +2,34 @@ extern crate client;
 extern crate schema;
 extern crate utils;
 
-use super::db_layer::fork_db;
-use client::client::{ClientObj, SyncState};
+use super::node_context::NodeContext;
+use client::peer_client::{PeerClient, SyncState};
 use exonum_crypto::Hash;
 use exonum_derive::FromAccess;
 use exonum_merkledb::{
     access::{Access, FromAccess, RawAccessMut},
-    Fork, ListIndex, ObjectHash, ProofMapIndex,
+    Fork, Group, KeySetIndex, ListIndex, ObjectHash, ProofMapIndex,
 };
+use schema::app_registry::{get_registry, put_registry, AppRegistryState, REGISTRY_APP_NAME};
+use schema::appdata::AppData;
 use schema::block::{Block, BlockTraits, SignedBlock};
+use schema::checkpoint::Checkpoint;
 use schema::signed_transaction::SignedTransaction;
 use schema::state::State;
 use schema::transaction_pool::{
-    TransactionPool, TransactionPoolTraits, TxnPool, TxnPoolKeyType, POOL,
+    TransactionPool, TransactionPoolTraits, TxnExecutor, TxnPool, TxnPoolKeyType,
 };
-use sdk::traits::{PoolTrait, StateContext};
+use sdk::abi::ABI_ERROR_CODE;
+use sdk::block_context::BlockContext;
+use sdk::errors::AppError;
+use sdk::traits::{AppHandler, AppStorage, PoolTrait, ReadOnlyStateContext, StateContext};
+use std::collections::{BTreeMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Mutex;
 use std::time::SystemTime;
-use utils::configreader;
 use utils::configreader::BlockConfig;
 use utils::keypair::{CryptoKeypair, Keypair, KeypairType};
+use utils::peer_reports::{Offence, PeerReports};
 
 #[derive(FromAccess)]
 pub struct SchemaFork<T: Access> {
@@ -28,8 +37,26 @@ pub struct SchemaFork<T: Access> {
     block_list: ListIndex<T::Base, SignedBlock>,
     state_trie: ProofMapIndex<T::Base, String, State>,
     storage_trie: ProofMapIndex<T::Base, Hash, SignedTransaction>,
+    // APP_NAME => namespaced state of the app, its root is kept in state_trie
+    app_state: Group<T, String, ProofMapIndex<T::Base, String, State>>,
+    // APP_NAME => ordered keys of app_state, ProofMapIndex iterates in key hash order
+    app_keys: Group<T, String, KeySetIndex<T::Base, String>>,
 }
 
+/// state_trie key under which the root of an app namespace is committed
+pub fn app_root_key(app_name: &String) -> String {
+    format!("app_root/{}", app_name)
+}
+
+/// state_trie key marking that app_name migrated its legacy state
+pub fn legacy_migrated_key(app_name: &String) -> String {
+    format!("legacy_migrated/{}", app_name)
+}
+
+/// prefixes of the state_trie keys written by the node, the other keys of
+/// state_trie were written by apps before app namespaces existed
+const NODE_KEY_PREFIXES: [&str; 3] = ["app_root/", "legacy_migrated/", "txn_status/"];
+
 impl<T: Access> SchemaFork<T> {
     pub fn new(access: T) -> Self {
         match Self::from_root(access) {
@@ -42,21 +69,27 @@ impl<T: Access> SchemaFork<T> {
     }
 }
 
-impl<T: Access> StateContext for SchemaFork<T>
+impl<T: Access> ReadOnlyStateContext for SchemaFork<T>
 where
     T::Base: RawAccessMut,
 {
-    fn put(&mut self, key: &String, state: State) {
-        self.state_trie.put(key, state);
-    }
     fn get(&self, key: &String) -> Option<State> {
         self.state_trie.get(key)
     }
     fn contains(&self, key: &String) -> bool {
         self.state_trie.contains(key)
     }
-    fn put_txn(&mut self, key: &Hash, txn: SignedTransaction) {
-        self.txn_trie.put(key, txn);
+    /// state_trie keys are hashed, so the range is collected by a full scan.
+    /// Only node level code uses the global context, apps get app_context.
+    fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+        let mut entries: Vec<(String, State)> = self
+            .state_trie
+            .iter()
+            .filter(|(key, _)| key >= start && (end.is_empty() || key < end))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        entries.truncate(limit);
+        entries
     }
     fn get_txn(&self, key: &Hash) -> Option<SignedTransaction> {
         self.txn_trie.get(key)
@@ -66,6 +99,284 @@ where
     }
 }
 
+impl<T: Access> StateContext for SchemaFork<T>
+where
+    T::Base: RawAccessMut,
+{
+    fn put(&mut self, key: &String, state: State) {
+        self.state_trie.put(key, state);
+    }
+    fn delete(&mut self, key: &String) {
+        self.state_trie.remove(key);
+    }
+    fn put_txn(&mut self, key: &Hash, txn: SignedTransaction) {
+        self.txn_trie.put(key, txn);
+    }
+}
+
+impl<T: Access> AppStorage for SchemaFork<T>
+where
+    T::Base: RawAccessMut,
+{
+    fn app_context(&mut self, app_name: &String) -> Box<dyn StateContext + '_> {
+        Box::new(AppStateContext {
+            schema: self,
+            app_name: app_name.clone(),
+        })
+    }
+}
+
+/// AppStateContext is the StateContext handed to an app. Keys are read,
+/// written & deleted in the app namespace only and every write commits the
+/// namespace root into state_trie under app_root_key, as storage_root of that
+/// State. Keys written to state_trie before namespaces existed aren't visible,
+/// apps move them over in AppHandler::migrate_legacy_state.
+pub struct AppStateContext<'a, T: Access>
+where
+    T::Base: RawAccessMut,
+{
+    schema: &'a mut SchemaFork<T>,
+    app_name: String,
+}
+
+impl<'a, T: Access> AppStateContext<'a, T>
+where
+    T::Base: RawAccessMut,
+{
+    fn states(&self) -> ProofMapIndex<T::Base, String, State> {
+        self.schema.app_state.get(&self.app_name)
+    }
+
+    fn keys(&self) -> KeySetIndex<T::Base, String> {
+        self.schema.app_keys.get(&self.app_name)
+    }
+
+    fn commit_root(&mut self) {
+        let mut root: State = State::new();
+        root.set_storage_root(self.states().object_hash());
+        self.schema
+            .state_trie
+            .put(&app_root_key(&self.app_name), root);
+    }
+}
+
+impl<'a, T: Access> ReadOnlyStateContext for AppStateContext<'a, T>
+where
+    T::Base: RawAccessMut,
+{
+    fn get(&self, key: &String) -> Option<State> {
+        self.states().get(key)
+    }
+    fn contains(&self, key: &String) -> bool {
+        self.states().contains(key)
+    }
+    fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+        let states = self.states();
+        self.keys()
+            .iter_from(start)
+            .take_while(|key| end.is_empty() || key < end)
+            .take(limit)
+            .filter_map(|key| states.get(&key).map(|state| (key, state)))
+            .collect()
+    }
+    fn get_txn(&self, key: &Hash) -> Option<SignedTransaction> {
+        self.schema.txn_trie.get(key)
+    }
+    fn contains_txn(&self, key: &Hash) -> bool {
+        self.schema.txn_trie.contains(key)
+    }
+}
+
+impl<'a, T: Access> StateContext for AppStateContext<'a, T>
+where
+    T::Base: RawAccessMut,
+{
+    fn put(&mut self, key: &String, state: State) {
+        self.states().put(key, state);
+        self.keys().insert(key);
+        self.commit_root();
+    }
+    fn delete(&mut self, key: &String) {
+        self.states().remove(key);
+        self.keys().remove(key);
+        self.commit_root();
+    }
+    fn put_txn(&mut self, key: &Hash, txn: SignedTransaction) {
+        self.schema.txn_trie.put(key, txn);
+    }
+}
+
+/// LegacyStates is the legacy StateContext of AppHandler::migrate_legacy_state,
+/// a copy of the app keys of state_trie. Deleting a key takes it over, the
+/// taken keys are removed from state_trie once the migration succeeded.
+pub struct LegacyStates {
+    states: BTreeMap<String, State>,
+    taken: Vec<String>,
+}
+
+impl LegacyStates {
+    pub fn new(states: BTreeMap<String, State>) -> LegacyStates {
+        LegacyStates {
+            states,
+            taken: Vec::new(),
+        }
+    }
+}
+
+impl ReadOnlyStateContext for LegacyStates {
+    fn get(&self, key: &String) -> Option<State> {
+        self.states.get(key).cloned()
+    }
+    fn contains(&self, key: &String) -> bool {
+        self.states.contains_key(key)
+    }
+    fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+        self.states
+            .range(start.clone()..)
+            .take_while(|(key, _)| end.is_empty() || *key < end)
+            .take(limit)
+            .map(|(key, state)| (key.clone(), state.clone()))
+            .collect()
+    }
+    fn get_txn(&self, _key: &Hash) -> Option<SignedTransaction> {
+        None
+    }
+    fn contains_txn(&self, _key: &Hash) -> bool {
+        false
+    }
+}
+
+impl StateContext for LegacyStates {
+    fn put(&mut self, key: &String, _state: State) {
+        warn!("write to legacy key {:?} ignored", key);
+    }
+    fn delete(&mut self, key: &String) {
+        if self.states.remove(key).is_some() {
+            self.taken.push(key.clone());
+        }
+    }
+    fn put_txn(&mut self, _key: &Hash, _txn: SignedTransaction) {}
+}
+
+/// state roots after each txn of txn_hashes and after finalize_block, when
+/// the block is executed on a fresh fork of the committed state
+fn replay_block(
+    context: &NodeContext,
+    txn_pool: &TransactionPool,
+    txn_hashes: &Vec<Hash>,
+    block_context: &BlockContext,
+) -> Vec<Hash> {
+    let fork: Fork = context.db.fork();
+    let mut schema = SchemaFork::new(&fork);
+    schema.migrate_legacy_state(context);
+    let mut state_roots: Vec<Hash> = Vec::with_capacity(txn_hashes.len() + 1);
+    for txn_hash in txn_hashes.iter() {
+        schema.update_transactions(context, txn_pool, &vec![*txn_hash], block_context);
+        state_roots.push(schema.state_trie_merkle_hash());
+    }
+    context
+        .apps
+        .lock()
+        .unwrap()
+        .finalize_block(&block_context.author, &mut schema as &mut dyn AppStorage);
+    state_roots.push(schema.state_trie_merkle_hash());
+    state_roots
+}
+
+/// determinism test mode (determinism_check of config.toml): execute the
+/// block twice on fresh forks and compare the state root after every txn,
+/// the first txn leading to different roots is reported
+pub fn check_determinism(
+    context: &NodeContext,
+    txn_pool: &TransactionPool,
+    txn_hashes: &Vec<Hash>,
+    block_context: &BlockContext,
+) -> Result<(), String> {
+    let first_run: Vec<Hash> = replay_block(context, txn_pool, txn_hashes, block_context);
+    let second_run: Vec<Hash> = replay_block(context, txn_pool, txn_hashes, block_context);
+    for (index, (first_root, second_root)) in first_run.iter().zip(second_run.iter()).enumerate() {
+        if first_root != second_root {
+            return Err(match txn_hashes.get(index) {
+                Some(txn_hash) => format!(
+                    "transaction {:?} of block {} isn't deterministic",
+                    txn_hash, block_context.height
+                ),
+                None => format!(
+                    "finalize_block of block {} isn't deterministic",
+                    block_context.height
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// a block can't be older than the previous block nor more than
+/// max_block_time_drift ahead of the local clock, apps rely on its timestamp
+pub fn check_block_timestamp(
+    block_config: &BlockConfig,
+    previous: u128,
+    timestamp: u128,
+) -> Result<(), String> {
+    if timestamp < previous {
+        return Err(format!(
+            "block timestamp {} is before the previous block timestamp {}",
+            timestamp, previous
+        ));
+    }
+    let current_timestamp: u128 = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_micros();
+    if timestamp > current_timestamp + block_config.max_block_time_drift {
+        return Err(format!(
+            "block timestamp {} is too far ahead of the local time {}",
+            timestamp, current_timestamp
+        ));
+    }
+    Ok(())
+}
+
+/// checks of the block transactions which only depend on the block itself:
+/// every txn is signed, within the size limits and in the block once
+pub fn check_block_txns(
+    block_config: &BlockConfig,
+    txn_pool: &TransactionPool,
+    txn_hashes: &Vec<Hash>,
+) -> Result<(), String> {
+    let mut seen: HashSet<&Hash> = HashSet::with_capacity(txn_hashes.len());
+    for txn_hash in txn_hashes.iter() {
+        if !seen.insert(txn_hash) {
+            return Err(format!("transaction {:?} is twice in the block", txn_hash));
+        }
+        let txn: SignedTransaction = match txn_pool.get(txn_hash) {
+            Some(txn) => txn,
+            None => return Err(format!("transaction {:?} is missing", txn_hash)),
+        };
+        if !txn.verify() {
+            return Err(format!(
+                "transaction {:?} has an invalid signature",
+                txn_hash
+            ));
+        }
+        txn.check_size(block_config)
+            .map_err(|error| format!("transaction {:?} is too large: {}", txn_hash, error))?;
+    }
+    Ok(())
+}
+
+/// blame the author of a signed block failing check_block_txns. Failures
+/// which depend on our own state or apps, like execution or header roots,
+/// aren't reported: a node with a wrong app would ban honest authors.
+/// Blocks that merely don't extend our chain or whose signature fails
+/// aren't reported either.
+fn report_block_author(peer_reports: &Mutex<PeerReports>, signed_block: &SignedBlock) {
+    peer_reports
+        .lock()
+        .unwrap()
+        .report_public_key(&signed_block.block.peer_id, Offence::InvalidBlock);
+}
+
 impl<T: Access> SchemaFork<T>
 where
     T::Base: RawAccessMut,
@@ -86,11 +397,29 @@ where
         self.block_list.len()
     }
 
-    pub fn initialize_db(&mut self, custom_headers: Vec<u8>, timestamp: u128) -> SignedBlock {
+    pub fn initialize_db(
+        &mut self,
+        context: &NodeContext,
+        custom_headers: Vec<u8>,
+        timestamp: u128,
+    ) -> SignedBlock {
+        let registry_app: String = String::from(REGISTRY_APP_NAME);
+        for app_name in context
+            .apps
+            .lock()
+            .unwrap()
+            .appdata
+            .keys()
+            .chain(std::iter::once(&registry_app))
+        {
+            self.app_state.get(app_name).clear();
+            self.app_keys.get(app_name).clear();
+        }
         self.state_trie.clear();
         self.txn_trie.clear();
         self.storage_trie.clear();
         self.block_list.clear();
+        self.init_genesis_state(context);
         let mut block = Block::genesis_block(custom_headers, timestamp);
         block.header[0] = self.state_trie_merkle_hash();
         block.header[1] = self.storage_trie_merkle_hash();
@@ -102,46 +431,203 @@ where
         return genesis_block;
     }
 
+    /// genesis state: the app registry enabling the loaded apps and the state
+    /// each app writes in init_genesis, in app name order. Every node builds
+    /// its own, so a node loading other apps or genesis values than the
+    /// genesis node ends up with another genesis state root and rejects the block.
+    fn init_genesis_state(&mut self, context: &NodeContext) {
+        self.seed_app_registry(context);
+        let appdata = context.apps.lock().unwrap();
+        let mut app_names: Vec<&String> = appdata.appdata.keys().collect();
+        app_names.sort();
+        for app_name in app_names {
+            if let Some(app) = appdata.appdata.get(app_name) {
+                let app = app.lock().unwrap();
+                let mut app_context = self.app_context(app_name);
+                let mut checkpoint = Checkpoint::new(app_context.as_mut());
+                match panic::catch_unwind(AssertUnwindSafe(|| app.init_genesis(&mut checkpoint))) {
+                    Result::Ok(Result::Ok(())) => checkpoint.commit(),
+                    Result::Ok(Result::Err(error)) => {
+                        error!("app {:?} genesis state failed: {}", app_name, error)
+                    }
+                    Result::Err(_) => error!("app {:?} panicked in init_genesis", app_name),
+                }
+            }
+        }
+    }
+
+    /// enable the loaded apps from genesis in the app registry
+    fn seed_app_registry(&mut self, context: &NodeContext) {
+        let apps: BTreeMap<String, String> = context.apps.lock().unwrap().app_versions();
+        if apps.is_empty() {
+            return;
+        }
+        let registry: AppRegistryState = AppRegistryState::genesis(&apps);
+        let mut registry_context = self.app_context(&String::from(REGISTRY_APP_NAME));
+        if !put_registry(registry_context.as_mut(), &registry) {
+            error!("app registry couldn't be serialized");
+        }
+    }
+
+    /// app keys of state_trie, written before app namespaces existed
+    fn legacy_states(&self) -> BTreeMap<String, State> {
+        self.state_trie
+            .iter()
+            .filter(|(key, _)| {
+                !NODE_KEY_PREFIXES
+                    .iter()
+                    .any(|prefix| key.starts_with(prefix))
+            })
+            .collect()
+    }
+
+    /// let app move its legacy keys into its namespace, unless it did so
+    /// already. The writes of a failed or panicked migration are discarded.
+    pub fn migrate_app_legacy_state(
+        &mut self,
+        app_name: &String,
+        app: &dyn AppHandler,
+    ) -> Result<(), AppError> {
+        let migrated_key: String = legacy_migrated_key(app_name);
+        if self.state_trie.contains(&migrated_key) {
+            return Ok(());
+        }
+        let mut legacy = LegacyStates::new(self.legacy_states());
+        {
+            let mut app_context = self.app_context(app_name);
+            let mut checkpoint = Checkpoint::new(app_context.as_mut());
+            match panic::catch_unwind(AssertUnwindSafe(|| {
+                app.migrate_legacy_state(&mut legacy, &mut checkpoint)
+            })) {
+                Result::Ok(Result::Ok(())) => checkpoint.commit(),
+                Result::Ok(Result::Err(error)) => return Err(error),
+                Result::Err(_) => return Err(AppError::new(ABI_ERROR_CODE, "app panicked")),
+            }
+        }
+        for key in legacy.taken.iter() {
+            self.state_trie.remove(key);
+        }
+        self.state_trie.put(&migrated_key, State::new());
+        Ok(())
+    }
+
+    /// run the legacy state migration of every loaded app which hasn't
+    /// migrated yet, in app name order. Called at the start of every block,
+    /// so it runs once after an upgrade and outside of any txn gas.
+    pub fn migrate_legacy_state(&mut self, context: &NodeContext) {
+        let appdata = context.apps.lock().unwrap();
+        let mut app_names: Vec<&String> = appdata.appdata.keys().collect();
+        app_names.sort();
+        for app_name in app_names {
+            if let Some(app) = appdata.appdata.get(app_name) {
+                let app = app.lock().unwrap();
+                if let Err(error) = self.migrate_app_legacy_state(app_name, app.as_ref()) {
+                    warn!(
+                        "app {:?} couldn't migrate its legacy state: {}",
+                        app_name, error
+                    );
+                }
+            }
+        }
+    }
+
+    /// check the loaded apps against the apps the registry enables at height
+    pub fn check_app_registry(&mut self, context: &NodeContext, height: u64) -> Result<(), String> {
+        let registry: Option<AppRegistryState> = {
+            let registry_context = self.app_context(&String::from(REGISTRY_APP_NAME));
+            get_registry(registry_context.as_ref())
+        };
+        match registry {
+            Some(registry) => registry.check(height, &context.apps.lock().unwrap().app_versions()),
+            None => Ok(()),
+        }
+    }
+
     /**
      * this function will iterate over txn_order_pool and return a vec of SignedTransaction and
      * all changes due to these transaction also updated in state_trie
      */
-    pub fn execute_transactions(&mut self, txn_pool: &mut TransactionPool) -> Vec<Hash> {
-        let txn_pool_as_trait = txn_pool as &dyn PoolTrait<T, State, SignedTransaction>;
-        let state_context = self as &mut dyn StateContext;
-        let (executed_txns, unknown_app_txns_hash) =
-            txn_pool_as_trait.execute_transactions(state_context);
+    pub fn execute_transactions(
+        &mut self,
+        context: &NodeContext,
+        txn_pool: &mut TransactionPool,
+        block_context: &BlockContext,
+    ) -> Vec<Hash> {
+        let (executed_txns, unknown_app_txns_hash) = {
+            let app_data: &AppData = &context.apps.lock().unwrap();
+            let executor = TxnExecutor {
+                txn_pool,
+                app_data,
+                block_config: &context.config.block_config,
+                peer_reports: &context.peer_reports,
+            };
+            let executor_as_trait = &executor as &dyn PoolTrait<T, State, SignedTransaction>;
+            let storage = self as &mut dyn AppStorage;
+            executor_as_trait.execute_transactions(storage, block_context)
+        };
         txn_pool.sync_pool(&unknown_app_txns_hash);
         executed_txns
     }
 
     /// this function only will called when the node willing to propose block and for that agree to compute block
-    pub fn create_block(&mut self, kp: &KeypairType, custom_headers: Vec<u8>) -> SignedBlock {
+    ///
+    /// Err if the loaded apps aren't the ones the registry enables for the block
+    pub fn create_block(
+        &mut self,
+        context: &NodeContext,
+        kp: &KeypairType,
+        custom_headers: Vec<u8>,
+    ) -> Result<SignedBlock, String> {
+        self.check_app_registry(context, self.block_list.len())?;
+        let length = self.block_list.len();
+        let last_block: SignedBlock = match length
+            .checked_sub(1)
+            .and_then(|index| self.block_list.get(index))
+        {
+            Some(block) => block,
+            None => return Err(String::from("last block not found")),
+        };
+        let prev_hash = last_block.object_hash();
+        let public_key = hex::encode(Keypair::public(&kp).encode());
+        // a clock behind the previous author's still makes a valid block
+        let timestamp: u128 = std::cmp::max(
+            SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap()
+                .as_micros(),
+            last_block.block.timestamp,
+        );
+        let block_context: BlockContext =
+            BlockContext::new(length, timestamp, &public_key, &prev_hash);
+        self.migrate_legacy_state(context);
         // all trie's state before current block computation
         #[allow(unused_assignments)]
         let mut executed_txns: Vec<Hash> = vec![];
         {
-            let mut txn_pool = POOL.pool.lock().unwrap();
-            executed_txns = self.execute_transactions(&mut txn_pool);
+            let mut txn_pool = context.pool.pool.lock().unwrap();
+            executed_txns = self.execute_transactions(context, &mut txn_pool, &block_context);
         }
         info!("txn count in proposed block {}", executed_txns.len());
-        let length = self.block_list.len();
-        let last_block: SignedBlock = match self.block_list.get(length - 1) {
-            Some(block) => block,
-            None => {
-                error!("last block not found");
-                panic!("last block not found");
+        context
+            .apps
+            .lock()
+            .unwrap()
+            .finalize_block(&public_key, self as &mut dyn AppStorage);
+        if context.config.block_config.determinism_check {
+            let txn_pool = context.pool.pool.lock().unwrap();
+            if let Err(error) =
+                check_determinism(context, &txn_pool, &executed_txns, &block_context)
+            {
+                error!("{}", error);
             }
-        };
-        let prev_hash = last_block.object_hash();
+        }
         let header: [Hash; 3] = [
             self.state_trie_merkle_hash(),
             self.storage_trie_merkle_hash(),
             self.txn_trie_merkle_hash(),
         ];
         // updated merkle root of all tries
-        let public_key = hex::encode(Keypair::public(&kp).encode());
-        let block = Block::new_block(
+        let mut block = Block::new_block(
             length,
             public_key,
             prev_hash,
@@ -149,19 +635,23 @@ where
             header,
             custom_headers,
         );
+        // apps saw this timestamp in BlockContext, validators replay with it
+        block.timestamp = block_context.timestamp;
         let signature: Vec<u8> = block.sign(kp);
         let auth_headers: Vec<u8> = Vec::new();
         let signed_block: SignedBlock = SignedBlock::create_block(block, signature, auth_headers);
         self.block_list.push(signed_block.clone());
-        signed_block
+        Ok(signed_block)
     }
 
+    /// create_block until the block is full or block_creation_time_limit is over
     pub fn forge_new_block(
         &self,
+        context: &NodeContext,
         kp: &KeypairType,
         custom_headers: Vec<u8>,
-    ) -> (Fork, SignedBlock) {
-        let block_config: &BlockConfig = &configreader::GLOBAL_CONFIG.block_config;
+    ) -> Result<(Fork, SignedBlock), String> {
+        let block_config: &BlockConfig = &context.config.block_config;
         let mut timestamp: TxnPoolKeyType = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
@@ -172,14 +662,14 @@ where
             .unwrap()
             .as_micros();
         #[allow(unused_assignments)]
-        let mut fork_instance: Fork = fork_db();
+        let mut fork_instance: Fork = context.db.fork();
         // dummy signed block
         let mut block_instance: SignedBlock =
             SignedBlock::create_block(Block::genesis_block(Vec::new(), 0), Vec::new(), Vec::new());
         while current_timestamp < timestamp {
             {
                 let mut schema = SchemaFork::new(&fork_instance);
-                block_instance = schema.create_block(kp, custom_headers.clone());
+                block_instance = schema.create_block(context, kp, custom_headers.clone())?;
             }
             if block_instance.block.txn_pool.len() >= block_config.block_transaction_limit as usize
             {
@@ -190,28 +680,37 @@ where
                     .unwrap()
                     .as_micros();
                 if current_timestamp < timestamp {
-                    fork_instance = fork_db();
+                    fork_instance = context.db.fork();
                 }
             }
             let sleep_time: u64 = block_config.block_creation_time_limit as u64 / 10;
             std::thread::sleep(std::time::Duration::from_micros(sleep_time));
         }
-        (fork_instance, block_instance)
+        Ok((fork_instance, block_instance))
     }
 
     /// this function will update state_trie for given transaction
     pub fn update_transactions(
         &mut self,
+        context: &NodeContext,
         txn_pool: &TransactionPool,
         hash_vec: &Vec<Hash>,
+        block_context: &BlockContext,
     ) -> bool {
-        let txn_pool_as_trait = txn_pool as &dyn PoolTrait<T, State, SignedTransaction>;
-        let state_context = self as &mut dyn StateContext;
-        txn_pool_as_trait.update_transactions(state_context, hash_vec)
+        let app_data: &AppData = &context.apps.lock().unwrap();
+        let executor = TxnExecutor {
+            txn_pool,
+            app_data,
+            block_config: &context.config.block_config,
+            peer_reports: &context.peer_reports,
+        };
+        let executor_as_trait = &executor as &dyn PoolTrait<T, State, SignedTransaction>;
+        let storage = self as &mut dyn AppStorage;
+        executor_as_trait.update_transactions(storage, hash_vec, block_context)
     }
 
     /// this function will update fork for given block
-    pub fn update_block(&mut self, signed_block: &SignedBlock) -> bool {
+    pub fn update_block(&mut self, context: &NodeContext, signed_block: &SignedBlock) -> bool {
         let length = self.block_list.len();
         // block height check
         if signed_block.block.id != length {
@@ -222,8 +721,19 @@ where
             return false;
         }
 
+        let block_config: &BlockConfig = &context.config.block_config;
+        if signed_block.block.txn_pool.len() as u64 > block_config.block_transaction_limit {
+            error!(
+                "block has {} transactions, the limit is {}",
+                signed_block.block.txn_pool.len(),
+                block_config.block_transaction_limit
+            );
+            return false;
+        }
+
         // genesis block check
         if signed_block.block.id == 0 {
+            self.init_genesis_state(context);
             let header: [Hash; 3] = [
                 self.state_trie_merkle_hash(),
                 self.storage_trie_merkle_hash(),
@@ -258,28 +768,79 @@ where
                 return false;
             }
 
+            // block timestamp check
+            if let Err(error) = check_block_timestamp(
+                block_config,
+                last_block.block.timestamp,
+                signed_block.block.timestamp,
+            ) {
+                error!("{}, block declined", error);
+                return false;
+            }
+
             // block signature check
             if !signed_block.validate() {
                 error!("block signature couldn't verified");
                 return false;
             }
 
-            // check all transactions are present or not in the POOL
-            let client_obj: ClientObj = ClientObj::new();
+            // loaded apps must be the ones enabled by the registry at this height
+            if let Err(error) = self.check_app_registry(context, signed_block.block.id) {
+                error!("{}, block declined", error);
+                return false;
+            }
+
+            // check all transactions are present or not in the pool
+            let peer_client: PeerClient = context.peer_client();
             let pk: String = signed_block.block.peer_id.clone();
-            if !client_obj.sync_txn_pool(pk, &signed_block.block.txn_pool) {
+            if !peer_client.sync_txn_pool(pk, &signed_block.block.txn_pool) {
                 error!("some transactions are missing from the txn_pool, block declined");
                 return false;
             }
 
+            self.migrate_legacy_state(context);
             // block txn pool validation
+            let block_context: BlockContext = BlockContext::new(
+                signed_block.block.id,
+                signed_block.block.timestamp,
+                &signed_block.block.peer_id,
+                &signed_block.block.prev_hash,
+            );
             {
-                let txn_pool = POOL.pool.lock().unwrap();
-                if !self.update_transactions(&txn_pool, &signed_block.block.txn_pool) {
+                let txn_pool = context.pool.pool.lock().unwrap();
+                if let Err(error) =
+                    check_block_txns(block_config, &txn_pool, &signed_block.block.txn_pool)
+                {
+                    error!("{}, block declined", error);
+                    report_block_author(&context.peer_reports, signed_block);
+                    return false;
+                }
+                if block_config.determinism_check {
+                    if let Err(error) = check_determinism(
+                        context,
+                        &txn_pool,
+                        &signed_block.block.txn_pool,
+                        &block_context,
+                    ) {
+                        error!("{}, block declined", error);
+                        return false;
+                    }
+                }
+                if !self.update_transactions(
+                    context,
+                    &txn_pool,
+                    &signed_block.block.txn_pool,
+                    &block_context,
+                ) {
                     error!("block txn_pool couldn't updated, block declined");
                     return false;
                 }
             }
+            context
+                .apps
+                .lock()
+                .unwrap()
+                .finalize_block(&signed_block.block.peer_id, self as &mut dyn AppStorage);
 
             // block header check
             let header: [Hash; 3] = [
@@ -305,8 +866,8 @@ where
     }
 
     /// this function will sync blockchain state with other peers
-    pub fn sync_state(&mut self) -> bool {
-        let client_instance = ClientObj::new();
+    pub fn sync_state(&mut self, context: &NodeContext) -> bool {
+        let client_instance = context.peer_client();
         let mut own_chain_length = self.block_list.len();
         // let block_threads_vec = vec![];
         #[allow(unused_assignments)]
@@ -325,7 +886,7 @@ where
                             match txn.header.get(&String::from("timestamp")) {
                                 Some(string) => {
                                     match string.parse::<TxnPoolKeyType>() {
-                                        Ok(timestamp) => POOL.insert_op(&timestamp, &txn),
+                                        Ok(timestamp) => context.pool.insert_op(&timestamp, &txn),
                                         Err(error) => {
                                             block_fetch_flag = false;
                                             error!("transaction timestamp error {:?}", error);
@@ -339,13 +900,13 @@ where
                         }
                     }
                     if block_fetch_flag {
-                        if self.update_block(signed_block) {
+                        if self.update_block(context, signed_block) {
                             own_chain_length = own_chain_length + 1;
                         } else {
                             block_fetch_flag = false;
                         }
                     }
-                    POOL.sync_pool(&signed_block.block.txn_pool);
+                    context.pool.sync_pool(&signed_block.block.txn_pool);
                 }
                 None => {
                     own_chain_length = sync_data.index;
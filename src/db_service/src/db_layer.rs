@@ -1,46 +1,50 @@
 use exonum_merkledb::{Database, DbOptions, Fork, RocksDB, Snapshot};
-use utils::configreader;
-use utils::configreader::Configuration;
+use std::sync::Arc;
 
-fn create_db_instance() -> RocksDB {
-    let db_options: DbOptions = Default::default();
-    let config: &Configuration = &configreader::GLOBAL_CONFIG;
-    match RocksDB::open(&config.db.dbpath, &db_options) {
-        Ok(connection) => return connection,
-        Err(_) => panic!("can't able to create new db instance"),
-    };
+/// Db is the storage of a node. Its clones share the db, which is closed
+/// once the last clone and the forks & snapshots taken from it are dropped.
+#[derive(Clone)]
+pub struct Db {
+    db: Arc<RocksDB>,
 }
 
-lazy_static! {
-    pub static ref DB_INSTANCE: RocksDB = create_db_instance();
-}
+impl Db {
+    /// open the db at db_path, Err if it can't be opened, e.g. because
+    /// another node uses it
+    pub fn open(db_path: &str) -> Result<Db, String> {
+        let db: RocksDB = RocksDB::open(db_path, &DbOptions::default())
+            .map_err(|error| format!("db at {} couldn't be opened: {}", db_path, error))?;
+        Ok(Db { db: Arc::new(db) })
+    }
 
-pub fn fork_db() -> Fork {
-    DB_INSTANCE.fork()
-    // db.fork()
-}
+    pub fn fork(&self) -> Fork {
+        self.db.fork()
+    }
 
-pub fn snapshot_db() -> Box<dyn Snapshot> {
-    DB_INSTANCE.snapshot()
-}
+    pub fn snapshot(&self) -> Box<dyn Snapshot> {
+        self.db.snapshot()
+    }
 
-pub fn patch_db(fork: Fork) {
-    if let Err(error) = DB_INSTANCE.merge(fork.into_patch()) {
-        error!("error occurred in patch_db process {:?}", error);
-        println!("error occurred in patch_db process {:?}", error);
+    pub fn patch(&self, fork: Fork) {
+        if let Err(error) = self.db.merge(fork.into_patch()) {
+            error!("error occurred in patch process {:?}", error);
+            println!("error occurred in patch process {:?}", error);
+        }
     }
 }
 
 #[cfg(test)]
 mod test_db_layer {
     use super::*;
-    use utils::configreader::initialize_config;
 
     #[test]
-    #[should_panic]
-    fn test_create_db_instance() {
-        initialize_config("../../config.toml");
-        fork_db();
-        create_db_instance();
+    fn test_open_db_twice() {
+        let db_path: &str = "test_open_db_twice";
+        let db: Db = Db::open(db_path).unwrap();
+        // rocksdb locks the db of a node
+        assert_eq!(Db::open(db_path).is_err(), true);
+        drop(db);
+        assert_eq!(Db::open(db_path).is_ok(), true);
+        std::fs::remove_dir_all(db_path).unwrap();
     }
 }
@@ -5,11 +5,14 @@ use exonum_crypto::Hash;
 use exonum_derive::FromAccess;
 use exonum_merkledb::{
     access::{Access, FromAccess, RawAccess},
-    ListIndex, ObjectHash, ProofMapIndex,
+    Group, KeySetIndex, ListIndex, ObjectHash, ProofMapIndex,
 };
+use schema::app_registry::{get_registry, REGISTRY_APP_NAME};
 use schema::block::SignedBlock;
 use schema::signed_transaction::SignedTransaction;
 use schema::state::State;
+use sdk::traits::ReadOnlyStateContext;
+use std::collections::BTreeMap;
 
 #[derive(FromAccess)]
 pub struct SchemaSnap<T: Access> {
@@ -17,6 +20,8 @@ pub struct SchemaSnap<T: Access> {
     block_list: ListIndex<T::Base, SignedBlock>,
     state_trie: ProofMapIndex<T::Base, String, State>,
     storage_trie: ProofMapIndex<T::Base, Hash, SignedTransaction>,
+    app_state: Group<T, String, ProofMapIndex<T::Base, String, State>>,
+    app_keys: Group<T, String, KeySetIndex<T::Base, String>>,
 }
 
 impl<T: Access> SchemaSnap<T> {
@@ -107,4 +112,67 @@ where
     pub fn get_state(&self, public_key: String) -> Option<State> {
         self.state().get(&public_key)
     }
+
+    /// state of key in the app_name namespace
+    pub fn get_app_state(&self, app_name: &String, key: &String) -> Option<State> {
+        self.app_state.get(app_name).get(key)
+    }
+
+    /// check app_versions, name => version of the apps a node loads, against
+    /// the apps the registry enables at height
+    pub fn check_app_registry(
+        &self,
+        height: u64,
+        app_versions: &BTreeMap<String, String>,
+    ) -> Result<(), String> {
+        let registry_context = self.app_context(&String::from(REGISTRY_APP_NAME));
+        match get_registry(&registry_context) {
+            Some(registry) => registry.check(height, app_versions),
+            None => Ok(()),
+        }
+    }
+
+    /// read only view of the app_name namespace, handed to app queries
+    pub fn app_context(&self, app_name: &String) -> AppSnapContext<'_, T> {
+        AppSnapContext {
+            schema: self,
+            app_name: app_name.clone(),
+        }
+    }
+}
+
+/// AppSnapContext is the read only counterpart of AppStateContext, it reads
+/// only the app namespace of a snapshot.
+pub struct AppSnapContext<'a, T: Access> {
+    schema: &'a SchemaSnap<T>,
+    app_name: String,
+}
+
+impl<'a, T: Access> ReadOnlyStateContext for AppSnapContext<'a, T>
+where
+    T::Base: RawAccess,
+{
+    fn get(&self, key: &String) -> Option<State> {
+        self.schema.get_app_state(&self.app_name, key)
+    }
+    fn contains(&self, key: &String) -> bool {
+        self.schema.app_state.get(&self.app_name).contains(key)
+    }
+    fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+        let states = self.schema.app_state.get(&self.app_name);
+        self.schema
+            .app_keys
+            .get(&self.app_name)
+            .iter_from(start)
+            .take_while(|key| end.is_empty() || key < end)
+            .take(limit)
+            .filter_map(|key| states.get(&key).map(|state| (key, state)))
+            .collect()
+    }
+    fn get_txn(&self, key: &Hash) -> Option<SignedTransaction> {
+        self.schema.txn_trie.get(key)
+    }
+    fn contains_txn(&self, key: &Hash) -> bool {
+        self.schema.txn_trie.contains(key)
+    }
 }
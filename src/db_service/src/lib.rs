@@ -1,10 +1,9 @@
-#[macro_use]
-extern crate lazy_static;
-
 #[macro_use]
 extern crate log;
 
 pub mod db_fork_ref;
 pub mod db_layer;
 pub mod db_snapshot_ref;
+pub mod node_context;
+pub mod peer_service;
 pub mod tests;
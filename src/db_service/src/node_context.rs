@@ -0,0 +1,62 @@
+use super::db_layer::Db;
+use client::peer_client::PeerClient;
+use message_handler::block_relay::{BlockRelay, RecentBlocks};
+use message_handler::messages::MessageDispatcher;
+use message_handler::peer_request::PeerRequests;
+use schema::appdata::AppData;
+use schema::transaction_pool::{Pool, TxnPool};
+use std::sync::{Arc, Mutex};
+use utils::configreader::Configuration;
+use utils::global_peer_data::GlobalData;
+use utils::peer_reports::PeerReports;
+use utils::queue_metrics::QueueMetrics;
+
+/// NodeContext is the state of one node: its config, db, pool & apps and the
+/// queues between its threads & its swarm. The clones share the state, so
+/// nodes with their own context run side by side in one process.
+#[derive(Clone)]
+pub struct NodeContext {
+    pub config: Arc<Configuration>,
+    pub db: Db,
+    pub pool: Pool,
+    pub apps: Arc<Mutex<AppData>>,
+    pub dispatcher: MessageDispatcher,
+    pub peer_requests: Arc<PeerRequests>,
+    pub recent_blocks: Arc<Mutex<RecentBlocks>>,
+    // peers the swarm found, with the address of their api
+    pub peers: Arc<Mutex<GlobalData>>,
+    pub peer_reports: Arc<Mutex<PeerReports>>,
+    pub queue_metrics: Arc<Mutex<QueueMetrics>>,
+}
+
+impl NodeContext {
+    pub fn new(config: Arc<Configuration>, db: Db, apps: AppData) -> NodeContext {
+        let queue_metrics = Arc::new(Mutex::new(QueueMetrics::new()));
+        NodeContext {
+            config,
+            db,
+            pool: Pool::new(),
+            apps: Arc::new(Mutex::new(apps)),
+            dispatcher: MessageDispatcher::new(&queue_metrics),
+            peer_requests: Arc::new(PeerRequests::new(&queue_metrics)),
+            recent_blocks: Arc::new(Mutex::new(RecentBlocks::new())),
+            peers: Arc::new(Mutex::new(GlobalData::new())),
+            peer_reports: Arc::new(Mutex::new(PeerReports::new())),
+            queue_metrics,
+        }
+    }
+
+    /// client fetching blocks & transactions from the peers of the node
+    pub fn peer_client(&self) -> PeerClient {
+        PeerClient::new(self.peer_requests.clone(), self.pool.clone())
+    }
+
+    /// relay of the blocks of the node
+    pub fn block_relay(&self) -> BlockRelay {
+        BlockRelay::new(
+            self.pool.clone(),
+            self.peer_requests.clone(),
+            self.recent_blocks.clone(),
+        )
+    }
+}
@@ -0,0 +1,61 @@
+use super::db_layer::Db;
+use super::db_snapshot_ref::SchemaSnap;
+use super::node_context::NodeContext;
+use exonum_crypto::Hash;
+use exonum_merkledb::ObjectHash;
+use message_handler::handshake::Handshake;
+use message_handler::peer_request::{PeerRequest, PeerResponse};
+use schema::signed_transaction::SignedTransaction;
+use schema::transaction_pool::TxnPool;
+
+/// answer a request of another node from the latest snapshot & the pool of
+/// the node of context
+pub fn serve_peer_request(context: &NodeContext, request: PeerRequest) -> PeerResponse {
+    let snapshot = context.db.snapshot();
+    let schema = SchemaSnap::new(&snapshot);
+    match request {
+        PeerRequest::FetchBlock(block_index) => PeerResponse::Block(schema.get_block(block_index)),
+        PeerRequest::FetchLatestBlock => PeerResponse::Block(schema.get_root_block()),
+        PeerRequest::FetchBlockchainLength => {
+            PeerResponse::BlockchainLength(schema.get_blockchain_length())
+        }
+        PeerRequest::FetchTransaction(txn_hash) => match context.pool.get(&txn_hash) {
+            Some(transaction) => PeerResponse::Transaction(Some(transaction)),
+            None => PeerResponse::Transaction(schema.get_transaction(txn_hash)),
+        },
+        PeerRequest::FetchBlockTransactions(salt, indexes) => PeerResponse::Transactions(
+            context
+                .recent_blocks
+                .lock()
+                .unwrap()
+                .transactions(&salt, &indexes)
+                .unwrap_or_default(),
+        ),
+        PeerRequest::FetchTransactions(txn_hashes) => {
+            // no more than a block holds
+            let limit: u64 = context.config.block_config.block_transaction_limit;
+            if txn_hashes.len() as u64 > limit {
+                return PeerResponse::Transactions(Vec::new());
+            }
+            let txns: Option<Vec<SignedTransaction>> = txn_hashes
+                .iter()
+                .map(|txn_hash| match context.pool.get(txn_hash) {
+                    Some(transaction) => Some(transaction),
+                    None => schema.get_transaction(*txn_hash),
+                })
+                .collect();
+            PeerResponse::Transactions(txns.unwrap_or_default())
+        }
+        PeerRequest::Handshake(_) => PeerResponse::Handshake(Handshake::local(
+            &context.config.node.chain_id,
+            schema.get_block(0).map(|block| block.object_hash()),
+        )),
+    }
+}
+
+/// hash of the genesis block in db, None before the node has one
+pub fn genesis_hash(db: &Db) -> Option<Hash> {
+    let snapshot = db.snapshot();
+    let schema = SchemaSnap::new(&snapshot);
+    schema.get_block(0).map(|block| block.object_hash())
+}
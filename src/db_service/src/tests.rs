@@ -1,47 +1,56 @@
 #[cfg(test)]
 mod test_db_service {
     use crate::db_fork_ref::*;
-    use crate::db_layer::{fork_db, patch_db, snapshot_db};
+    use crate::db_layer::Db;
     use crate::db_snapshot_ref::*;
+    use crate::node_context::NodeContext;
+    use crate::peer_service::{genesis_hash, serve_peer_request};
     use exonum_crypto::Hash;
     use exonum_merkledb::{Fork, ObjectHash, Snapshot};
+    use message_handler::peer_request::{PeerRequest, PeerResponse};
+    use schema::app_registry::{put_registry, AppRegistryState, RegistryEntry, REGISTRY_APP_NAME};
+    use schema::appdata::AppData;
     use schema::block::{Block, BlockTraits, SignedBlock};
     use schema::signed_transaction::SignedTransaction;
     use schema::state::State;
-    use sdk::traits::StateContext;
+    use schema::transaction_pool::{TransactionPool, TransactionPoolTraits};
+    use sdk::block_context::BlockContext;
+    use sdk::errors::AppError;
+    use sdk::traits::{AppHandler, AppStorage, ReadOnlyStateContext, StateContext};
     use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
     use std::time::SystemTime;
-    use utils::configreader;
-    use utils::configreader::BlockConfig;
+    use utils::configreader::{BlockConfig, Configuration};
     use utils::keypair::{CryptoKeypair, Keypair, KeypairType};
 
-    fn test_db_initialization_check() {
+    fn test_db_initialization_check(context: &NodeContext) {
         // reset_db_state
         let kp: KeypairType = Keypair::generate();
         let pk: String = hex::encode(kp.public().encode());
         let block: Block = Block::genesis_block(Vec::new(), 0);
         #[allow(unused_assignments)]
         let mut signed_block: SignedBlock = SignedBlock::create_block(block, vec![0], Vec::new());
-        let fork: Fork = fork_db();
+        let fork: Fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
-            signed_block = schema.initialize_db(Vec::new(), 0);
+            signed_block = schema.initialize_db(context, Vec::new(), 0);
             assert_eq!(
                 signed_block.block.validate(&pk, &signed_block.signature),
                 false
             );
         }
-        patch_db(fork);
-        // not using patch_db so that we can update this block
-        let fork: Fork = fork_db();
+        context.db.patch(fork);
+        // not patching the fork so that we can update this block
+        let fork: Fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
-            assert_eq!(false, schema.update_block(&signed_block));
+            assert_eq!(false, schema.update_block(context, &signed_block));
         }
 
         // check db state to check whether fork and patch are working using snapshot
         let empty_hash: Hash = signed_block.block.header[0].clone();
-        let snapshot: Box<dyn Snapshot> = snapshot_db();
+        let snapshot: Box<dyn Snapshot> = context.db.snapshot();
         {
             let schema = SchemaSnap::new(&snapshot);
             assert_eq!(schema.get_blockchain_length(), 1);
@@ -51,54 +60,50 @@ mod test_db_service {
         }
     }
 
-    fn test_db_read_write_check() {
+    fn test_db_read_write_check(context: &NodeContext) {
         // reset_db_state
         let kp: KeypairType = Keypair::generate();
-        let snapshot: Box<dyn Snapshot> = snapshot_db();
+        let snapshot: Box<dyn Snapshot> = context.db.snapshot();
         {
             let schema = SchemaSnap::new(&snapshot);
             if !schema.is_db_initialized() {}
         }
         // db is initialized create one block and verify it with snapshot
-        let fork: Fork = fork_db();
+        let fork: Fork = context.db.fork();
         let block: Block = Block::genesis_block(Vec::new(), 0);
         #[allow(unused_assignments)]
         let mut signed_block: SignedBlock = SignedBlock::create_block(block, vec![0], Vec::new());
         {
             let mut schema = SchemaFork::new(&fork);
-            signed_block = schema.create_block(&kp, Vec::new());
+            signed_block = schema.create_block(context, &kp, Vec::new()).unwrap();
         }
-        // not using patch_db so that we can update this block
-        let fork: Fork = fork_db();
+        // not patching the fork so that we can update this block
+        let fork: Fork = context.db.fork();
         #[allow(unused_assignments)]
         let mut update_flag: bool = false;
         {
             let mut schema = SchemaFork::new(&fork);
-            update_flag = schema.update_block(&signed_block);
+            update_flag = schema.update_block(context, &signed_block);
         }
         assert_eq!(update_flag, true);
         if update_flag {
-            patch_db(fork);
+            context.db.patch(fork);
         }
         // check db state to check whether fork and patch are working using snapshot
-        let snapshot: Box<dyn Snapshot> = snapshot_db();
+        let snapshot: Box<dyn Snapshot> = context.db.snapshot();
         {
             let schema = SchemaSnap::new(&snapshot);
             assert_eq!(schema.get_root_block_hash(), signed_block.get_hash());
         }
     }
 
-    fn test_db_state_context() {
+    fn test_db_state_context(context: &NodeContext) {
         let kp: KeypairType = Keypair::generate();
         let pk: String = hex::encode(kp.public().encode());
-        let fork: Fork = fork_db();
+        let fork: Fork = context.db.fork();
         let state: State = State::new();
-        let txn: SignedTransaction = SignedTransaction {
-            txn: vec![0],
-            app_name: String::from("app_name"),
-            header: HashMap::new(),
-            signature: vec![0],
-        };
+        let txn: SignedTransaction =
+            SignedTransaction::create(&kp, "app_name", HashMap::new(), vec![0]);
         let txn_hash: Hash = txn.object_hash();
         {
             let mut schema = SchemaFork::new(&fork);
@@ -106,8 +111,8 @@ mod test_db_service {
             schema.put(&pk, state.clone());
             schema.put_txn(&txn_hash, txn.clone());
         }
-        patch_db(fork);
-        let fork: Fork = fork_db();
+        context.db.patch(fork);
+        let fork: Fork = context.db.fork();
         {
             let schema = SchemaFork::new(&fork);
             let is_contains: bool = schema.contains(&pk);
@@ -122,66 +127,408 @@ mod test_db_service {
         }
     }
 
-    fn test_db_sync_state() {
+    fn test_db_app_state_context(context: &NodeContext) {
+        let app_name: String = String::from("app_name");
+        let other_app: String = String::from("other_app");
+        let legacy_key: String = String::from("legacy_key");
+        let mut state: State = State::new();
+        state.set_data(&vec![1, 2, 3]);
+        let fork: Fork = context.db.fork();
+        {
+            let mut schema = SchemaFork::new(&fork);
+            schema.put(&legacy_key, state.clone());
+            {
+                let mut app_context = schema.app_context(&app_name);
+                // keys written before namespaces existed aren't visible
+                assert_eq!(app_context.get(&legacy_key), None);
+                app_context.put(&String::from("b"), state.clone());
+                app_context.put(&String::from("a"), state.clone());
+                app_context.put(&String::from("c"), state.clone());
+                app_context.delete(&legacy_key);
+                let keys: Vec<String> = app_context
+                    .range(&String::from("a"), &String::from("c"), 10)
+                    .into_iter()
+                    .map(|(key, _)| key)
+                    .collect();
+                assert_eq!(keys, vec![String::from("a"), String::from("b")]);
+            }
+            assert_eq!(schema.get(&legacy_key), Some(state.clone()));
+            {
+                // other_app can neither see nor delete the keys of app_name
+                let mut other_context = schema.app_context(&other_app);
+                assert_eq!(other_context.get(&String::from("a")), None);
+                assert_eq!(other_context.contains(&String::from("a")), false);
+                other_context.delete(&String::from("a"));
+            }
+            let app_context = schema.app_context(&app_name);
+            assert_eq!(app_context.get(&String::from("a")), Some(state.clone()));
+        }
+        context.db.patch(fork);
+        let snapshot = context.db.snapshot();
+        let schema = SchemaSnap::new(&snapshot);
+        assert_eq!(
+            schema.get_app_state(&app_name, &String::from("a")),
+            Some(state.clone())
+        );
+        assert_eq!(schema.get_app_state(&other_app, &String::from("a")), None);
+        assert_eq!(schema.get_app_state(&app_name, &legacy_key), None);
+        let app_root: State = schema.get_state(app_root_key(&app_name)).unwrap();
+        assert_ne!(app_root.get_storage_root(), Hash::zero());
+    }
+
+    struct MigratingApp {}
+
+    impl AppHandler for MigratingApp {
+        fn execute(
+            &self,
+            _txn: &SignedTransaction,
+            _state_context: &mut dyn StateContext,
+            _block_context: &BlockContext,
+        ) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        fn name(&self) -> String {
+            String::from("migrating_app")
+        }
+
+        fn migrate_legacy_state(
+            &self,
+            legacy: &mut dyn StateContext,
+            state_context: &mut dyn StateContext,
+        ) -> Result<(), AppError> {
+            let key: String = String::from("legacy_key");
+            match legacy.get(&key) {
+                Some(state) => {
+                    legacy.delete(&key);
+                    state_context.put(&key, state);
+                    Ok(())
+                }
+                None => Err(AppError::new(1, "legacy_key is missing")),
+            }
+        }
+    }
+
+    fn test_db_legacy_migration(context: &NodeContext) {
+        let app_name: String = String::from("migrating_app");
+        let legacy_key: String = String::from("legacy_key");
+        let mut state: State = State::new();
+        state.set_data(&vec![4]);
+        let fork: Fork = context.db.fork();
+        {
+            let mut schema = SchemaFork::new(&fork);
+            // a failed migration leaves the legacy key & is retried
+            schema.delete(&legacy_key);
+            let app = MigratingApp {};
+            assert_eq!(
+                schema.migrate_app_legacy_state(&app_name, &app).is_err(),
+                true
+            );
+            assert_eq!(schema.contains(&legacy_migrated_key(&app_name)), false);
+            schema.put(&legacy_key, state.clone());
+            assert_eq!(schema.migrate_app_legacy_state(&app_name, &app), Ok(()));
+            assert_eq!(schema.contains(&legacy_key), false);
+            assert_eq!(
+                schema.app_context(&app_name).get(&legacy_key),
+                Some(state.clone())
+            );
+            // migrated apps aren't asked again
+            assert_eq!(schema.migrate_app_legacy_state(&app_name, &app), Ok(()));
+        }
+    }
+
+    fn test_db_sync_state(context: &NodeContext) {
         // since it is unit test case sync-state should return zero-state not error
-        let fork: Fork = fork_db();
+        let fork: Fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
-            assert_eq!(schema.sync_state(), false);
+            assert_eq!(schema.sync_state(context), false);
         }
     }
 
-    fn test_failed_scenarios() {
+    fn test_peer_service(context: &NodeContext) {
+        let snapshot: Box<dyn Snapshot> = context.db.snapshot();
+        let schema = SchemaSnap::new(&snapshot);
+        let length: u64 = schema.get_blockchain_length();
+        assert_eq!(
+            serve_peer_request(context, PeerRequest::FetchBlockchainLength),
+            PeerResponse::BlockchainLength(length)
+        );
+        assert_eq!(
+            serve_peer_request(context, PeerRequest::FetchLatestBlock),
+            PeerResponse::Block(schema.get_root_block())
+        );
+        assert_eq!(
+            serve_peer_request(context, PeerRequest::FetchBlock(0)),
+            PeerResponse::Block(schema.get_block(0))
+        );
+        assert_eq!(
+            serve_peer_request(context, PeerRequest::FetchBlock(length)),
+            PeerResponse::Block(None)
+        );
+        assert_eq!(
+            serve_peer_request(context, PeerRequest::FetchTransaction(Hash::zero())),
+            PeerResponse::Transaction(None)
+        );
+        assert_eq!(
+            serve_peer_request(
+                context,
+                PeerRequest::FetchBlockTransactions(Hash::zero(), vec![0])
+            ),
+            PeerResponse::Transactions(Vec::new())
+        );
+        // all of the transactions or none
+        assert_eq!(
+            serve_peer_request(context, PeerRequest::FetchTransactions(vec![Hash::zero()])),
+            PeerResponse::Transactions(Vec::new())
+        );
+        assert_eq!(
+            serve_peer_request(context, PeerRequest::FetchTransactions(Vec::new())),
+            PeerResponse::Transactions(Vec::new())
+        );
+        assert_eq!(
+            genesis_hash(&context.db),
+            schema.get_block(0).map(|block| block.object_hash())
+        );
+        assert_eq!(genesis_hash(&context.db).is_some(), true);
+    }
+
+    fn test_db_app_registry(context: &NodeContext) {
+        let fork: Fork = context.db.fork();
+        {
+            let mut schema = SchemaFork::new(&fork);
+            // chains without registry aren't checked
+            assert_eq!(schema.check_app_registry(context, 1), Ok(()));
+            let mut registry = AppRegistryState::default();
+            registry.schedule(RegistryEntry {
+                app_name: String::from("wallet"),
+                version: Some(String::from("0.1.0")),
+                activation_height: 10,
+            });
+            {
+                let mut registry_context = schema.app_context(&String::from(REGISTRY_APP_NAME));
+                assert_eq!(put_registry(registry_context.as_mut(), &registry), true);
+            }
+            assert_eq!(schema.check_app_registry(context, 9), Ok(()));
+            // wallet isn't loaded by the test node
+            assert_eq!(schema.check_app_registry(context, 10).is_err(), true);
+        }
+    }
+
+    /// writes the txn payload under its signer, the same on every replay
+    struct SteadyApp {}
+
+    impl AppHandler for SteadyApp {
+        fn execute(
+            &self,
+            txn: &SignedTransaction,
+            state_context: &mut dyn StateContext,
+            _block_context: &BlockContext,
+        ) -> Result<(), AppError> {
+            let mut state: State = State::new();
+            state.set_data(&txn.txn);
+            state_context.put(&txn.signer, state);
+            Ok(())
+        }
+
+        fn name(&self) -> String {
+            String::from("steady_app")
+        }
+    }
+
+    /// writes how many times it ran, a replay of a txn writes another value
+    struct DriftingApp {
+        runs: AtomicU64,
+    }
+
+    impl AppHandler for DriftingApp {
+        fn execute(
+            &self,
+            txn: &SignedTransaction,
+            state_context: &mut dyn StateContext,
+            _block_context: &BlockContext,
+        ) -> Result<(), AppError> {
+            let runs: u64 = self.runs.fetch_add(1, Ordering::SeqCst);
+            let mut state: State = State::new();
+            state.set_data(&runs.to_be_bytes().to_vec());
+            state_context.put(&txn.signer, state);
+            Ok(())
+        }
+
+        fn name(&self) -> String {
+            String::from("drifting_app")
+        }
+    }
+
+    fn test_db_determinism_check(context: &NodeContext) {
+        let apps: Vec<Box<dyn AppHandler + Send>> = vec![
+            Box::new(SteadyApp {}),
+            Box::new(DriftingApp {
+                runs: AtomicU64::new(0),
+            }),
+        ];
+        for app in apps.into_iter() {
+            context
+                .apps
+                .lock()
+                .unwrap()
+                .appdata
+                .insert(app.name(), Arc::new(Mutex::new(app)));
+        }
+        let kp: KeypairType = Keypair::generate();
+        let steady_txn = SignedTransaction::create(&kp, "steady_app", HashMap::new(), vec![1]);
+        let drifting_txn = SignedTransaction::create(&kp, "drifting_app", HashMap::new(), vec![2]);
+        let mut txn_pool = TransactionPool::new();
+        txn_pool.insert_op(&1, &steady_txn);
+        txn_pool.insert_op(&2, &drifting_txn);
+        let block_context = BlockContext::new(1, 0, &String::from("author"), &Hash::zero());
+
+        // the same txns applied on two forks of the committed state reach the same root
+        let committed_root: Hash = SchemaFork::new(&context.db.fork()).state_trie_merkle_hash();
+        let state_roots: Vec<Hash> = (0..2)
+            .map(|_| {
+                let fork: Fork = context.db.fork();
+                let mut schema = SchemaFork::new(&fork);
+                let txn_hashes: Vec<Hash> = vec![steady_txn.object_hash()];
+                assert_eq!(
+                    schema.update_transactions(context, &txn_pool, &txn_hashes, &block_context),
+                    true
+                );
+                schema.state_trie_merkle_hash()
+            })
+            .collect();
+        assert_ne!(state_roots[0], committed_root);
+        assert_eq!(state_roots[0], state_roots[1]);
+        assert_eq!(
+            check_determinism(
+                context,
+                &txn_pool,
+                &vec![steady_txn.object_hash()],
+                &block_context
+            ),
+            Ok(())
+        );
+        // the replays part at the txn of drifting_app
+        let txn_hashes: Vec<Hash> = vec![steady_txn.object_hash(), drifting_txn.object_hash()];
+        assert_eq!(
+            check_determinism(context, &txn_pool, &txn_hashes, &block_context),
+            Err(format!(
+                "transaction {:?} of block 1 isn't deterministic",
+                drifting_txn.object_hash()
+            ))
+        );
+        let mut appdata = context.apps.lock().unwrap();
+        appdata.appdata.remove("steady_app");
+        appdata.appdata.remove("drifting_app");
+    }
+
+    fn test_block_timestamp_check(context: &NodeContext) {
+        let block_config: &BlockConfig = &context.config.block_config;
+        let current_timestamp: u128 = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_micros();
+        assert_eq!(
+            check_block_timestamp(block_config, current_timestamp - 10, current_timestamp),
+            Ok(())
+        );
+        assert_eq!(
+            check_block_timestamp(block_config, current_timestamp, current_timestamp),
+            Ok(())
+        );
+        // going back in time or too far ahead of the local clock
+        assert_eq!(
+            check_block_timestamp(block_config, current_timestamp, current_timestamp - 10).is_err(),
+            true
+        );
+        let future: u128 = current_timestamp + block_config.max_block_time_drift + 60_000_000;
+        assert_eq!(
+            check_block_timestamp(block_config, 0, future).is_err(),
+            true
+        );
+    }
+
+    fn test_block_txns_check(context: &NodeContext) {
+        let block_config: &BlockConfig = &context.config.block_config;
+        let mut txn_pool = TransactionPool::new();
+        let kp: KeypairType = Keypair::generate();
+        let txn = SignedTransaction::create(&kp, "Cryptocurrency", HashMap::new(), vec![1]);
+        let mut forged_txn =
+            SignedTransaction::create(&kp, "Cryptocurrency", HashMap::new(), vec![2]);
+        forged_txn.txn = vec![3];
+        txn_pool.insert_op(&1, &txn);
+        txn_pool.insert_op(&2, &forged_txn);
+        assert_eq!(
+            check_block_txns(block_config, &txn_pool, &vec![txn.object_hash()]),
+            Ok(())
+        );
+        // a txn twice in the block or with a broken signature is the author's fault
+        assert_eq!(
+            check_block_txns(
+                block_config,
+                &txn_pool,
+                &vec![txn.object_hash(), txn.object_hash()]
+            )
+            .is_err(),
+            true
+        );
+        assert_eq!(
+            check_block_txns(block_config, &txn_pool, &vec![forged_txn.object_hash()]).is_err(),
+            true
+        );
+    }
+
+    fn test_failed_scenarios(context: &NodeContext) {
         let kp: KeypairType = Keypair::generate();
         // db is initialized create one block and verify it with snapshot
-        let fork: Fork = fork_db();
+        let fork: Fork = context.db.fork();
         let block: Block = Block::genesis_block(Vec::new(), 0);
         #[allow(unused_assignments)]
         let mut signed_block: SignedBlock = SignedBlock::create_block(block, vec![0], Vec::new());
         {
             let mut schema = SchemaFork::new(&fork);
-            signed_block = schema.create_block(&kp, Vec::new());
+            signed_block = schema.create_block(context, &kp, Vec::new()).unwrap();
         }
         // signature error
         let mut wrong_block: SignedBlock = signed_block.clone();
-        let fork: Fork = fork_db();
+        let fork: Fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
             wrong_block.block.header[2] = Hash::zero();
-            assert_eq!(false, schema.update_block(&wrong_block));
+            assert_eq!(false, schema.update_block(context, &wrong_block));
         }
         {
             let mut schema = SchemaFork::new(&fork);
             wrong_block.block.header[1] = Hash::zero();
-            assert_eq!(false, schema.update_block(&wrong_block));
+            assert_eq!(false, schema.update_block(context, &wrong_block));
         }
         {
             let mut schema = SchemaFork::new(&fork);
             wrong_block.block.header[0] = Hash::zero();
-            assert_eq!(false, schema.update_block(&wrong_block));
+            assert_eq!(false, schema.update_block(context, &wrong_block));
         }
         {
             let mut schema = SchemaFork::new(&fork);
             wrong_block.block.prev_hash = Hash::zero();
-            assert_eq!(false, schema.update_block(&wrong_block));
+            assert_eq!(false, schema.update_block(context, &wrong_block));
         }
         {
             let mut schema = SchemaFork::new(&fork);
             wrong_block.block.id = schema.blockchain_length() - 1;
-            assert_eq!(false, schema.update_block(&wrong_block));
+            assert_eq!(false, schema.update_block(context, &wrong_block));
         }
         {
             let mut schema = SchemaFork::new(&fork);
             wrong_block.signature = vec![0];
-            assert_eq!(false, schema.update_block(&wrong_block));
+            assert_eq!(false, schema.update_block(context, &wrong_block));
         }
         {
             let mut schema = SchemaFork::new(&fork);
-            assert_eq!(true, schema.update_block(&signed_block));
+            assert_eq!(true, schema.update_block(context, &signed_block));
         }
-        patch_db(fork);
+        context.db.patch(fork);
         // check db state to check whether fork and patch are working using snapshot
-        let snapshot: Box<dyn Snapshot> = snapshot_db();
+        let snapshot: Box<dyn Snapshot> = context.db.snapshot();
         {
             let schema = SchemaSnap::new(&snapshot);
             assert_eq!(
@@ -191,10 +538,10 @@ mod test_db_service {
         }
     }
 
-    fn test_block_creation_config() {
+    fn test_block_creation_config(context: &NodeContext) {
         let kp: KeypairType = Keypair::generate();
-        let block_config: &BlockConfig = &configreader::GLOBAL_CONFIG.block_config;
-        let fork = fork_db();
+        let block_config: &BlockConfig = &context.config.block_config;
+        let fork = context.db.fork();
         {
             let schema = SchemaFork::new(&fork);
             let mut timestamp: u128 = SystemTime::now()
@@ -202,7 +549,8 @@ mod test_db_service {
                 .unwrap()
                 .as_micros();
             timestamp = timestamp + block_config.block_creation_time_limit;
-            let (_fork_instance, _signed_block) = schema.forge_new_block(&kp, Vec::new());
+            let (_fork_instance, _signed_block) =
+                schema.forge_new_block(context, &kp, Vec::new()).unwrap();
             let current_timestamp: u128 = SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
                 .unwrap()
@@ -220,7 +568,7 @@ mod test_db_service {
                 .unwrap()
                 .as_micros();
             timestamp = timestamp + block_config.block_creation_time_limit;
-            let _signed_block = schema.create_block(&kp, Vec::new());
+            let _signed_block = schema.create_block(context, &kp, Vec::new()).unwrap();
             let current_timestamp: u128 = SystemTime::now()
                 .duration_since(SystemTime::UNIX_EPOCH)
                 .unwrap()
@@ -233,14 +581,49 @@ mod test_db_service {
         }
     }
 
+    fn test_close_db_flush(context: NodeContext) {
+        let key: String = String::from("flush_key");
+        let mut state: State = State::new();
+        state.set_data(&vec![7, 8, 9]);
+        let fork: Fork = context.db.fork();
+        {
+            let mut schema = SchemaFork::new(&fork);
+            schema.put(&key, state.clone());
+        }
+        context.db.patch(fork);
+        let db_path: String = context.config.db.dbpath.clone();
+        // the last handle of the node closes the db
+        assert_eq!(Db::open(&db_path).is_err(), true);
+        drop(context);
+
+        // the closed db must release its lock and keep the patched state,
+        // a restarted node opens it again
+        let db: Db = Db::open(&db_path).unwrap();
+        assert_eq!(Db::open(&db_path).is_err(), true);
+        let snapshot: Box<dyn Snapshot> = db.snapshot();
+        let schema = SchemaSnap::new(&snapshot);
+        assert_eq!(schema.get(&key).unwrap(), state);
+    }
+
     #[test]
     fn test_db_services_checks() {
         std::thread::sleep(std::time::Duration::from_millis(100));
-        test_db_initialization_check();
-        test_db_read_write_check();
-        test_db_state_context();
-        test_db_sync_state();
-        test_failed_scenarios();
-        test_block_creation_config();
+        let config: Configuration = Configuration::from_file("../../config.toml").unwrap();
+        let db: Db = Db::open(&config.db.dbpath).unwrap();
+        let context = NodeContext::new(Arc::new(config), db, AppData::new());
+        test_db_initialization_check(&context);
+        test_db_read_write_check(&context);
+        test_peer_service(&context);
+        test_db_state_context(&context);
+        test_db_app_state_context(&context);
+        test_db_legacy_migration(&context);
+        test_db_app_registry(&context);
+        test_db_determinism_check(&context);
+        test_block_timestamp_check(&context);
+        test_block_txns_check(&context);
+        test_db_sync_state(&context);
+        test_failed_scenarios(&context);
+        test_block_creation_config(&context);
+        test_close_db_flush(context);
     }
 }
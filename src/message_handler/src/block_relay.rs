@@ -0,0 +1,225 @@
+use super::peer_request::{PeerRequest, PeerRequests, PeerResponse};
+use exonum_crypto::Hash;
+use exonum_merkledb::ObjectHash;
+use libp2p::{identity::PublicKey, PeerId};
+use schema::block::SignedBlock;
+use schema::compact_block::CompactBlock;
+use schema::signed_transaction::SignedTransaction;
+use schema::transaction_pool::{Pool, TxnPool, TxnPoolKeyType};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use utils::configreader::BlockConfig;
+
+/// blocks whose transactions are kept for the peers rebuilding them
+pub const MAX_RECENT_BLOCKS: usize = 64;
+
+/// RecentBlocks keeps the transactions of the last blocks this node relayed.
+/// They leave the pool once the block is committed, peers missing some of
+/// them ask this node for them by index.
+#[derive(Debug, Default)]
+pub struct RecentBlocks {
+    // salt of the compact block => its transactions in block order
+    blocks: HashMap<Hash, Vec<Option<SignedTransaction>>>,
+    order: VecDeque<Hash>,
+}
+
+impl RecentBlocks {
+    pub fn new() -> RecentBlocks {
+        RecentBlocks::default()
+    }
+
+    pub fn insert(&mut self, salt: Hash, txns: Vec<Option<SignedTransaction>>) {
+        if self.blocks.insert(salt, txns).is_none() {
+            self.order.push_back(salt);
+        }
+        while self.order.len() > MAX_RECENT_BLOCKS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.blocks.remove(&oldest);
+            }
+        }
+    }
+
+    /// transactions at indexes of the block, None if any of them is unknown
+    pub fn transactions(&self, salt: &Hash, indexes: &Vec<u32>) -> Option<Vec<SignedTransaction>> {
+        let txns: &Vec<Option<SignedTransaction>> = self.blocks.get(salt)?;
+        indexes
+            .iter()
+            .map(|index| txns.get(*index as usize).cloned().unwrap_or(None))
+            .collect()
+    }
+}
+
+fn author_peer_id(pk: &String) -> Option<PeerId> {
+    match utils::keypair::PublicKey::from_string(pk) {
+        Some(public_key) => Some(PeerId::from_public_key(PublicKey::Ed25519(public_key))),
+        None => None,
+    }
+}
+
+/// check a gossiped compact_block against the limits of block_config, its
+/// signature is checked once the block is rebuilt
+pub fn check_compact_block(
+    compact_block: &CompactBlock,
+    block_config: &BlockConfig,
+) -> Result<(), String> {
+    if compact_block.short_ids.len() as u64 > block_config.block_transaction_limit {
+        return Err(format!(
+            "block has {} transactions, the limit is {}",
+            compact_block.short_ids.len(),
+            block_config.block_transaction_limit
+        ));
+    }
+    if !compact_block.header.txn_pool.is_empty() {
+        return Err(String::from("compact block with a txn_pool"));
+    }
+    if utils::keypair::PublicKey::from_string(&compact_block.header.peer_id).is_none() {
+        return Err(String::from("compact block of an invalid author"));
+    }
+    Ok(())
+}
+
+/// BlockRelay relays the blocks of a node as compact blocks & rebuilds the
+/// compact blocks of its peers from its pool. The clones share the pool &
+/// the recent blocks.
+#[derive(Clone)]
+pub struct BlockRelay {
+    pool: Pool,
+    peer_requests: Arc<PeerRequests>,
+    recent_blocks: Arc<Mutex<RecentBlocks>>,
+}
+
+impl BlockRelay {
+    pub fn new(
+        pool: Pool,
+        peer_requests: Arc<PeerRequests>,
+        recent_blocks: Arc<Mutex<RecentBlocks>>,
+    ) -> BlockRelay {
+        BlockRelay {
+            pool,
+            peer_requests,
+            recent_blocks,
+        }
+    }
+
+    fn insert_in_pool(&self, txn: &SignedTransaction) -> bool {
+        match txn.header.get(&String::from("timestamp")) {
+            Some(string) => match string.parse::<TxnPoolKeyType>() {
+                Ok(timestamp) => {
+                    self.pool.insert_op(&timestamp, txn);
+                    true
+                }
+                Err(_) => false,
+            },
+            None => false,
+        }
+    }
+
+    fn remember_block(&self, compact_block: &CompactBlock, signed_block: &SignedBlock) {
+        let txns: Vec<Option<SignedTransaction>> = signed_block
+            .block
+            .txn_pool
+            .iter()
+            .map(|txn_hash| self.pool.get(txn_hash))
+            .collect();
+        self.recent_blocks
+            .lock()
+            .unwrap()
+            .insert(compact_block.salt(), txns);
+    }
+
+    /// compact form of a block this node relays, its transactions have to be in
+    /// the pool still so peers can fetch the ones they miss
+    pub fn compact_block(&self, signed_block: &SignedBlock) -> CompactBlock {
+        let compact_block: CompactBlock = CompactBlock::new(signed_block);
+        self.remember_block(&compact_block, signed_block);
+        compact_block
+    }
+
+    /// missing transactions of compact_block from the block author, or else any
+    /// connected peer, in a single request
+    fn fetch_block_transactions(
+        &self,
+        compact_block: &CompactBlock,
+        missing: &Vec<u32>,
+    ) -> Option<Vec<SignedTransaction>> {
+        let request = PeerRequest::FetchBlockTransactions(compact_block.salt(), missing.clone());
+        let mut peers: Vec<Option<PeerId>> = vec![None];
+        if let Some(author) = author_peer_id(&compact_block.header.peer_id) {
+            peers.insert(0, Some(author));
+        }
+        for peer_id in peers {
+            if let Some(PeerResponse::Transactions(txns)) =
+                self.peer_requests.request(peer_id, request.clone())
+            {
+                if txns.len() == missing.len() {
+                    return Some(txns);
+                }
+            }
+        }
+        None
+    }
+
+    /// rebuild the block announced by compact_block from the pool, fetching the
+    /// missing transactions. The fetched transactions are added to the pool.
+    /// None if the block couldn't be rebuilt or doesn't match its signature.
+    pub fn reconstruct_block(&self, compact_block: &CompactBlock) -> Option<SignedBlock> {
+        let mut txn_pool: Vec<Option<Hash>> = {
+            let pool = self.pool.pool.lock().unwrap();
+            compact_block.match_txns(pool.txn_hashes())
+        };
+        let missing: Vec<u32> = txn_pool
+            .iter()
+            .enumerate()
+            .filter(|(_, txn_hash)| txn_hash.is_none())
+            .map(|(index, _)| index as u32)
+            .collect();
+        if !missing.is_empty() {
+            info!(
+                "block {} misses {} of {} transactions",
+                compact_block.header.id,
+                missing.len(),
+                txn_pool.len()
+            );
+            let txns: Vec<SignedTransaction> =
+                match self.fetch_block_transactions(compact_block, &missing) {
+                    Some(txns) => txns,
+                    None => {
+                        warn!(
+                            "missing transactions of block {} couldn't be fetched",
+                            compact_block.header.id
+                        );
+                        return None;
+                    }
+                };
+            for (index, txn) in missing.iter().zip(txns.iter()) {
+                let txn_hash: Hash = txn.object_hash();
+                if !compact_block.matches(*index as usize, &txn_hash) || !txn.verify() {
+                    warn!(
+                        "peer sent a wrong transaction for block {}",
+                        compact_block.header.id
+                    );
+                    return None;
+                }
+                if !self.insert_in_pool(txn) {
+                    return None;
+                }
+                txn_pool[*index as usize] = Some(txn_hash);
+            }
+        }
+        let txn_pool: Vec<Hash> = txn_pool
+            .into_iter()
+            .filter_map(|txn_hash| txn_hash)
+            .collect();
+        let signed_block: SignedBlock = compact_block.to_signed_block(txn_pool);
+        if !signed_block.validate() {
+            warn!(
+                "rebuilt block {} doesn't match its signature",
+                compact_block.header.id
+            );
+            return None;
+        }
+        // peers may rebuild the block from this node too
+        self.remember_block(compact_block, &signed_block);
+        Some(signed_block)
+    }
+}
@@ -0,0 +1,53 @@
+use utils::serializer::{Deserialize, Serialize};
+
+/// version of the wire format, bump it on any change of MessageTypes,
+/// NodeMessageTypes, the consensus message types or PeerRequest
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// versions this node reads, highest first
+pub const SUPPORTED_VERSIONS: &[u32] = &[PROTOCOL_VERSION];
+
+/// MessageKind tells how the payload of an Envelope is encoded
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum MessageKind {
+    Node,
+    Consensus,
+}
+
+/// Envelope wraps every gossip message, so nodes running another protocol
+/// version or chain reject it explicitly instead of failing to decode it.
+/// Its own layout must never change.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub version: u32,
+    pub chain_id: String,
+    pub kind: MessageKind,
+    pub payload: Vec<u8>,
+}
+
+impl Envelope {
+    /// envelope of a payload sent by a node of chain_id
+    pub fn seal(chain_id: &String, kind: MessageKind, payload: Vec<u8>) -> Envelope {
+        Envelope {
+            version: PROTOCOL_VERSION,
+            chain_id: chain_id.clone(),
+            kind,
+            payload,
+        }
+    }
+
+    /// payload of the envelope, Err unless it has a supported version,
+    /// chain_id & kind
+    pub fn open(self, chain_id: &String, kind: MessageKind) -> Result<Vec<u8>, String> {
+        if !SUPPORTED_VERSIONS.contains(&self.version) {
+            return Err(format!("unsupported protocol version {}", self.version));
+        }
+        if &self.chain_id != chain_id {
+            return Err(format!("message of chain {}", self.chain_id));
+        }
+        if self.kind != kind {
+            return Err(format!("{:?} message on the {:?} topic", self.kind, kind));
+        }
+        Ok(self.payload)
+    }
+}
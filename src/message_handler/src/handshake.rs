@@ -0,0 +1,54 @@
+use super::envelope::SUPPORTED_VERSIONS;
+use exonum_crypto::Hash;
+use std::sync::Arc;
+use utils::serializer::{Deserialize, Serialize};
+
+/// Handshake is exchanged by nodes once connected, peers which share no
+/// protocol version, run another chain or started from another genesis
+/// block are refused
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Handshake {
+    pub versions: Vec<u32>,
+    pub chain_id: String,
+    /// None until the node has a genesis block
+    pub genesis_hash: Option<Hash>,
+}
+
+/// GenesisHashProvider reads the genesis block hash of a node, it is
+/// handed to the swarm by the node, which has access to the db
+pub type GenesisHashProvider = Arc<dyn Fn() -> Option<Hash> + Send + Sync>;
+
+impl Handshake {
+    /// handshake of a node of chain_id with genesis_hash
+    pub fn local(chain_id: &String, genesis_hash: Option<Hash>) -> Handshake {
+        Handshake {
+            versions: SUPPORTED_VERSIONS.to_vec(),
+            chain_id: chain_id.clone(),
+            genesis_hash,
+        }
+    }
+
+    /// highest protocol version both nodes support, Err if the peer is
+    /// incompatible. A node without genesis block can join any chain.
+    pub fn negotiate(&self, peer: &Handshake) -> Result<u32, String> {
+        if self.chain_id != peer.chain_id {
+            return Err(format!("peer runs chain {}", peer.chain_id));
+        }
+        if let (Some(genesis_hash), Some(peer_genesis_hash)) =
+            (self.genesis_hash, peer.genesis_hash)
+        {
+            if genesis_hash != peer_genesis_hash {
+                return Err(format!("peer has genesis block {}", peer_genesis_hash));
+            }
+        }
+        self.versions
+            .iter()
+            .filter(|version| peer.versions.contains(version))
+            .max()
+            .cloned()
+            .ok_or(format!(
+                "no common protocol version, peer supports {:?}",
+                peer.versions
+            ))
+    }
+}
@@ -1,12 +1,15 @@
-#[macro_use]
-extern crate lazy_static;
-
 #[macro_use]
 extern crate log;
 
+pub mod block_relay;
 pub mod constants;
+pub mod envelope;
+pub mod handshake;
 pub mod message_sender;
 pub mod message_traits;
 pub mod messages;
 pub mod node_messages;
+pub mod peer_request;
+pub mod queues;
+pub mod shutdown;
 pub mod tests;
@@ -1,14 +1,17 @@
+use crate::block_relay::BlockRelay;
 use crate::messages::MessageTypes;
 use crate::node_messages::NodeMessageTypes;
+use crate::queues::GossipSender;
 use futures::channel::mpsc::*;
 use schema::{block::SignedBlock, signed_transaction::SignedTransaction};
 
 pub struct MessageSender {}
 
 impl MessageSender {
-    pub fn send_block_msg(sender: &mut Sender<Option<MessageTypes>>, msg: SignedBlock) {
-        let data = Some(MessageTypes::NodeMsg(NodeMessageTypes::SignedBlockEnum(
-            msg,
+    /// relay msg as a CompactBlock, its transactions must still be in the pool
+    pub fn send_block_msg(sender: &mut GossipSender, relay: &BlockRelay, msg: SignedBlock) {
+        let data = Some(MessageTypes::NodeMsg(NodeMessageTypes::CompactBlockEnum(
+            relay.compact_block(&msg),
         )));
         let error: Result<(), TrySendError<Option<MessageTypes>>> = sender.try_send(data);
         if error.is_err() {
@@ -16,13 +19,16 @@ impl MessageSender {
         }
     }
 
-    pub fn send_transaction_msg(sender: &mut Sender<Option<MessageTypes>>, msg: SignedTransaction) {
+    /// queue msg for gossip, false if the gossip queue is full
+    pub fn send_transaction_msg(sender: &mut GossipSender, msg: SignedTransaction) -> bool {
         let data = Some(MessageTypes::NodeMsg(
             NodeMessageTypes::SignedTransactionEnum(msg),
         ));
         let error: Result<(), TrySendError<Option<MessageTypes>>> = sender.try_send(data);
         if error.is_err() {
             error!("{:?}", error);
+            return false;
         }
+        true
     }
 }
@@ -1,9 +1,18 @@
+use super::block_relay::check_compact_block;
 use super::constants;
+use super::envelope::{Envelope, MessageKind};
 use super::node_messages::NodeMessageTypes;
+use super::queues::{
+    INBOUND_CONSENSUS_CAPACITY, INBOUND_CONSENSUS_QUEUE, INBOUND_NODE_CAPACITY, INBOUND_NODE_QUEUE,
+};
+use exonum_merkledb::ObjectHash;
 use futures::{channel::mpsc::channel, channel::mpsc::Receiver, channel::mpsc::Sender};
-use libp2p::floodsub::{protocol, Topic};
+use libp2p::gossipsub::{GossipsubMessage, Topic, TopicHash};
 use std::sync::{Arc, Mutex};
-use utils::serializer::{deserialize, Deserialize, Serialize};
+use utils::configreader::{BlockConfig, Configuration};
+use utils::peer_reports::PeerReports;
+use utils::queue_metrics::QueueMetrics;
+use utils::serializer::{deserialize_bounded, Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum MessageTypes {
@@ -17,78 +26,236 @@ impl From<MessageTypes> for Vec<Topic> {
         let mut ret: Vec<Topic> = Vec::new();
         match msg {
             MessageTypes::NodeMsg(_data) => {
-                ret.push(Topic::new(constants::NODE));
+                ret.push(Topic::new(String::from(constants::NODE)));
             }
             MessageTypes::ConsensusMsg(_data) => {
-                ret.push(Topic::new(constants::CONSENSUS));
+                ret.push(Topic::new(String::from(constants::CONSENSUS)));
             }
         }
         ret
     }
 }
 
-///Process FloodSubMessages
-///
+/// MsgValidation is the verdict on a gossiped message. Accepted messages are
+/// dispatched & forwarded to other peers, rejected ones are dropped and count
+/// against the peer which sent them. Ignored messages are valid but couldn't
+/// be queued, they are neither forwarded nor held against the peer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MsgValidation {
+    Accept,
+    Reject(String),
+    Ignore(String),
+}
+
+/// ConsensusValidator checks the structure & signatures of a consensus
+/// message, it is registered by the consensus engine the node runs
+pub type ConsensusValidator = Box<dyn Fn(&Vec<u8>) -> Result<(), String> + Send>;
+
+///Process GossipsubMessages, validate them against config and dispatch the
+///valid ones with dispatcher, transactions are traced to their publisher in
+///peer_reports
 pub trait MsgProcess {
-    fn process(&self, topics: &Vec<Topic>, data: &Vec<u8>);
+    fn process(
+        &self,
+        topics: &Vec<TopicHash>,
+        data: &Vec<u8>,
+        config: &Configuration,
+        dispatcher: &MessageDispatcher,
+        peer_reports: &Mutex<PeerReports>,
+    ) -> MsgValidation;
 }
 
-impl MsgProcess for protocol::FloodsubMessage {
-    fn process(&self, topics: &Vec<Topic>, data: &Vec<u8>) {
-        if topics[0] == Topic::new(constants::NODE) {
+/// check a gossiped node message against the size limits of block_config
+/// and the signature of the transaction or block it carries
+pub fn validate_node_msg(msg: &NodeMessageTypes, block_config: &BlockConfig) -> Result<(), String> {
+    match msg {
+        NodeMessageTypes::SignedTransactionEnum(txn) => {
+            txn.check_size(block_config)?;
+            if !txn.verify() {
+                return Err(String::from("transaction signature couldn't verify"));
+            }
+            Ok(())
+        }
+        NodeMessageTypes::SignedBlockEnum(signed_block) => {
+            if signed_block.block.txn_pool.len() as u64 > block_config.block_transaction_limit {
+                return Err(format!(
+                    "block has {} transactions, the limit is {}",
+                    signed_block.block.txn_pool.len(),
+                    block_config.block_transaction_limit
+                ));
+            }
+            if !signed_block.validate() {
+                return Err(String::from("block signature couldn't verify"));
+            }
+            Ok(())
+        }
+        NodeMessageTypes::CompactBlockEnum(compact_block) => {
+            check_compact_block(compact_block, block_config)
+        }
+    }
+}
+
+/// payload of a gossiped Envelope of kind, Err if it is malformed or of
+/// another protocol version or chain than chain_id
+pub fn open_envelope(
+    data: &Vec<u8>,
+    kind: MessageKind,
+    chain_id: &String,
+    block_config: &BlockConfig,
+) -> Result<Vec<u8>, String> {
+    let envelope: Envelope = deserialize_bounded(data, block_config.max_message_size)?;
+    envelope.open(chain_id, kind)
+}
+
+impl MsgProcess for GossipsubMessage {
+    fn process(
+        &self,
+        topics: &Vec<TopicHash>,
+        data: &Vec<u8>,
+        config: &Configuration,
+        dispatcher: &MessageDispatcher,
+        peer_reports: &Mutex<PeerReports>,
+    ) -> MsgValidation {
+        let chain_id: &String = &config.node.chain_id;
+        let block_config: &BlockConfig = &config.block_config;
+        if topics.is_empty() {
+            return MsgValidation::Reject(String::from("message without topic"));
+        }
+        if topics[0] == Topic::new(String::from(constants::NODE)).no_hash() {
             debug!("NodeMessageTypes data received");
-            if let Ok(deserialize_msg) = deserialize::<NodeMessageTypes>(data) {
-                let result = MSG_DISPATCHER
-                    .node_msg_dispatcher
-                    .clone()
-                    .try_send(Some(deserialize_msg));
-                if result.is_err() {
-                    result.unwrap_err().into_send_error();
-                }
+            let payload: Vec<u8> =
+                match open_envelope(data, MessageKind::Node, chain_id, block_config) {
+                    Result::Ok(payload) => payload,
+                    Result::Err(error) => return MsgValidation::Reject(error),
+                };
+            let deserialize_msg: NodeMessageTypes =
+                match deserialize_bounded(&payload, block_config.max_message_size) {
+                    Result::Ok(msg) => msg,
+                    Result::Err(error) => return MsgValidation::Reject(error),
+                };
+            if let Err(error) = validate_node_msg(&deserialize_msg, block_config) {
+                return MsgValidation::Reject(error);
             }
-        } else if topics[0] == Topic::new(constants::CONSENSUS) {
+            // the pool reports transactions it refuses against their publisher
+            if let (NodeMessageTypes::SignedTransactionEnum(txn), Some(source)) =
+                (&deserialize_msg, &self.source)
+            {
+                peer_reports
+                    .lock()
+                    .unwrap()
+                    .record_origin(txn.object_hash().to_hex(), source.clone());
+            }
+            if !dispatcher.dispatch_node_msg(deserialize_msg) {
+                return MsgValidation::Ignore(String::from("node message queue is full"));
+            }
+            MsgValidation::Accept
+        } else if topics[0] == Topic::new(String::from(constants::CONSENSUS)).no_hash() {
             debug!("ConsensusMessageTypes data received");
-            if let Ok(deserialize_msg) = deserialize::<Vec<u8>>(data) {
-                let result = MSG_DISPATCHER
-                    .consensus_msg_dispatcher
-                    .clone()
-                    .try_send(Some(deserialize_msg));
-                if result.is_err() {
-                    result.unwrap_err().into_send_error();
-                }
+            let payload: Vec<u8> =
+                match open_envelope(data, MessageKind::Consensus, chain_id, block_config) {
+                    Result::Ok(payload) => payload,
+                    Result::Err(error) => return MsgValidation::Reject(error),
+                };
+            let deserialize_msg: Vec<u8> =
+                match deserialize_bounded(&payload, block_config.max_message_size) {
+                    Result::Ok(msg) => msg,
+                    Result::Err(error) => return MsgValidation::Reject(error),
+                };
+            if let Err(error) = dispatcher.validate_consensus_msg(&deserialize_msg) {
+                return MsgValidation::Reject(error);
             }
+            if !dispatcher.dispatch_consensus_msg(deserialize_msg) {
+                return MsgValidation::Ignore(String::from("consensus message queue is full"));
+            }
+            MsgValidation::Accept
+        } else {
+            MsgValidation::Reject(String::from("message on an unknown topic"))
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// MessageDispatcher queues the gossiped messages for the node & consensus
+/// threads. Every clone of a futures Sender may queue one message beyond the
+/// capacity, so the senders are shared rather than cloned per message.
+/// Each node has its own dispatcher, the clones share its queues.
+#[derive(Clone)]
 pub struct MessageDispatcher {
-    pub node_msg_dispatcher: Sender<Option<NodeMessageTypes>>,
+    pub node_msg_dispatcher: Arc<Mutex<Sender<Option<NodeMessageTypes>>>>,
     pub node_msg_receiver: Arc<Mutex<Receiver<Option<NodeMessageTypes>>>>,
-    pub consensus_msg_dispatcher: Sender<Option<Vec<u8>>>,
+    pub consensus_msg_dispatcher: Arc<Mutex<Sender<Option<Vec<u8>>>>>,
     pub consensus_msg_receiver: Arc<Mutex<Receiver<Option<Vec<u8>>>>>,
+    consensus_validator: Arc<Mutex<Option<ConsensusValidator>>>,
+    queue_metrics: Arc<Mutex<QueueMetrics>>,
 }
 
 impl MessageDispatcher {
-    pub fn new() -> Self {
-        let (tx, rx) = channel::<Option<NodeMessageTypes>>(1024);
-        let (tx_consensus, rx_consensus) = channel::<Option<Vec<u8>>>(1024);
+    pub fn new(queue_metrics: &Arc<Mutex<QueueMetrics>>) -> Self {
+        let (tx, rx) = channel::<Option<NodeMessageTypes>>(INBOUND_NODE_CAPACITY);
+        let (tx_consensus, rx_consensus) = channel::<Option<Vec<u8>>>(INBOUND_CONSENSUS_CAPACITY);
+        {
+            let mut metrics = queue_metrics.lock().unwrap();
+            metrics.register(INBOUND_NODE_QUEUE, INBOUND_NODE_CAPACITY);
+            metrics.register(INBOUND_CONSENSUS_QUEUE, INBOUND_CONSENSUS_CAPACITY);
+        }
         MessageDispatcher {
-            node_msg_dispatcher: tx,
+            node_msg_dispatcher: Arc::new(Mutex::new(tx)),
             node_msg_receiver: Arc::new(Mutex::new(rx)),
-            consensus_msg_dispatcher: tx_consensus,
+            consensus_msg_dispatcher: Arc::new(Mutex::new(tx_consensus)),
             consensus_msg_receiver: Arc::new(Mutex::new(rx_consensus)),
+            consensus_validator: Arc::new(Mutex::new(None)),
+            queue_metrics: queue_metrics.clone(),
         }
     }
     pub fn set_node_msg_dispatcher(&mut self, tx: &Sender<Option<NodeMessageTypes>>) {
-        self.node_msg_dispatcher = tx.clone();
+        *self.node_msg_dispatcher.lock().unwrap() = tx.clone();
     }
 
     pub fn set_consensus_msg_dispatcher(&mut self, tx: &Sender<Option<Vec<u8>>>) {
-        self.consensus_msg_dispatcher = tx.clone();
+        *self.consensus_msg_dispatcher.lock().unwrap() = tx.clone();
+    }
+
+    /// set the validator of consensus messages, without one any consensus
+    /// message within the size limit is accepted
+    pub fn set_consensus_validator(&self, validator: ConsensusValidator) {
+        *self.consensus_validator.lock().unwrap() = Some(validator);
     }
-}
 
-lazy_static! {
-    pub static ref MSG_DISPATCHER: MessageDispatcher = MessageDispatcher::new();
+    /// check a gossiped consensus message with the registered ConsensusValidator
+    pub fn validate_consensus_msg(&self, msg: &Vec<u8>) -> Result<(), String> {
+        match self.consensus_validator.lock().unwrap().as_ref() {
+            Some(validator) => validator(msg),
+            None => Ok(()),
+        }
+    }
+
+    /// queue msg for the NodeMsgProcessor, false if the queue is full
+    pub fn dispatch_node_msg(&self, msg: NodeMessageTypes) -> bool {
+        let result = self.node_msg_dispatcher.lock().unwrap().try_send(Some(msg));
+        if result.is_err() {
+            warn!("node message dropped, {} is full", INBOUND_NODE_QUEUE);
+        }
+        self.queue_metrics
+            .lock()
+            .unwrap()
+            .record(INBOUND_NODE_QUEUE, &result)
+    }
+
+    /// queue msg for the consensus engine, false if the queue is full
+    pub fn dispatch_consensus_msg(&self, msg: Vec<u8>) -> bool {
+        let result = self
+            .consensus_msg_dispatcher
+            .lock()
+            .unwrap()
+            .try_send(Some(msg));
+        if result.is_err() {
+            warn!(
+                "consensus message dropped, {} is full",
+                INBOUND_CONSENSUS_QUEUE
+            );
+        }
+        self.queue_metrics
+            .lock()
+            .unwrap()
+            .record(INBOUND_CONSENSUS_QUEUE, &result)
+    }
 }
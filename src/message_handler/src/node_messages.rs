@@ -1,16 +1,20 @@
 use super::constants;
 use super::message_traits::Message;
-use libp2p::floodsub::Topic;
+use libp2p::gossipsub::Topic;
 use schema::block::SignedBlock;
+use schema::compact_block::CompactBlock;
 use schema::signed_transaction::SignedTransaction;
 use utils::serializer::{Deserialize, Serialize};
 
-pub const NODE_MSG_TOPIC_STR: &'static [&'static str] = &["SignedTransaction", "SignedBlock"];
+pub const NODE_MSG_TOPIC_STR: &'static [&'static str] =
+    &["SignedTransaction", "SignedBlock", "CompactBlock"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NodeMessageTypes {
     SignedTransactionEnum(SignedTransaction),
     SignedBlockEnum(SignedBlock),
+    /// blocks are relayed compact, see block_relay
+    CompactBlockEnum(CompactBlock),
 }
 
 impl Message for SignedTransaction {
@@ -29,12 +33,21 @@ impl Message for SignedBlock {
     }
 }
 
+impl Message for CompactBlock {
+    const TOPIC: &'static str = NODE_MSG_TOPIC_STR[2];
+    const MODULE_TOPIC: &'static str = constants::NODE;
+    fn handler(&self) {
+        info!("i am CompactBlock handler");
+    }
+}
+
 //TODO : Try using macro to implement this for all variations
 impl From<NodeMessageTypes> for Topic {
     fn from(msg: NodeMessageTypes) -> Topic {
         match msg {
             NodeMessageTypes::SignedBlockEnum(data) => Topic::new(data.topic()),
             NodeMessageTypes::SignedTransactionEnum(data) => Topic::new(data.topic()),
+            NodeMessageTypes::CompactBlockEnum(data) => Topic::new(data.topic()),
         }
     }
 }
@@ -0,0 +1,133 @@
+use super::handshake::Handshake;
+use super::queues::{PEER_REQUEST_CAPACITY, PEER_REQUEST_QUEUE};
+use exonum_crypto::Hash;
+use futures::channel::mpsc::{channel, Receiver, Sender};
+use libp2p::PeerId;
+use schema::block::SignedBlock;
+use schema::signed_transaction::SignedTransaction;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
+use utils::queue_metrics::QueueMetrics;
+use utils::serializer::{Deserialize, Serialize};
+
+/// libp2p protocol nodes fetch blocks & transactions from each other with
+pub const PEER_PROTOCOL: &[u8] = b"/simpleblockchain/peer/1.0.0";
+
+/// time a peer has to answer a PeerRequest
+pub const PEER_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// PeerRequest is what a node can ask another node over the p2p connection
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PeerRequest {
+    FetchBlock(u64),
+    FetchLatestBlock,
+    FetchBlockchainLength,
+    /// transaction of the pool or of the chain
+    FetchTransaction(Hash),
+    /// transactions at the indexes of the compact block with the salt
+    FetchBlockTransactions(Hash, Vec<u32>),
+    /// transactions of the pool or of the chain, in one request
+    FetchTransactions(Vec<Hash>),
+    /// answered by the p2p layer with the handshake of the node
+    Handshake(Handshake),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PeerResponse {
+    Block(Option<SignedBlock>),
+    BlockchainLength(u64),
+    Transaction(Option<SignedTransaction>),
+    /// empty if any of the requested transactions is unknown
+    Transactions(Vec<SignedTransaction>),
+    Handshake(Handshake),
+}
+
+/// PeerRequestHandler answers the requests of other peers, it is handed to
+/// the swarm by the node, which has access to the db & the pool
+pub type PeerRequestHandler = Arc<dyn Fn(PeerRequest) -> PeerResponse + Send + Sync>;
+
+/// OutboundPeerRequest is a request of this node, the swarm sends it to
+/// peer_id (any connected peer for None) and replies the peer which answered
+/// with its response, None if the request failed
+pub struct OutboundPeerRequest {
+    pub peer_id: Option<PeerId>,
+    pub request: PeerRequest,
+    pub reply: mpsc::Sender<Option<(PeerId, PeerResponse)>>,
+}
+
+/// PeerRequests hands the requests of the node's threads to the swarm of
+/// the node
+pub struct PeerRequests {
+    pub sender: Mutex<Sender<OutboundPeerRequest>>,
+    pub receiver: Arc<Mutex<Receiver<OutboundPeerRequest>>>,
+    // set once a swarm polls the receiver
+    serving: AtomicBool,
+    queue_metrics: Arc<Mutex<QueueMetrics>>,
+}
+
+impl PeerRequests {
+    pub fn new(queue_metrics: &Arc<Mutex<QueueMetrics>>) -> Self {
+        let (sender, receiver) = channel::<OutboundPeerRequest>(PEER_REQUEST_CAPACITY);
+        queue_metrics
+            .lock()
+            .unwrap()
+            .register(PEER_REQUEST_QUEUE, PEER_REQUEST_CAPACITY);
+        PeerRequests {
+            sender: Mutex::new(sender),
+            receiver: Arc::new(Mutex::new(receiver)),
+            serving: AtomicBool::new(false),
+            queue_metrics: queue_metrics.clone(),
+        }
+    }
+
+    /// receiver of the requests, for the swarm
+    pub fn serve(&self) -> Arc<Mutex<Receiver<OutboundPeerRequest>>> {
+        self.serving.store(true, Ordering::SeqCst);
+        self.receiver.clone()
+    }
+
+    /// true once a swarm polls the requests
+    pub fn is_serving(&self) -> bool {
+        self.serving.load(Ordering::SeqCst)
+    }
+
+    /// send request to peer_id through the swarm & wait for the response,
+    /// None at once when no swarm runs, e.g. in tests & tools
+    pub fn request(&self, peer_id: Option<PeerId>, request: PeerRequest) -> Option<PeerResponse> {
+        self.request_peer(peer_id, request)
+            .map(|(_, response)| response)
+    }
+
+    /// like request, with the peer which answered, so follow-up requests can
+    /// go to the same peer
+    pub fn request_peer(
+        &self,
+        peer_id: Option<PeerId>,
+        request: PeerRequest,
+    ) -> Option<(PeerId, PeerResponse)> {
+        if !self.is_serving() {
+            return None;
+        }
+        let (reply, response) = mpsc::channel::<Option<(PeerId, PeerResponse)>>();
+        let outbound_request = OutboundPeerRequest {
+            peer_id,
+            request,
+            reply,
+        };
+        let result = self.sender.lock().unwrap().try_send(outbound_request);
+        if !self
+            .queue_metrics
+            .lock()
+            .unwrap()
+            .record(PEER_REQUEST_QUEUE, &result)
+        {
+            warn!("peer request dropped, {} is full", PEER_REQUEST_QUEUE);
+            return None;
+        }
+        match response.recv_timeout(PEER_REQUEST_TIMEOUT) {
+            Result::Ok(response) => response,
+            Result::Err(_) => None,
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use super::messages::MessageTypes;
+use futures::channel::mpsc::{channel, Receiver, Sender, TryRecvError, TrySendError};
+use futures::{task::Context, task::Poll, Stream, StreamExt};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use utils::queue_metrics::QueueMetrics;
+
+/// consensus messages of this node waiting for the swarm
+pub const GOSSIP_CONSENSUS_QUEUE: &str = "gossip_consensus";
+pub const GOSSIP_CONSENSUS_CAPACITY: usize = 1024;
+/// blocks & transactions of this node waiting for the swarm
+pub const GOSSIP_NODE_QUEUE: &str = "gossip_node";
+pub const GOSSIP_NODE_CAPACITY: usize = 4096;
+/// gossiped node messages waiting for the NodeMsgProcessor
+pub const INBOUND_NODE_QUEUE: &str = "inbound_node";
+pub const INBOUND_NODE_CAPACITY: usize = 1024;
+/// gossiped consensus messages waiting for the consensus engine
+pub const INBOUND_CONSENSUS_QUEUE: &str = "inbound_consensus";
+pub const INBOUND_CONSENSUS_CAPACITY: usize = 1024;
+/// requests of the node's threads waiting for the swarm
+pub const PEER_REQUEST_QUEUE: &str = "peer_requests";
+pub const PEER_REQUEST_CAPACITY: usize = 1024;
+/// gossiped blocks waiting to be applied
+pub const PENDING_BLOCK_QUEUE: &str = "pending_blocks";
+pub const PENDING_BLOCK_CAPACITY: usize = 256;
+
+/// GossipSender hands the messages of this node to the swarm. Consensus
+/// messages have a queue of their own which the swarm drains first, so a
+/// burst of transactions can't delay them. A full queue refuses the message
+/// and counts it as dropped, the caller decides what to do about it.
+#[derive(Debug, Clone)]
+pub struct GossipSender {
+    consensus: Sender<Option<MessageTypes>>,
+    node: Sender<Option<MessageTypes>>,
+    queue_metrics: Arc<Mutex<QueueMetrics>>,
+}
+
+impl GossipSender {
+    pub fn try_send(
+        &mut self,
+        msg: Option<MessageTypes>,
+    ) -> Result<(), TrySendError<Option<MessageTypes>>> {
+        let (queue, sender) = match msg {
+            Some(MessageTypes::ConsensusMsg(_)) => (GOSSIP_CONSENSUS_QUEUE, &mut self.consensus),
+            _ => (GOSSIP_NODE_QUEUE, &mut self.node),
+        };
+        let result = sender.try_send(msg);
+        self.queue_metrics.lock().unwrap().record(queue, &result);
+        result
+    }
+}
+
+/// GossipReceiver yields the consensus messages before the node messages
+#[derive(Debug)]
+pub struct GossipReceiver {
+    consensus: Receiver<Option<MessageTypes>>,
+    node: Receiver<Option<MessageTypes>>,
+}
+
+impl GossipReceiver {
+    pub fn try_next(&mut self) -> Result<Option<Option<MessageTypes>>, TryRecvError> {
+        match self.consensus.try_next() {
+            Result::Ok(Some(msg)) => Ok(Some(msg)),
+            _ => self.node.try_next(),
+        }
+    }
+}
+
+impl Stream for GossipReceiver {
+    type Item = Option<MessageTypes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        // both queues close together as every GossipSender holds the two
+        if let Poll::Ready(Some(msg)) = self.consensus.poll_next_unpin(cx) {
+            return Poll::Ready(Some(msg));
+        }
+        self.node.poll_next_unpin(cx)
+    }
+}
+
+/// the bounded gossip queues of a swarm, counted in queue_metrics
+pub fn gossip_channel(queue_metrics: &Arc<Mutex<QueueMetrics>>) -> (GossipSender, GossipReceiver) {
+    let (consensus_sender, consensus_receiver) = channel(GOSSIP_CONSENSUS_CAPACITY);
+    let (node_sender, node_receiver) = channel(GOSSIP_NODE_CAPACITY);
+    {
+        let mut metrics = queue_metrics.lock().unwrap();
+        metrics.register(GOSSIP_CONSENSUS_QUEUE, GOSSIP_CONSENSUS_CAPACITY);
+        metrics.register(GOSSIP_NODE_QUEUE, GOSSIP_NODE_CAPACITY);
+    }
+    (
+        GossipSender {
+            consensus: consensus_sender,
+            node: node_sender,
+            queue_metrics: queue_metrics.clone(),
+        },
+        GossipReceiver {
+            consensus: consensus_receiver,
+            node: node_receiver,
+        },
+    )
+}
@@ -0,0 +1,69 @@
+use futures::future::{self, Either, Future};
+use futures::task::{Context, Poll, Waker};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::delay_for;
+
+#[derive(Debug, Default)]
+struct ShutdownState {
+    triggered: bool,
+    wakers: Vec<Waker>,
+}
+
+/// Shutdown tells the tasks & threads of a node to stop. Tasks await wait()
+/// or pause() between their steps, blocking loops check is_triggered().
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown {
+    state: Arc<Mutex<ShutdownState>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Shutdown {
+        Shutdown::default()
+    }
+
+    /// stop everything waiting on this Shutdown, it stays triggered
+    pub fn trigger(&self) {
+        let wakers: Vec<Waker> = {
+            let mut state = self.state.lock().unwrap();
+            state.triggered = true;
+            std::mem::replace(&mut state.wakers, Vec::new())
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.state.lock().unwrap().triggered
+    }
+
+    /// Ready once triggered, else cx is woken by trigger()
+    pub fn poll_triggered(&self, cx: &mut Context) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.triggered {
+            return Poll::Ready(());
+        }
+        if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            state.wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+
+    pub fn wait(&self) -> impl Future<Output = ()> {
+        let shutdown: Shutdown = self.clone();
+        future::poll_fn(move |cx| shutdown.poll_triggered(cx))
+    }
+
+    /// wait for duration, false if triggered meanwhile. The timer is the one
+    /// of the tokio runtime, e.g. of the actix System.
+    pub async fn pause(&self, duration: Duration) -> bool {
+        if self.is_triggered() {
+            return false;
+        }
+        match future::select(Box::pin(delay_for(duration)), self.wait()).await {
+            Either::Left(_) => !self.is_triggered(),
+            Either::Right(_) => false,
+        }
+    }
+}
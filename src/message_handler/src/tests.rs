@@ -1,25 +1,48 @@
 #[cfg(test)]
 mod test_message_handler {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
+    use crate::block_relay::{BlockRelay, RecentBlocks, MAX_RECENT_BLOCKS};
     use crate::constants;
+    use crate::envelope::{Envelope, MessageKind, PROTOCOL_VERSION};
+    use crate::handshake::Handshake;
     use crate::message_sender::MessageSender;
-    use crate::messages::{MessageTypes, MSG_DISPATCHER};
+    use crate::messages::{open_envelope, validate_node_msg, MessageDispatcher, MessageTypes};
     use crate::node_messages::NodeMessageTypes;
+    use crate::peer_request::PeerRequests;
+    use crate::queues::{gossip_channel, GOSSIP_CONSENSUS_CAPACITY, GOSSIP_CONSENSUS_QUEUE};
+    use crate::shutdown::Shutdown;
+    use exonum_crypto::Hash;
     use futures::channel::mpsc::*;
-    use libp2p::floodsub::Topic;
+    use futures::executor::block_on;
+    use libp2p::gossipsub::Topic;
     use schema::block::{Block, BlockTraits, SignedBlock};
+    use schema::compact_block::CompactBlock;
+    use schema::signed_transaction::SignedTransaction;
+    use schema::transaction_pool::{Pool, TxnPool};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
     use std::{thread, time::Duration};
+    use utils::configreader::Configuration;
+    use utils::keypair::{CryptoKeypair, Keypair, KeypairType};
+    use utils::queue_metrics::QueueMetrics;
     use utils::serializer::{deserialize, serialize};
     // fn to test state operations
 
     #[test]
     fn test_node_message_operations() {
-        let (tx1, mut rx1) = channel::<Option<MessageTypes>>(4194304);
+        let queue_metrics = Arc::new(Mutex::new(QueueMetrics::new()));
+        let dispatcher = MessageDispatcher::new(&queue_metrics);
+        let relay = BlockRelay::new(
+            Pool::new(),
+            Arc::new(PeerRequests::new(&queue_metrics)),
+            Arc::new(Mutex::new(RecentBlocks::new())),
+        );
+        let (tx1, mut rx1) = gossip_channel(&queue_metrics);
         let mut sender = tx1.clone();
         let genesis_block: Block = Block::genesis_block(Vec::new(), 0);
         let block: SignedBlock = SignedBlock::create_block(genesis_block, vec![0], Vec::new());
         // let data = MessageTypes::NodeMsg(NodeMessageTypes::SignedBlockEnum(block));
-        MessageSender::send_block_msg(&mut sender, block.clone());
+        MessageSender::send_block_msg(&mut sender, &relay, block.clone());
         thread::sleep(Duration::from_millis(1000));
         let received_data = rx1.try_next().unwrap();
         match received_data {
@@ -31,27 +54,18 @@ mod test_message_handler {
                                 info!("NodeMsg received {:?}", data);
                                 let topics: Vec<Topic> =
                                     Vec::<Topic>::from(MessageTypes::NodeMsg(data.clone()));
-                                assert_eq!(topics[0], Topic::new(constants::NODE));
-                                let result = MSG_DISPATCHER
-                                    .node_msg_dispatcher
-                                    .clone()
-                                    .try_send(Some(data));
-                                if result.is_err() {
-                                    result.unwrap_err().into_send_error();
-                                }
+                                assert_eq!(topics[0], Topic::new(String::from(constants::NODE)));
+                                assert_eq!(dispatcher.dispatch_node_msg(data), true);
                             }
                             MessageTypes::ConsensusMsg(data) => {
                                 info!("ConsensusMsg received {:?}", data);
                                 let topics: Vec<Topic> =
                                     Vec::<Topic>::from(MessageTypes::ConsensusMsg(data.clone()));
-                                assert_eq!(topics[0], Topic::new(constants::CONSENSUS));
-                                let result = MSG_DISPATCHER
-                                    .consensus_msg_dispatcher
-                                    .clone()
-                                    .try_send(Some(data));
-                                if result.is_err() {
-                                    result.unwrap_err().into_send_error();
-                                }
+                                assert_eq!(
+                                    topics[0],
+                                    Topic::new(String::from(constants::CONSENSUS))
+                                );
+                                assert_eq!(dispatcher.dispatch_consensus_msg(data), true);
                             }
                         };
                     }
@@ -61,7 +75,7 @@ mod test_message_handler {
             None => panic!("test case failed due to data failure at receiver end"),
         };
         thread::sleep(Duration::from_millis(1000));
-        let dispatched_data = MSG_DISPATCHER
+        let dispatched_data = dispatcher
             .node_msg_receiver
             .lock()
             .unwrap()
@@ -69,14 +83,21 @@ mod test_message_handler {
             .unwrap();
         let dispatched_data = dispatched_data.unwrap().unwrap();
         match dispatched_data {
-            NodeMessageTypes::SignedBlockEnum(value) => assert_eq!(value, block),
-            NodeMessageTypes::SignedTransactionEnum(_) => panic!("wrong data"),
+            NodeMessageTypes::CompactBlockEnum(value) => {
+                assert_eq!(value, CompactBlock::new(&block));
+                assert_eq!(value.to_signed_block(Vec::new()), block);
+                // the genesis block carries no author signature
+                assert_eq!(relay.reconstruct_block(&value), None);
+            }
+            _ => panic!("wrong data"),
         };
     }
 
     #[test]
     fn test_consensus_message_operations() {
-        let (tx1, mut rx1) = channel::<Option<MessageTypes>>(4194304);
+        let queue_metrics = Arc::new(Mutex::new(QueueMetrics::new()));
+        let dispatcher = MessageDispatcher::new(&queue_metrics);
+        let (tx1, mut rx1) = gossip_channel(&queue_metrics);
         let mut sender = tx1.clone();
         let peer_id: String = String::from("mock_peer_id");
         // let data = MessageTypes::NodeMsg(NodeMessageTypes::SignedBlockEnum(block));
@@ -96,27 +117,18 @@ mod test_message_handler {
                                 info!("NodeMsg received {:?}", data);
                                 let topics: Vec<Topic> =
                                     Vec::<Topic>::from(MessageTypes::NodeMsg(data.clone()));
-                                assert_eq!(topics[0], Topic::new(constants::NODE));
-                                let result = MSG_DISPATCHER
-                                    .node_msg_dispatcher
-                                    .clone()
-                                    .try_send(Some(data));
-                                if result.is_err() {
-                                    result.unwrap_err().into_send_error();
-                                }
+                                assert_eq!(topics[0], Topic::new(String::from(constants::NODE)));
+                                assert_eq!(dispatcher.dispatch_node_msg(data), true);
                             }
                             MessageTypes::ConsensusMsg(data) => {
                                 info!("ConsensusMsg received {:?}", data);
                                 let topics: Vec<Topic> =
                                     Vec::<Topic>::from(MessageTypes::ConsensusMsg(data.clone()));
-                                assert_eq!(topics[0], Topic::new(constants::CONSENSUS));
-                                let result = MSG_DISPATCHER
-                                    .consensus_msg_dispatcher
-                                    .clone()
-                                    .try_send(Some(data));
-                                if result.is_err() {
-                                    result.unwrap_err().into_send_error();
-                                }
+                                assert_eq!(
+                                    topics[0],
+                                    Topic::new(String::from(constants::CONSENSUS))
+                                );
+                                assert_eq!(dispatcher.dispatch_consensus_msg(data), true);
                             }
                         };
                     }
@@ -126,7 +138,7 @@ mod test_message_handler {
             None => panic!("test case failed due to data failure at receiver end"),
         };
         thread::sleep(Duration::from_millis(1000));
-        let dispatched_data = MSG_DISPATCHER
+        let dispatched_data = dispatcher
             .consensus_msg_receiver
             .lock()
             .unwrap()
@@ -135,4 +147,207 @@ mod test_message_handler {
         let dispatched_data = deserialize::<String>(&dispatched_data.unwrap().unwrap()).unwrap();
         assert_eq!(dispatched_data, peer_id);
     }
+
+    #[test]
+    fn test_node_message_validation() {
+        let config: Configuration = Configuration::from_file("../../config.toml").unwrap();
+        let block_config = &config.block_config;
+        let kp: KeypairType = Keypair::generate();
+        let mut header = HashMap::new();
+        header.insert(String::from("timestamp"), String::from("1000"));
+        let txn = SignedTransaction::create(&kp, "MockApp", header, vec![1, 2, 3]);
+        let msg = NodeMessageTypes::SignedTransactionEnum(txn.clone());
+        assert_eq!(validate_node_msg(&msg, block_config), Ok(()));
+        let mut tampered = txn.clone();
+        tampered.txn = vec![3, 2, 1];
+        let msg = NodeMessageTypes::SignedTransactionEnum(tampered);
+        assert_eq!(validate_node_msg(&msg, block_config).is_err(), true);
+
+        let block: Block = Block::genesis_block(Vec::new(), 0);
+        let signature: Vec<u8> = block.sign(&kp);
+        let mut signed_block: SignedBlock =
+            SignedBlock::create_block(block, signature.clone(), Vec::new());
+        signed_block.block.peer_id = hex::encode(kp.public().encode());
+        signed_block.signature = signed_block.block.sign(&kp);
+        let msg = NodeMessageTypes::SignedBlockEnum(signed_block.clone());
+        assert_eq!(validate_node_msg(&msg, block_config), Ok(()));
+        let mut compact_block: CompactBlock = CompactBlock::new(&signed_block);
+        let msg = NodeMessageTypes::CompactBlockEnum(compact_block.clone());
+        assert_eq!(validate_node_msg(&msg, block_config), Ok(()));
+        compact_block.header.peer_id = String::from("genesis_block");
+        let msg = NodeMessageTypes::CompactBlockEnum(compact_block);
+        assert_eq!(validate_node_msg(&msg, block_config).is_err(), true);
+        signed_block.signature = signature;
+        let msg = NodeMessageTypes::SignedBlockEnum(signed_block);
+        assert_eq!(validate_node_msg(&msg, block_config).is_err(), true);
+    }
+
+    #[test]
+    fn test_recent_blocks() {
+        let kp: KeypairType = Keypair::generate();
+        let mut header = HashMap::new();
+        header.insert(String::from("timestamp"), String::from("1000"));
+        let txn = SignedTransaction::create(&kp, "MockApp", header, vec![1, 2, 3]);
+        let mut recent_blocks = RecentBlocks::new();
+        let salt: Hash = Hash::zero();
+        recent_blocks.insert(salt, vec![Some(txn.clone()), None]);
+        assert_eq!(recent_blocks.transactions(&salt, &vec![0]), Some(vec![txn]));
+        // unknown & out of range transactions
+        assert_eq!(recent_blocks.transactions(&salt, &vec![0, 1]), None);
+        assert_eq!(recent_blocks.transactions(&salt, &vec![2]), None);
+        for index in 0..MAX_RECENT_BLOCKS {
+            recent_blocks.insert(Hash::new([index as u8 + 1; 32]), Vec::new());
+        }
+        assert_eq!(recent_blocks.transactions(&salt, &vec![]), None);
+    }
+
+    #[test]
+    fn test_envelope() {
+        let config: Configuration = Configuration::from_file("../../config.toml").unwrap();
+        let block_config = &config.block_config;
+        let chain_id: &String = &config.node.chain_id;
+        let envelope: Envelope = Envelope::seal(chain_id, MessageKind::Node, vec![1, 2, 3]);
+        assert_eq!(envelope.version, PROTOCOL_VERSION);
+        let data: Vec<u8> = serialize(&envelope).unwrap();
+        assert_eq!(
+            open_envelope(&data, MessageKind::Node, chain_id, block_config),
+            Ok(vec![1, 2, 3])
+        );
+        assert_eq!(
+            open_envelope(&data, MessageKind::Consensus, chain_id, block_config).is_err(),
+            true
+        );
+        let mut future_envelope: Envelope = envelope.clone();
+        future_envelope.version = PROTOCOL_VERSION + 1;
+        assert_eq!(
+            future_envelope.open(chain_id, MessageKind::Node).is_err(),
+            true
+        );
+        let mut other_chain: Envelope = envelope;
+        other_chain.chain_id = String::from("other_chain");
+        assert_eq!(other_chain.open(chain_id, MessageKind::Node).is_err(), true);
+        // messages without envelope are refused
+        let data: Vec<u8> = serialize(&vec![1u8, 2, 3]).unwrap();
+        assert_eq!(
+            open_envelope(&data, MessageKind::Node, chain_id, block_config).is_err(),
+            true
+        );
+    }
+
+    #[test]
+    fn test_handshake() {
+        let local: Handshake = Handshake::local(&String::from("chain"), None);
+        let mut peer: Handshake = local.clone();
+        assert_eq!(local.negotiate(&peer), Ok(PROTOCOL_VERSION));
+        peer.versions = vec![PROTOCOL_VERSION, PROTOCOL_VERSION + 1];
+        assert_eq!(local.negotiate(&peer), Ok(PROTOCOL_VERSION));
+        peer.versions = vec![PROTOCOL_VERSION + 1];
+        assert_eq!(local.negotiate(&peer).is_err(), true);
+
+        let mut peer: Handshake = local.clone();
+        peer.chain_id = String::from("other_chain");
+        assert_eq!(local.negotiate(&peer).is_err(), true);
+        // a node without genesis block may join
+        let mut local: Handshake = local;
+        let mut peer: Handshake = local.clone();
+        local.genesis_hash = Some(Hash::zero());
+        peer.genesis_hash = None;
+        assert_eq!(local.negotiate(&peer), Ok(PROTOCOL_VERSION));
+        peer.genesis_hash = Some(Hash::new([1; 32]));
+        assert_eq!(local.negotiate(&peer).is_err(), true);
+    }
+
+    fn dropped(queue_metrics: &Mutex<QueueMetrics>, queue: &str) -> u64 {
+        queue_metrics
+            .lock()
+            .unwrap()
+            .stats()
+            .into_iter()
+            .find(|stats| stats.name == queue)
+            .map(|stats| stats.dropped)
+            .unwrap_or(0)
+    }
+
+    #[test]
+    fn test_gossip_queues() {
+        let queue_metrics = Arc::new(Mutex::new(QueueMetrics::new()));
+        let (mut sender, mut receiver) = gossip_channel(&queue_metrics);
+        let consensus_msg = Some(MessageTypes::ConsensusMsg(Vec::new()));
+        let txn =
+            SignedTransaction::create(&Keypair::generate(), "MockApp", HashMap::new(), vec![]);
+        let txn_msg = Some(MessageTypes::NodeMsg(
+            NodeMessageTypes::SignedTransactionEnum(txn),
+        ));
+        assert_eq!(sender.try_send(txn_msg).is_ok(), true);
+        assert_eq!(sender.try_send(consensus_msg).is_ok(), true);
+        // consensus messages come first whatever the order they were sent in
+        match receiver.try_next() {
+            Ok(Some(Some(MessageTypes::ConsensusMsg(_)))) => {}
+            other => panic!("consensus message expected, got {:?}", other),
+        }
+        match receiver.try_next() {
+            Ok(Some(Some(MessageTypes::NodeMsg(_)))) => {}
+            other => panic!("node message expected, got {:?}", other),
+        }
+
+        // a full queue refuses messages and counts them
+        let dropped_before: u64 = dropped(&queue_metrics, GOSSIP_CONSENSUS_QUEUE);
+        let mut queued: usize = 0;
+        while sender
+            .try_send(Some(MessageTypes::ConsensusMsg(Vec::new())))
+            .is_ok()
+        {
+            queued += 1;
+        }
+        assert_eq!(queued > GOSSIP_CONSENSUS_CAPACITY, true);
+        assert_eq!(
+            dropped(&queue_metrics, GOSSIP_CONSENSUS_QUEUE) > dropped_before,
+            true
+        );
+        // the node queue still takes transactions
+        assert_eq!(sender.try_send(None).is_ok(), true);
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let shutdown = Shutdown::new();
+        assert_eq!(shutdown.is_triggered(), false);
+        let waiting: Shutdown = shutdown.clone();
+        let waiter = thread::spawn(move || block_on(waiting.wait()));
+        thread::sleep(Duration::from_millis(100));
+        shutdown.trigger();
+        waiter.join().unwrap();
+        assert_eq!(shutdown.is_triggered(), true);
+        // waiting after the trigger returns at once
+        block_on(shutdown.wait());
+    }
+
+    #[test]
+    fn test_shutdown_pause() {
+        let mut runtime = tokio::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_time()
+            .build()
+            .unwrap();
+        let shutdown = Shutdown::new();
+        assert_eq!(
+            runtime.block_on(shutdown.pause(Duration::from_millis(10))),
+            true
+        );
+        // a trigger ends the pause early
+        let stop: Shutdown = shutdown.clone();
+        let trigger = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            stop.trigger();
+        });
+        assert_eq!(
+            runtime.block_on(shutdown.pause(Duration::from_secs(60))),
+            false
+        );
+        trigger.join().unwrap();
+        assert_eq!(
+            runtime.block_on(shutdown.pause(Duration::from_millis(10))),
+            false
+        );
+    }
 }
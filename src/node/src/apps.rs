@@ -0,0 +1,100 @@
+use db_service::db_layer::Db;
+use db_service::db_snapshot_ref::SchemaSnap;
+use libloading::{Library, Symbol};
+use schema::app_registry::AppRegistry;
+use schema::appdata::AppData;
+use sdk::abi::{AbiApp, AppVTable, ABI_VERSION, ABI_VERSION_SYMBOL, REGISTER_APP_SYMBOL};
+use sdk::traits::AppHandler;
+use std::sync::{Arc, Mutex};
+use utils::configreader::Configuration;
+use wasm_app::transaction::WasmApp;
+
+/// load the app library at path. It must export the sdk app ABI (sdk::export_app!)
+/// of the same ABI_VERSION as the node, anything else is rejected.
+fn load_app(path: &String) -> Result<(Library, Box<dyn AppHandler + Send>), String> {
+    let applib = Library::new(path)
+        .map_err(|error| format!("loading app library {:?} failed: {}", path, error))?;
+    let abi_version: u32 = {
+        let version_symbol: Symbol<extern "C" fn() -> u32> =
+            unsafe { applib.get(ABI_VERSION_SYMBOL) }.map_err(|_| {
+                format!(
+                    "app library {:?} doesn't export the app ABI, rebuild it with sdk::export_app!",
+                    path
+                )
+            })?;
+        version_symbol()
+    };
+    if abi_version != ABI_VERSION {
+        return Err(format!(
+            "app library {:?} is built for app ABI version {}, node supports version {}",
+            path, abi_version, ABI_VERSION
+        ));
+    }
+    let app: AbiApp = {
+        let register_symbol: Symbol<extern "C" fn() -> AppVTable> =
+            unsafe { applib.get(REGISTER_APP_SYMBOL) }
+                .map_err(|_| format!("register symbol is not found in app library {:?}", path))?;
+        unsafe { AbiApp::new(register_symbol()) }
+            .map_err(|error| format!("app library {:?} rejected: {}", path, error))?
+    };
+    Ok((applib, Box::new(app)))
+}
+
+fn register_app(
+    app_data: &mut AppData,
+    app_handler: Box<dyn AppHandler + Send>,
+    applib: Option<Library>,
+) {
+    let app_name = app_handler.name();
+    info!("Loaded app {:?}", app_name);
+    if let Some(applib) = applib {
+        app_data.lib.push(Arc::new(applib));
+    }
+    app_data
+        .appdata
+        .insert(app_name, Arc::new(Mutex::new(app_handler)));
+}
+
+/// load the client_apps of config, the libraries at app_libraries and apps,
+/// next to the app registry & the wasm runtime the node brings. The node runs
+/// the apps once they passed the registry check.
+pub fn load_apps(
+    config: &Configuration,
+    app_libraries: &Vec<String>,
+    apps: Vec<Box<dyn AppHandler + Send>>,
+) -> Result<AppData, String> {
+    let mut app_data = AppData::new();
+    for app in config.node.client_apps.iter().chain(app_libraries.iter()) {
+        info!("loading library {:?}", app);
+        let (applib, app_handler) = load_app(app)?;
+        register_app(&mut app_data, app_handler, Some(applib));
+    }
+    for app_handler in apps {
+        register_app(&mut app_data, app_handler, None);
+    }
+    register_app(
+        &mut app_data,
+        Box::new(AppRegistry::new(&config.node.registry_admins)),
+        None,
+    );
+    if config.wasm_config.enabled {
+        let wasm_app: Box<dyn AppHandler + Send> = Box::new(WasmApp::new(
+            config.wasm_config.gas_limit,
+            config.wasm_config.max_code_size,
+        ));
+        info!("Registered wasm app runtime as {:?}", wasm_app.name());
+        register_app(&mut app_data, wasm_app, None);
+    }
+    Ok(app_data)
+}
+
+/// Err when the apps of app_data aren't the ones the on-chain app registry
+/// of db enables for the next block
+pub fn check_app_registry(db: &Db, app_data: &AppData) -> Result<(), String> {
+    let snapshot = db.snapshot();
+    let schema = SchemaSnap::new(&snapshot);
+    if !schema.is_db_initialized() {
+        return Ok(());
+    }
+    schema.check_app_registry(schema.get_blockchain_length(), &app_data.app_versions())
+}
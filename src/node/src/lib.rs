@@ -0,0 +1,12 @@
+extern crate consensus;
+extern crate controllers;
+extern crate db_service;
+extern crate p2plib;
+extern crate schema;
+
+#[macro_use]
+extern crate log;
+
+pub mod apps;
+pub mod node;
+mod nodemsgprocessor;
@@ -0,0 +1,403 @@
+use super::apps::{check_app_registry, load_apps};
+use super::nodemsgprocessor::NodeMsgProcessor;
+use actix_rt::System;
+use consensus::consensus_interface::{Consensus, ConsensusEngine};
+use controllers::client_controller::{ClientController, Controller};
+use db_service::db_layer::Db;
+use db_service::db_snapshot_ref::SchemaSnap;
+use db_service::node_context::NodeContext;
+use db_service::peer_service::{genesis_hash, serve_peer_request};
+use futures::FutureExt;
+use libp2p::{identity::PublicKey, PeerId};
+use message_handler::constants;
+use message_handler::handshake::GenesisHashProvider;
+use message_handler::peer_request::PeerRequestHandler;
+use message_handler::shutdown::Shutdown;
+use p2plib::peer_manager::PeerManager;
+use p2plib::simpleswarm::SimpleSwarm;
+use p2plib::swarm_context::SwarmContext;
+use schema::appdata::AppData;
+use sdk::traits::AppHandler;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use utils::configreader::{Configuration, TomlReaderConfig, NODETYPE};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeState {
+    Running,
+    Stopping,
+    Stopped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeStatus {
+    pub state: NodeState,
+    pub peer_id: String,
+    pub connected_peers: usize,
+    /// None unless the node is running
+    pub blockchain_length: Option<u64>,
+}
+
+/// NodeBuilder collects what a Node runs with
+pub struct NodeBuilder {
+    config: TomlReaderConfig,
+    apps: Vec<Box<dyn AppHandler + Send>>,
+    app_libraries: Vec<String>,
+    consensus_engine: Option<ConsensusEngine>,
+    consensus_file_path: Option<String>,
+}
+
+impl NodeBuilder {
+    pub fn new(config: TomlReaderConfig) -> NodeBuilder {
+        NodeBuilder {
+            config,
+            apps: Vec::new(),
+            app_libraries: Vec::new(),
+            consensus_engine: None,
+            consensus_file_path: None,
+        }
+    }
+
+    /// keep the chain state in db_path instead of the dbpath of the config
+    pub fn storage(mut self, db_path: &str) -> NodeBuilder {
+        self.config.set_dbpath(db_path);
+        self
+    }
+
+    /// run app next to the client_apps of the config
+    pub fn app(mut self, app: Box<dyn AppHandler + Send>) -> NodeBuilder {
+        self.apps.push(app);
+        self
+    }
+
+    /// load the app library at path next to the client_apps of the config
+    pub fn app_library(mut self, path: &str) -> NodeBuilder {
+        self.app_libraries.push(String::from(path));
+        self
+    }
+
+    /// run engine instead of the consensus_name of the config
+    pub fn consensus(mut self, engine: ConsensusEngine) -> NodeBuilder {
+        self.consensus_engine = Some(engine);
+        self
+    }
+
+    /// config file of the consensus engine, validators need one
+    pub fn consensus_config(mut self, consensus_file_path: &str) -> NodeBuilder {
+        self.consensus_file_path = Some(String::from(consensus_file_path));
+        self
+    }
+
+    /// the Node with its storage open & its apps loaded, Err if the config is
+    /// invalid, the storage can't be opened or the apps couldn't be loaded
+    /// or aren't the ones the app registry enables
+    pub fn build(self) -> Result<Node, String> {
+        let config: Arc<Configuration> = Arc::new(Configuration::from_toml(self.config)?);
+        let consensus: Option<(ConsensusEngine, String)> = match config.node.node_type {
+            NODETYPE::FullNode => None,
+            NODETYPE::Validator => {
+                let engine: ConsensusEngine = match self.consensus_engine {
+                    Some(engine) => engine,
+                    None => ConsensusEngine::from_name(&config.node.consensus_name)
+                        .ok_or(format!("unknown consensus {}", config.node.consensus_name))?,
+                };
+                match self.consensus_file_path {
+                    Some(consensus_file_path) => Some((engine, consensus_file_path)),
+                    None => return Err(String::from("a validator needs a consensus config")),
+                }
+            }
+        };
+        let app_data: AppData = load_apps(&config, &self.app_libraries, self.apps)?;
+        let db: Db = Db::open(&config.db.dbpath)?;
+        check_app_registry(&db, &app_data)?;
+        let context = NodeContext::new(config.clone(), db.clone(), app_data);
+        let request_context: NodeContext = context.clone();
+        let peer_request_handler: PeerRequestHandler =
+            Arc::new(move |request| serve_peer_request(&request_context, request));
+        let genesis_hash_provider: GenesisHashProvider = Arc::new(move || genesis_hash(&db));
+        Ok(Node {
+            peer_manager: Arc::new(Mutex::new(PeerManager::from_config(&config.node))),
+            config,
+            context,
+            peer_request_handler,
+            genesis_hash_provider,
+            consensus,
+        })
+    }
+}
+
+/// Node is a configured node with all of its state: the config, storage,
+/// pool, apps & peers. Nodes share nothing, so nodes with a storage of their
+/// own run side by side in one process. start runs the node, a stopped node
+/// is started again by building it again.
+pub struct Node {
+    config: Arc<Configuration>,
+    // db, pool, apps & message dispatcher of the node
+    context: NodeContext,
+    peer_manager: Arc<Mutex<PeerManager>>,
+    // the swarm answers peer requests & handshakes from the db of the node
+    peer_request_handler: PeerRequestHandler,
+    genesis_hash_provider: GenesisHashProvider,
+    consensus: Option<(ConsensusEngine, String)>,
+}
+
+impl Node {
+    pub fn builder(config: TomlReaderConfig) -> NodeBuilder {
+        NodeBuilder::new(config)
+    }
+
+    /// run the node on a thread of its own, Err if the thread couldn't
+    /// start. The storage is closed once the node stopped & its NodeHandle
+    /// is gone.
+    pub fn start(self) -> Result<NodeHandle, String> {
+        let pk: PublicKey = PublicKey::Ed25519(self.config.node.public.clone());
+        let peer_id = PeerId::from_public_key(pk);
+        let stop = Shutdown::new();
+        let state = Arc::new(Mutex::new(NodeState::Running));
+        let db: Db = self.context.db.clone();
+        let peer_manager: Arc<Mutex<PeerManager>> = self.peer_manager.clone();
+        let node_stop: Shutdown = stop.clone();
+        let node_state = state.clone();
+        let node_peer_id = peer_id.clone();
+        let thread = thread::Builder::new()
+            .name(String::from("node"))
+            .spawn(move || {
+                let result = panic::catch_unwind(AssertUnwindSafe(|| {
+                    System::new("node").block_on(run_node(
+                        self,
+                        node_peer_id,
+                        node_stop,
+                        node_state.clone(),
+                    ))
+                }));
+                *node_state.lock().unwrap() = NodeState::Stopped;
+                if let Err(panic) = result {
+                    panic::resume_unwind(panic);
+                }
+            })
+            .map_err(|error| format!("node thread couldn't start: {}", error))?;
+        Ok(NodeHandle {
+            peer_id,
+            stop,
+            state,
+            thread,
+            db,
+            peer_manager,
+        })
+    }
+
+    /// the part of the node the swarm runs with
+    fn swarm_context(&self) -> SwarmContext {
+        SwarmContext {
+            config: self.config.clone(),
+            peer_manager: self.peer_manager.clone(),
+            dispatcher: self.context.dispatcher.clone(),
+            peer_requests: self.context.peer_requests.clone(),
+            peer_request_handler: self.peer_request_handler.clone(),
+            genesis_hash_provider: self.genesis_hash_provider.clone(),
+            peers: self.context.peers.clone(),
+            peer_reports: self.context.peer_reports.clone(),
+            queue_metrics: self.context.queue_metrics.clone(),
+        }
+    }
+}
+
+/// NodeHandle controls a started Node. Dropping it leaves the node running.
+pub struct NodeHandle {
+    peer_id: PeerId,
+    stop: Shutdown,
+    state: Arc<Mutex<NodeState>>,
+    thread: thread::JoinHandle<()>,
+    db: Db,
+    peer_manager: Arc<Mutex<PeerManager>>,
+}
+
+impl NodeHandle {
+    pub fn status(&self) -> NodeStatus {
+        let state = self.state.lock().unwrap();
+        let blockchain_length: Option<u64> = match *state {
+            NodeState::Running => {
+                let snapshot = self.db.snapshot();
+                Some(SchemaSnap::new(&snapshot).get_blockchain_length())
+            }
+            _ => None,
+        };
+        NodeStatus {
+            state: *state,
+            peer_id: self.peer_id.to_string(),
+            connected_peers: self.peer_manager.lock().unwrap().connected_peers().len(),
+            blockchain_length,
+        }
+    }
+
+    /// signal which stops the node once triggered, e.g. from a Ctrl-C handler
+    pub fn stop_signal(&self) -> Shutdown {
+        self.stop.clone()
+    }
+
+    /// wait until the node stopped, a Node on its storage can start after
+    pub fn wait(self) -> Result<(), String> {
+        self.thread
+            .join()
+            .map_err(|_| String::from("node stopped with a panic"))
+    }
+
+    /// stop the node & wait until it stopped
+    pub fn shutdown(self) -> Result<(), String> {
+        self.stop.trigger();
+        self.wait()
+    }
+}
+
+/// run node on the actix System until stop is triggered. On shutdown the api
+/// stops taking requests, the pending blocks are applied and consensus stops
+/// before the swarm, the db is closed once all of them let it go.
+async fn run_node(node: Node, peer_id: PeerId, stop: Shutdown, state: Arc<Mutex<NodeState>>) {
+    info!("peer id = {:?}", peer_id);
+    let config: Arc<Configuration> = node.config.clone();
+    let context: NodeContext = node.context.clone();
+    let is_validator: bool = node.consensus.is_some();
+    let shutdown = Shutdown::new();
+    // the swarm outlives the other tasks, they may still fetch from peers
+    let swarm_shutdown = Shutdown::new();
+
+    let mut swarm = SimpleSwarm::new(node.swarm_context());
+    if is_validator {
+        swarm
+            .topic_list
+            .push(String::from(constants::CONSENSUS.clone()));
+    }
+    swarm.topic_list.push(String::from(constants::NODE.clone()));
+    let sender = swarm.tx.clone();
+    let txn_sender = swarm.tx.clone();
+
+    let node_msg_processor = NodeMsgProcessor::new(context.clone());
+    let (processor_task, processor) = node_msg_processor.run(shutdown.clone()).remote_handle();
+    actix_rt::spawn(processor_task);
+
+    // the engine & its message receiver run their db work on the blocking
+    // pool, the task ends once both stopped
+    let consensus = match node.consensus {
+        Some((engine, consensus_file_path)) => {
+            let (consensus_task, consensus_stopped) = Consensus::run_engine(
+                engine,
+                context.clone(),
+                consensus_file_path,
+                sender,
+                shutdown.clone(),
+            )
+            .remote_handle();
+            actix_rt::spawn(consensus_task);
+            Some(consensus_stopped)
+        }
+        None => None,
+    };
+
+    let network_shutdown = swarm_shutdown.clone();
+    let (swarm_task, swarm_stopped) = async move {
+        if let Err(error) = swarm.run(peer_id, network_shutdown).await {
+            error!("swarm messaging system broken: {}", error);
+        }
+    }
+    .remote_handle();
+    actix_rt::spawn(swarm_task);
+
+    //Starting the Transaction Service
+    let port_from_config = config.node.client_port;
+    let host_from_config = config.node.client_host.clone();
+    let mut api_service = ClientController::new(
+        &host_from_config,
+        port_from_config,
+        context,
+        node.peer_manager.clone(),
+    );
+    info!("Starting api_service");
+    let started: bool = if is_validator {
+        api_service.run_validator_controller(txn_sender)
+    } else {
+        api_service.run_fullnode_controller(txn_sender)
+    };
+    if started {
+        info!("Started api_service");
+        stop.wait().await;
+    }
+
+    *state.lock().unwrap() = NodeState::Stopping;
+    info!("Stopping REST End Point");
+    if let Some(server) = api_service.server() {
+        server.stop(true).await;
+    }
+    shutdown.trigger();
+    processor.await;
+    if let Some(consensus) = consensus {
+        consensus.await;
+    }
+    swarm_shutdown.trigger();
+    swarm_stopped.await;
+    info!("Node stopped");
+}
+
+#[cfg(test)]
+mod tests_node {
+    use super::*;
+    use std::fs;
+    use std::path::Path;
+
+    /// full node of config.toml without app libraries, keeping its chain
+    /// state in db_path & serving peers at p2p_port and clients at
+    /// client_port
+    fn fullnode_config(db_path: &str, p2p_port: u32, client_port: u32) -> TomlReaderConfig {
+        let config: String = fs::read_to_string("../../config.toml")
+            .unwrap()
+            .replace("node_type = \"validator\"", "node_type = \"fullnode\"")
+            .replace(
+                "client_apps = [\"./libdoc_app.so\", \"./libwallet_app.so\"]",
+                "client_apps = []",
+            )
+            .replace("mdns = true", "mdns = false")
+            .replace("p2p_port = 4444", &format!("p2p_port = {}", p2p_port))
+            .replace(
+                "client_port = 8089",
+                &format!("client_port = {}", client_port),
+            )
+            .replace(
+                "banned_peers_path = \"./banned_peers\"",
+                &format!("banned_peers_path = \"{}_banned_peers\"", db_path),
+            );
+        let mut config: TomlReaderConfig = TomlReaderConfig::from_toml_str(&config).unwrap();
+        config.set_dbpath(db_path);
+        config
+    }
+
+    fn start(db_path: &str, p2p_port: u32, client_port: u32) -> Result<NodeHandle, String> {
+        Node::builder(fullnode_config(db_path, p2p_port, client_port))
+            .build()?
+            .start()
+    }
+
+    #[test]
+    fn test_nodes_side_by_side() {
+        let (first_db, second_db) = ("./rockdb/node_test_first", "./rockdb/node_test_second");
+        let first: NodeHandle = start(first_db, 4491, 8091).unwrap();
+        // the storage of a node is its own while it runs
+        assert_eq!(start(first_db, 4493, 8093).is_err(), true);
+        let second: NodeHandle = start(second_db, 4492, 8092).unwrap();
+        for handle in [&first, &second].iter() {
+            let status: NodeStatus = handle.status();
+            assert_eq!(status.state, NodeState::Running);
+            assert_eq!(status.blockchain_length.is_some(), true);
+        }
+        assert_eq!(first.shutdown(), Ok(()));
+        assert_eq!(second.status().state, NodeState::Running);
+        assert_eq!(second.shutdown(), Ok(()));
+
+        // started again on the same storage
+        let handle: NodeHandle = start(first_db, 4491, 8091).unwrap();
+        assert_eq!(handle.status().state, NodeState::Running);
+        assert_eq!(handle.shutdown(), Ok(()));
+        assert_eq!(Path::new(second_db).exists(), true);
+        fs::remove_dir_all(first_db).unwrap();
+        fs::remove_dir_all(second_db).unwrap();
+    }
+}
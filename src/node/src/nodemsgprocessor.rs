@@ -1,129 +1,274 @@
 extern crate db_service;
 use db_service::db_fork_ref::SchemaFork;
-use db_service::db_layer::{fork_db, patch_db};
+use db_service::node_context::NodeContext;
 use exonum_merkledb::ObjectHash;
-use futures::{channel::mpsc::*, executor::*, future, prelude::*, task::*};
+use futures::{channel::mpsc::*, future, future::Either, prelude::*, task::*};
 use message_handler::node_messages::NodeMessageTypes;
+use message_handler::queues::{PENDING_BLOCK_CAPACITY, PENDING_BLOCK_QUEUE};
+use message_handler::shutdown::Shutdown;
+use schema::appdata::AppData;
 use schema::block::SignedBlock;
+use schema::compact_block::CompactBlock;
 use schema::signed_transaction::SignedTransaction;
-use schema::transaction_pool::{TxnPool, TxnPoolKeyType, POOL};
+use schema::transaction_pool::{TxnPool, TxnPoolKeyType};
+use sdk::traits::AppHandler;
+use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::thread;
-use std::time::Duration;
+use tokio::task;
+use utils::peer_reports::Offence;
+use utils::queue_metrics::QueueMetrics;
+
+/// check txn with the app it is sent to, like transactions submitted by
+/// clients are
+fn validate_txn(apps: &Mutex<AppData>, txn: &SignedTransaction) -> Result<(), String> {
+    let app = apps.lock().unwrap().appdata.get(&txn.app_name).cloned();
+    match app {
+        Some(app) => app
+            .lock()
+            .unwrap()
+            .validate(txn)
+            .map_err(|error| error.to_string()),
+        None => Ok(()),
+    }
+}
 
-#[derive(Debug)]
 pub struct NodeMsgProcessor {
     pub _rx: Arc<Mutex<Receiver<Option<NodeMessageTypes>>>>,
+    // node the messages are applied to
+    context: NodeContext,
+}
+
+/// compact blocks wait in the queue as gossiped and are rebuilt when their
+/// turn comes, the transactions gossiped ahead of them are in the pool then
+pub enum PendingBlock {
+    Full(SignedBlock),
+    Compact(CompactBlock),
 }
 
 pub struct Blocks {
-    pending_blocks: std::collections::VecDeque<SignedBlock>,
+    pending_blocks: VecDeque<PendingBlock>,
+    queue_metrics: Arc<Mutex<QueueMetrics>>,
+}
+
+impl Blocks {
+    /// queue block behind the pending ones, a full queue drops it since the
+    /// blocks ahead have to be applied first, sync_state catches up later
+    fn push(&mut self, block: PendingBlock) {
+        if self.pending_blocks.len() >= PENDING_BLOCK_CAPACITY {
+            warn!("block dropped, {} is full", PENDING_BLOCK_QUEUE);
+            self.queue_metrics
+                .lock()
+                .unwrap()
+                .dropped(PENDING_BLOCK_QUEUE);
+            return;
+        }
+        self.pending_blocks.push_back(block);
+        self.queue_metrics
+            .lock()
+            .unwrap()
+            .enqueued(PENDING_BLOCK_QUEUE);
+        info!("block queue length {}", self.pending_blocks.len());
+    }
 }
 
 impl NodeMsgProcessor {
-    pub fn new(rx: Arc<Mutex<Receiver<Option<NodeMessageTypes>>>>) -> Self {
-        // let (mut tx, mut rx) = channel::<Option<NodeMessageTypes>>(1024);
-        // NodeMsgProcessor { _tx: tx, _rx: rx }
-        NodeMsgProcessor { _rx: rx }
+    /// processor of the node messages the dispatcher of context receives
+    pub fn new(context: NodeContext) -> Self {
+        NodeMsgProcessor {
+            _rx: context.dispatcher.node_msg_receiver.clone(),
+            context,
+        }
     }
 
-    pub fn start(&mut self) {
-        //, rx: &'static mut Receiver<Option<NodeMessageTypes>>) {
-        // let thread_handle = thread::spawn(move || {
-        let pending_blocks_obj = Blocks {
-            pending_blocks: std::collections::VecDeque::new(),
-        };
-        let arc_pending_blocks = Arc::new(Mutex::new(pending_blocks_obj));
-        NodeMsgProcessor::pending_block_processing_thread(arc_pending_blocks.clone());
-        let pending_blocks = arc_pending_blocks.clone();
-        block_on(future::poll_fn(move |cx: &mut Context| {
-            loop {
-                match self._rx.lock().unwrap().poll_next_unpin(cx) {
-                    Poll::Ready(Some(msg)) => {
-                        // info!("msg received {:?}", msg);
-                        match msg {
-                            None => info!("Empty msg received !"),
-                            Some(msgtype) => match msgtype {
-                                NodeMessageTypes::SignedBlockEnum(data) => {
-                                    info!(
-                                        "Signed Block msg in NodeMsgProcessor with data {:?}",
-                                        data.object_hash()
-                                    );
-                                    let signed_block: SignedBlock = data;
-                                    let mut block_queue = pending_blocks.lock().unwrap();
-                                    block_queue.pending_blocks.push_back(signed_block);
-                                    info!(
-                                        "block queue length {}",
-                                        block_queue.pending_blocks.len()
-                                    );
-                                }
-                                NodeMessageTypes::SignedTransactionEnum(data) => {
-                                    let txn: SignedTransaction = data;
-                                    info!(
-                                        "Signed Transaction msg in NodeMsgProcessor with Hash {:?}",
-                                        txn.object_hash()
-                                    );
-                                    if let Some(string) = txn.header.get(&String::from("timestamp"))
-                                    {
-                                        if let Ok(timestamp) = string.parse::<TxnPoolKeyType>() {
-                                            POOL.insert_op(&timestamp, &txn);
-                                        }
-                                    }
-                                }
-                            },
-                        }
-                    }
-                    Poll::Ready(None) => {
+    /// process gossiped node messages until shutdown is triggered, the blocks
+    /// still pending then are applied before it returns
+    pub async fn run(self, shutdown: Shutdown) {
+        let pending_blocks = Arc::new(Mutex::new(Blocks {
+            pending_blocks: VecDeque::with_capacity(PENDING_BLOCK_CAPACITY),
+            queue_metrics: self.context.queue_metrics.clone(),
+        }));
+        self.context
+            .queue_metrics
+            .lock()
+            .unwrap()
+            .register(PENDING_BLOCK_QUEUE, PENDING_BLOCK_CAPACITY);
+        // one pending notification stands for any number of new blocks
+        let (block_notifier, block_events) = channel::<()>(0);
+        future::join(
+            NodeMsgProcessor::receive(
+                self._rx,
+                self.context.clone(),
+                pending_blocks.clone(),
+                block_notifier,
+                shutdown,
+            ),
+            NodeMsgProcessor::apply_blocks(self.context, pending_blocks, block_events),
+        )
+        .await;
+    }
+
+    async fn receive(
+        rx: Arc<Mutex<Receiver<Option<NodeMessageTypes>>>>,
+        context: NodeContext,
+        pending_blocks: Arc<Mutex<Blocks>>,
+        mut block_notifier: Sender<()>,
+        shutdown: Shutdown,
+    ) {
+        loop {
+            let next_msg =
+                future::poll_fn(|cx: &mut Context| rx.lock().unwrap().poll_next_unpin(cx));
+            let msg: Option<NodeMessageTypes> =
+                match future::select(next_msg, shutdown.wait()).await {
+                    Either::Left((Some(msg), _)) => msg,
+                    Either::Left((None, _)) => {
                         info!("channel closed !");
-                        return Poll::Ready(1);
+                        break;
+                    }
+                    Either::Right(_) => break,
+                };
+            match msg {
+                None => info!("Empty msg received !"),
+                Some(NodeMessageTypes::SignedBlockEnum(signed_block)) => {
+                    info!(
+                        "Signed Block msg in NodeMsgProcessor with data {:?}",
+                        signed_block.object_hash()
+                    );
+                    pending_blocks
+                        .lock()
+                        .unwrap()
+                        .push(PendingBlock::Full(signed_block));
+                    // a notification still pending covers this block too
+                    let _ = block_notifier.try_send(());
+                }
+                Some(NodeMessageTypes::CompactBlockEnum(compact_block)) => {
+                    info!(
+                        "Compact Block msg in NodeMsgProcessor with id {}",
+                        compact_block.header.id
+                    );
+                    pending_blocks
+                        .lock()
+                        .unwrap()
+                        .push(PendingBlock::Compact(compact_block));
+                    let _ = block_notifier.try_send(());
+                }
+                Some(NodeMessageTypes::SignedTransactionEnum(txn)) => {
+                    info!(
+                        "Signed Transaction msg in NodeMsgProcessor with Hash {:?}",
+                        txn.object_hash()
+                    );
+                    if !txn.verify() {
+                        warn!("transaction with invalid signature dropped");
+                        continue;
+                    }
+                    if let Err(error) = validate_txn(&context.apps, &txn) {
+                        warn!("invalid transaction dropped: {}", error);
+                        context.peer_reports.lock().unwrap().report_origin(
+                            &txn.object_hash().to_hex(),
+                            Offence::InvalidTransaction,
+                        );
+                        continue;
+                    }
+                    if let Some(string) = txn.header.get(&String::from("timestamp")) {
+                        if let Ok(timestamp) = string.parse::<TxnPoolKeyType>() {
+                            context.pool.insert_op(&timestamp, &txn);
+                        }
                     }
-                    Poll::Pending => break,
                 }
             }
-            Poll::Pending
-        }));
+        }
+        info!("node messages no longer received");
     }
 
-    fn pending_block_processing_thread(pending_blocks: Arc<Mutex<Blocks>>) {
-        thread::spawn(move || {
-            loop {
-                thread::sleep(Duration::from_millis(2000));
-                // no polling machenism of txn_pool and create block need to implement or modified here
-                // if one want to change the create_block and txn priority then change/ implment that part in
-                // schema operations and p2p module
-                let mut block_queue = pending_blocks.lock().unwrap();
-                if block_queue.pending_blocks.len() > 0 {
-                    let fork = fork_db();
-                    let mut flag = false;
-                    {
-                        let mut schema = SchemaFork::new(&fork);
-                        let block: &SignedBlock = block_queue.pending_blocks.get_mut(0).unwrap();
-                        if schema.update_block(block) {
-                            POOL.sync_pool(&block.block.txn_pool);
-                            info!(
-                                "block height {}, block hash {}",
-                                block.block.id,
-                                block.object_hash()
-                            );
-                            flag = true;
-                        } else {
-                            info!("block couldn't verified");
-                            if schema.blockchain_length() > block.block.id {
-                                block_queue.pending_blocks.pop_front();
-                            } else {
-                                flag = true;
-                                schema.sync_state();
-                            }
-                        }
-                    }
-                    if flag {
-                        patch_db(fork);
+    /// apply the pending blocks whenever new ones arrive, once the receiver
+    /// stops the blocks left are applied as well
+    async fn apply_blocks(
+        context: NodeContext,
+        pending_blocks: Arc<Mutex<Blocks>>,
+        mut block_events: Receiver<()>,
+    ) {
+        while block_events.next().await.is_some() {
+            NodeMsgProcessor::apply_pending(context.clone(), pending_blocks.clone()).await;
+        }
+        NodeMsgProcessor::apply_pending(context, pending_blocks).await;
+        info!("pending blocks applied");
+    }
+
+    /// db writes & transaction fetches block, so blocks are applied on the
+    /// blocking pool of the runtime
+    async fn apply_pending(context: NodeContext, pending_blocks: Arc<Mutex<Blocks>>) {
+        let result = task::spawn_blocking(move || {
+            NodeMsgProcessor::apply_pending_blocks(&context, &pending_blocks)
+        })
+        .await;
+        if let Err(error) = result {
+            error!("pending blocks couldn't be applied: {:?}", error);
+        }
+    }
+
+    /// rebuild the compact block at the front of the queue, the lock isn't
+    /// held while its missing transactions are fetched. Blocks which can't be
+    /// rebuilt are dropped, sync_state catches up with them later.
+    fn rebuild_pending_block(context: &NodeContext, pending_blocks: &Arc<Mutex<Blocks>>) {
+        let compact_block: CompactBlock = match pending_blocks.lock().unwrap().pending_blocks.get(0)
+        {
+            Some(PendingBlock::Compact(compact_block)) => compact_block.clone(),
+            _ => return,
+        };
+        let rebuilt: Option<SignedBlock> = context.block_relay().reconstruct_block(&compact_block);
+        let mut block_queue = pending_blocks.lock().unwrap();
+        block_queue.pending_blocks.pop_front();
+        match rebuilt {
+            Some(signed_block) => block_queue
+                .pending_blocks
+                .push_front(PendingBlock::Full(signed_block)),
+            None => warn!(
+                "block {} couldn't be rebuilt, dropped",
+                compact_block.header.id
+            ),
+        }
+    }
+
+    /// apply the pending blocks in order until none is left
+    fn apply_pending_blocks(context: &NodeContext, pending_blocks: &Arc<Mutex<Blocks>>) {
+        loop {
+            NodeMsgProcessor::rebuild_pending_block(context, pending_blocks);
+            let mut block_queue = pending_blocks.lock().unwrap();
+            if block_queue.pending_blocks.is_empty() {
+                return;
+            }
+            let fork = context.db.fork();
+            let mut flag = false;
+            {
+                let mut schema = SchemaFork::new(&fork);
+                let block: &SignedBlock = match block_queue.pending_blocks.get(0) {
+                    Some(PendingBlock::Full(block)) => block,
+                    // rebuilt on the next turn
+                    _ => continue,
+                };
+                if schema.update_block(context, block) {
+                    context.pool.sync_pool(&block.block.txn_pool);
+                    info!(
+                        "block height {}, block hash {}",
+                        block.block.id,
+                        block.object_hash()
+                    );
+                    flag = true;
+                } else {
+                    info!("block couldn't verified");
+                    if schema.blockchain_length() > block.block.id {
                         block_queue.pending_blocks.pop_front();
-                        info!("block updated in db");
+                    } else {
+                        flag = true;
+                        schema.sync_state(context);
                     }
                 }
             }
-        });
+            if flag {
+                context.db.patch(fork);
+                block_queue.pending_blocks.pop_front();
+                info!("block updated in db");
+            }
+        }
     }
 }
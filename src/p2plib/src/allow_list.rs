@@ -0,0 +1,66 @@
+use libp2p::{identity::PublicKey, PeerId};
+use std::collections::HashSet;
+use utils::crypto::keypair;
+
+/// peer id of the node owning the hex ed25519 public key
+pub fn peer_id_from_public_key(public_key: &String) -> Result<PeerId, String> {
+    match keypair::PublicKey::from_string(public_key) {
+        Some(public_key) => Ok(PublicKey::Ed25519(public_key).into_peer_id()),
+        None => Err(format!("invalid public key {}", public_key)),
+    }
+}
+
+/// AllowList restricts the network to the peers of a configured key list,
+/// e.g. the validators of the chain. An empty key list allows every peer.
+#[derive(Debug, Default)]
+pub struct AllowList {
+    peers: Option<HashSet<PeerId>>,
+}
+
+impl AllowList {
+    pub fn new(public_keys: &Vec<String>) -> Result<AllowList, String> {
+        if public_keys.is_empty() {
+            return Ok(AllowList { peers: None });
+        }
+        let mut peers: HashSet<PeerId> = HashSet::new();
+        for public_key in public_keys.iter() {
+            peers.insert(peer_id_from_public_key(public_key)?);
+        }
+        Ok(AllowList { peers: Some(peers) })
+    }
+
+    pub fn is_permissioned(&self) -> bool {
+        self.peers.is_some()
+    }
+
+    pub fn is_allowed(&self, peer_id: &PeerId) -> bool {
+        match &self.peers {
+            Some(peers) => peers.contains(peer_id),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests_allow_list {
+    use super::*;
+    use libp2p::identity::ed25519;
+
+    #[test]
+    fn test_allow_list() {
+        let allowed = ed25519::Keypair::generate();
+        let hex_public: String = hex::encode(allowed.public().encode());
+        let allowed_id: PeerId = PublicKey::Ed25519(allowed.public()).into_peer_id();
+        assert_eq!(peer_id_from_public_key(&hex_public), Ok(allowed_id.clone()));
+
+        let open = AllowList::new(&vec![]).unwrap();
+        assert_eq!(open.is_permissioned(), false);
+        assert_eq!(open.is_allowed(&PeerId::random()), true);
+
+        let permissioned = AllowList::new(&vec![hex_public]).unwrap();
+        assert_eq!(permissioned.is_permissioned(), true);
+        assert_eq!(permissioned.is_allowed(&allowed_id), true);
+        assert_eq!(permissioned.is_allowed(&PeerId::random()), false);
+        assert_eq!(AllowList::new(&vec![String::from("00")]).is_err(), true);
+    }
+}
@@ -1,5 +1,11 @@
 #[macro_use]
 extern crate log;
 
+pub mod allow_list;
 pub mod p2pbehaviour;
+pub mod peer_manager;
+pub mod peer_protocol;
+pub mod peer_score;
 pub mod simpleswarm;
+pub mod swarm_context;
+pub mod transport;
@@ -1,37 +1,279 @@
+use super::allow_list::AllowList;
+use super::peer_protocol::{PeerCodec, PeerProtocol};
+use super::swarm_context::SwarmContext;
+use exonum_crypto::hash;
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::StreamExt;
 use libp2p::{
-    floodsub::{self, Floodsub, FloodsubEvent},
+    gossipsub::{
+        Gossipsub, GossipsubConfigBuilder, GossipsubEvent, GossipsubMessage, MessageAuthenticity,
+        MessageId, Topic,
+    },
+    identity::Keypair,
+    kad::{record::store::MemoryStore, Kademlia, KademliaEvent},
     mdns::{Mdns, MdnsEvent},
-    swarm::NetworkBehaviourEventProcess,
-    NetworkBehaviour, PeerId,
+    multiaddr::Protocol,
+    request_response::{
+        OutboundFailure, ProtocolSupport, RequestId, RequestResponse, RequestResponseConfig,
+        RequestResponseEvent, RequestResponseMessage, ResponseChannel,
+    },
+    swarm::{toggle::Toggle, NetworkBehaviourEventProcess},
+    Multiaddr, NetworkBehaviour, PeerId,
 };
-use message_handler::messages::MsgProcess;
+use message_handler::handshake::Handshake;
+use message_handler::messages::{MsgProcess, MsgValidation};
+use message_handler::peer_request::{
+    OutboundPeerRequest, PeerRequest, PeerResponse, PEER_REQUEST_TIMEOUT,
+};
+use std::collections::HashMap;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Mutex};
+use std::task::{Context, Poll};
 use std::time::SystemTime;
-use utils::global_peer_data::{PeerData, GLOBALDATA};
+use tokio::task;
+use utils::global_peer_data::{GlobalData, PeerData};
 
 const LOCALHOST_V4: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
 const LOCALHOST_V6: IpAddr = IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 1));
 
-/// Network behavior defined combining, floodsub and mdns (for discovery)
+/// response of the handler to a request of peer, None if it couldn't answer
+type PeerAnswer = (PeerId, ResponseChannel<PeerResponse>, Option<PeerResponse>);
+
+/// gossip messages are identified by the hash of their content, so the same
+/// transaction or block published twice is only relayed once
+fn content_message_id(message: &GossipsubMessage) -> MessageId {
+    MessageId::from(hash(&message.data).to_hex())
+}
+
+/// split a bootstrap multiaddr into the peer id of its trailing /p2p/ part,
+/// if any, and the address to dial
+pub fn parse_bootstrap_peer(bootstrap_peer: &str) -> Result<(Option<PeerId>, Multiaddr), String> {
+    let mut multi_addr: Multiaddr = bootstrap_peer
+        .parse()
+        .map_err(|_| format!("invalid bootstrap peer address {}", bootstrap_peer))?;
+    match multi_addr.pop() {
+        Some(Protocol::P2p(multihash)) => match PeerId::from_multihash(multihash) {
+            Result::Ok(peer_id) => Ok((Some(peer_id), multi_addr)),
+            Result::Err(_) => Err(format!("invalid peer id in {}", bootstrap_peer)),
+        },
+        Some(protocol) => {
+            multi_addr.push(protocol);
+            Ok((None, multi_addr))
+        }
+        None => Err(format!("empty bootstrap peer address {}", bootstrap_peer)),
+    }
+}
+
+/// add or refresh peer_id in peers, loopback addresses are skipped as other
+/// nodes couldn't reach them
+fn record_peer(peers: &Mutex<GlobalData>, peer_id: PeerId, multi_address: Multiaddr) {
+    let peerid_str = peer_id.to_string();
+    let time_stamp = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_micros();
+    let mut global_data = peers.lock().unwrap();
+    if let Some(peer_data) = global_data.peers.get_mut(&peerid_str) {
+        peer_data.last_seen = time_stamp;
+        return;
+    }
+    let temp_peer_data = PeerData::new(peer_id, time_stamp, multi_address);
+    match temp_peer_data.get_network_addr() {
+        Result::Ok(net_addr) if net_addr != LOCALHOST_V4 && net_addr != LOCALHOST_V6 => {
+            global_data.peers.insert(peerid_str, temp_peer_data);
+        }
+        _ => {}
+    }
+}
+
+/// Network behavior defined combining, gossipsub, kademlia & optionally mdns
+/// (for discovery) and request_response (to fetch blocks & transactions).
+/// Gossip messages are only forwarded once MsgProcess accepted them. Peers
+/// outside the allow_list are disconnected as soon as they show up, they
+/// aren't banned since the allow list keeps them out anyway.
 ///
 #[derive(NetworkBehaviour)]
 pub struct P2PBehaviour {
-    pub floodsub: Floodsub,
-    pub mdns: Mdns,
+    pub gossipsub: Gossipsub,
+    pub kademlia: Kademlia<MemoryStore>,
+    pub mdns: Toggle<Mdns>,
+    pub request_response: RequestResponse<PeerCodec>,
+    // reply channels of the requests this node sent
+    #[behaviour(ignore)]
+    pub pending_requests: HashMap<RequestId, mpsc::Sender<Option<(PeerId, PeerResponse)>>>,
+    // requests of peers are answered on the blocking pool, the answers come
+    // back to the swarm task through this channel
+    #[behaviour(ignore)]
+    answer_sender: UnboundedSender<PeerAnswer>,
+    #[behaviour(ignore)]
+    answers: UnboundedReceiver<PeerAnswer>,
+    // requests of peers being answered, their handler reads the db
+    #[behaviour(ignore)]
+    answering: usize,
+    // set on shutdown, requests of peers are no longer answered
+    #[behaviour(ignore)]
+    stopping: bool,
+    // handshakes sent to connected peers
+    #[behaviour(ignore)]
+    pub pending_handshakes: HashMap<RequestId, PeerId>,
+    // discovered peers the swarm has to dial
+    #[behaviour(ignore)]
+    pub pending_dials: Vec<PeerId>,
+    // peers the swarm has to disconnect, e.g. peers outside the allow_list
+    #[behaviour(ignore)]
+    pub pending_disconnects: Vec<PeerId>,
+    // kademlia bootstrap ran with at least one known peer
+    #[behaviour(ignore)]
+    pub bootstrapped: bool,
+    #[behaviour(ignore)]
+    pub allow_list: AllowList,
+    #[behaviour(ignore)]
+    pub context: SwarmContext,
 }
 
 impl P2PBehaviour {
-    pub fn new(peer_id: PeerId) -> Self {
-        let mdns = Mdns::new().unwrap();
+    pub fn new(keypair: Keypair, allow_list: AllowList, context: SwarmContext) -> Self {
+        let peer_id: PeerId = keypair.public().into_peer_id();
+        let mdns: Option<Mdns> = if context.config.node.mdns {
+            Some(Mdns::new().unwrap())
+        } else {
+            None
+        };
+        let gossipsub_config = GossipsubConfigBuilder::new()
+            .message_id_fn(content_message_id)
+            .validate_messages()
+            .build();
+        let mut request_response_config = RequestResponseConfig::default();
+        request_response_config.set_request_timeout(PEER_REQUEST_TIMEOUT);
+        let peer_codec = PeerCodec {
+            max_message_size: context.config.block_config.max_message_size as usize,
+        };
+        let (answer_sender, answers) = unbounded::<PeerAnswer>();
         let behaviour = P2PBehaviour {
-            floodsub: Floodsub::new(peer_id.clone()),
-            mdns,
+            gossipsub: Gossipsub::new(MessageAuthenticity::Signed(keypair), gossipsub_config),
+            kademlia: Kademlia::new(peer_id.clone(), MemoryStore::new(peer_id)),
+            mdns: Toggle::from(mdns),
+            request_response: RequestResponse::new(
+                peer_codec,
+                vec![(PeerProtocol(), ProtocolSupport::Full)],
+                request_response_config,
+            ),
+            pending_requests: HashMap::new(),
+            answer_sender,
+            answers,
+            answering: 0,
+            stopping: false,
+            pending_handshakes: HashMap::new(),
+            pending_dials: Vec::new(),
+            pending_disconnects: Vec::new(),
+            bootstrapped: false,
+            allow_list,
+            context,
         };
         behaviour
     }
+
+    /// send a request of the node, to any connected peer if it names none.
+    /// The response, None on failure, is sent to the reply channel.
+    pub fn send_peer_request(&mut self, outbound_request: OutboundPeerRequest) {
+        let peer_id: Option<PeerId> = match outbound_request.peer_id {
+            Some(peer_id) => Some(peer_id),
+            None => self
+                .context
+                .peer_manager
+                .lock()
+                .unwrap()
+                .connected_peers()
+                .into_iter()
+                .next(),
+        };
+        match peer_id {
+            Some(peer_id) => {
+                let request_id: RequestId = self
+                    .request_response
+                    .send_request(&peer_id, outbound_request.request);
+                self.pending_requests
+                    .insert(request_id, outbound_request.reply);
+            }
+            None => {
+                debug!("no peer to send {:?} to", outbound_request.request);
+                let _ = outbound_request.reply.send(None);
+            }
+        }
+    }
+
+    /// send the responses of the answered peer requests. Without a response
+    /// the channel is dropped & the peer sees the request fail.
+    pub fn poll_answers(&mut self, cx: &mut Context) {
+        while let Poll::Ready(Some((peer, channel, response))) = self.answers.poll_next_unpin(cx) {
+            self.answering -= 1;
+            match response {
+                Some(response) => self.request_response.send_response(channel, response),
+                None => warn!("peer request of {} couldn't be handled", peer),
+            }
+        }
+    }
+
+    /// stop answering requests of peers, true once none is being answered so
+    /// the db may be closed
+    pub fn stop_answering(&mut self) -> bool {
+        self.stopping = true;
+        self.answering == 0
+    }
+
+    /// send the handshake of the node to a newly connected peer
+    pub fn send_handshake(&mut self, peer_id: &PeerId) {
+        let request_id: RequestId = self
+            .request_response
+            .send_request(peer_id, PeerRequest::Handshake(self.context.handshake()));
+        self.pending_handshakes.insert(request_id, peer_id.clone());
+    }
+
+    /// agree on a protocol version with peer_id or refuse it
+    fn complete_handshake(&mut self, peer_id: &PeerId, handshake: &Handshake) {
+        match self.context.handshake().negotiate(handshake) {
+            Ok(version) => {
+                debug!("peer {} speaks protocol version {}", peer_id, version);
+                self.context
+                    .peer_manager
+                    .lock()
+                    .unwrap()
+                    .handshaken(peer_id, version);
+            }
+            Err(error) => {
+                warn!("incompatible peer {} refused: {}", peer_id, error);
+                self.context.peer_manager.lock().unwrap().refuse(peer_id);
+            }
+        }
+    }
+
+    fn is_banned(&self, peer_id: &PeerId) -> bool {
+        self.context.peer_manager.lock().unwrap().is_banned(peer_id)
+    }
+
+    /// false & disconnect peer_id if it isn't in the allow_list
+    fn check_allowed(&mut self, peer_id: &PeerId) -> bool {
+        if self.allow_list.is_allowed(peer_id) {
+            return true;
+        }
+        if !self.pending_disconnects.contains(peer_id) {
+            warn!("peer {:?} is not in the allow list, disconnected", peer_id);
+            self.pending_disconnects.push(peer_id.clone());
+        }
+        false
+    }
+
+    /// look up peers close to us through the known kademlia peers
+    pub fn bootstrap(&mut self) {
+        match self.kademlia.bootstrap() {
+            Result::Ok(_) => self.bootstrapped = true,
+            Result::Err(_) => info!("no known peer to bootstrap kademlia from"),
+        }
+    }
     pub fn subscribe(&mut self, topic_str: &String) {
-        let floodsub_topic = floodsub::Topic::new(topic_str);
-        self.floodsub.subscribe(floodsub_topic);
+        let gossipsub_topic = Topic::new(topic_str.clone());
+        self.gossipsub.subscribe(gossipsub_topic);
     }
 }
 
@@ -41,63 +283,220 @@ impl NetworkBehaviourEventProcess<MdnsEvent> for P2PBehaviour {
             MdnsEvent::Discovered(discovered_nodes) => {
                 debug!("Discovered address {:?}", discovered_nodes);
                 for (peer_id, multi_address) in discovered_nodes {
-                    let peerid_str = peer_id.to_string();
                     debug!("peer discovered {} with address {}", peer_id, multi_address);
-                    self.floodsub.add_node_to_partial_view(peer_id.clone());
-                    let time_stamp = SystemTime::now()
-                        .duration_since(SystemTime::UNIX_EPOCH)
-                        .unwrap()
-                        .as_micros();
-                    if !GLOBALDATA.lock().unwrap().peers.contains_key(&peerid_str) {
-                        let temp_peer_data = PeerData::new(peer_id, time_stamp, multi_address);
-                        let net_addr = temp_peer_data.get_network_addr().unwrap();
-                        if net_addr != LOCALHOST_V4 && net_addr != LOCALHOST_V6 {
-                            GLOBALDATA
-                                .lock()
-                                .unwrap()
-                                .peers
-                                .insert(peerid_str, temp_peer_data);
-                        }
-                    } else {
-                        GLOBALDATA
-                            .lock()
-                            .unwrap()
-                            .peers
-                            .get_mut(&peerid_str)
-                            .unwrap()
-                            .last_seen = time_stamp;
+                    if !self.allow_list.is_allowed(&peer_id) {
+                        continue;
+                    }
+                    if !self.is_banned(&peer_id) {
+                        self.pending_dials.push(peer_id.clone());
                     }
+                    // mdns peers are also shared with the rest of the network
+                    self.kademlia.add_address(&peer_id, multi_address.clone());
+                    record_peer(&self.context.peers, peer_id, multi_address);
                 }
             }
             MdnsEvent::Expired(expired_nodes) => {
                 debug!("Expired address {:?}", expired_nodes);
                 for (peer_id, _) in expired_nodes {
                     let peerid_str = peer_id.to_string();
-                    self.floodsub.remove_node_from_partial_view(&peer_id);
-                    GLOBALDATA.lock().unwrap().peers.remove(&peerid_str);
+                    self.context.peers.lock().unwrap().peers.remove(&peerid_str);
                 }
             }
         }
     }
 }
 
-impl NetworkBehaviourEventProcess<FloodsubEvent> for P2PBehaviour {
-    fn inject_event(&mut self, pubsub_event: FloodsubEvent) {
+impl NetworkBehaviourEventProcess<KademliaEvent> for P2PBehaviour {
+    fn inject_event(&mut self, kademlia_event: KademliaEvent) {
+        match kademlia_event {
+            KademliaEvent::RoutingUpdated {
+                peer, addresses, ..
+            } => {
+                debug!("kademlia routing updated for peer {}", peer);
+                if !self.check_allowed(&peer) {
+                    return;
+                }
+                if !self.is_banned(&peer) {
+                    self.pending_dials.push(peer.clone());
+                }
+                if let Some(multi_address) = addresses.iter().next() {
+                    record_peer(&self.context.peers, peer, multi_address.clone());
+                }
+                // peers became known after a bootstrap without any
+                if !self.bootstrapped {
+                    self.bootstrap();
+                }
+            }
+            event => debug!("kademlia event {:?}", event),
+        }
+    }
+}
+
+impl NetworkBehaviourEventProcess<RequestResponseEvent<PeerRequest, PeerResponse>>
+    for P2PBehaviour
+{
+    fn inject_event(&mut self, event: RequestResponseEvent<PeerRequest, PeerResponse>) {
+        match event {
+            RequestResponseEvent::Message { peer, message } => match message {
+                RequestResponseMessage::Request {
+                    request, channel, ..
+                } => {
+                    debug!("peer request {:?} from {}", request, peer);
+                    if self.stopping || self.is_banned(&peer) || !self.check_allowed(&peer) {
+                        return;
+                    }
+                    if let PeerRequest::Handshake(handshake) = request {
+                        let response = PeerResponse::Handshake(self.context.handshake());
+                        self.request_response.send_response(channel, response);
+                        self.complete_handshake(&peer, &handshake);
+                        return;
+                    }
+                    // the handler reads the db, so it doesn't run on the swarm
+                    // task, poll_answers sends its response
+                    let answer_sender = self.answer_sender.clone();
+                    let handler = self.context.peer_request_handler.clone();
+                    self.answering += 1;
+                    task::spawn_blocking(move || {
+                        // a panicking handler still answers, or the swarm
+                        // would wait for it on shutdown
+                        let response: Option<PeerResponse> =
+                            panic::catch_unwind(AssertUnwindSafe(|| handler(request))).ok();
+                        let _ = answer_sender.unbounded_send((peer, channel, response));
+                    });
+                }
+                RequestResponseMessage::Response {
+                    request_id,
+                    response,
+                } => {
+                    if self.pending_handshakes.remove(&request_id).is_some() {
+                        match response {
+                            PeerResponse::Handshake(handshake) => {
+                                self.complete_handshake(&peer, &handshake)
+                            }
+                            _ => {
+                                warn!("peer {} answered the handshake wrong, refused", peer);
+                                self.context.peer_manager.lock().unwrap().refuse(&peer);
+                            }
+                        }
+                    } else if let Some(reply) = self.pending_requests.remove(&request_id) {
+                        let _ = reply.send(Some((peer, response)));
+                    }
+                }
+            },
+            RequestResponseEvent::OutboundFailure {
+                peer,
+                request_id,
+                error,
+            } => {
+                warn!("peer request to {} failed: {:?}", peer, error);
+                if self.pending_handshakes.remove(&request_id).is_some() {
+                    match error {
+                        // nodes of older versions don't speak the peer protocol
+                        OutboundFailure::UnsupportedProtocols => {
+                            self.context.peer_manager.lock().unwrap().refuse(&peer);
+                        }
+                        // e.g. a timeout, the peer may handshake once it
+                        // connects again
+                        _ => {
+                            warn!("handshake with {} failed, disconnected", peer);
+                            if !self.pending_disconnects.contains(&peer) {
+                                self.pending_disconnects.push(peer);
+                            }
+                        }
+                    }
+                } else if let Some(reply) = self.pending_requests.remove(&request_id) {
+                    let _ = reply.send(None);
+                }
+            }
+            RequestResponseEvent::InboundFailure { peer, error, .. } => {
+                debug!("peer request of {} failed: {:?}", peer, error)
+            }
+        }
+    }
+}
+
+impl NetworkBehaviourEventProcess<GossipsubEvent> for P2PBehaviour {
+    fn inject_event(&mut self, pubsub_event: GossipsubEvent) {
         match pubsub_event {
-            FloodsubEvent::Message(msg) => {
+            GossipsubEvent::Message(propagation_source, message_id, msg) => {
                 debug!(
                     "Message received from {:?}, msg topic {:?}",
-                    msg.source, msg.topics
+                    propagation_source, msg.topics
+                );
+                if self.is_banned(&propagation_source) || !self.check_allowed(&propagation_source) {
+                    return;
+                }
+                // the version & chain of a peer are only known after the
+                // handshake, until then its gossip isn't processed nor relayed
+                if self
+                    .context
+                    .peer_manager
+                    .lock()
+                    .unwrap()
+                    .protocol_version(&propagation_source)
+                    .is_none()
+                {
+                    debug!(
+                        "message from {:?} dropped, no handshake yet",
+                        propagation_source
+                    );
+                    return;
+                }
+                // in a permissioned network only allowed peers may publish
+                if self.allow_list.is_permissioned() {
+                    let allowed_source: bool = match &msg.source {
+                        Some(source) => self.allow_list.is_allowed(source),
+                        None => false,
+                    };
+                    if !allowed_source {
+                        warn!("message published by {:?} dropped, not allowed", msg.source);
+                        return;
+                    }
+                }
+                let validation: MsgValidation = msg.process(
+                    &msg.topics,
+                    &msg.data,
+                    &self.context.config,
+                    &self.context.dispatcher,
+                    &self.context.peer_reports,
                 );
-                msg.process(&msg.topics, &msg.data);
+                match validation {
+                    MsgValidation::Accept => {
+                        self.context
+                            .peer_manager
+                            .lock()
+                            .unwrap()
+                            .reward(&propagation_source);
+                        self.gossipsub
+                            .propagate_message(&message_id, &propagation_source);
+                    }
+                    MsgValidation::Reject(reason) => {
+                        warn!(
+                            "invalid message from {:?} dropped: {}",
+                            propagation_source, reason
+                        );
+                        if self
+                            .context
+                            .peer_manager
+                            .lock()
+                            .unwrap()
+                            .penalise_invalid_msg(&propagation_source)
+                        {
+                            warn!("peer {:?} banned for invalid messages", propagation_source);
+                        }
+                    }
+                    // valid but not queued, other peers may still relay it
+                    MsgValidation::Ignore(reason) => {
+                        debug!("message from {:?} ignored: {}", propagation_source, reason);
+                    }
+                }
             }
-            FloodsubEvent::Subscribed {
-                peer_id: _,
-                topic: _,
-            } => {
-                // info!("subscribed by peer {:?} topic {:?}", peer_id, topic);
+            GossipsubEvent::Subscribed { peer_id, topic: _ } => {
+                // the swarm already closes connections of peers outside the
+                // allow list, this catches the ones still open
+                self.check_allowed(&peer_id);
             }
-            FloodsubEvent::Unsubscribed {
+            GossipsubEvent::Unsubscribed {
                 peer_id: _,
                 topic: _,
             } => {
@@ -106,3 +505,20 @@ impl NetworkBehaviourEventProcess<FloodsubEvent> for P2PBehaviour {
         }
     }
 }
+
+#[cfg(test)]
+mod tests_p2pbehaviour {
+    use super::*;
+
+    #[test]
+    fn test_parse_bootstrap_peer() {
+        let peer_id: PeerId = Keypair::generate_ed25519().public().into_peer_id();
+        let (bootstrap_id, multi_addr) =
+            parse_bootstrap_peer(&format!("/ip4/10.0.0.2/tcp/4444/p2p/{}", peer_id)).unwrap();
+        assert_eq!(bootstrap_id, Some(peer_id));
+        assert_eq!(multi_addr, "/ip4/10.0.0.2/tcp/4444".parse().unwrap());
+        let (bootstrap_id, _) = parse_bootstrap_peer("/ip4/10.0.0.2/tcp/4444").unwrap();
+        assert_eq!(bootstrap_id, None);
+        assert_eq!(parse_bootstrap_peer("10.0.0.2:4444").is_err(), true);
+    }
+}
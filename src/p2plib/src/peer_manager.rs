@@ -0,0 +1,383 @@
+use super::peer_score::{
+    PeerScores, INVALID_BLOCK_PENALTY, INVALID_MSG_PENALTY, INVALID_TXN_PENALTY,
+};
+use libp2p::PeerId;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::time::SystemTime;
+use utils::configreader::Node;
+use utils::peer_reports::Offence;
+use utils::serializer::{deserialize, serialize, Deserialize, Serialize};
+
+/// direction of the connection to a peer
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Direction {
+    Inbound,
+    Outbound,
+}
+
+/// PeerInfo describes a peer known by the PeerManager, it is returned by
+/// the admin api
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PeerInfo {
+    pub peer_id: String,
+    pub score: i32,
+    pub connection: Option<Direction>,
+    /// unix time in seconds the ban of the peer ends
+    pub banned_until: Option<u64>,
+    /// wire protocol version agreed on in the handshake
+    pub protocol_version: Option<u32>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+pub fn offence_penalty(offence: Offence) -> i32 {
+    match offence {
+        Offence::InvalidBlock => INVALID_BLOCK_PENALTY,
+        Offence::InvalidTransaction => INVALID_TXN_PENALTY,
+    }
+}
+
+/// PeerManager keeps the connections within max_inbound & max_outbound,
+/// scores peers and bans the misbehaving ones for ban_duration seconds.
+/// Bans are written to bans_path so a restart doesn't lift them. The swarm
+/// applies the bans & unbans it queues.
+#[derive(Debug)]
+pub struct PeerManager {
+    scores: PeerScores,
+    // peer id => unix time in seconds the ban ends
+    bans: HashMap<PeerId, u64>,
+    pending_bans: Vec<PeerId>,
+    pending_unbans: Vec<PeerId>,
+    inbound: HashSet<PeerId>,
+    outbound: HashSet<PeerId>,
+    // connected peers which completed the handshake => agreed protocol version
+    protocol_versions: HashMap<PeerId, u32>,
+    max_inbound: usize,
+    max_outbound: usize,
+    ban_duration: u64,
+    bans_path: Option<String>,
+}
+
+impl PeerManager {
+    /// bans of bans_path that didn't end yet are loaded and queued
+    pub fn new(
+        max_inbound: usize,
+        max_outbound: usize,
+        ban_duration: u64,
+        bans_path: Option<String>,
+    ) -> PeerManager {
+        let mut peer_manager = PeerManager {
+            scores: PeerScores::new(),
+            bans: HashMap::new(),
+            pending_bans: Vec::new(),
+            pending_unbans: Vec::new(),
+            inbound: HashSet::new(),
+            outbound: HashSet::new(),
+            protocol_versions: HashMap::new(),
+            max_inbound,
+            max_outbound,
+            ban_duration,
+            bans_path,
+        };
+        peer_manager.load_bans();
+        peer_manager
+    }
+
+    pub fn from_config(node: &Node) -> PeerManager {
+        PeerManager::new(
+            node.max_inbound_peers,
+            node.max_outbound_peers,
+            node.ban_duration,
+            Some(node.banned_peers_path.clone()),
+        )
+    }
+
+    fn load_bans(&mut self) {
+        let bans_path: &String = match &self.bans_path {
+            Some(bans_path) => bans_path,
+            None => return,
+        };
+        let data: Vec<u8> = match std::fs::read(bans_path) {
+            Result::Ok(data) => data,
+            // no ban was written yet
+            Result::Err(_) => return,
+        };
+        let bans: BTreeMap<String, u64> = match deserialize(&data) {
+            Result::Ok(bans) => bans,
+            Result::Err(error) => {
+                error!("banned peers of {} couldn't load: {}", bans_path, error);
+                return;
+            }
+        };
+        let current_time: u64 = now();
+        for (peer_id, banned_until) in bans.into_iter() {
+            match peer_id.parse::<PeerId>() {
+                Result::Ok(peer_id) if banned_until > current_time => {
+                    self.bans.insert(peer_id.clone(), banned_until);
+                    self.pending_bans.push(peer_id);
+                }
+                Result::Ok(_) => {}
+                Result::Err(_) => warn!("invalid banned peer id {} ignored", peer_id),
+            }
+        }
+        info!("{} banned peers loaded", self.bans.len());
+    }
+
+    fn save_bans(&self) {
+        let bans_path: &String = match &self.bans_path {
+            Some(bans_path) => bans_path,
+            None => return,
+        };
+        let bans: BTreeMap<String, u64> = self
+            .bans
+            .iter()
+            .map(|(peer_id, banned_until)| (peer_id.to_string(), *banned_until))
+            .collect();
+        match serialize(&bans) {
+            Result::Ok(data) => {
+                if let Err(error) = std::fs::write(bans_path, data) {
+                    error!(
+                        "banned peers couldn't be written to {}: {}",
+                        bans_path, error
+                    );
+                }
+            }
+            Result::Err(error) => error!("banned peers couldn't serialize: {}", error),
+        }
+    }
+
+    pub fn score(&self, peer_id: &PeerId) -> i32 {
+        self.scores.score(peer_id)
+    }
+
+    pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+        match self.bans.get(peer_id) {
+            Some(banned_until) => *banned_until > now(),
+            None => false,
+        }
+    }
+
+    pub fn reward(&mut self, peer_id: &PeerId) {
+        self.scores.reward(peer_id);
+    }
+
+    /// lower the score of peer_id, true if the peer just got banned
+    pub fn penalise(&mut self, peer_id: &PeerId, penalty: i32) -> bool {
+        if self.scores.penalise(peer_id, penalty) {
+            return self.ban(peer_id, self.ban_duration);
+        }
+        false
+    }
+
+    pub fn penalise_invalid_msg(&mut self, peer_id: &PeerId) -> bool {
+        self.penalise(peer_id, INVALID_MSG_PENALTY)
+    }
+
+    /// apply an offence found by block or transaction processing
+    pub fn report(&mut self, peer_id: &PeerId, offence: Offence) -> bool {
+        self.penalise(peer_id, offence_penalty(offence))
+    }
+
+    /// ban peer_id for ban_duration, e.g. for failing the handshake
+    pub fn refuse(&mut self, peer_id: &PeerId) -> bool {
+        self.ban(peer_id, self.ban_duration)
+    }
+
+    /// ban peer_id for duration seconds, true if it wasn't banned yet. A
+    /// banned peer is banned again for the new duration.
+    pub fn ban(&mut self, peer_id: &PeerId, duration: u64) -> bool {
+        let banned: bool = self.is_banned(peer_id);
+        self.bans
+            .insert(peer_id.clone(), now().saturating_add(duration));
+        self.pending_bans.push(peer_id.clone());
+        self.save_bans();
+        !banned
+    }
+
+    /// lift the ban of peer_id, false if it wasn't banned
+    pub fn unban(&mut self, peer_id: &PeerId) -> bool {
+        if self.bans.remove(peer_id).is_none() {
+            return false;
+        }
+        self.scores.reset(peer_id);
+        self.pending_unbans.push(peer_id.clone());
+        self.save_bans();
+        true
+    }
+
+    /// lift the bans which ended
+    pub fn expire_bans(&mut self) {
+        let current_time: u64 = now();
+        let expired: Vec<PeerId> = self
+            .bans
+            .iter()
+            .filter(|(_, banned_until)| **banned_until <= current_time)
+            .map(|(peer_id, _)| peer_id.clone())
+            .collect();
+        for peer_id in expired.iter() {
+            info!("ban of peer {} ended", peer_id);
+            self.unban(peer_id);
+        }
+    }
+
+    /// register a new connection, false if the peer is banned or the limit
+    /// of its direction is reached, the swarm then has to disconnect it
+    pub fn connected(&mut self, peer_id: &PeerId, direction: Direction) -> bool {
+        if self.is_banned(peer_id) {
+            return false;
+        }
+        let (connections, max_connections) = match direction {
+            Direction::Inbound => (&mut self.inbound, self.max_inbound),
+            Direction::Outbound => (&mut self.outbound, self.max_outbound),
+        };
+        if !connections.contains(peer_id) && connections.len() >= max_connections {
+            warn!(
+                "{:?} connection limit {} reached, peer {} refused",
+                direction, max_connections, peer_id
+            );
+            return false;
+        }
+        connections.insert(peer_id.clone());
+        true
+    }
+
+    pub fn disconnected(&mut self, peer_id: &PeerId) {
+        self.inbound.remove(peer_id);
+        self.outbound.remove(peer_id);
+        self.protocol_versions.remove(peer_id);
+    }
+
+    /// peer_id completed the handshake agreeing on version
+    pub fn handshaken(&mut self, peer_id: &PeerId, version: u32) {
+        if self.is_connected(peer_id) {
+            self.protocol_versions.insert(peer_id.clone(), version);
+        }
+    }
+
+    pub fn protocol_version(&self, peer_id: &PeerId) -> Option<u32> {
+        self.protocol_versions.get(peer_id).cloned()
+    }
+
+    pub fn is_connected(&self, peer_id: &PeerId) -> bool {
+        self.inbound.contains(peer_id) || self.outbound.contains(peer_id)
+    }
+
+    /// connected peers which completed the handshake
+    pub fn connected_peers(&self) -> Vec<PeerId> {
+        self.outbound
+            .iter()
+            .chain(self.inbound.iter())
+            .filter(|peer_id| self.protocol_versions.contains_key(peer_id))
+            .cloned()
+            .collect()
+    }
+
+    /// false once max_outbound connections are dialed
+    pub fn can_dial(&self) -> bool {
+        self.outbound.len() < self.max_outbound
+    }
+
+    /// peers banned since the last call, to be disconnected by the swarm
+    pub fn take_pending_bans(&mut self) -> Vec<PeerId> {
+        std::mem::replace(&mut self.pending_bans, Vec::new())
+    }
+
+    /// peers unbanned since the last call, to be unbanned by the swarm
+    pub fn take_pending_unbans(&mut self) -> Vec<PeerId> {
+        std::mem::replace(&mut self.pending_unbans, Vec::new())
+    }
+
+    /// connected, banned & scored peers
+    pub fn peers(&self) -> Vec<PeerInfo> {
+        let mut peer_ids: BTreeSet<String> = BTreeSet::new();
+        let mut known: Vec<PeerId> = self.scores.scored_peers();
+        known.extend(self.bans.keys().cloned());
+        known.extend(self.inbound.iter().cloned());
+        known.extend(self.outbound.iter().cloned());
+        let mut peers: Vec<PeerInfo> = Vec::new();
+        for peer_id in known.into_iter() {
+            if !peer_ids.insert(peer_id.to_string()) {
+                continue;
+            }
+            let connection: Option<Direction> = if self.inbound.contains(&peer_id) {
+                Some(Direction::Inbound)
+            } else if self.outbound.contains(&peer_id) {
+                Some(Direction::Outbound)
+            } else {
+                None
+            };
+            peers.push(PeerInfo {
+                peer_id: peer_id.to_string(),
+                score: self.scores.score(&peer_id),
+                connection,
+                banned_until: self.bans.get(&peer_id).cloned(),
+                protocol_version: self.protocol_version(&peer_id),
+            });
+        }
+        peers.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+        peers
+    }
+}
+
+#[cfg(test)]
+mod tests_peer_manager {
+    use super::*;
+
+    #[test]
+    fn test_peer_manager_limits() {
+        let mut peer_manager = PeerManager::new(1, 1, 60, None);
+        let (first, second) = (PeerId::random(), PeerId::random());
+        assert_eq!(peer_manager.connected(&first, Direction::Inbound), true);
+        assert_eq!(peer_manager.connected(&second, Direction::Inbound), false);
+        assert_eq!(peer_manager.can_dial(), true);
+        assert_eq!(peer_manager.connected(&second, Direction::Outbound), true);
+        assert_eq!(peer_manager.can_dial(), false);
+        peer_manager.disconnected(&first);
+        assert_eq!(peer_manager.is_connected(&first), false);
+        assert_eq!(peer_manager.connected(&first, Direction::Inbound), true);
+        assert_eq!(peer_manager.peers().len(), 2);
+        // requests only go to peers which completed the handshake
+        assert_eq!(peer_manager.connected_peers(), Vec::<PeerId>::new());
+        peer_manager.handshaken(&first, 1);
+        assert_eq!(peer_manager.connected_peers(), vec![first.clone()]);
+        assert_eq!(peer_manager.protocol_version(&first), Some(1));
+        peer_manager.disconnected(&first);
+        assert_eq!(peer_manager.protocol_version(&first), None);
+    }
+
+    #[test]
+    fn test_peer_manager_bans() {
+        let bans_path = std::env::temp_dir().join(format!("banned_peers_{}", now()));
+        let bans_path: String = bans_path.to_str().unwrap().to_string();
+        let peer_id = PeerId::random();
+        {
+            let mut peer_manager = PeerManager::new(10, 10, 60, Some(bans_path.clone()));
+            let mut banned: bool = false;
+            while !banned {
+                banned = peer_manager.report(&peer_id, Offence::InvalidBlock);
+            }
+            assert_eq!(peer_manager.is_banned(&peer_id), true);
+            assert_eq!(peer_manager.take_pending_bans(), vec![peer_id.clone()]);
+            assert_eq!(peer_manager.connected(&peer_id, Direction::Inbound), false);
+        }
+        // the ban survives a restart
+        let mut peer_manager = PeerManager::new(10, 10, 60, Some(bans_path.clone()));
+        assert_eq!(peer_manager.is_banned(&peer_id), true);
+        assert_eq!(peer_manager.take_pending_bans(), vec![peer_id.clone()]);
+        assert_eq!(peer_manager.unban(&peer_id), true);
+        assert_eq!(peer_manager.unban(&peer_id), false);
+        assert_eq!(peer_manager.take_pending_unbans(), vec![peer_id.clone()]);
+
+        // a ban of 0 seconds ends at once
+        peer_manager.ban(&peer_id, 0);
+        peer_manager.expire_bans();
+        assert_eq!(peer_manager.is_banned(&peer_id), false);
+        assert_eq!(peer_manager.take_pending_unbans(), vec![peer_id]);
+        let _ = std::fs::remove_file(bans_path);
+    }
+}
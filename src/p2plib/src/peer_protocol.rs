@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use futures::prelude::*;
+use libp2p::core::upgrade::{read_one, write_one, ProtocolName};
+use libp2p::request_response::RequestResponseCodec;
+use message_handler::peer_request::{PeerRequest, PeerResponse, PEER_PROTOCOL};
+use std::io;
+use utils::serializer::{deserialize, serialize};
+
+#[derive(Debug, Clone)]
+pub struct PeerProtocol();
+
+impl ProtocolName for PeerProtocol {
+    fn protocol_name(&self) -> &[u8] {
+        PEER_PROTOCOL
+    }
+}
+
+/// PeerCodec sends PeerRequest & PeerResponse serialized with a length
+/// prefix, messages over max_message_size are refused
+#[derive(Debug, Clone)]
+pub struct PeerCodec {
+    pub max_message_size: usize,
+}
+
+fn invalid_data<E: ToString>(error: E) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+async fn read_message<T, M>(io: &mut T, max_message_size: usize) -> io::Result<M>
+where
+    T: AsyncRead + Unpin + Send,
+    M: serde::de::DeserializeOwned,
+{
+    // read_one refuses messages over max_message_size before reading them
+    let data: Vec<u8> = read_one(io, max_message_size).await.map_err(invalid_data)?;
+    deserialize(&data).map_err(invalid_data)
+}
+
+async fn write_message<T, M>(io: &mut T, message: &M) -> io::Result<()>
+where
+    T: AsyncWrite + Unpin + Send,
+    M: serde::Serialize,
+{
+    let data: Vec<u8> = serialize(message).map_err(invalid_data)?;
+    write_one(io, data).await
+}
+
+#[async_trait]
+impl RequestResponseCodec for PeerCodec {
+    type Protocol = PeerProtocol;
+    type Request = PeerRequest;
+    type Response = PeerResponse;
+
+    async fn read_request<T>(&mut self, _: &PeerProtocol, io: &mut T) -> io::Result<PeerRequest>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_message(io, self.max_message_size).await
+    }
+
+    async fn read_response<T>(&mut self, _: &PeerProtocol, io: &mut T) -> io::Result<PeerResponse>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        read_message(io, self.max_message_size).await
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &PeerProtocol,
+        io: &mut T,
+        request: PeerRequest,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_message(io, &request).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &PeerProtocol,
+        io: &mut T,
+        response: PeerResponse,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        write_message(io, &response).await
+    }
+}
@@ -0,0 +1,82 @@
+use libp2p::PeerId;
+use std::collections::HashMap;
+
+/// score of a peer we have no opinion about
+pub const INITIAL_SCORE: i32 = 0;
+/// score gained by a valid gossip message, up to MAX_SCORE
+pub const VALID_MSG_REWARD: i32 = 1;
+/// score lost by an invalid gossip message
+pub const INVALID_MSG_PENALTY: i32 = 20;
+/// score lost by a transaction the pool refused
+pub const INVALID_TXN_PENALTY: i32 = 20;
+/// score lost by a block update_block refused
+pub const INVALID_BLOCK_PENALTY: i32 = 50;
+pub const MAX_SCORE: i32 = 100;
+/// peers at or below this score are banned
+pub const BAN_SCORE: i32 = -100;
+
+/// PeerScores tracks the behaviour of peers. Valid messages slowly raise
+/// the score of a peer, invalid ones lower it fast, so a peer that keeps
+/// sending invalid data ends up at BAN_SCORE.
+#[derive(Debug, Default)]
+pub struct PeerScores {
+    scores: HashMap<PeerId, i32>,
+}
+
+impl PeerScores {
+    pub fn new() -> PeerScores {
+        PeerScores::default()
+    }
+
+    pub fn score(&self, peer_id: &PeerId) -> i32 {
+        *self.scores.get(peer_id).unwrap_or(&INITIAL_SCORE)
+    }
+
+    /// peers with a score other than INITIAL_SCORE
+    pub fn scored_peers(&self) -> Vec<PeerId> {
+        self.scores.keys().cloned().collect()
+    }
+
+    pub fn reward(&mut self, peer_id: &PeerId) {
+        let score: i32 = std::cmp::min(self.score(peer_id) + VALID_MSG_REWARD, MAX_SCORE);
+        self.scores.insert(peer_id.clone(), score);
+    }
+
+    /// lower the score of peer_id by penalty, true once it reached BAN_SCORE
+    pub fn penalise(&mut self, peer_id: &PeerId, penalty: i32) -> bool {
+        let score: i32 = self.score(peer_id) - penalty;
+        self.scores.insert(peer_id.clone(), score);
+        score <= BAN_SCORE
+    }
+
+    /// forget the score of peer_id, e.g. when its ban is over
+    pub fn reset(&mut self, peer_id: &PeerId) {
+        self.scores.remove(peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests_peer_score {
+    use super::*;
+
+    #[test]
+    fn test_peer_scores() {
+        let mut peer_scores = PeerScores::new();
+        let peer_id = PeerId::random();
+        peer_scores.reward(&peer_id);
+        assert_eq!(peer_scores.score(&peer_id), VALID_MSG_REWARD);
+        let mut penalties: i32 = 0;
+        while !peer_scores.penalise(&peer_id, INVALID_MSG_PENALTY) {
+            penalties += 1;
+        }
+        assert_eq!(
+            penalties,
+            (VALID_MSG_REWARD - BAN_SCORE) / INVALID_MSG_PENALTY
+        );
+        assert_eq!(peer_scores.score(&peer_id) <= BAN_SCORE, true);
+        assert_eq!(peer_scores.scored_peers(), vec![peer_id.clone()]);
+        peer_scores.reset(&peer_id);
+        assert_eq!(peer_scores.score(&peer_id), INITIAL_SCORE);
+        assert_eq!(peer_scores.score(&PeerId::random()), INITIAL_SCORE);
+    }
+}
@@ -1,41 +1,165 @@
-use super::p2pbehaviour::P2PBehaviour;
-use futures::{channel::mpsc::*, executor::*, future, prelude::*, task::*};
-use libp2p::{floodsub::Topic, PeerId, Swarm};
+use super::allow_list::AllowList;
+use super::p2pbehaviour::{parse_bootstrap_peer, P2PBehaviour};
+use super::peer_manager::Direction;
+use super::swarm_context::SwarmContext;
+use super::transport::build_transport;
+use futures::{future, pin_mut, prelude::*, task::*};
+use libp2p::{
+    core::ConnectedPoint,
+    gossipsub::Topic,
+    swarm::{SwarmEvent, SwarmEvent::*},
+    PeerId, Swarm,
+};
+use message_handler::envelope::{Envelope, MessageKind};
 use message_handler::messages::*;
+use message_handler::queues::{gossip_channel, GossipReceiver, GossipSender};
+use message_handler::shutdown::Shutdown;
 use std::error::Error;
+use std::sync::Arc;
 use utils::configreader::Configuration;
 use utils::serializer::*;
 
+/// publish value on topics in the envelope of this node
+fn publish(swarm: &mut Swarm<P2PBehaviour>, topics: Vec<Topic>, kind: MessageKind, value: Vec<u8>) {
+    let chain_id: &String = &swarm.context.config.node.chain_id;
+    let value: Vec<u8> = match serialize(&Envelope::seal(chain_id, kind, value)) {
+        Result::Ok(value) => value,
+        Result::Err(error) => {
+            warn!("envelope couldn't serialize: {}", error);
+            return;
+        }
+    };
+    for topic in topics.iter() {
+        if let Err(error) = swarm.gossipsub.publish(topic, value.clone()) {
+            warn!("message couldn't be published: {:?}", error);
+        }
+    }
+}
+
+/// close the connections to peer_id without keeping it banned
+fn disconnect(swarm: &mut Swarm<P2PBehaviour>, peer_id: PeerId) {
+    Swarm::ban_peer_id(swarm, peer_id.clone());
+    Swarm::unban_peer_id(swarm, peer_id);
+}
+
+/// track connections in the PeerManager, the ones it refuses and the ones of
+/// peers outside the allow list are closed
+fn on_swarm_event<E: std::fmt::Debug, H: std::fmt::Debug>(
+    swarm: &mut Swarm<P2PBehaviour>,
+    event: SwarmEvent<E, H>,
+) {
+    match event {
+        ConnectionEstablished {
+            peer_id, endpoint, ..
+        } => {
+            if !swarm.allow_list.is_allowed(&peer_id) {
+                warn!("peer {:?} is not in the allow list, disconnected", peer_id);
+                disconnect(swarm, peer_id);
+                return;
+            }
+            let direction: Direction = match endpoint {
+                ConnectedPoint::Dialer { .. } => Direction::Outbound,
+                ConnectedPoint::Listener { .. } => Direction::Inbound,
+            };
+            let accepted: bool = swarm
+                .context
+                .peer_manager
+                .lock()
+                .unwrap()
+                .connected(&peer_id, direction);
+            if accepted {
+                swarm.send_handshake(&peer_id);
+            } else {
+                disconnect(swarm, peer_id);
+            }
+        }
+        ConnectionClosed {
+            peer_id,
+            num_established,
+            ..
+        } => {
+            if num_established == 0 {
+                swarm
+                    .context
+                    .peer_manager
+                    .lock()
+                    .unwrap()
+                    .disconnected(&peer_id);
+            }
+        }
+        event => debug!("{:?}", event),
+    }
+}
+
+/// dial discovered peers within the outbound limit, apply the bans of the
+/// PeerManager & the offences reported by block & transaction processing
+fn manage_peers(swarm: &mut Swarm<P2PBehaviour>) {
+    let context: SwarmContext = swarm.context.clone();
+    let mut peer_manager = context.peer_manager.lock().unwrap();
+    for (peer_id, offence) in context.peer_reports.lock().unwrap().take_reports() {
+        if peer_manager.report(&peer_id, offence) {
+            warn!("peer {:?} banned for {:?}", peer_id, offence);
+        }
+    }
+    peer_manager.expire_bans();
+    for peer_id in peer_manager.take_pending_bans() {
+        Swarm::ban_peer_id(swarm, peer_id);
+    }
+    for peer_id in peer_manager.take_pending_unbans() {
+        Swarm::unban_peer_id(swarm, peer_id);
+    }
+    for peer_id in swarm.pending_disconnects.split_off(0) {
+        // a banned peer stays banned
+        if !peer_manager.is_banned(&peer_id) {
+            disconnect(swarm, peer_id);
+        }
+    }
+    for peer_id in swarm.pending_dials.split_off(0) {
+        if Swarm::is_connected(swarm, &peer_id) || peer_manager.is_banned(&peer_id) {
+            continue;
+        }
+        if !peer_manager.can_dial() {
+            break;
+        }
+        if let Err(error) = Swarm::dial(swarm, &peer_id) {
+            warn!("peer {:?} couldn't be dialed: {:?}", peer_id, error);
+        }
+    }
+}
+
+/// SimpleSwarm is the p2p network of a node, described by its context
 pub struct SimpleSwarm {
     // behaviour: Option<P2PBehaviour<TSubstream>>,
     pub topic_list: Vec<String>,
-    pub tx: Sender<Option<MessageTypes>>,
-    pub rx: Receiver<Option<MessageTypes>>,
+    pub tx: GossipSender,
+    pub rx: GossipReceiver,
+    pub context: SwarmContext,
 }
 
 impl SimpleSwarm {
-    pub fn new() -> Self {
-        let (tx1, rx1) = channel::<Option<MessageTypes>>(4194304);
+    pub fn new(context: SwarmContext) -> Self {
+        let (tx1, rx1) = gossip_channel(&context.queue_metrics);
         SimpleSwarm {
             topic_list: Vec::new(),
             tx: tx1,
             rx: rx1,
+            context,
         }
     }
-    pub fn process(
-        &mut self,
-        peer_id: PeerId,
-        config: &Configuration,
-    ) -> Result<(), Box<dyn Error>> {
+    /// run the swarm until shutdown is triggered or the gossip queues close
+    pub async fn run(&mut self, peer_id: PeerId, shutdown: Shutdown) -> Result<(), Box<dyn Error>> {
+        let config: Arc<Configuration> = self.context.config.clone();
         // let transport = libp2p::build_tcp_ws_secio_mplex_yamux(libp2p::identity::Keypair::Ed25519(
         // config.node.keypair.clone(),
         // ))
         // .unwrap();
-        let transport = libp2p::build_development_transport(libp2p::identity::Keypair::Ed25519(
-            config.node.keypair.clone(),
-        ))
-        .unwrap();
-        let mut behaviour = P2PBehaviour::new(peer_id.clone());
+        let keypair = libp2p::identity::Keypair::Ed25519(config.node.keypair.clone());
+        let transport = build_transport(&keypair)?;
+        let allow_list = AllowList::new(&config.node.allowed_peers)?;
+        if allow_list.is_permissioned() {
+            info!("permissioned network, only allowed peers may connect");
+        }
+        let mut behaviour = P2PBehaviour::new(keypair, allow_list, self.context.clone());
         for topic in &self.topic_list {
             behaviour.subscribe(&topic);
         }
@@ -50,8 +174,34 @@ impl SimpleSwarm {
         )
         .unwrap();
 
+        // bootstrap peers with a known peer id seed kademlia, the others are
+        // dialed by address & learnt once the connection is up
+        for bootstrap_peer in config.node.bootstrap_peers.iter() {
+            match parse_bootstrap_peer(bootstrap_peer) {
+                Result::Ok((Some(bootstrap_id), _))
+                    if !swarm.allow_list.is_allowed(&bootstrap_id) =>
+                {
+                    warn!("bootstrap peer {} is not in the allow list", bootstrap_id)
+                }
+                Result::Ok((Some(bootstrap_id), multi_addr)) => {
+                    info!("bootstrap peer {} at {}", bootstrap_id, multi_addr);
+                    swarm.kademlia.add_address(&bootstrap_id, multi_addr);
+                    swarm.pending_dials.push(bootstrap_id);
+                }
+                Result::Ok((None, multi_addr)) => {
+                    info!("bootstrap address {}", multi_addr);
+                    if let Err(error) = Swarm::dial_addr(&mut swarm, multi_addr) {
+                        warn!("bootstrap address couldn't be dialed: {:?}", error);
+                    }
+                }
+                Result::Err(error) => warn!("{}", error),
+            }
+        }
+        swarm.bootstrap();
+
+        let peer_requests = self.context.peer_requests.serve();
         let mut listening = false;
-        block_on(future::poll_fn(move |cx: &mut Context| {
+        future::poll_fn(move |cx: &mut Context| {
             loop {
                 match self.rx.poll_next_unpin(cx) {
                     Poll::Ready(Some(msg)) => {
@@ -63,7 +213,7 @@ impl SimpleSwarm {
                                     if let Ok(value) = serialize(&data) {
                                         let topics: Vec<Topic> =
                                             Vec::<Topic>::from(MessageTypes::NodeMsg(data)); //TODO Find way to get rid of clone
-                                        swarm.floodsub.publish_many(topics, value);
+                                        publish(&mut swarm, topics, MessageKind::Node, value);
                                     };
                                 }
                                 MessageTypes::ConsensusMsg(data) => {
@@ -71,7 +221,7 @@ impl SimpleSwarm {
                                     if let Ok(value) = serialize(&data) {
                                         let topics: Vec<Topic> =
                                             Vec::<Topic>::from(MessageTypes::ConsensusMsg(data));
-                                        swarm.floodsub.publish_many(topics, value);
+                                        publish(&mut swarm, topics, MessageKind::Consensus, value);
                                     };
                                 }
                             },
@@ -87,10 +237,31 @@ impl SimpleSwarm {
             }
 
             loop {
-                match swarm.poll_next_unpin(cx) {
-                    Poll::Ready(Some(event)) => info!("{:?}", event),
-                    Poll::Ready(None) => return Poll::Ready(Ok(())),
+                match peer_requests.lock().unwrap().poll_next_unpin(cx) {
+                    Poll::Ready(Some(outbound_request)) => {
+                        swarm.send_peer_request(outbound_request)
+                    }
+                    Poll::Ready(None) | Poll::Pending => break,
+                }
+            }
+            swarm.poll_answers(cx);
+            // the db is closed once the swarm stopped, so it waits for the
+            // requests of peers it is still answering
+            if shutdown.poll_triggered(cx).is_ready() && swarm.stop_answering() {
+                info!("swarm stopped");
+                return Poll::Ready(Ok(()));
+            }
+
+            loop {
+                let swarm_event = {
+                    let next_event = swarm.next_event();
+                    pin_mut!(next_event);
+                    next_event.poll(cx)
+                };
+                match swarm_event {
+                    Poll::Ready(event) => on_swarm_event(&mut swarm, event),
                     Poll::Pending => {
+                        manage_peers(&mut swarm);
                         if !listening {
                             if let Some(a) = Swarm::listeners(&swarm).next() {
                                 info!("Listening on {:?}", a);
@@ -102,6 +273,7 @@ impl SimpleSwarm {
                 }
             }
             Poll::Pending
-        }))
+        })
+        .await
     }
 }
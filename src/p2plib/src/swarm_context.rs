@@ -0,0 +1,31 @@
+use super::peer_manager::PeerManager;
+use message_handler::handshake::{GenesisHashProvider, Handshake};
+use message_handler::messages::MessageDispatcher;
+use message_handler::peer_request::{PeerRequestHandler, PeerRequests};
+use std::sync::{Arc, Mutex};
+use utils::configreader::Configuration;
+use utils::global_peer_data::GlobalData;
+use utils::peer_reports::PeerReports;
+use utils::queue_metrics::QueueMetrics;
+
+/// SwarmContext is what the swarm shares with the rest of its node: the
+/// config, the peers & queues and the handlers reading the db of the node
+#[derive(Clone)]
+pub struct SwarmContext {
+    pub config: Arc<Configuration>,
+    pub peer_manager: Arc<Mutex<PeerManager>>,
+    pub dispatcher: MessageDispatcher,
+    pub peer_requests: Arc<PeerRequests>,
+    pub peer_request_handler: PeerRequestHandler,
+    pub genesis_hash_provider: GenesisHashProvider,
+    pub peers: Arc<Mutex<GlobalData>>,
+    pub peer_reports: Arc<Mutex<PeerReports>>,
+    pub queue_metrics: Arc<Mutex<QueueMetrics>>,
+}
+
+impl SwarmContext {
+    /// handshake of the node
+    pub fn handshake(&self) -> Handshake {
+        Handshake::local(&self.config.node.chain_id, (self.genesis_hash_provider)())
+    }
+}
@@ -0,0 +1,38 @@
+use libp2p::{
+    core::{
+        muxing::StreamMuxerBox,
+        transport::{boxed::Boxed, Transport},
+        upgrade,
+    },
+    dns::DnsConfig,
+    identity::Keypair,
+    noise::{self, NoiseConfig, X25519},
+    tcp::TcpConfig,
+    yamux, PeerId,
+};
+use std::io;
+use std::time::Duration;
+
+/// time allowed to negotiate noise & yamux on a new connection
+const UPGRADE_TIMEOUT: Duration = Duration::from_secs(20);
+
+pub type P2PTransport = Boxed<(PeerId, StreamMuxerBox), io::Error>;
+
+/// tcp (with dns resolution) transport, connections are authenticated and
+/// encrypted with the noise XX handshake signed by the node keypair, then
+/// multiplexed with yamux
+pub fn build_transport(keypair: &Keypair) -> Result<P2PTransport, String> {
+    let noise_keys = noise::Keypair::<X25519>::new()
+        .into_authentic(keypair)
+        .map_err(|error| format!("noise keys couldn't be created: {:?}", error))?;
+    let tcp = DnsConfig::new(TcpConfig::new().nodelay(true))
+        .map_err(|error| format!("dns transport couldn't be created: {:?}", error))?;
+    Ok(tcp
+        .upgrade(upgrade::Version::V1)
+        .authenticate(NoiseConfig::xx(noise_keys).into_authenticated())
+        .multiplex(yamux::Config::default())
+        .map(|(peer_id, muxer), _| (peer_id, StreamMuxerBox::new(muxer)))
+        .timeout(UPGRADE_TIMEOUT)
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
+        .boxed())
+}
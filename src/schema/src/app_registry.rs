@@ -0,0 +1,364 @@
+extern crate utils;
+use exonum_merkledb::ObjectHash;
+use sdk::block_context::BlockContext;
+use sdk::errors::AppError;
+use sdk::signed_transaction::SignedTransaction;
+use sdk::storage::StateValue;
+use sdk::traits::{AppHandler, ReadOnlyStateContext, StateContext};
+use std::collections::BTreeMap;
+use utils::serializer::{deserialize, serialize, Deserialize, Serialize};
+
+/// name of the built-in app keeping the registry of enabled apps
+pub const REGISTRY_APP_NAME: &str = "app_registry";
+
+/// key of AppRegistryState in the app_registry namespace
+const REGISTRY_KEY: &str = "registry";
+
+/// key of the admins (hex public keys) allowed to schedule a RegistryEntry
+const ADMINS_KEY: &str = "admins";
+
+/// RegistryEntry enables version of app_name from block activation_height
+/// onwards, version None disables the app from that height. It is also the
+/// txn payload of a SignedTransaction sent to the app_registry app.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    pub app_name: String,
+    pub version: Option<String>,
+    pub activation_height: u64,
+}
+
+/// AppRegistryState lists every RegistryEntry ordered by activation_height
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct AppRegistryState {
+    pub entries: Vec<RegistryEntry>,
+}
+
+impl AppRegistryState {
+    /// registry of a new chain, apps (name => version) are enabled from genesis
+    pub fn genesis(apps: &BTreeMap<String, String>) -> AppRegistryState {
+        AppRegistryState {
+            entries: apps
+                .iter()
+                .map(|(app_name, version)| RegistryEntry {
+                    app_name: app_name.clone(),
+                    version: Some(version.clone()),
+                    activation_height: 0,
+                })
+                .collect(),
+        }
+    }
+
+    /// add entry, it replaces an entry of the same app & activation_height
+    pub fn schedule(&mut self, entry: RegistryEntry) {
+        self.entries.retain(|scheduled| {
+            scheduled.app_name != entry.app_name
+                || scheduled.activation_height != entry.activation_height
+        });
+        let index: usize = self
+            .entries
+            .iter()
+            .position(|scheduled| scheduled.activation_height > entry.activation_height)
+            .unwrap_or(self.entries.len());
+        self.entries.insert(index, entry);
+    }
+
+    /// app name => version of the apps enabled at block height
+    pub fn active_apps(&self, height: u64) -> BTreeMap<String, String> {
+        let mut apps: BTreeMap<String, Option<String>> = BTreeMap::new();
+        for entry in self.entries.iter() {
+            if entry.activation_height <= height {
+                apps.insert(entry.app_name.clone(), entry.version.clone());
+            }
+        }
+        apps.into_iter()
+            .filter_map(|(app_name, version)| version.map(|version| (app_name, version)))
+            .collect()
+    }
+
+    /// compare the apps loaded by the node (name => version) with the apps
+    /// enabled at block height. A registry with no entry active yet doesn't
+    /// constrain the node, that's the case of chains adopting the registry.
+    pub fn check(&self, height: u64, local_apps: &BTreeMap<String, String>) -> Result<(), String> {
+        if !self
+            .entries
+            .iter()
+            .any(|entry| entry.activation_height <= height)
+        {
+            return Ok(());
+        }
+        let active_apps: BTreeMap<String, String> = self.active_apps(height);
+        let mut mismatches: Vec<String> = Vec::new();
+        for (app_name, version) in active_apps.iter() {
+            match local_apps.get(app_name) {
+                Some(local_version) if local_version == version => {}
+                Some(local_version) => mismatches.push(format!(
+                    "{} {} is loaded but {} is required",
+                    app_name, local_version, version
+                )),
+                None => mismatches.push(format!("{} {} is not loaded", app_name, version)),
+            }
+        }
+        for (app_name, local_version) in local_apps.iter() {
+            if !active_apps.contains_key(app_name) {
+                mismatches.push(format!(
+                    "{} {} is loaded but not enabled",
+                    app_name, local_version
+                ));
+            }
+        }
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "loaded apps don't match the app registry at height {}: {}",
+                height,
+                mismatches.join(", ")
+            ))
+        }
+    }
+}
+
+fn registry_value() -> StateValue<AppRegistryState> {
+    StateValue::new(REGISTRY_KEY)
+}
+
+/// registry kept in the app_registry namespace, None for chains created
+/// before the registry existed
+pub fn get_registry<C: ReadOnlyStateContext + ?Sized>(
+    registry_context: &C,
+) -> Option<AppRegistryState> {
+    registry_value().get(registry_context)
+}
+
+pub fn put_registry(registry_context: &mut dyn StateContext, registry: &AppRegistryState) -> bool {
+    registry_value().set(registry_context, registry)
+}
+
+fn admins_value() -> StateValue<Vec<String>> {
+    StateValue::new(ADMINS_KEY)
+}
+
+/// registry admins kept in the app_registry namespace
+pub fn get_admins<C: ReadOnlyStateContext + ?Sized>(registry_context: &C) -> Vec<String> {
+    admins_value().get(registry_context).unwrap_or_default()
+}
+
+/// AppRegistry is the built-in app handling RegistryEntry transactions.
+/// Only admins kept in its state can schedule an entry. They are written at
+/// genesis from registry_admins of config.toml, the config isn't read later.
+pub struct AppRegistry {
+    name: String,
+    genesis_admins: Vec<String>,
+}
+
+impl AppRegistry {
+    /// genesis_admins are the admins of a new chain
+    pub fn new(genesis_admins: &Vec<String>) -> AppRegistry {
+        AppRegistry {
+            name: String::from(REGISTRY_APP_NAME),
+            genesis_admins: genesis_admins.clone(),
+        }
+    }
+
+    fn put_admins(&self, state_context: &mut dyn StateContext) -> Result<(), AppError> {
+        if admins_value().set(state_context, &self.genesis_admins) {
+            Ok(())
+        } else {
+            Err(AppError::new(3, "registry admins couldn't be serialized"))
+        }
+    }
+
+    fn schedule(
+        &self,
+        txn: &SignedTransaction,
+        state_context: &mut dyn StateContext,
+        block_context: &BlockContext,
+    ) -> Result<(), AppError> {
+        let entry: RegistryEntry = deserialize(txn.txn.as_slice())
+            .map_err(|_| AppError::new(1, "registry transaction couldn't be decoded"))?;
+        if !get_admins(state_context).contains(&txn.signer) {
+            return Err(AppError::new(
+                2,
+                &format!("{} is not a registry admin", txn.signer),
+            ));
+        }
+        // apps of a block are checked before it runs, so an entry can only
+        // take effect from a later block
+        if entry.activation_height <= block_context.height {
+            return Err(AppError::new(
+                5,
+                &format!(
+                    "activation height {} must be after block {}",
+                    entry.activation_height, block_context.height
+                ),
+            ));
+        }
+        let mut registry: AppRegistryState = get_registry(state_context).unwrap_or_default();
+        info!(
+            "app {} version {:?} scheduled at height {}",
+            entry.app_name, entry.version, entry.activation_height
+        );
+        registry.schedule(entry);
+        if put_registry(state_context, &registry) {
+            Ok(())
+        } else {
+            Err(AppError::new(3, "registry couldn't be serialized"))
+        }
+    }
+}
+
+impl AppHandler for AppRegistry {
+    fn execute(
+        &self,
+        txn: &SignedTransaction,
+        state_context: &mut dyn StateContext,
+        block_context: &BlockContext,
+    ) -> Result<(), AppError> {
+        let result = self.schedule(txn, state_context, block_context);
+        state_context.put_txn(&txn.object_hash(), txn.clone());
+        result
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// the signer is checked against the admins on execution, they are state
+    fn validate(&self, txn: &SignedTransaction) -> Result<(), AppError> {
+        match deserialize::<RegistryEntry>(txn.txn.as_slice()) {
+            Result::Ok(_) => Ok(()),
+            Result::Err(_) => Err(AppError::new(1, "registry transaction couldn't be decoded")),
+        }
+    }
+
+    fn init_genesis(&self, state_context: &mut dyn StateContext) -> Result<(), AppError> {
+        self.put_admins(state_context)
+    }
+
+    /// chains created before the admins were kept in state take them from
+    /// the config once, at the first block after the upgrade
+    fn migrate_legacy_state(
+        &self,
+        _legacy: &mut dyn StateContext,
+        state_context: &mut dyn StateContext,
+    ) -> Result<(), AppError> {
+        if admins_value().get(state_context).is_some() {
+            return Ok(());
+        }
+        self.put_admins(state_context)
+    }
+
+    /// "registry" returns the AppRegistryState, "active_apps" the
+    /// BTreeMap<String, String> of apps enabled at the u64 height in args,
+    /// "admins" the Vec<String> of registry admins
+    fn query(
+        &self,
+        method: &String,
+        args: &Vec<u8>,
+        state_context: &dyn ReadOnlyStateContext,
+    ) -> Result<Vec<u8>, AppError> {
+        let registry: AppRegistryState = get_registry(state_context).unwrap_or_default();
+        let result = if method == "registry" {
+            serialize(&registry)
+        } else if method == "admins" {
+            serialize(&get_admins(state_context))
+        } else if method == "active_apps" {
+            let height: u64 = deserialize(args)
+                .map_err(|_| AppError::new(3, "query args couldn't deserialize"))?;
+            serialize(&registry.active_apps(height))
+        } else {
+            return Err(AppError::new(4, &format!("unknown query {}", method)));
+        };
+        result.map_err(|_| AppError::new(3, "query result couldn't serialize"))
+    }
+}
+
+#[cfg(test)]
+mod tests_app_registry {
+    use super::*;
+    use crate::checkpoint::tests_checkpoint::MockStorage;
+    use exonum_crypto::Hash;
+    use std::collections::HashMap;
+    use utils::keypair::{CryptoKeypair, Keypair};
+
+    fn apps(list: &[(&str, &str)]) -> BTreeMap<String, String> {
+        list.iter()
+            .map(|(app_name, version)| (app_name.to_string(), version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_registry_check() {
+        let mut registry = AppRegistryState::default();
+        // nothing active yet, any node may proceed
+        assert_eq!(registry.check(5, &apps(&[("wallet", "0.1.0")])), Ok(()));
+
+        registry = AppRegistryState::genesis(&apps(&[("wallet", "0.1.0"), ("doc", "0.1.0")]));
+        registry.schedule(RegistryEntry {
+            app_name: String::from("wallet"),
+            version: Some(String::from("0.2.0")),
+            activation_height: 10,
+        });
+        registry.schedule(RegistryEntry {
+            app_name: String::from("doc"),
+            version: None,
+            activation_height: 20,
+        });
+        let old_apps = apps(&[("wallet", "0.1.0"), ("doc", "0.1.0")]);
+        let new_apps = apps(&[("wallet", "0.2.0"), ("doc", "0.1.0")]);
+        assert_eq!(registry.check(9, &old_apps), Ok(()));
+        assert_eq!(registry.check(9, &new_apps).is_err(), true);
+        assert_eq!(registry.check(10, &old_apps).is_err(), true);
+        assert_eq!(registry.check(10, &new_apps), Ok(()));
+        assert_eq!(registry.check(20, &new_apps).is_err(), true);
+        assert_eq!(registry.check(20, &apps(&[("wallet", "0.2.0")])), Ok(()));
+
+        // rescheduling the same height replaces the entry
+        registry.schedule(RegistryEntry {
+            app_name: String::from("wallet"),
+            version: Some(String::from("0.3.0")),
+            activation_height: 10,
+        });
+        assert_eq!(registry.entries.len(), 4);
+        assert_eq!(
+            registry.active_apps(15),
+            apps(&[("wallet", "0.3.0"), ("doc", "0.1.0")])
+        );
+    }
+    #[test]
+    fn test_admins_from_state() {
+        let admin = Keypair::generate();
+        let stranger = Keypair::generate();
+        let admin_key = hex::encode(admin.public().encode());
+        let stranger_key = hex::encode(stranger.public().encode());
+        let mut storage = MockStorage::default();
+        AppRegistry::new(&vec![admin_key.clone()])
+            .init_genesis(&mut storage)
+            .unwrap();
+        assert_eq!(get_admins(&storage), vec![admin_key.clone()]);
+
+        // a node configured with other admins still follows the state
+        let registry = AppRegistry::new(&vec![stranger_key]);
+        let entry = serialize(&RegistryEntry {
+            app_name: String::from("wallet"),
+            version: Some(String::from("0.2.0")),
+            activation_height: 10,
+        })
+        .unwrap();
+        let block_context = BlockContext::new(1, 0, &String::from("author"), &Hash::zero());
+        let txn =
+            SignedTransaction::create(&stranger, REGISTRY_APP_NAME, HashMap::new(), entry.clone());
+        assert_eq!(registry.validate(&txn), Ok(()));
+        let result = registry.execute(&txn, &mut storage, &block_context);
+        assert_eq!(result.map_err(|error| error.code), Err(2));
+        let txn = SignedTransaction::create(&admin, REGISTRY_APP_NAME, HashMap::new(), entry);
+        assert_eq!(registry.execute(&txn, &mut storage, &block_context), Ok(()));
+        assert_eq!(get_registry(&storage).unwrap().entries.len(), 1);
+
+        // the config isn't read again once the admins are in state
+        registry
+            .migrate_legacy_state(&mut MockStorage::default(), &mut storage)
+            .unwrap();
+        assert_eq!(get_admins(&storage), vec![admin_key]);
+    }
+}
@@ -1,6 +1,7 @@
+use super::app_registry::REGISTRY_APP_NAME;
 use libloading::Library;
-use sdk::traits::AppHandler;
-use std::collections::HashMap;
+use sdk::traits::{AppHandler, AppStorage};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 
 pub struct AppData {
@@ -15,16 +16,53 @@ impl AppData {
             lib: Vec::new(),
         }
     }
-}
 
-lazy_static! {
-    pub static ref APPDATA: Arc<Mutex<AppData>> = Arc::new(Mutex::new(AppData::new()));
+    /// name => version of the loaded apps, as matched against the app
+    /// registry. The registry app is part of the node and isn't listed.
+    pub fn app_versions(&self) -> BTreeMap<String, String> {
+        self.appdata
+            .iter()
+            .filter(|(app_name, _)| app_name.as_str() != REGISTRY_APP_NAME)
+            .map(|(app_name, app)| (app_name.clone(), app.lock().unwrap().version()))
+            .collect()
+    }
+
+    /// names of the loaded apps charging the fee header of their txns
+    pub fn fee_apps(&self) -> HashSet<String> {
+        self.appdata
+            .iter()
+            .filter(|(_, app)| app.lock().unwrap().charges_fee())
+            .map(|(app_name, _)| app_name.clone())
+            .collect()
+    }
+
+    /// run finalize_block of all apps, in app name order so every node
+    /// applies block level changes identically. Each app only sees its own
+    /// namespace of the state.
+    pub fn finalize_block(&self, author: &String, storage: &mut dyn AppStorage) {
+        let mut app_names: Vec<&String> = self.appdata.keys().collect();
+        app_names.sort();
+        for app_name in app_names {
+            if let Some(app) = self.appdata.get(app_name) {
+                let mut app_context = storage.app_context(app_name);
+                if let Err(error) = app
+                    .lock()
+                    .unwrap()
+                    .finalize_block(author, app_context.as_mut())
+                {
+                    warn!("app {:?} couldn't finalize block: {}", app_name, error);
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests_app_data {
 
     use super::*;
+    use sdk::block_context::BlockContext;
+    use sdk::errors::AppError;
     use sdk::signed_transaction::SignedTransaction;
     use sdk::traits::StateContext;
     const APPNAME: &str = "MockApp";
@@ -40,8 +78,13 @@ mod tests_app_data {
     }
 
     impl AppHandler for MockApp {
-        fn execute(&self, _txn: &SignedTransaction, _state_context: &mut dyn StateContext) -> bool {
-            true
+        fn execute(
+            &self,
+            _txn: &SignedTransaction,
+            _state_context: &mut dyn StateContext,
+            _block_context: &BlockContext,
+        ) -> Result<(), AppError> {
+            Ok(())
         }
 
         fn name(&self) -> String {
@@ -55,20 +98,15 @@ mod tests_app_data {
 
     #[test]
     pub fn test_app_data_insertion() {
+        let mut app_data: AppData = AppData::new();
         {
             let app_handle = Arc::new(Mutex::new(register_app()));
             let app_name = app_handle.lock().unwrap().name();
             println!("Loaded app {:?}", app_name);
-            let mut locked_app_data = APPDATA.lock().unwrap();
-            // locked_app_data.lib.push(Arc::new(applib));
-            locked_app_data
+            app_data
                 .appdata
                 .insert(app_name.clone(), app_handle.clone());
         }
-        assert_eq!(
-            APPDATA.lock().unwrap().appdata.len(),
-            1,
-            "Issue with appdata insert"
-        );
+        assert_eq!(app_data.appdata.len(), 1, "Issue with appdata insert");
     }
 }
@@ -0,0 +1,233 @@
+use super::signed_transaction::SignedTransaction;
+use super::state::State;
+use exonum_crypto::Hash;
+use sdk::traits::{ReadOnlyStateContext, StateContext};
+use std::collections::BTreeMap;
+
+/// Checkpoint buffers the writes of a single transaction on top of an app
+/// StateContext. commit applies them, dropping the Checkpoint discards them,
+/// so a failed or panicked txn leaves no partial state in the fork.
+pub struct Checkpoint<'a> {
+    state_context: &'a mut dyn StateContext,
+    // None marks a deleted key
+    states: BTreeMap<String, Option<State>>,
+    txns: Vec<(Hash, SignedTransaction)>,
+}
+
+impl<'a> Checkpoint<'a> {
+    pub fn new(state_context: &'a mut dyn StateContext) -> Checkpoint<'a> {
+        Checkpoint {
+            state_context,
+            states: BTreeMap::new(),
+            txns: Vec::new(),
+        }
+    }
+
+    /// apply the buffered writes, in key order
+    pub fn commit(self) {
+        for (key, state) in self.states.into_iter() {
+            match state {
+                Some(state) => self.state_context.put(&key, state),
+                None => self.state_context.delete(&key),
+            }
+        }
+        for (txn_hash, txn) in self.txns.into_iter() {
+            self.state_context.put_txn(&txn_hash, txn);
+        }
+    }
+}
+
+impl<'a> ReadOnlyStateContext for Checkpoint<'a> {
+    fn get(&self, key: &String) -> Option<State> {
+        match self.states.get(key) {
+            Some(state) => state.clone(),
+            None => self.state_context.get(key),
+        }
+    }
+
+    fn contains(&self, key: &String) -> bool {
+        match self.states.get(key) {
+            Some(state) => state.is_some(),
+            None => self.state_context.contains(key),
+        }
+    }
+
+    /// buffered writes can hide at most states.len() keys of the underlying
+    /// range, so that many extra keys are read before merging
+    fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+        let mut entries: BTreeMap<String, State> = self
+            .state_context
+            .range(start, end, limit.saturating_add(self.states.len()))
+            .into_iter()
+            .collect();
+        for (key, state) in self
+            .states
+            .range(start.clone()..)
+            .take_while(|(key, _)| end.is_empty() || *key < end)
+        {
+            match state {
+                Some(state) => entries.insert(key.clone(), state.clone()),
+                None => entries.remove(key),
+            };
+        }
+        entries.into_iter().take(limit).collect()
+    }
+
+    fn get_txn(&self, key: &Hash) -> Option<SignedTransaction> {
+        match self.txns.iter().find(|(txn_hash, _)| txn_hash == key) {
+            Some((_, txn)) => Some(txn.clone()),
+            None => self.state_context.get_txn(key),
+        }
+    }
+
+    fn contains_txn(&self, key: &Hash) -> bool {
+        self.txns.iter().any(|(txn_hash, _)| txn_hash == key)
+            || self.state_context.contains_txn(key)
+    }
+}
+
+impl<'a> StateContext for Checkpoint<'a> {
+    fn put(&mut self, key: &String, state: State) {
+        self.states.insert(key.clone(), Some(state));
+    }
+
+    fn delete(&mut self, key: &String) {
+        self.states.insert(key.clone(), None);
+    }
+
+    fn put_txn(&mut self, key: &Hash, txn: SignedTransaction) {
+        self.txns.push((*key, txn));
+    }
+}
+
+#[cfg(test)]
+pub mod tests_checkpoint {
+    use super::*;
+    use sdk::traits::AppStorage;
+    use std::collections::HashMap;
+
+    /// in memory storage, app contexts share its states
+    #[derive(Default)]
+    pub struct MockStorage {
+        pub states: BTreeMap<String, State>,
+        pub txns: HashMap<Hash, SignedTransaction>,
+    }
+
+    pub struct MockAppContext<'a> {
+        storage: &'a mut MockStorage,
+    }
+
+    impl ReadOnlyStateContext for MockStorage {
+        fn get(&self, key: &String) -> Option<State> {
+            self.states.get(key).cloned()
+        }
+        fn contains(&self, key: &String) -> bool {
+            self.states.contains_key(key)
+        }
+        fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+            self.states
+                .range(start.clone()..)
+                .take_while(|(key, _)| end.is_empty() || *key < end)
+                .take(limit)
+                .map(|(key, state)| (key.clone(), state.clone()))
+                .collect()
+        }
+        fn get_txn(&self, key: &Hash) -> Option<SignedTransaction> {
+            self.txns.get(key).cloned()
+        }
+        fn contains_txn(&self, key: &Hash) -> bool {
+            self.txns.contains_key(key)
+        }
+    }
+
+    impl StateContext for MockStorage {
+        fn put(&mut self, key: &String, state: State) {
+            self.states.insert(key.clone(), state);
+        }
+        fn delete(&mut self, key: &String) {
+            self.states.remove(key);
+        }
+        fn put_txn(&mut self, key: &Hash, txn: SignedTransaction) {
+            self.txns.insert(*key, txn);
+        }
+    }
+
+    impl<'a> ReadOnlyStateContext for MockAppContext<'a> {
+        fn get(&self, key: &String) -> Option<State> {
+            self.storage.get(key)
+        }
+        fn contains(&self, key: &String) -> bool {
+            self.storage.contains(key)
+        }
+        fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+            self.storage.range(start, end, limit)
+        }
+        fn get_txn(&self, key: &Hash) -> Option<SignedTransaction> {
+            self.storage.get_txn(key)
+        }
+        fn contains_txn(&self, key: &Hash) -> bool {
+            self.storage.contains_txn(key)
+        }
+    }
+
+    impl<'a> StateContext for MockAppContext<'a> {
+        fn put(&mut self, key: &String, state: State) {
+            self.storage.put(key, state);
+        }
+        fn delete(&mut self, key: &String) {
+            self.storage.delete(key);
+        }
+        fn put_txn(&mut self, key: &Hash, txn: SignedTransaction) {
+            self.storage.put_txn(key, txn);
+        }
+    }
+
+    impl AppStorage for MockStorage {
+        fn app_context(&mut self, _app_name: &String) -> Box<dyn StateContext + '_> {
+            Box::new(MockAppContext { storage: self })
+        }
+    }
+
+    pub fn state(data: u8) -> State {
+        let mut state = State::new();
+        state.set_data(&vec![data]);
+        state
+    }
+
+    fn keys(entries: Vec<(String, State)>) -> Vec<String> {
+        entries.into_iter().map(|(key, _)| key).collect()
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let mut storage = MockStorage::default();
+        for key in ["a", "b", "c", "d"].iter() {
+            storage.put(&key.to_string(), state(0));
+        }
+        let (a, b, e) = (String::from("a"), String::from("b"), String::from("e"));
+        {
+            let mut checkpoint = Checkpoint::new(&mut storage);
+            checkpoint.delete(&a);
+            checkpoint.put(&b, state(1));
+            checkpoint.put(&e, state(1));
+            assert_eq!(checkpoint.contains(&a), false);
+            assert_eq!(checkpoint.get(&b), Some(state(1)));
+            assert_eq!(
+                keys(checkpoint.range(&String::new(), &String::new(), 2)),
+                vec!["b", "c"]
+            );
+            assert_eq!(keys(checkpoint.range(&b, &e, 10)), vec!["b", "c", "d"]);
+            // dropped without commit
+        }
+        assert_eq!(storage.get(&a), Some(state(0)));
+        assert_eq!(storage.contains(&e), false);
+        {
+            let mut checkpoint = Checkpoint::new(&mut storage);
+            checkpoint.delete(&a);
+            checkpoint.put(&e, state(1));
+            checkpoint.commit();
+        }
+        assert_eq!(storage.contains(&a), false);
+        assert_eq!(storage.get(&e), Some(state(1)));
+    }
+}
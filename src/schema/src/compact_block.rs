@@ -0,0 +1,147 @@
+use super::block::{Block, SignedBlock};
+use exonum_crypto::{hash, Hash};
+use std::collections::HashMap;
+use utils::serializer::{Deserialize, Serialize};
+
+pub type ShortTxnId = u64;
+
+/// bytes of the salted txn hash kept in a ShortTxnId
+pub const SHORT_TXN_ID_BYTES: usize = 6;
+
+/// short id of txn_hash in the block salted with salt, salting per block
+/// keeps collisions from repeating across blocks
+pub fn short_txn_id(salt: &Hash, txn_hash: &Hash) -> ShortTxnId {
+    let mut data: Vec<u8> = salt.as_ref().to_vec();
+    data.extend_from_slice(txn_hash.as_ref());
+    let digest: Hash = hash(&data);
+    let mut short_id: ShortTxnId = 0;
+    for byte in digest.as_ref()[..SHORT_TXN_ID_BYTES].iter() {
+        short_id = (short_id << 8) | *byte as ShortTxnId;
+    }
+    short_id
+}
+
+/// CompactBlock announces a SignedBlock with short ids in place of its
+/// transaction hashes. Receivers rebuild the txn_pool from the transactions
+/// they already know and only fetch the missing ones, the block signature
+/// checks the result.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompactBlock {
+    /// the block with an empty txn_pool
+    pub header: Block,
+    pub short_ids: Vec<ShortTxnId>,
+    pub signature: Vec<u8>,
+    pub auth_headers: Vec<u8>,
+}
+
+impl CompactBlock {
+    pub fn new(signed_block: &SignedBlock) -> CompactBlock {
+        let mut header: Block = signed_block.block.clone();
+        let txn_pool: Vec<Hash> = std::mem::replace(&mut header.txn_pool, Vec::new());
+        let salt: Hash = hash(&signed_block.signature);
+        CompactBlock {
+            header,
+            short_ids: txn_pool
+                .iter()
+                .map(|txn_hash| short_txn_id(&salt, txn_hash))
+                .collect(),
+            signature: signed_block.signature.clone(),
+            auth_headers: signed_block.auth_headers.clone(),
+        }
+    }
+
+    /// salt of the short ids, unique per block since the signature is
+    pub fn salt(&self) -> Hash {
+        hash(&self.signature)
+    }
+
+    /// txn_pool of the block rebuilt from known_txns, None for the hashes
+    /// which are unknown or whose short id is ambiguous
+    pub fn match_txns<'a, I>(&self, known_txns: I) -> Vec<Option<Hash>>
+    where
+        I: Iterator<Item = &'a Hash>,
+    {
+        let salt: Hash = self.salt();
+        let mut candidates: HashMap<ShortTxnId, Option<Hash>> = HashMap::new();
+        for txn_hash in known_txns {
+            candidates
+                .entry(short_txn_id(&salt, txn_hash))
+                .and_modify(|candidate| {
+                    if *candidate != Some(*txn_hash) {
+                        *candidate = None
+                    }
+                })
+                .or_insert(Some(*txn_hash));
+        }
+        self.short_ids
+            .iter()
+            .map(|short_id| candidates.get(short_id).cloned().unwrap_or(None))
+            .collect()
+    }
+
+    /// true if txn_hash has the short id at index
+    pub fn matches(&self, index: usize, txn_hash: &Hash) -> bool {
+        match self.short_ids.get(index) {
+            Some(short_id) => *short_id == short_txn_id(&self.salt(), txn_hash),
+            None => false,
+        }
+    }
+
+    /// the SignedBlock with txn_pool, SignedBlock::validate tells whether
+    /// txn_pool was rebuilt right
+    pub fn to_signed_block(&self, txn_pool: Vec<Hash>) -> SignedBlock {
+        let mut block: Block = self.header.clone();
+        block.txn_pool = txn_pool;
+        SignedBlock::create_block(block, self.signature.clone(), self.auth_headers.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests_compact_block {
+    use super::*;
+    use crate::block::BlockTraits;
+    use utils::keypair::{CryptoKeypair, Keypair, KeypairType};
+
+    #[test]
+    fn test_compact_block() {
+        let kp: KeypairType = Keypair::generate();
+        let pk: String = hex::encode(kp.public().encode());
+        let txn_pool: Vec<Hash> = (0..4u8).map(|index| hash(&[index])).collect();
+        let block: Block = Block::new_block(
+            1,
+            pk,
+            Hash::zero(),
+            txn_pool.clone(),
+            [Hash::zero(), Hash::zero(), Hash::zero()],
+            Vec::new(),
+        );
+        let signed_block: SignedBlock =
+            SignedBlock::create_block(block.clone(), block.sign(&kp), Vec::new());
+        let compact_block: CompactBlock = CompactBlock::new(&signed_block);
+        assert_eq!(compact_block.short_ids.len(), txn_pool.len());
+        assert_eq!(compact_block.header.txn_pool, Vec::<Hash>::new());
+
+        // the last txn is unknown, an unrelated one is ignored
+        let known_txns: Vec<Hash> = vec![txn_pool[0], txn_pool[2], txn_pool[1], hash(&[9])];
+        let matched: Vec<Option<Hash>> = compact_block.match_txns(known_txns.iter());
+        assert_eq!(
+            matched,
+            vec![
+                Some(txn_pool[0]),
+                Some(txn_pool[1]),
+                Some(txn_pool[2]),
+                None
+            ]
+        );
+        assert_eq!(compact_block.matches(3, &txn_pool[3]), true);
+        assert_eq!(compact_block.matches(3, &txn_pool[0]), false);
+        assert_eq!(compact_block.matches(4, &txn_pool[3]), false);
+
+        let rebuilt: SignedBlock = compact_block.to_signed_block(txn_pool.clone());
+        assert_eq!(rebuilt, signed_block);
+        assert_eq!(rebuilt.validate(), true);
+        let mut wrong_pool: Vec<Hash> = txn_pool;
+        wrong_pool.swap(0, 1);
+        assert_eq!(compact_block.to_signed_block(wrong_pool).validate(), false);
+    }
+}
@@ -1,16 +1,16 @@
 #[macro_use]
 extern crate exonum_derive;
 
-#[macro_use]
-extern crate lazy_static;
-
 #[macro_use]
 extern crate log;
 
 pub mod types;
 
+pub mod app_registry;
 pub mod appdata;
 pub mod block;
+pub mod checkpoint;
+pub mod compact_block;
 pub mod signed_transaction;
 pub mod state;
 pub mod transaction_pool;
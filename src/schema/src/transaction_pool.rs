@@ -1,5 +1,6 @@
 extern crate utils;
-use super::appdata::APPDATA;
+use super::appdata::AppData;
+use super::checkpoint::Checkpoint;
 use super::signed_transaction::SignedTransaction;
 use super::state::State;
 use exonum_crypto::Hash;
@@ -7,12 +8,19 @@ use exonum_merkledb::{
     access::{Access, RawAccessMut},
     ObjectHash,
 };
-use sdk::traits::{PoolTrait, StateContext};
-use std::collections::BTreeMap;
+use sdk::abi::ABI_ERROR_CODE;
+use sdk::block_context::BlockContext;
+use sdk::errors::AppError;
+use sdk::gas::{out_of_gas_error, GasMeter};
+use sdk::traits::{AppHandler, AppStorage, PoolTrait};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet, VecDeque};
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::{Arc, Mutex};
 use std::time::SystemTime;
-use utils::configreader;
 use utils::configreader::BlockConfig;
+use utils::peer_reports::{Offence, PeerReports};
+use utils::serializer::serialize;
 
 pub type TxnPoolKeyType = u128;
 pub type TxnPoolValueType = SignedTransaction;
@@ -48,10 +56,21 @@ pub struct TransactionPool {
     order_pool: BTreeMap<TxnPoolKeyType, TxnPoolValueType>,
 }
 
+/// Pool is the transaction pool of a node, its clones share the pool
+#[derive(Debug, Clone)]
 pub struct Pool {
     pub pool: Arc<std::sync::Mutex<TransactionPool>>,
 }
 
+/// TxnExecutor executes the transactions of txn_pool with the apps & the
+/// block limits of a node, refused transactions are reported to peer_reports
+pub struct TxnExecutor<'a> {
+    pub txn_pool: &'a TransactionPool,
+    pub app_data: &'a AppData,
+    pub block_config: &'a BlockConfig,
+    pub peer_reports: &'a Mutex<PeerReports>,
+}
+
 impl TransactionPoolTraits for TransactionPool {
     /// this function will create a new instance of transcation pool object
     fn new() -> TransactionPool {
@@ -115,6 +134,56 @@ impl TransactionPoolTraits for TransactionPool {
     }
 }
 
+impl TransactionPool {
+    /// hashes of the pooled transactions
+    pub fn txn_hashes(&self) -> impl Iterator<Item = &Hash> {
+        self.hash_pool.keys()
+    }
+
+    /// order_pool arranged by offered fee, highest first. Transactions of the same
+    /// signer keep their arrival order, so account nonces are still executed in sequence.
+    /// Only the fee of txns of fee_apps, the apps charging it, is counted.
+    pub fn fee_priority_order(
+        &self,
+        fee_apps: &HashSet<String>,
+    ) -> Vec<(&TxnPoolKeyType, &TxnPoolValueType)> {
+        let offered_fee = |txn: &TxnPoolValueType| {
+            if fee_apps.contains(&txn.app_name) {
+                txn.fee()
+            } else {
+                0
+            }
+        };
+        let mut signer_queues: HashMap<&String, VecDeque<(&TxnPoolKeyType, &TxnPoolValueType)>> =
+            HashMap::new();
+        for (timestamp, txn) in self.order_pool.iter() {
+            signer_queues
+                .entry(&txn.signer)
+                .or_insert_with(VecDeque::new)
+                .push_back((timestamp, txn));
+        }
+        let mut heads: BinaryHeap<(u64, Reverse<TxnPoolKeyType>, &String)> = BinaryHeap::new();
+        for queue in signer_queues.values() {
+            if let Some((timestamp, txn)) = queue.front() {
+                heads.push((offered_fee(txn), Reverse(**timestamp), &txn.signer));
+            }
+        }
+        let mut ordered_txns: Vec<(&TxnPoolKeyType, &TxnPoolValueType)> =
+            Vec::with_capacity(self.order_pool.len());
+        while let Some((_, _, signer)) = heads.pop() {
+            if let Some(queue) = signer_queues.get_mut(signer) {
+                if let Some(entry) = queue.pop_front() {
+                    ordered_txns.push(entry);
+                }
+                if let Some((timestamp, txn)) = queue.front() {
+                    heads.push((offered_fee(txn), Reverse(**timestamp), &txn.signer));
+                }
+            }
+        }
+        ordered_txns
+    }
+}
+
 impl TxnPool for Pool {
     /// this function will create a new instance of transcation pool object
     fn new() -> Pool {
@@ -166,37 +235,65 @@ impl TxnPool for Pool {
     }
 }
 
-impl<T: Access> PoolTrait<T, State, SignedTransaction> for TransactionPool
+impl<'a, T: Access> PoolTrait<T, State, SignedTransaction> for TxnExecutor<'a>
 where
     T::Base: RawAccessMut,
 {
-    fn execute_transactions(&self, state_context: &mut dyn StateContext) -> (Vec<Hash>, Vec<Hash>) {
+    fn execute_transactions(
+        &self,
+        storage: &mut dyn AppStorage,
+        block_context: &BlockContext,
+    ) -> (Vec<Hash>, Vec<Hash>) {
         let mut temp_vec: Vec<Hash> = Vec::with_capacity(15);
         // compute until order_pool exhusted or transaction limit crossed
         // let txn_pool = self.pool.lock().unwrap();
 
-        let block_config: &BlockConfig = &configreader::GLOBAL_CONFIG.block_config;
+        let block_config: &BlockConfig = self.block_config;
         // delayed transaction & transaction from unknown app will be added
         // in this list. This list will be used to sync-up txn_pool.
         let mut remove_txn_list: Vec<Hash> = Vec::new();
+        let mut block_gas_used: u64 = 0;
         let current_timestamp: TxnPoolKeyType = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
             .as_micros();
-        for (timestamp, sign_txn) in self.order_pool.iter() {
+        let fee_apps: HashSet<String> = self.app_data.fee_apps();
+        for (timestamp, sign_txn) in self.txn_pool.fee_priority_order(&fee_apps) {
             if temp_vec.len() < block_config.block_transaction_limit as usize {
                 let txn_hash: Hash = sign_txn.object_hash();
                 // check is transaction already added in the previously
-                if !state_context.contains_txn(&txn_hash) {
+                if !storage.contains_txn(&txn_hash) {
                     let timestamp: TxnPoolKeyType = timestamp.clone();
                     if current_timestamp
                         > (timestamp + block_config.transaction_execution_delay_limit)
                     {
                         remove_txn_list.push(txn_hash);
+                    } else if !sign_txn.verify() {
+                        remove_txn_list.push(txn_hash);
+                        warn!("transaction with hash {:?} has invalid signature", txn_hash);
+                        self.report_txn(&txn_hash);
+                    } else if let Err(error) = sign_txn.check_size(block_config) {
+                        remove_txn_list.push(txn_hash);
+                        self.report_txn(&txn_hash);
+                        warn!(
+                            "transaction with hash {:?} is too large: {}",
+                            txn_hash, error
+                        );
+                    } else if block_gas_used.saturating_add(txn_gas_limit(sign_txn, block_config))
+                        > block_config.block_gas_limit
+                    {
+                        // stays in the pool for a later block
+                        debug!("transaction with hash {:?} exceeds block gas", txn_hash);
                     } else if current_timestamp > timestamp {
-                        match APPDATA.lock().unwrap().appdata.get(&sign_txn.app_name) {
+                        match self.app_data.appdata.get(&sign_txn.app_name) {
                             Some(app) => {
-                                app.lock().unwrap().execute(sign_txn, state_context);
+                                let gas_limit: u64 = txn_gas_limit(sign_txn, block_config);
+                                let (gas_used, result) =
+                                    execute_txn(app, sign_txn, storage, block_context, gas_limit);
+                                if let Err(error) = result {
+                                    warn!("transaction with hash {:?} failed: {}", txn_hash, error);
+                                }
+                                block_gas_used += gas_used;
                                 temp_vec.push(txn_hash);
                                 debug!("transaction with hash {:?} executed", txn_hash);
                             }
@@ -217,25 +314,51 @@ where
 
     fn update_transactions(
         &self,
-        state_context: &mut dyn StateContext,
+        storage: &mut dyn AppStorage,
         hash_vec: &Vec<Hash>,
+        block_context: &BlockContext,
     ) -> bool {
         // compute until order_pool exhusted or transaction limit crossed
         // let txn_pool = self.pool.lock().unwrap();
+        let block_config: &BlockConfig = self.block_config;
+        let mut block_gas_used: u64 = 0;
         for each in hash_vec.iter() {
-            let signed_txn = self.get(each);
-            if state_context.contains_txn(each) {
+            let signed_txn = self.txn_pool.get(each);
+            if storage.contains_txn(each) {
                 warn!("try to execute duplicate transaction");
                 return false;
             }
             if let Some(txn) = signed_txn {
-                match APPDATA.lock().unwrap().appdata.get(&txn.app_name) {
+                if !txn.verify() {
+                    warn!("transaction with hash {:?} has invalid signature", each);
+                    return false;
+                }
+                if let Err(error) = txn.check_size(block_config) {
+                    warn!("transaction with hash {:?} is too large: {}", each, error);
+                    return false;
+                }
+                match self.app_data.appdata.get(&txn.app_name) {
                     Some(app) => {
                         debug!("transaction with hash {:?} updated", each);
-                        app.lock().unwrap().execute(&txn, state_context);
+                        let gas_limit: u64 = txn_gas_limit(&txn, block_config);
+                        let (gas_used, result) =
+                            execute_txn(app, &txn, storage, block_context, gas_limit);
+                        if let Err(error) = result {
+                            warn!("transaction with hash {:?} failed: {}", each, error);
+                        }
+                        block_gas_used += gas_used;
+                        if block_gas_used > block_config.block_gas_limit {
+                            warn!("block transactions exceed block gas limit");
+                            return false;
+                        }
                     }
                     None => {
-                        warn!("unknown app transaction bypassed in update_transaction process");
+                        // as in execute_transactions, a txn of an app which isn't loaded can't run
+                        warn!(
+                            "transaction with hash {:?} of unknown app {:?}",
+                            each, txn.app_name
+                        );
+                        return false;
                     }
                 }
             } else {
@@ -247,17 +370,97 @@ where
     }
 }
 
-lazy_static! {
-    pub static ref POOL: Pool = Pool::new();
+impl<'a> TxnExecutor<'a> {
+    /// count a transaction the pool refused against the peer which sent it
+    fn report_txn(&self, txn_hash: &Hash) {
+        self.peer_reports
+            .lock()
+            .unwrap()
+            .report_origin(&txn_hash.to_hex(), Offence::InvalidTransaction);
+    }
+}
+
+/// key of the state keeping the AppError of a failed transaction
+pub fn txn_status_key(txn_hash: &Hash) -> String {
+    format!("txn_status/{}", txn_hash.to_hex())
+}
+
+/// gas limit of txn, the "gas_limit" header can lower txn_gas_limit of config
+pub fn txn_gas_limit(txn: &SignedTransaction, block_config: &BlockConfig) -> u64 {
+    match txn.gas_limit() {
+        Some(gas_limit) => std::cmp::min(gas_limit, block_config.txn_gas_limit),
+        None => block_config.txn_gas_limit,
+    }
+}
+
+/// execute txn in a Checkpoint over its app state, metered by a GasMeter of
+/// gas_limit. The writes are applied only if the app returns Ok within the
+/// gas limit. If it fails, runs out of gas or panics they are discarded, the
+/// txn is still stored & its AppError kept under txn_status_key, so the txn
+/// is part of the block as a failed one. Returns the gas used by the txn.
+fn execute_txn(
+    app: &Mutex<Box<dyn AppHandler + Send>>,
+    txn: &SignedTransaction,
+    storage: &mut dyn AppStorage,
+    block_context: &BlockContext,
+    gas_limit: u64,
+) -> (u64, Result<(), AppError>) {
+    // the app is locked outside of catch_unwind so a panic can't poison it
+    let app = app.lock().unwrap();
+    let (gas_used, result) = {
+        let mut app_context = storage.app_context(&txn.app_name);
+        let mut checkpoint = Checkpoint::new(app_context.as_mut());
+        let (gas_used, result) = {
+            let mut gas_meter = GasMeter::new(&mut checkpoint, gas_limit);
+            let result = if !gas_meter.charge_txn(txn) {
+                Err(out_of_gas_error())
+            } else {
+                match panic::catch_unwind(AssertUnwindSafe(|| {
+                    app.execute(txn, &mut gas_meter, block_context)
+                })) {
+                    Result::Ok(result) => result,
+                    Result::Err(_) => Err(AppError::new(ABI_ERROR_CODE, "app panicked")),
+                }
+            };
+            // the app may have ignored a dropped write, out of gas wins
+            if gas_meter.out_of_gas() {
+                (gas_meter.gas_used(), Err(out_of_gas_error()))
+            } else {
+                (gas_meter.gas_used(), result)
+            }
+        };
+        if result.is_ok() {
+            checkpoint.commit();
+        }
+        (gas_used, result)
+    };
+    if let Err(error) = &result {
+        let txn_hash: Hash = txn.object_hash();
+        storage.put_txn(&txn_hash, txn.clone());
+        match serialize(error) {
+            Result::Ok(data) => {
+                let mut status = State::new();
+                status.set_data(&data);
+                storage.put(&txn_status_key(&txn_hash), status);
+            }
+            Result::Err(_) => error!("status of transaction {:?} couldn't serialize", txn_hash),
+        }
+    }
+    (gas_used, result)
 }
 
 #[cfg(test)]
 mod tests_transaction_pool {
 
     use super::*;
+    use crate::checkpoint::tests_checkpoint::{state, MockStorage};
+    use exonum_merkledb::Fork;
     pub use sdk::signed_transaction::SignedTransaction;
+    use sdk::traits::{ReadOnlyStateContext, StateContext};
     use std::collections::HashMap;
     use std::time::SystemTime;
+    use utils::configreader::Configuration;
+    use utils::keypair::{CryptoKeypair, Keypair, KeypairType};
     use utils::serializer::{Deserialize, Serialize};
 
     #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, BinaryValue, ObjectHash)]
@@ -318,21 +521,140 @@ mod tests_transaction_pool {
         assert_eq!(temp_pool.length_hash_pool(), 0, "Issue with sync_pool");
     }
 
+    #[test]
+    pub fn test_fee_priority_order() {
+        let mut txn_pool: TransactionPool = TransactionPool::new();
+        let kp_a = Keypair::generate();
+        let kp_b = Keypair::generate();
+        let prepare = |kp: &KeypairType, timestamp: TxnPoolKeyType, fee: u64| {
+            let mut header = HashMap::default();
+            header.insert("timestamp".to_string(), timestamp.to_string());
+            header.insert("fee".to_string(), fee.to_string());
+            SignedTransaction::create(kp, "Mockcurrency", header, vec![])
+        };
+        let txn_a1 = prepare(&kp_a, 1, 1);
+        let txn_b = prepare(&kp_b, 2, 5);
+        let txn_a2 = prepare(&kp_a, 3, 10);
+        txn_pool.insert_op(&1, &txn_a1);
+        txn_pool.insert_op(&2, &txn_b);
+        txn_pool.insert_op(&3, &txn_a2);
+        let ordered = |fee_apps: &HashSet<String>| -> Vec<TxnPoolValueType> {
+            txn_pool
+                .fee_priority_order(fee_apps)
+                .into_iter()
+                .map(|(_, txn)| txn.clone())
+                .collect()
+        };
+        let fee_apps: HashSet<String> = vec![String::from("Mockcurrency")].into_iter().collect();
+        // higher fee of a2 can't jump ahead of a1 from the same signer
+        assert_eq!(
+            ordered(&fee_apps),
+            vec![txn_b.clone(), txn_a1.clone(), txn_a2.clone()]
+        );
+        // fees of an app which doesn't charge them don't count
+        assert_eq!(ordered(&HashSet::new()), vec![txn_a1, txn_b, txn_a2]);
+    }
+
+    /// writes "written" then succeeds, fails or panics on txn payload 0, 1, 2
+    struct MockApp;
+
+    impl AppHandler for MockApp {
+        fn execute(
+            &self,
+            txn: &SignedTransaction,
+            state_context: &mut dyn StateContext,
+            _block_context: &BlockContext,
+        ) -> Result<(), AppError> {
+            state_context.put(&String::from("written"), state(txn.txn[0]));
+            state_context.put_txn(&txn.object_hash(), txn.clone());
+            match txn.txn[0] {
+                0 => Ok(()),
+                1 => Err(AppError::new(1, "failed")),
+                _ => panic!("mock app panicked"),
+            }
+        }
+
+        fn name(&self) -> String {
+            String::from("Mockcurrency")
+        }
+    }
+
+    #[test]
+    pub fn test_execute_txn_rollback() {
+        let app: Mutex<Box<dyn AppHandler + Send>> = Mutex::new(Box::new(MockApp));
+        let block_context = BlockContext::new(1, 0, &String::from("author"), &Hash::zero());
+        let kp = Keypair::generate();
+        let written = String::from("written");
+        for payload in 0..3 {
+            let mut storage = MockStorage::default();
+            let txn = SignedTransaction::create(&kp, "Mockcurrency", HashMap::new(), vec![payload]);
+            let txn_hash: Hash = txn.object_hash();
+            let (_, result) = execute_txn(&app, &txn, &mut storage, &block_context, 1_000_000);
+            // failed txns are stored, their writes are not
+            assert_eq!(storage.contains_txn(&txn_hash), true);
+            assert_eq!(result.is_ok(), payload == 0);
+            assert_eq!(storage.contains(&written), payload == 0);
+            assert_eq!(storage.contains(&txn_status_key(&txn_hash)), payload != 0);
+        }
+        // a panic doesn't poison the app
+        assert_eq!(app.is_poisoned(), false);
+    }
+
+    #[test]
+    pub fn test_execute_txn_gas() {
+        let app: Mutex<Box<dyn AppHandler + Send>> = Mutex::new(Box::new(MockApp));
+        let block_context = BlockContext::new(1, 0, &String::from("author"), &Hash::zero());
+        let mut storage = MockStorage::default();
+        let mut header = HashMap::new();
+        header.insert(String::from("gas_limit"), String::from("1500"));
+        let txn = SignedTransaction::create(&Keypair::generate(), "Mockcurrency", header, vec![0]);
+        let config: Configuration = Configuration::from_file("../../config.toml").unwrap();
+        let block_config: &BlockConfig = &config.block_config;
+        assert_eq!(txn_gas_limit(&txn, block_config), 1500);
+        // the txn itself fits but its writes don't
+        let (gas_used, result) = execute_txn(&app, &txn, &mut storage, &block_context, 1500);
+        assert_eq!(result, Err(out_of_gas_error()));
+        assert_eq!(gas_used, 1500);
+        assert_eq!(storage.contains(&String::from("written")), false);
+        assert_eq!(storage.contains(&txn_status_key(&txn.object_hash())), true);
+    }
+
+    #[test]
+    pub fn test_update_transactions_unknown_app() {
+        let config: Configuration = Configuration::from_file("../../config.toml").unwrap();
+        let mut txn_pool: TransactionPool = TransactionPool::new();
+        let txn =
+            SignedTransaction::create(&Keypair::generate(), "Unknownapp", HashMap::new(), vec![0]);
+        txn_pool.insert_op(&1, &txn);
+        let block_context = BlockContext::new(1, 0, &String::from("author"), &Hash::zero());
+        let mut storage = MockStorage::default();
+        let app_data: AppData = AppData::new();
+        let peer_reports = Mutex::new(PeerReports::new());
+        let executor = TxnExecutor {
+            txn_pool: &txn_pool,
+            app_data: &app_data,
+            block_config: &config.block_config,
+            peer_reports: &peer_reports,
+        };
+        let pool: &dyn PoolTrait<&Fork, State, SignedTransaction> = &executor;
+        // a block carrying a txn of an app the node doesn't run is refused
+        assert_eq!(
+            pool.update_transactions(&mut storage, &vec![txn.object_hash()], &block_context),
+            false
+        );
+        assert_eq!(storage.contains_txn(&txn.object_hash()), false);
+    }
+
     pub fn prepare_transaction(_txn_fxn: String) -> SignedTransaction {
         let mock_app = "Mockcurrency";
         let serialized_txn = vec![];
-        let signed_txn = vec![];
+        let kp = Keypair::generate();
         let time_stamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
             .as_micros();
         let mut header = HashMap::default();
         header.insert("timestamp".to_string(), time_stamp.to_string());
-        SignedTransaction {
-            txn: serialized_txn,
-            app_name: String::from(mock_app),
-            signature: signed_txn,
-            header,
-        }
+        SignedTransaction::create(&kp, mock_app, header, serialized_txn)
     }
 }
@@ -0,0 +1,976 @@
+extern crate utils;
+use super::block_context::BlockContext;
+use super::errors::AppError;
+use super::gas::out_of_gas_error;
+use super::signed_transaction::SignedTransaction;
+use super::state::State;
+use super::traits::{AppHandler, ReadOnlyStateContext, StateContext};
+use exonum_crypto::Hash;
+use std::ffi::c_void;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::slice;
+use utils::serializer::{deserialize, serialize};
+
+/// version of the app plugin ABI, bumped whenever a type or function below changes.
+/// The node refuses to load an app built for another version.
+pub const ABI_VERSION: u32 = 8;
+
+/// symbol returning the ABI_VERSION an app was built with
+pub const ABI_VERSION_SYMBOL: &[u8] = b"sbc_app_abi_version";
+
+/// symbol returning the AppVTable of an app
+pub const REGISTER_APP_SYMBOL: &[u8] = b"sbc_register_app";
+
+pub const ABI_OK: i32 = 0;
+pub const ABI_NOT_FOUND: i32 = 1;
+pub const ABI_ERROR: i32 = -1;
+
+/// AppError code used when a call fails at the ABI level, not in the app
+pub const ABI_ERROR_CODE: u32 = u32::MAX;
+
+/// AbiSink receives bytes produced on the other side of the boundary. The
+/// receiver copies them, so memory is always freed by the side that allocated it.
+#[repr(C)]
+pub struct AbiSink {
+    pub sink: *mut c_void,
+    pub write: extern "C" fn(sink: *mut c_void, data: *const u8, len: usize),
+}
+
+impl AbiSink {
+    fn send(&self, data: &[u8]) {
+        (self.write)(self.sink, data.as_ptr(), data.len());
+    }
+}
+
+extern "C" fn write_to_vec(sink: *mut c_void, data: *const u8, len: usize) {
+    let buffer: &mut Vec<u8> = unsafe { &mut *(sink as *mut Vec<u8>) };
+    buffer.extend_from_slice(unsafe { bytes(data, len) });
+}
+
+fn vec_sink(buffer: &mut Vec<u8>) -> AbiSink {
+    AbiSink {
+        sink: buffer as *mut Vec<u8> as *mut c_void,
+        write: write_to_vec,
+    }
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len)
+    }
+}
+
+unsafe fn string(data: *const u8, len: usize) -> Option<String> {
+    String::from_utf8(bytes(data, len).to_vec()).ok()
+}
+
+/// AbiStateContext exposes the node StateContext of a call to the app. States &
+/// transactions cross it serialized, keys as utf8 and txn hashes as 32 bytes.
+#[repr(C)]
+pub struct AbiStateContext {
+    pub ctx: *mut c_void,
+    pub get: extern "C" fn(ctx: *mut c_void, key: *const u8, key_len: usize, out: AbiSink) -> i32,
+    pub contains: extern "C" fn(ctx: *mut c_void, key: *const u8, key_len: usize) -> i32,
+    /// out receives the serialized Vec<(String, State)>
+    pub range: extern "C" fn(
+        ctx: *mut c_void,
+        start: *const u8,
+        start_len: usize,
+        end: *const u8,
+        end_len: usize,
+        limit: u64,
+        out: AbiSink,
+    ) -> i32,
+    pub get_txn: extern "C" fn(ctx: *mut c_void, hash: *const u8, out: AbiSink) -> i32,
+    pub contains_txn: extern "C" fn(ctx: *mut c_void, hash: *const u8) -> i32,
+    pub put: extern "C" fn(
+        ctx: *mut c_void,
+        key: *const u8,
+        key_len: usize,
+        state: *const u8,
+        state_len: usize,
+    ) -> i32,
+    pub delete: extern "C" fn(ctx: *mut c_void, key: *const u8, key_len: usize) -> i32,
+    pub put_txn:
+        extern "C" fn(ctx: *mut c_void, hash: *const u8, txn: *const u8, txn_len: usize) -> i32,
+    /// ABI_ERROR when the txn is out of gas
+    pub charge_gas: extern "C" fn(ctx: *mut c_void, gas: u64) -> i32,
+    pub remaining_gas: extern "C" fn(ctx: *mut c_void) -> u64,
+}
+
+/// AppVTable is returned by REGISTER_APP_SYMBOL. Every call reports its
+/// result as ABI_OK or ABI_ERROR, with the serialized AppError written to err.
+#[repr(C)]
+pub struct AppVTable {
+    pub abi_version: u32,
+    pub instance: *mut c_void,
+    pub name: extern "C" fn(instance: *const c_void, out: AbiSink) -> i32,
+    pub version: extern "C" fn(instance: *const c_void, out: AbiSink) -> i32,
+    pub charges_fee: extern "C" fn(instance: *const c_void) -> bool,
+    /// block is the serialized BlockContext
+    pub execute: extern "C" fn(
+        instance: *const c_void,
+        txn: *const u8,
+        txn_len: usize,
+        block: *const u8,
+        block_len: usize,
+        context: *const AbiStateContext,
+        err: AbiSink,
+    ) -> i32,
+    pub validate:
+        extern "C" fn(instance: *const c_void, txn: *const u8, txn_len: usize, err: AbiSink) -> i32,
+    pub finalize_block: extern "C" fn(
+        instance: *const c_void,
+        author: *const u8,
+        author_len: usize,
+        context: *const AbiStateContext,
+        err: AbiSink,
+    ) -> i32,
+    pub init_genesis: extern "C" fn(
+        instance: *const c_void,
+        context: *const AbiStateContext,
+        err: AbiSink,
+    ) -> i32,
+    /// legacy is the pre-namespace state handed to AppHandler::migrate_legacy_state
+    pub migrate_legacy_state: extern "C" fn(
+        instance: *const c_void,
+        legacy: *const AbiStateContext,
+        context: *const AbiStateContext,
+        err: AbiSink,
+    ) -> i32,
+    pub query: extern "C" fn(
+        instance: *const c_void,
+        method: *const u8,
+        method_len: usize,
+        args: *const u8,
+        args_len: usize,
+        context: *const AbiStateContext,
+        out: AbiSink,
+        err: AbiSink,
+    ) -> i32,
+    pub drop: extern "C" fn(instance: *mut c_void),
+}
+
+/// export the AppHandler returned by register as the app of this library,
+/// register must be a `fn() -> Box<dyn AppHandler + Send>`
+#[macro_export]
+macro_rules! export_app {
+    ($register:path) => {
+        #[no_mangle]
+        pub extern "C" fn sbc_app_abi_version() -> u32 {
+            $crate::abi::ABI_VERSION
+        }
+
+        #[no_mangle]
+        pub extern "C" fn sbc_register_app() -> $crate::abi::AppVTable {
+            $crate::abi::export_handler($register())
+        }
+    };
+}
+
+// --- app side, called by the node through AppVTable ---
+
+/// StateContext of the app, every call goes back to the node through AbiStateContext
+struct PluginContext {
+    abi: *const AbiStateContext,
+}
+
+impl PluginContext {
+    fn abi(&self) -> &AbiStateContext {
+        unsafe { &*self.abi }
+    }
+}
+
+impl ReadOnlyStateContext for PluginContext {
+    fn get(&self, key: &String) -> Option<State> {
+        let abi: &AbiStateContext = self.abi();
+        let mut buffer: Vec<u8> = Vec::new();
+        match (abi.get)(abi.ctx, key.as_ptr(), key.len(), vec_sink(&mut buffer)) {
+            ABI_OK => deserialize(&buffer).ok(),
+            _ => None,
+        }
+    }
+    fn contains(&self, key: &String) -> bool {
+        let abi: &AbiStateContext = self.abi();
+        (abi.contains)(abi.ctx, key.as_ptr(), key.len()) == ABI_OK
+    }
+    fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+        let abi: &AbiStateContext = self.abi();
+        let mut buffer: Vec<u8> = Vec::new();
+        let result: i32 = (abi.range)(
+            abi.ctx,
+            start.as_ptr(),
+            start.len(),
+            end.as_ptr(),
+            end.len(),
+            limit as u64,
+            vec_sink(&mut buffer),
+        );
+        match result {
+            ABI_OK => deserialize(&buffer).unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+    fn get_txn(&self, key: &Hash) -> Option<SignedTransaction> {
+        let abi: &AbiStateContext = self.abi();
+        let mut buffer: Vec<u8> = Vec::new();
+        match (abi.get_txn)(abi.ctx, key.as_ref().as_ptr(), vec_sink(&mut buffer)) {
+            ABI_OK => deserialize(&buffer).ok(),
+            _ => None,
+        }
+    }
+    fn contains_txn(&self, key: &Hash) -> bool {
+        let abi: &AbiStateContext = self.abi();
+        (abi.contains_txn)(abi.ctx, key.as_ref().as_ptr()) == ABI_OK
+    }
+}
+
+// writes only fail on the read only context of a query, which never hands
+// out a StateContext, so their status is not reported back to the app
+impl StateContext for PluginContext {
+    fn put(&mut self, key: &String, state: State) {
+        let abi: &AbiStateContext = self.abi();
+        if let Ok(serialized_state) = serialize(&state) {
+            (abi.put)(
+                abi.ctx,
+                key.as_ptr(),
+                key.len(),
+                serialized_state.as_ptr(),
+                serialized_state.len(),
+            );
+        }
+    }
+    fn delete(&mut self, key: &String) {
+        let abi: &AbiStateContext = self.abi();
+        (abi.delete)(abi.ctx, key.as_ptr(), key.len());
+    }
+    fn put_txn(&mut self, key: &Hash, txn: SignedTransaction) {
+        let abi: &AbiStateContext = self.abi();
+        if let Ok(serialized_txn) = serialize(&txn) {
+            (abi.put_txn)(
+                abi.ctx,
+                key.as_ref().as_ptr(),
+                serialized_txn.as_ptr(),
+                serialized_txn.len(),
+            );
+        }
+    }
+    fn charge_gas(&mut self, gas: u64) -> Result<(), AppError> {
+        let abi: &AbiStateContext = self.abi();
+        match (abi.charge_gas)(abi.ctx, gas) {
+            ABI_OK => Ok(()),
+            _ => Err(out_of_gas_error()),
+        }
+    }
+    fn remaining_gas(&self) -> u64 {
+        let abi: &AbiStateContext = self.abi();
+        (abi.remaining_gas)(abi.ctx)
+    }
+}
+
+fn handler<'a>(instance: *const c_void) -> &'a (dyn AppHandler + Send) {
+    unsafe { &**(instance as *const Box<dyn AppHandler + Send>) }
+}
+
+fn abi_error(message: &str) -> AppError {
+    AppError::new(ABI_ERROR_CODE, message)
+}
+
+/// run call, a panic of the app is reported as an error instead of unwinding
+/// into the node
+fn guarded<F: FnOnce() -> Result<(), AppError>>(err: &AbiSink, call: F) -> i32 {
+    let error: AppError = match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(())) => return ABI_OK,
+        Ok(Err(error)) => error,
+        Err(_) => abi_error("app panicked"),
+    };
+    if let Ok(serialized_error) = serialize(&error) {
+        err.send(&serialized_error);
+    }
+    ABI_ERROR
+}
+
+extern "C" fn plugin_name(instance: *const c_void, out: AbiSink) -> i32 {
+    match catch_unwind(AssertUnwindSafe(|| handler(instance).name())) {
+        Ok(name) => {
+            out.send(name.as_bytes());
+            ABI_OK
+        }
+        Err(_) => ABI_ERROR,
+    }
+}
+
+extern "C" fn plugin_version(instance: *const c_void, out: AbiSink) -> i32 {
+    match catch_unwind(AssertUnwindSafe(|| handler(instance).version())) {
+        Ok(version) => {
+            out.send(version.as_bytes());
+            ABI_OK
+        }
+        Err(_) => ABI_ERROR,
+    }
+}
+
+/// an app panicking here is taken as not charging fees
+extern "C" fn plugin_charges_fee(instance: *const c_void) -> bool {
+    catch_unwind(AssertUnwindSafe(|| handler(instance).charges_fee())).unwrap_or(false)
+}
+
+extern "C" fn plugin_execute(
+    instance: *const c_void,
+    txn: *const u8,
+    txn_len: usize,
+    block: *const u8,
+    block_len: usize,
+    context: *const AbiStateContext,
+    err: AbiSink,
+) -> i32 {
+    guarded(&err, || {
+        let txn: SignedTransaction = deserialize(unsafe { bytes(txn, txn_len) })
+            .map_err(|_| abi_error("txn couldn't deserialize"))?;
+        let block_context: BlockContext = deserialize(unsafe { bytes(block, block_len) })
+            .map_err(|_| abi_error("block context couldn't deserialize"))?;
+        let mut state_context = PluginContext { abi: context };
+        handler(instance).execute(&txn, &mut state_context, &block_context)
+    })
+}
+
+extern "C" fn plugin_validate(
+    instance: *const c_void,
+    txn: *const u8,
+    txn_len: usize,
+    err: AbiSink,
+) -> i32 {
+    guarded(&err, || {
+        let txn: SignedTransaction = deserialize(unsafe { bytes(txn, txn_len) })
+            .map_err(|_| abi_error("txn couldn't deserialize"))?;
+        handler(instance).validate(&txn)
+    })
+}
+
+extern "C" fn plugin_finalize_block(
+    instance: *const c_void,
+    author: *const u8,
+    author_len: usize,
+    context: *const AbiStateContext,
+    err: AbiSink,
+) -> i32 {
+    guarded(&err, || {
+        let author: String =
+            unsafe { string(author, author_len) }.ok_or_else(|| abi_error("author isn't utf8"))?;
+        let mut state_context = PluginContext { abi: context };
+        handler(instance).finalize_block(&author, &mut state_context)
+    })
+}
+
+extern "C" fn plugin_init_genesis(
+    instance: *const c_void,
+    context: *const AbiStateContext,
+    err: AbiSink,
+) -> i32 {
+    guarded(&err, || {
+        let mut state_context = PluginContext { abi: context };
+        handler(instance).init_genesis(&mut state_context)
+    })
+}
+
+extern "C" fn plugin_migrate_legacy_state(
+    instance: *const c_void,
+    legacy: *const AbiStateContext,
+    context: *const AbiStateContext,
+    err: AbiSink,
+) -> i32 {
+    guarded(&err, || {
+        let mut legacy_context = PluginContext { abi: legacy };
+        let mut state_context = PluginContext { abi: context };
+        handler(instance).migrate_legacy_state(&mut legacy_context, &mut state_context)
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+extern "C" fn plugin_query(
+    instance: *const c_void,
+    method: *const u8,
+    method_len: usize,
+    args: *const u8,
+    args_len: usize,
+    context: *const AbiStateContext,
+    out: AbiSink,
+    err: AbiSink,
+) -> i32 {
+    guarded(&err, || {
+        let method: String = unsafe { string(method, method_len) }
+            .ok_or_else(|| abi_error("query method isn't utf8"))?;
+        let args: Vec<u8> = unsafe { bytes(args, args_len) }.to_vec();
+        let state_context = PluginContext { abi: context };
+        let result: Vec<u8> = handler(instance).query(&method, &args, &state_context)?;
+        out.send(&result);
+        Ok(())
+    })
+}
+
+extern "C" fn plugin_drop(instance: *mut c_void) {
+    unsafe { drop(Box::from_raw(instance as *mut Box<dyn AppHandler + Send>)) };
+}
+
+/// AppVTable of app, used by export_app!
+pub fn export_handler(app: Box<dyn AppHandler + Send>) -> AppVTable {
+    AppVTable {
+        abi_version: ABI_VERSION,
+        instance: Box::into_raw(Box::new(app)) as *mut c_void,
+        name: plugin_name,
+        version: plugin_version,
+        charges_fee: plugin_charges_fee,
+        execute: plugin_execute,
+        validate: plugin_validate,
+        finalize_block: plugin_finalize_block,
+        init_genesis: plugin_init_genesis,
+        migrate_legacy_state: plugin_migrate_legacy_state,
+        query: plugin_query,
+        drop: plugin_drop,
+    }
+}
+
+// --- node side, AbiStateContext callbacks over the StateContext of a call ---
+
+enum HostContext<'a> {
+    ReadWrite(&'a mut dyn StateContext),
+    ReadOnly(&'a dyn ReadOnlyStateContext),
+}
+
+impl<'a> HostContext<'a> {
+    fn abi(&mut self) -> AbiStateContext {
+        AbiStateContext {
+            ctx: self as *mut HostContext as *mut c_void,
+            get: host_get,
+            contains: host_contains,
+            range: host_range,
+            get_txn: host_get_txn,
+            contains_txn: host_contains_txn,
+            put: host_put,
+            delete: host_delete,
+            put_txn: host_put_txn,
+            charge_gas: host_charge_gas,
+            remaining_gas: host_remaining_gas,
+        }
+    }
+
+    fn get(&self, key: &String) -> Option<State> {
+        match self {
+            HostContext::ReadWrite(state_context) => state_context.get(key),
+            HostContext::ReadOnly(state_context) => state_context.get(key),
+        }
+    }
+
+    fn contains(&self, key: &String) -> bool {
+        match self {
+            HostContext::ReadWrite(state_context) => state_context.contains(key),
+            HostContext::ReadOnly(state_context) => state_context.contains(key),
+        }
+    }
+
+    fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+        match self {
+            HostContext::ReadWrite(state_context) => state_context.range(start, end, limit),
+            HostContext::ReadOnly(state_context) => state_context.range(start, end, limit),
+        }
+    }
+
+    fn get_txn(&self, key: &Hash) -> Option<SignedTransaction> {
+        match self {
+            HostContext::ReadWrite(state_context) => state_context.get_txn(key),
+            HostContext::ReadOnly(state_context) => state_context.get_txn(key),
+        }
+    }
+
+    fn contains_txn(&self, key: &Hash) -> bool {
+        match self {
+            HostContext::ReadWrite(state_context) => state_context.contains_txn(key),
+            HostContext::ReadOnly(state_context) => state_context.contains_txn(key),
+        }
+    }
+
+    fn writer(&mut self) -> Option<&mut dyn StateContext> {
+        match self {
+            HostContext::ReadWrite(state_context) => Some(&mut **state_context),
+            HostContext::ReadOnly(_) => None,
+        }
+    }
+}
+
+fn host<'a>(ctx: *mut c_void) -> &'a mut HostContext<'a> {
+    unsafe { &mut *(ctx as *mut HostContext) }
+}
+
+unsafe fn hash(data: *const u8) -> Option<Hash> {
+    Hash::from_slice(bytes(data, 32))
+}
+
+extern "C" fn host_get(ctx: *mut c_void, key: *const u8, key_len: usize, out: AbiSink) -> i32 {
+    let key: String = match unsafe { string(key, key_len) } {
+        Some(key) => key,
+        None => return ABI_ERROR,
+    };
+    match host(ctx).get(&key) {
+        Some(state) => match serialize(&state) {
+            Result::Ok(serialized_state) => {
+                out.send(&serialized_state);
+                ABI_OK
+            }
+            Result::Err(_) => ABI_ERROR,
+        },
+        None => ABI_NOT_FOUND,
+    }
+}
+
+extern "C" fn host_contains(ctx: *mut c_void, key: *const u8, key_len: usize) -> i32 {
+    match unsafe { string(key, key_len) } {
+        Some(key) if host(ctx).contains(&key) => ABI_OK,
+        Some(_) => ABI_NOT_FOUND,
+        None => ABI_ERROR,
+    }
+}
+
+extern "C" fn host_range(
+    ctx: *mut c_void,
+    start: *const u8,
+    start_len: usize,
+    end: *const u8,
+    end_len: usize,
+    limit: u64,
+    out: AbiSink,
+) -> i32 {
+    let (start, end): (String, String) =
+        match unsafe { (string(start, start_len), string(end, end_len)) } {
+            (Some(start), Some(end)) => (start, end),
+            _ => return ABI_ERROR,
+        };
+    let entries: Vec<(String, State)> = host(ctx).range(&start, &end, limit as usize);
+    match serialize(&entries) {
+        Result::Ok(serialized_entries) => {
+            out.send(&serialized_entries);
+            ABI_OK
+        }
+        Result::Err(_) => ABI_ERROR,
+    }
+}
+
+extern "C" fn host_get_txn(ctx: *mut c_void, hash_ptr: *const u8, out: AbiSink) -> i32 {
+    let key: Hash = match unsafe { hash(hash_ptr) } {
+        Some(key) => key,
+        None => return ABI_ERROR,
+    };
+    match host(ctx).get_txn(&key) {
+        Some(txn) => match serialize(&txn) {
+            Result::Ok(serialized_txn) => {
+                out.send(&serialized_txn);
+                ABI_OK
+            }
+            Result::Err(_) => ABI_ERROR,
+        },
+        None => ABI_NOT_FOUND,
+    }
+}
+
+extern "C" fn host_contains_txn(ctx: *mut c_void, hash_ptr: *const u8) -> i32 {
+    match unsafe { hash(hash_ptr) } {
+        Some(key) if host(ctx).contains_txn(&key) => ABI_OK,
+        Some(_) => ABI_NOT_FOUND,
+        None => ABI_ERROR,
+    }
+}
+
+extern "C" fn host_put(
+    ctx: *mut c_void,
+    key: *const u8,
+    key_len: usize,
+    state: *const u8,
+    state_len: usize,
+) -> i32 {
+    let key: String = match unsafe { string(key, key_len) } {
+        Some(key) => key,
+        None => return ABI_ERROR,
+    };
+    let state: State = match deserialize(unsafe { bytes(state, state_len) }) {
+        Result::Ok(state) => state,
+        Result::Err(_) => return ABI_ERROR,
+    };
+    match host(ctx).writer() {
+        Some(state_context) => {
+            state_context.put(&key, state);
+            ABI_OK
+        }
+        None => ABI_ERROR,
+    }
+}
+
+extern "C" fn host_delete(ctx: *mut c_void, key: *const u8, key_len: usize) -> i32 {
+    let key: String = match unsafe { string(key, key_len) } {
+        Some(key) => key,
+        None => return ABI_ERROR,
+    };
+    match host(ctx).writer() {
+        Some(state_context) => {
+            state_context.delete(&key);
+            ABI_OK
+        }
+        None => ABI_ERROR,
+    }
+}
+
+extern "C" fn host_put_txn(
+    ctx: *mut c_void,
+    hash_ptr: *const u8,
+    txn: *const u8,
+    txn_len: usize,
+) -> i32 {
+    let key: Hash = match unsafe { hash(hash_ptr) } {
+        Some(key) => key,
+        None => return ABI_ERROR,
+    };
+    let txn: SignedTransaction = match deserialize(unsafe { bytes(txn, txn_len) }) {
+        Result::Ok(txn) => txn,
+        Result::Err(_) => return ABI_ERROR,
+    };
+    match host(ctx).writer() {
+        Some(state_context) => {
+            state_context.put_txn(&key, txn);
+            ABI_OK
+        }
+        None => ABI_ERROR,
+    }
+}
+
+extern "C" fn host_charge_gas(ctx: *mut c_void, gas: u64) -> i32 {
+    match host(ctx).writer() {
+        Some(state_context) => match state_context.charge_gas(gas) {
+            Result::Ok(_) => ABI_OK,
+            Result::Err(_) => ABI_ERROR,
+        },
+        None => ABI_OK,
+    }
+}
+
+extern "C" fn host_remaining_gas(ctx: *mut c_void) -> u64 {
+    match host(ctx).writer() {
+        Some(state_context) => state_context.remaining_gas(),
+        None => u64::MAX,
+    }
+}
+
+/// utf8 string written by an AppVTable getter like name, None if the call fails
+fn read_string(
+    getter: extern "C" fn(instance: *const c_void, out: AbiSink) -> i32,
+    instance: *const c_void,
+) -> Option<String> {
+    let mut buffer: Vec<u8> = Vec::new();
+    if getter(instance, vec_sink(&mut buffer)) != ABI_OK {
+        return None;
+    }
+    String::from_utf8(buffer).ok()
+}
+
+/// AbiApp is the AppHandler of an app loaded through AppVTable
+pub struct AbiApp {
+    vtable: AppVTable,
+    name: String,
+    version: String,
+    charges_fee: bool,
+}
+
+// the app instance is only reached through the vtable calls, which the node
+// serializes behind the app Mutex
+unsafe impl Send for AbiApp {}
+
+impl AbiApp {
+    /// wrap vtable, rejecting apps built for another ABI_VERSION. The app
+    /// instance is released if its name or version can't be read, the vtable
+    /// of another ABI version isn't called at all, not even to drop it.
+    /// Safety: vtable must come from REGISTER_APP_SYMBOL of a library which
+    /// stays loaded for the lifetime of AbiApp.
+    pub unsafe fn new(vtable: AppVTable) -> Result<AbiApp, String> {
+        if vtable.abi_version != ABI_VERSION {
+            return Err(format!(
+                "app is built for ABI version {}, node supports {}",
+                vtable.abi_version, ABI_VERSION
+            ));
+        }
+        // owns the instance from here on, dropping app on Err releases it
+        let mut app = AbiApp {
+            vtable,
+            name: String::new(),
+            version: String::new(),
+            charges_fee: false,
+        };
+        app.name = read_string(app.vtable.name, app.vtable.instance)
+            .ok_or_else(|| String::from("app name couldn't be read"))?;
+        app.version = read_string(app.vtable.version, app.vtable.instance)
+            .ok_or_else(|| String::from("app version couldn't be read"))?;
+        app.charges_fee = (app.vtable.charges_fee)(app.vtable.instance);
+        Ok(app)
+    }
+
+    fn call<F: FnOnce(AbiSink) -> i32>(&self, call: F) -> Result<(), AppError> {
+        let mut error: Vec<u8> = Vec::new();
+        match call(vec_sink(&mut error)) {
+            ABI_OK => Ok(()),
+            _ => Err(deserialize(&error).unwrap_or_else(|_| abi_error("app call failed"))),
+        }
+    }
+}
+
+impl AppHandler for AbiApp {
+    fn execute(
+        &self,
+        txn: &SignedTransaction,
+        state_context: &mut dyn StateContext,
+        block_context: &BlockContext,
+    ) -> Result<(), AppError> {
+        let txn: Vec<u8> = serialize(txn).map_err(|_| abi_error("txn couldn't serialize"))?;
+        let block: Vec<u8> =
+            serialize(block_context).map_err(|_| abi_error("block context couldn't serialize"))?;
+        let mut host_context = HostContext::ReadWrite(state_context);
+        let context: AbiStateContext = host_context.abi();
+        self.call(|err| {
+            (self.vtable.execute)(
+                self.vtable.instance,
+                txn.as_ptr(),
+                txn.len(),
+                block.as_ptr(),
+                block.len(),
+                &context,
+                err,
+            )
+        })
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn version(&self) -> String {
+        self.version.clone()
+    }
+
+    fn charges_fee(&self) -> bool {
+        self.charges_fee
+    }
+
+    fn validate(&self, txn: &SignedTransaction) -> Result<(), AppError> {
+        let txn: Vec<u8> = serialize(txn).map_err(|_| abi_error("txn couldn't serialize"))?;
+        self.call(|err| (self.vtable.validate)(self.vtable.instance, txn.as_ptr(), txn.len(), err))
+    }
+
+    fn finalize_block(
+        &self,
+        author: &String,
+        state_context: &mut dyn StateContext,
+    ) -> Result<(), AppError> {
+        let mut host_context = HostContext::ReadWrite(state_context);
+        let context: AbiStateContext = host_context.abi();
+        self.call(|err| {
+            (self.vtable.finalize_block)(
+                self.vtable.instance,
+                author.as_ptr(),
+                author.len(),
+                &context,
+                err,
+            )
+        })
+    }
+
+    fn init_genesis(&self, state_context: &mut dyn StateContext) -> Result<(), AppError> {
+        let mut host_context = HostContext::ReadWrite(state_context);
+        let context: AbiStateContext = host_context.abi();
+        self.call(|err| (self.vtable.init_genesis)(self.vtable.instance, &context, err))
+    }
+
+    fn migrate_legacy_state(
+        &self,
+        legacy: &mut dyn StateContext,
+        state_context: &mut dyn StateContext,
+    ) -> Result<(), AppError> {
+        let mut host_legacy = HostContext::ReadWrite(legacy);
+        let legacy: AbiStateContext = host_legacy.abi();
+        let mut host_context = HostContext::ReadWrite(state_context);
+        let context: AbiStateContext = host_context.abi();
+        self.call(|err| {
+            (self.vtable.migrate_legacy_state)(self.vtable.instance, &legacy, &context, err)
+        })
+    }
+
+    fn query(
+        &self,
+        method: &String,
+        args: &Vec<u8>,
+        state_context: &dyn ReadOnlyStateContext,
+    ) -> Result<Vec<u8>, AppError> {
+        let mut host_context = HostContext::ReadOnly(state_context);
+        let context: AbiStateContext = host_context.abi();
+        let mut result: Vec<u8> = Vec::new();
+        let out: AbiSink = vec_sink(&mut result);
+        self.call(|err| {
+            (self.vtable.query)(
+                self.vtable.instance,
+                method.as_ptr(),
+                method.len(),
+                args.as_ptr(),
+                args.len(),
+                &context,
+                out,
+                err,
+            )
+        })?;
+        Ok(result)
+    }
+}
+
+impl Drop for AbiApp {
+    fn drop(&mut self) {
+        (self.vtable.drop)(self.vtable.instance);
+    }
+}
+
+#[cfg(test)]
+mod test_abi {
+    use super::*;
+    use crate::testing::MockStateContext;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct MockApp {}
+
+    impl AppHandler for MockApp {
+        fn execute(
+            &self,
+            txn: &SignedTransaction,
+            state_context: &mut dyn StateContext,
+            block_context: &BlockContext,
+        ) -> Result<(), AppError> {
+            if txn.txn.is_empty() {
+                panic!("empty txn");
+            }
+            assert_eq!(block_context.height, 3);
+            let mut state = State::new();
+            state.set_data(&txn.txn);
+            state_context.put(&String::from("key"), state);
+            Err(AppError::new(7, "executed"))
+        }
+
+        fn name(&self) -> String {
+            String::from("MockApp")
+        }
+
+        fn version(&self) -> String {
+            String::from("1.2.3")
+        }
+
+        fn charges_fee(&self) -> bool {
+            true
+        }
+
+        fn query(
+            &self,
+            method: &String,
+            _args: &Vec<u8>,
+            state_context: &dyn ReadOnlyStateContext,
+        ) -> Result<Vec<u8>, AppError> {
+            match state_context.get(method) {
+                Some(state) => Ok(state.get_data().clone()),
+                None => Err(AppError::new(8, "missing")),
+            }
+        }
+    }
+
+    fn signed_txn(payload: Vec<u8>) -> SignedTransaction {
+        SignedTransaction {
+            txn: payload,
+            app_name: String::from("MockApp"),
+            header: HashMap::new(),
+            signer: String::new(),
+            signature: vec![],
+        }
+    }
+
+    #[test]
+    fn test_abi_round_trip() {
+        let app: AbiApp = unsafe { AbiApp::new(export_handler(Box::new(MockApp {}))) }.unwrap();
+        assert_eq!(app.name(), String::from("MockApp"));
+        assert_eq!(app.version(), String::from("1.2.3"));
+        assert_eq!(app.charges_fee(), true);
+
+        let mut state_context = MockStateContext::default();
+        let block_context = BlockContext::new(3, 0, &String::new(), &Hash::zero());
+        let result = app.execute(&signed_txn(vec![1, 2]), &mut state_context, &block_context);
+        assert_eq!(result, Err(AppError::new(7, "executed")));
+        assert_eq!(
+            state_context.get(&String::from("key")).unwrap().get_data(),
+            &vec![1, 2]
+        );
+        // a panic inside the app comes back as an error
+        let result = app.execute(&signed_txn(vec![]), &mut state_context, &block_context);
+        assert_eq!(result.unwrap_err().code, ABI_ERROR_CODE);
+
+        let result = app.query(&String::from("key"), &vec![], &state_context);
+        assert_eq!(result, Ok(vec![1, 2]));
+        let result = app.query(&String::from("other"), &vec![], &state_context);
+        assert_eq!(result, Err(AppError::new(8, "missing")));
+        assert_eq!(app.validate(&signed_txn(vec![1])), Ok(()));
+        assert_eq!(app.init_genesis(&mut state_context), Ok(()));
+        let mut legacy = MockStateContext::default();
+        assert_eq!(
+            app.migrate_legacy_state(&mut legacy, &mut state_context),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_abi_version_mismatch() {
+        let mut vtable: AppVTable = export_handler(Box::new(MockApp {}));
+        vtable.abi_version = ABI_VERSION + 1;
+        let instance: *mut c_void = vtable.instance;
+        let drop_fn = vtable.drop;
+        assert_eq!(unsafe { AbiApp::new(vtable) }.is_err(), true);
+        // the vtable of another ABI version isn't trusted, its library has
+        // to release the instance. Here it is the vtable of this version.
+        drop_fn(instance);
+    }
+
+    static DROPPED_APPS: AtomicUsize = AtomicUsize::new(0);
+
+    /// app whose name panics, counts its drops
+    struct NamelessApp {}
+
+    impl AppHandler for NamelessApp {
+        fn execute(
+            &self,
+            _txn: &SignedTransaction,
+            _state_context: &mut dyn StateContext,
+            _block_context: &BlockContext,
+        ) -> Result<(), AppError> {
+            Ok(())
+        }
+
+        fn name(&self) -> String {
+            panic!("no name")
+        }
+    }
+
+    impl Drop for NamelessApp {
+        fn drop(&mut self) {
+            DROPPED_APPS.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_abi_rejected_app_dropped() {
+        let vtable: AppVTable = export_handler(Box::new(NamelessApp {}));
+        let result = unsafe { AbiApp::new(vtable) };
+        assert_eq!(
+            result.err(),
+            Some(String::from("app name couldn't be read"))
+        );
+        assert_eq!(DROPPED_APPS.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,57 @@
+extern crate utils;
+use exonum_crypto::{hash, Hash};
+use utils::serializer::{Deserialize, Serialize};
+
+/// BlockContext describes the block a transaction is executed in. Apps must
+/// take time, randomness & the block author from here instead of the wall
+/// clock, an OS RNG or node config, so every node computes the same state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockContext {
+    /// id of the block in the chain
+    pub height: u64,
+    /// block creation time in micro seconds, recorded in the block
+    pub timestamp: u128,
+    /// hex public key of the block proposer
+    pub author: String,
+    /// deterministic seed, derived from the previous block hash & height
+    pub seed: Hash,
+}
+
+impl BlockContext {
+    pub fn new(height: u64, timestamp: u128, author: &String, prev_hash: &Hash) -> BlockContext {
+        let mut seed_input: Vec<u8> = prev_hash.as_ref().to_vec();
+        seed_input.extend_from_slice(&height.to_be_bytes());
+        BlockContext {
+            height,
+            timestamp,
+            author: author.clone(),
+            seed: hash(&seed_input),
+        }
+    }
+
+    /// seed of a single transaction, two txns of a block never share it
+    pub fn txn_seed(&self, txn_hash: &Hash) -> Hash {
+        let mut seed_input: Vec<u8> = self.seed.as_ref().to_vec();
+        seed_input.extend_from_slice(txn_hash.as_ref());
+        hash(&seed_input)
+    }
+}
+
+#[cfg(test)]
+mod test_block_context {
+    use super::*;
+
+    #[test]
+    fn test_block_context_seed() {
+        let author = String::from("author");
+        let context = BlockContext::new(5, 100, &author, &Hash::zero());
+        assert_eq!(context, BlockContext::new(5, 100, &author, &Hash::zero()));
+        assert_ne!(
+            context.seed,
+            BlockContext::new(6, 100, &author, &Hash::zero()).seed
+        );
+        let txn_hash: Hash = hash(&[1]);
+        assert_eq!(context.txn_seed(&txn_hash), context.txn_seed(&txn_hash));
+        assert_ne!(context.txn_seed(&txn_hash), context.txn_seed(&hash(&[2])));
+    }
+}
@@ -0,0 +1,27 @@
+use std::fmt;
+use utils::serializer::{Deserialize, Serialize};
+
+/// AppError is returned by an app when a transaction couldn't be applied.
+/// code is app specific, message is meant for node logs and clients.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppError {
+    pub code: u32,
+    pub message: String,
+}
+
+impl AppError {
+    pub fn new(code: u32, message: &str) -> AppError {
+        AppError {
+            code,
+            message: String::from(message),
+        }
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "app error {}: {}", self.code, self.message)
+    }
+}
+
+impl std::error::Error for AppError {}
@@ -0,0 +1,215 @@
+use super::errors::AppError;
+use super::signed_transaction::SignedTransaction;
+use super::state::State;
+use super::traits::{ReadOnlyStateContext, StateContext};
+use exonum_crypto::Hash;
+use std::cell::Cell;
+
+/// gas charged once per transaction
+pub const TXN_BASE_GAS: u64 = 1_000;
+/// gas charged per byte of the transaction payload
+pub const TXN_BYTE_GAS: u64 = 10;
+/// gas charged by every state or transaction read
+pub const READ_GAS: u64 = 200;
+/// gas charged per byte of a key, state or transaction read
+pub const READ_BYTE_GAS: u64 = 1;
+/// gas charged by every state or transaction write & delete
+pub const WRITE_GAS: u64 = 1_000;
+/// gas charged per byte of a key, state or transaction written
+pub const WRITE_BYTE_GAS: u64 = 10;
+
+/// AppError code of a transaction that ran out of gas
+pub const OUT_OF_GAS_CODE: u32 = u32::MAX - 1;
+
+pub fn out_of_gas_error() -> AppError {
+    AppError::new(OUT_OF_GAS_CODE, "out of gas")
+}
+
+fn txn_size(txn: &SignedTransaction) -> u64 {
+    txn.txn.len() as u64
+}
+
+/// GasMeter charges the gas of every call an app makes on a StateContext,
+/// apps charge their own computation with StateContext::charge_gas. Once the
+/// gas limit is crossed reads return nothing & writes are dropped, the node
+/// then fails the transaction with out_of_gas_error.
+pub struct GasMeter<'a> {
+    state_context: &'a mut dyn StateContext,
+    gas_limit: u64,
+    // reads only borrow the meter, so gas_used needs interior mutability
+    gas_used: Cell<u64>,
+}
+
+impl<'a> GasMeter<'a> {
+    pub fn new(state_context: &'a mut dyn StateContext, gas_limit: u64) -> GasMeter<'a> {
+        GasMeter {
+            state_context,
+            gas_limit,
+            gas_used: Cell::new(0),
+        }
+    }
+
+    /// add gas to gas_used, false once gas_used is over gas_limit
+    fn consume(&self, gas: u64) -> bool {
+        let gas_used: u64 = self.gas_used.get().saturating_add(gas);
+        self.gas_used.set(gas_used);
+        gas_used <= self.gas_limit
+    }
+
+    /// charge the base & payload gas of txn before it is executed
+    pub fn charge_txn(&self, txn: &SignedTransaction) -> bool {
+        self.consume(TXN_BASE_GAS.saturating_add(TXN_BYTE_GAS.saturating_mul(txn_size(txn))))
+    }
+
+    /// gas used so far, never more than gas_limit
+    pub fn gas_used(&self) -> u64 {
+        std::cmp::min(self.gas_used.get(), self.gas_limit)
+    }
+
+    pub fn out_of_gas(&self) -> bool {
+        self.gas_used.get() > self.gas_limit
+    }
+
+    /// gas left before gas_limit is reached
+    pub fn remaining(&self) -> u64 {
+        self.gas_limit.saturating_sub(self.gas_used.get())
+    }
+
+    fn charge_read(&self, bytes: u64) -> bool {
+        self.consume(READ_GAS.saturating_add(READ_BYTE_GAS.saturating_mul(bytes)))
+    }
+
+    fn charge_write(&self, bytes: u64) -> bool {
+        self.consume(WRITE_GAS.saturating_add(WRITE_BYTE_GAS.saturating_mul(bytes)))
+    }
+}
+
+impl<'a> ReadOnlyStateContext for GasMeter<'a> {
+    fn get(&self, key: &String) -> Option<State> {
+        if !self.charge_read(key.len() as u64) {
+            return None;
+        }
+        let state: Option<State> = self.state_context.get(key);
+        match &state {
+            Some(value) if !self.consume(READ_BYTE_GAS * value.get_data().len() as u64) => None,
+            _ => state,
+        }
+    }
+
+    fn contains(&self, key: &String) -> bool {
+        self.charge_read(key.len() as u64) && self.state_context.contains(key)
+    }
+
+    fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+        if !self.charge_read((start.len() + end.len()) as u64) {
+            return Vec::new();
+        }
+        let entries: Vec<(String, State)> = self.state_context.range(start, end, limit);
+        let bytes: usize = entries
+            .iter()
+            .map(|(key, state)| key.len() + state.get_data().len())
+            .sum();
+        if self.consume(READ_BYTE_GAS * bytes as u64) {
+            entries
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn get_txn(&self, key: &Hash) -> Option<SignedTransaction> {
+        if !self.charge_read(0) {
+            return None;
+        }
+        let txn: Option<SignedTransaction> = self.state_context.get_txn(key);
+        match &txn {
+            Some(value) if !self.consume(READ_BYTE_GAS * txn_size(value)) => None,
+            _ => txn,
+        }
+    }
+
+    fn contains_txn(&self, key: &Hash) -> bool {
+        self.charge_read(0) && self.state_context.contains_txn(key)
+    }
+}
+
+impl<'a> StateContext for GasMeter<'a> {
+    fn put(&mut self, key: &String, state: State) {
+        if self.charge_write((key.len() + state.get_data().len()) as u64) {
+            self.state_context.put(key, state);
+        }
+    }
+
+    fn delete(&mut self, key: &String) {
+        if self.charge_write(key.len() as u64) {
+            self.state_context.delete(key);
+        }
+    }
+
+    fn put_txn(&mut self, key: &Hash, txn: SignedTransaction) {
+        if self.charge_write(txn_size(&txn)) {
+            self.state_context.put_txn(key, txn);
+        }
+    }
+
+    fn charge_gas(&mut self, gas: u64) -> Result<(), AppError> {
+        if self.consume(gas) {
+            Ok(())
+        } else {
+            Err(out_of_gas_error())
+        }
+    }
+
+    fn remaining_gas(&self) -> u64 {
+        self.remaining()
+    }
+}
+
+#[cfg(test)]
+mod test_gas {
+    use super::*;
+    use crate::testing::MockStateContext;
+    use std::collections::HashMap;
+
+    fn state(len: usize) -> State {
+        let mut state = State::new();
+        state.set_data(&vec![0; len]);
+        state
+    }
+
+    #[test]
+    fn test_gas_meter() {
+        let mut state_context = MockStateContext::default();
+        let key = String::from("key");
+        let txn = SignedTransaction {
+            txn: vec![0; 10],
+            app_name: String::from("MockApp"),
+            header: HashMap::new(),
+            signer: String::new(),
+            signature: vec![],
+        };
+        let write_gas: u64 = WRITE_GAS + WRITE_BYTE_GAS * (3 + 100);
+        let gas_limit: u64 = TXN_BASE_GAS + TXN_BYTE_GAS * 10 + write_gas;
+        {
+            let mut meter = GasMeter::new(&mut state_context, gas_limit);
+            assert_eq!(meter.charge_txn(&txn), true);
+            assert_eq!(meter.remaining_gas(), write_gas);
+            meter.put(&key, state(100));
+            assert_eq!(meter.gas_used(), gas_limit);
+            assert_eq!(meter.remaining_gas(), 0);
+            assert_eq!(meter.out_of_gas(), false);
+            // nothing left for the read
+            assert_eq!(meter.get(&key), None);
+            assert_eq!(meter.out_of_gas(), true);
+            assert_eq!(meter.charge_gas(1), Err(out_of_gas_error()));
+            assert_eq!(meter.gas_used(), gas_limit);
+        }
+        assert_eq!(state_context.get(&key), Some(state(100)));
+        {
+            let mut meter = GasMeter::new(&mut state_context, write_gas - 1);
+            meter.put(&String::from("other"), state(100));
+            assert_eq!(meter.out_of_gas(), true);
+        }
+        // writes past the limit are dropped
+        assert_eq!(state_context.contains(&String::from("other")), false);
+    }
+}
@@ -1,6 +1,13 @@
 #[macro_use]
 extern crate exonum_derive;
 
+pub mod abi;
+pub mod block_context;
+pub mod errors;
+pub mod gas;
 pub mod signed_transaction;
 pub mod state;
+pub mod storage;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod traits;
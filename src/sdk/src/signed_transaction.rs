@@ -1,5 +1,8 @@
+use std::collections::{BTreeMap, HashMap};
 use std::convert::AsRef;
-use utils::serializer::{Deserialize, Serialize};
+use utils::configreader::BlockConfig;
+use utils::keypair::{CryptoKeypair, Keypair, KeypairType, PublicKey, Verify};
+use utils::serializer::{serialize, Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, BinaryValue, ObjectHash)]
 #[binary_value(codec = "bincode")]
@@ -7,5 +10,174 @@ pub struct SignedTransaction {
     pub txn: ::std::vec::Vec<u8>,
     pub app_name: String,
     pub header: ::std::collections::HashMap<std::string::String, std::string::String>,
+    /// hex encoded public key of the account which signed the envelope
+    pub signer: String,
     pub signature: std::vec::Vec<u8>,
 }
+
+/// SigningEnvelope is the canonical form of a SignedTransaction which gets signed.
+/// header is kept in a BTreeMap so entries are always encoded in key order.
+#[derive(Serialize)]
+struct SigningEnvelope<'a> {
+    app_name: &'a String,
+    header: BTreeMap<&'a String, &'a String>,
+    signer: &'a String,
+    txn: &'a Vec<u8>,
+}
+
+impl SignedTransaction {
+    /// create a new SignedTransaction, signing app_name, header & txn payload with kp
+    pub fn create(
+        kp: &KeypairType,
+        app_name: &str,
+        header: HashMap<String, String>,
+        txn: Vec<u8>,
+    ) -> SignedTransaction {
+        let mut signed_txn = SignedTransaction {
+            txn,
+            app_name: String::from(app_name),
+            header,
+            signer: hex::encode(kp.public().encode()),
+            signature: vec![],
+        };
+        signed_txn.sign(kp);
+        signed_txn
+    }
+
+    /// deterministic bytes covered by the signature
+    pub fn signing_payload(&self) -> Result<Vec<u8>, String> {
+        let envelope = SigningEnvelope {
+            app_name: &self.app_name,
+            header: self.header.iter().collect(),
+            signer: &self.signer,
+            txn: &self.txn,
+        };
+        serialize(&envelope)
+    }
+
+    /// re-sign the envelope, signer is replaced by the kp public key
+    pub fn sign(&mut self, kp: &KeypairType) {
+        self.signer = hex::encode(kp.public().encode());
+        self.signature = match self.signing_payload() {
+            Result::Ok(payload) => Keypair::sign(&kp, &payload),
+            Result::Err(_) => vec![],
+        };
+    }
+
+    /// fee offered in the "fee" header, 0 when it is not set
+    pub fn fee(&self) -> u64 {
+        match self.header.get(&String::from("fee")) {
+            Some(fee) => fee.parse::<u64>().unwrap_or(0),
+            None => 0,
+        }
+    }
+
+    /// gas limit asked in the "gas_limit" header, None when it is not set
+    pub fn gas_limit(&self) -> Option<u64> {
+        match self.header.get(&String::from("gas_limit")) {
+            Some(gas_limit) => gas_limit.parse::<u64>().ok(),
+            None => None,
+        }
+    }
+
+    /// check txn against the size limits of block_config, the payload &
+    /// header keys and values together are bounded by max_txn_size
+    pub fn check_size(&self, block_config: &BlockConfig) -> Result<(), String> {
+        if self.header.len() as u64 > block_config.max_txn_headers {
+            return Err(format!(
+                "{} headers exceed the limit of {}",
+                self.header.len(),
+                block_config.max_txn_headers
+            ));
+        }
+        let size: usize = self.txn.len()
+            + self
+                .header
+                .iter()
+                .map(|(key, value)| key.len() + value.len())
+                .sum::<usize>();
+        if size as u64 > block_config.max_txn_size {
+            return Err(format!(
+                "{} bytes exceed the transaction size limit of {} bytes",
+                size, block_config.max_txn_size
+            ));
+        }
+        Ok(())
+    }
+
+    /// verify signature of the envelope against signer public key
+    pub fn verify(&self) -> bool {
+        match self.signing_payload() {
+            Result::Ok(payload) => {
+                PublicKey::verify_from_encoded_pk(&self.signer, &payload, &self.signature.as_ref())
+            }
+            Result::Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_signed_transaction {
+    use super::*;
+    use utils::configreader::Configuration;
+
+    fn prepare_transaction(kp: &KeypairType) -> SignedTransaction {
+        let mut header = HashMap::default();
+        header.insert("timestamp".to_string(), "1000".to_string());
+        header.insert("fee".to_string(), "10".to_string());
+        SignedTransaction::create(kp, "MockApp", header, vec![1, 2, 3])
+    }
+
+    #[test]
+    fn test_signed_transaction_envelope() {
+        let kp = Keypair::generate();
+        let signed_txn = prepare_transaction(&kp);
+        assert_eq!(signed_txn.verify(), true);
+
+        // header order must not change the signed payload
+        let mut rebuilt_header = HashMap::default();
+        for (key, value) in signed_txn.header.iter() {
+            rebuilt_header.insert(key.clone(), value.clone());
+        }
+        let mut rebuilt = signed_txn.clone();
+        rebuilt.header = rebuilt_header;
+        assert_eq!(rebuilt.verify(), true);
+
+        let mut tampered = signed_txn.clone();
+        tampered
+            .header
+            .insert("timestamp".to_string(), "2000".to_string());
+        assert_eq!(tampered.verify(), false);
+
+        let mut tampered = signed_txn.clone();
+        tampered.app_name = String::from("OtherApp");
+        assert_eq!(tampered.verify(), false);
+
+        let mut tampered = signed_txn.clone();
+        tampered.txn = vec![3, 2, 1];
+        assert_eq!(tampered.verify(), false);
+
+        let mut tampered = signed_txn.clone();
+        tampered.signer = hex::encode(Keypair::generate().public().encode());
+        assert_eq!(tampered.verify(), false);
+    }
+
+    #[test]
+    fn test_signed_transaction_size() {
+        let config: Configuration = Configuration::from_file("../../config.toml").unwrap();
+        let block_config: &BlockConfig = &config.block_config;
+        let kp = Keypair::generate();
+        let signed_txn = prepare_transaction(&kp);
+        assert_eq!(signed_txn.check_size(block_config), Ok(()));
+
+        let mut oversized = signed_txn.clone();
+        oversized.txn = vec![0; block_config.max_txn_size as usize];
+        assert_eq!(oversized.check_size(block_config).is_err(), true);
+
+        let mut oversized = signed_txn.clone();
+        for index in 0..=block_config.max_txn_headers {
+            oversized.header.insert(index.to_string(), String::new());
+        }
+        assert_eq!(oversized.check_size(block_config).is_err(), true);
+    }
+}
@@ -0,0 +1,252 @@
+extern crate utils;
+use super::state::State;
+use super::traits::{ReadOnlyStateContext, StateContext};
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use utils::serializer::{deserialize, serialize, Serialize};
+
+/// read the value stored under key, None if key is missing or doesn't decode as V
+pub fn read_value<V: DeserializeOwned, C: ReadOnlyStateContext + ?Sized>(
+    state_context: &C,
+    key: &String,
+) -> Option<V> {
+    match state_context.get(key) {
+        Some(state) => match deserialize(state.get_data().as_slice()) {
+            Result::Ok(value) => Some(value),
+            Result::Err(_) => None,
+        },
+        None => None,
+    }
+}
+
+/// store value under key, keeping storage_root & code_hash of an existing State
+pub fn write_value<V: Serialize>(
+    state_context: &mut dyn StateContext,
+    key: &String,
+    value: &V,
+) -> bool {
+    let mut state: State = match state_context.get(key) {
+        Some(state) => state,
+        None => State::new(),
+    };
+    match serialize(value) {
+        Result::Ok(serialized_value) => state.set_data(&serialized_value),
+        Result::Err(_) => return false,
+    };
+    state_context.put(key, state);
+    true
+}
+
+/// first key after every key starting with prefix
+fn prefix_end(prefix: &String) -> String {
+    let mut end: Vec<char> = prefix.chars().collect();
+    while let Some(last) = end.pop() {
+        if let Some(next) = std::char::from_u32(last as u32 + 1) {
+            end.push(next);
+            return end.into_iter().collect();
+        }
+    }
+    String::new()
+}
+
+/// StateValue is a single typed value kept under one key
+pub struct StateValue<V> {
+    key: String,
+    _value: PhantomData<V>,
+}
+
+impl<V: Serialize + DeserializeOwned> StateValue<V> {
+    pub fn new(key: &str) -> StateValue<V> {
+        StateValue {
+            key: String::from(key),
+            _value: PhantomData,
+        }
+    }
+
+    pub fn get<C: ReadOnlyStateContext + ?Sized>(&self, state_context: &C) -> Option<V> {
+        read_value(state_context, &self.key)
+    }
+
+    pub fn set(&self, state_context: &mut dyn StateContext, value: &V) -> bool {
+        write_value(state_context, &self.key, value)
+    }
+
+    pub fn delete(&self, state_context: &mut dyn StateContext) {
+        state_context.delete(&self.key);
+    }
+}
+
+/// StateMap keeps typed values under "<prefix>/<key>", entries can be listed
+/// in key order
+pub struct StateMap<V> {
+    prefix: String,
+    _value: PhantomData<V>,
+}
+
+impl<V: Serialize + DeserializeOwned> StateMap<V> {
+    pub fn new(prefix: &str) -> StateMap<V> {
+        StateMap {
+            prefix: format!("{}/", prefix),
+            _value: PhantomData,
+        }
+    }
+
+    fn key(&self, key: &str) -> String {
+        format!("{}{}", self.prefix, key)
+    }
+
+    pub fn get<C: ReadOnlyStateContext + ?Sized>(&self, state_context: &C, key: &str) -> Option<V> {
+        read_value(state_context, &self.key(key))
+    }
+
+    pub fn put(&self, state_context: &mut dyn StateContext, key: &str, value: &V) -> bool {
+        write_value(state_context, &self.key(key), value)
+    }
+
+    pub fn contains<C: ReadOnlyStateContext + ?Sized>(&self, state_context: &C, key: &str) -> bool {
+        state_context.contains(&self.key(key))
+    }
+
+    pub fn delete(&self, state_context: &mut dyn StateContext, key: &str) {
+        state_context.delete(&self.key(key));
+    }
+
+    /// entries with key >= start in key order, at most limit of them
+    pub fn entries<C: ReadOnlyStateContext + ?Sized>(
+        &self,
+        state_context: &C,
+        start: &str,
+        limit: usize,
+    ) -> Vec<(String, V)> {
+        state_context
+            .range(&self.key(start), &prefix_end(&self.prefix), limit)
+            .into_iter()
+            .filter_map(
+                |(key, state)| match deserialize::<V>(state.get_data().as_slice()) {
+                    Result::Ok(value) => Some((key[self.prefix.len()..].to_string(), value)),
+                    Result::Err(_) => None,
+                },
+            )
+            .collect()
+    }
+}
+
+/// StateList is an append only typed list, its length is kept under
+/// "<prefix>/len" and items under zero padded indexes so they stay in order
+pub struct StateList<V> {
+    prefix: String,
+    _value: PhantomData<V>,
+}
+
+impl<V: Serialize + DeserializeOwned> StateList<V> {
+    pub fn new(prefix: &str) -> StateList<V> {
+        StateList {
+            prefix: String::from(prefix),
+            _value: PhantomData,
+        }
+    }
+
+    fn len_key(&self) -> String {
+        format!("{}/len", self.prefix)
+    }
+
+    fn item_key(&self, index: u64) -> String {
+        format!("{}/item/{:020}", self.prefix, index)
+    }
+
+    pub fn len<C: ReadOnlyStateContext + ?Sized>(&self, state_context: &C) -> u64 {
+        read_value(state_context, &self.len_key()).unwrap_or(0)
+    }
+
+    pub fn is_empty<C: ReadOnlyStateContext + ?Sized>(&self, state_context: &C) -> bool {
+        self.len(state_context) == 0
+    }
+
+    pub fn get<C: ReadOnlyStateContext + ?Sized>(
+        &self,
+        state_context: &C,
+        index: u64,
+    ) -> Option<V> {
+        read_value(state_context, &self.item_key(index))
+    }
+
+    pub fn push(&self, state_context: &mut dyn StateContext, value: &V) -> bool {
+        let length: u64 = self.len(state_context);
+        write_value(state_context, &self.item_key(length), value)
+            && write_value(state_context, &self.len_key(), &(length + 1))
+    }
+
+    /// items from index start onwards, at most limit of them
+    pub fn items<C: ReadOnlyStateContext + ?Sized>(
+        &self,
+        state_context: &C,
+        start: u64,
+        limit: usize,
+    ) -> Vec<V> {
+        state_context
+            .range(
+                &self.item_key(start),
+                &prefix_end(&format!("{}/item/", self.prefix)),
+                limit,
+            )
+            .into_iter()
+            .filter_map(|(_, state)| deserialize::<V>(state.get_data().as_slice()).ok())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test_storage {
+    use super::*;
+    use crate::testing::MockStateContext;
+
+    #[test]
+    fn test_state_value_and_map() {
+        let mut state_context = MockStateContext::default();
+        let value: StateValue<u64> = StateValue::new("counter");
+        assert_eq!(value.get(&state_context), None);
+        assert_eq!(value.set(&mut state_context, &10), true);
+        assert_eq!(value.get(&state_context), Some(10));
+        value.delete(&mut state_context);
+        assert_eq!(value.get(&state_context), None);
+
+        let map: StateMap<String> = StateMap::new("names");
+        let other_map: StateMap<String> = StateMap::new("names_other");
+        map.put(&mut state_context, "b", &String::from("bob"));
+        map.put(&mut state_context, "a", &String::from("alice"));
+        map.put(&mut state_context, "c", &String::from("carol"));
+        other_map.put(&mut state_context, "a", &String::from("other"));
+        assert_eq!(map.contains(&state_context, "a"), true);
+        assert_eq!(
+            map.entries(&state_context, "", 10),
+            vec![
+                (String::from("a"), String::from("alice")),
+                (String::from("b"), String::from("bob")),
+                (String::from("c"), String::from("carol")),
+            ]
+        );
+        assert_eq!(
+            map.entries(&state_context, "b", 1),
+            vec![(String::from("b"), String::from("bob"))]
+        );
+        map.delete(&mut state_context, "a");
+        assert_eq!(map.get(&state_context, "a"), None);
+        assert_eq!(map.entries(&state_context, "", 10).len(), 2);
+    }
+
+    #[test]
+    fn test_state_list() {
+        let mut state_context = MockStateContext::default();
+        let list: StateList<u64> = StateList::new("numbers");
+        assert_eq!(list.is_empty(&state_context), true);
+        for number in 0..12 {
+            assert_eq!(list.push(&mut state_context, &(number * 2)), true);
+        }
+        assert_eq!(list.len(&state_context), 12);
+        assert_eq!(list.get(&state_context, 11), Some(22));
+        assert_eq!(list.get(&state_context, 12), None);
+        // zero padded indexes keep item 10 after item 9
+        assert_eq!(list.items(&state_context, 9, 2), vec![18, 20]);
+        assert_eq!(list.items(&state_context, 0, 100).len(), 12);
+    }
+}
@@ -0,0 +1,63 @@
+use super::signed_transaction::SignedTransaction;
+use super::state::State;
+use super::traits::{ReadOnlyStateContext, StateContext};
+use exonum_crypto::Hash;
+use std::collections::{BTreeMap, HashMap};
+use utils::keypair::{CryptoKeypair, Keypair, KeypairType};
+
+/// fixed keypair, identical transactions signed with it hash the same
+/// across test steps
+pub fn test_keypair() -> KeypairType {
+    let mut secret_bytes =
+        hex::decode("97ba6f71a5311c4986e01798d525d0da8ee5c54acbf6ef7c3fadd1e2f624442f")
+            .expect("invalid secret");
+    Keypair::generate_from(secret_bytes.as_mut_slice())
+}
+
+/// in memory StateContext for the tests of apps, enabled by the testing feature
+#[derive(Default)]
+pub struct MockStateContext {
+    states: BTreeMap<String, State>,
+    txns: HashMap<Hash, SignedTransaction>,
+}
+
+impl ReadOnlyStateContext for MockStateContext {
+    fn get(&self, key: &String) -> Option<State> {
+        self.states.get(key).cloned()
+    }
+
+    fn contains(&self, key: &String) -> bool {
+        self.states.contains_key(key)
+    }
+
+    fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)> {
+        self.states
+            .range(start.clone()..)
+            .take_while(|(key, _)| end.is_empty() || *key < end)
+            .take(limit)
+            .map(|(key, state)| (key.clone(), state.clone()))
+            .collect()
+    }
+
+    fn get_txn(&self, key: &Hash) -> Option<SignedTransaction> {
+        self.txns.get(key).cloned()
+    }
+
+    fn contains_txn(&self, key: &Hash) -> bool {
+        self.txns.contains_key(key)
+    }
+}
+
+impl StateContext for MockStateContext {
+    fn put(&mut self, key: &String, state: State) {
+        self.states.insert(key.clone(), state);
+    }
+
+    fn delete(&mut self, key: &String) {
+        self.states.remove(key);
+    }
+
+    fn put_txn(&mut self, key: &Hash, txn: SignedTransaction) {
+        self.txns.insert(key.clone(), txn);
+    }
+}
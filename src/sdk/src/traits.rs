@@ -1,27 +1,135 @@
+use super::block_context::BlockContext;
+use super::errors::AppError;
 use super::signed_transaction::SignedTransaction;
 use super::state::State;
 use exonum_crypto::Hash;
 use exonum_merkledb::access::Access;
+use utils::serializer::{Deserialize, Serialize};
 
-pub trait StateContext {
-    fn put(&mut self, key: &String, state: State);
+/// ReadOnlyStateContext is the read half of StateContext. Queries get only
+/// this, so they can't change state.
+pub trait ReadOnlyStateContext {
     fn get(&self, key: &String) -> Option<State>;
     fn contains(&self, key: &String) -> bool;
-    fn put_txn(&mut self, key: &Hash, txn: SignedTransaction);
+    /// entries with start <= key < end in key order, at most limit of them.
+    /// An empty end means no upper bound.
+    fn range(&self, start: &String, end: &String, limit: usize) -> Vec<(String, State)>;
     fn get_txn(&self, key: &Hash) -> Option<SignedTransaction>;
     fn contains_txn(&self, key: &Hash) -> bool;
 }
 
+pub trait StateContext: ReadOnlyStateContext {
+    fn put(&mut self, key: &String, state: State);
+    /// remove key, nothing happens if key isn't present
+    fn delete(&mut self, key: &String);
+    fn put_txn(&mut self, key: &Hash, txn: SignedTransaction);
+    /// charge gas for work of the app beyond its state accesses, an error
+    /// means the txn is out of gas and should return it. Contexts that don't
+    /// meter gas accept any charge.
+    fn charge_gas(&mut self, _gas: u64) -> Result<(), AppError> {
+        Ok(())
+    }
+    /// gas the txn can still use, u64::MAX for contexts that don't meter gas
+    fn remaining_gas(&self) -> u64 {
+        u64::MAX
+    }
+}
+
+/// AppStorage is implemented by the node storage. Apps never get it directly,
+/// the node hands every app its own namespaced StateContext, so apps can't
+/// read or overwrite keys of another app.
+pub trait AppStorage: StateContext {
+    fn app_context(&mut self, app_name: &String) -> Box<dyn StateContext + '_>;
+}
+
 pub trait AppHandler {
-    fn execute(&self, txn: &SignedTransaction, state_context: &mut dyn StateContext) -> bool;
+    /// apply txn on state_context, an error marks the txn as failed and
+    /// discards every write it made to state_context.
+    /// execute must be deterministic, block_context is the only source of
+    /// time & randomness an app may use.
+    fn execute(
+        &self,
+        txn: &SignedTransaction,
+        state_context: &mut dyn StateContext,
+        block_context: &BlockContext,
+    ) -> Result<(), AppError>;
     fn name(&self) -> String;
+    /// version of the app, matched against the on-chain app registry. Apps
+    /// usually return env!("CARGO_PKG_VERSION")
+    fn version(&self) -> String {
+        String::from("0.1.0")
+    }
+    /// stateless checks of txn, run when a client submits it so a txn which
+    /// can never succeed doesn't enter the pool
+    fn validate(&self, _txn: &SignedTransaction) -> Result<(), AppError> {
+        Ok(())
+    }
+    /// true if execute charges the fee offered in the "fee" header of its
+    /// transactions. The pool only orders transactions of such apps by fee,
+    /// the fee header of other apps is ignored.
+    fn charges_fee(&self) -> bool {
+        false
+    }
+    /// called once per block after its transactions are executed, author is the
+    /// block proposer (Block::peer_id). Apps settle block level state like fees here.
+    fn finalize_block(
+        &self,
+        _author: &String,
+        _state_context: &mut dyn StateContext,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+    /// write the initial state of the app into a new chain. This is the only
+    /// place an app may read its local config: every node runs it on its
+    /// genesis block and the genesis state root must match the one of the
+    /// genesis node, so all nodes need the same values.
+    fn init_genesis(&self, _state_context: &mut dyn StateContext) -> Result<(), AppError> {
+        Ok(())
+    }
+    /// move the keys this app wrote before app namespaces existed out of
+    /// legacy into state_context. legacy lists the pre-namespace keys of all
+    /// apps, deleting a key there takes it over, writes to it are ignored.
+    /// The node calls this once per app at a block boundary, outside of txn
+    /// gas. An error discards the migration, it is retried at the next block.
+    fn migrate_legacy_state(
+        &self,
+        _legacy: &mut dyn StateContext,
+        _state_context: &mut dyn StateContext,
+    ) -> Result<(), AppError> {
+        Ok(())
+    }
+    /// answer a read only query of a client, method & args encoding are app
+    /// defined and so is the encoding of the returned bytes
+    fn query(
+        &self,
+        method: &String,
+        _args: &Vec<u8>,
+        _state_context: &dyn ReadOnlyStateContext,
+    ) -> Result<Vec<u8>, AppError> {
+        Err(AppError::new(
+            0,
+            &format!("{} doesn't support query {}", self.name(), method),
+        ))
+    }
+}
+
+/// AppQuery is the body of a client/query/{app_name} request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppQuery {
+    pub method: String,
+    pub args: Vec<u8>,
 }
 
 pub trait PoolTrait<T: Access, StateObj, TransactionObj> {
-    fn execute_transactions(&self, state_context: &mut dyn StateContext) -> (Vec<Hash>, Vec<Hash>);
+    fn execute_transactions(
+        &self,
+        storage: &mut dyn AppStorage,
+        block_context: &BlockContext,
+    ) -> (Vec<Hash>, Vec<Hash>);
     fn update_transactions(
         &self,
-        state_context: &mut dyn StateContext,
+        storage: &mut dyn AppStorage,
         hash_vec: &Vec<Hash>,
+        block_context: &BlockContext,
     ) -> bool;
 }
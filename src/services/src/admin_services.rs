@@ -0,0 +1,62 @@
+use actix_web::{web, HttpResponse};
+use libp2p::PeerId;
+use p2plib::peer_manager::{PeerInfo, PeerManager};
+use std::sync::Mutex;
+use utils::queue_metrics::{QueueMetrics, QueueStats};
+use utils::serializer::{deserialize, serialize};
+
+pub struct AdminServices {}
+
+impl AdminServices {
+    /// connected, banned & scored peers as a serialized Vec<PeerInfo>
+    pub fn list_peers_service(peer_manager: &Mutex<PeerManager>) -> HttpResponse {
+        let peers: Vec<PeerInfo> = peer_manager.lock().unwrap().peers();
+        if let Ok(serialized_peers) = serialize(&peers) {
+            return HttpResponse::Ok().body(serialized_peers);
+        }
+        HttpResponse::BadRequest().body("BadRequest")
+    }
+
+    /// capacity, queued & dropped counts of the message queues as a
+    /// serialized Vec<QueueStats>
+    pub fn list_queues_service(queue_metrics: &Mutex<QueueMetrics>) -> HttpResponse {
+        let queues: Vec<QueueStats> = queue_metrics.lock().unwrap().stats();
+        if let Ok(serialized_queues) = serialize(&queues) {
+            return HttpResponse::Ok().body(serialized_queues);
+        }
+        HttpResponse::BadRequest().body("BadRequest")
+    }
+
+    /// ban the serialized peer id for ban_duration seconds
+    pub fn ban_peer_service(
+        peer_manager: &Mutex<PeerManager>,
+        ban_duration: u64,
+        peer_id: web::Bytes,
+    ) -> HttpResponse {
+        match deserialize::<String>(&peer_id).map(|peer_id| peer_id.parse::<PeerId>()) {
+            Ok(Ok(peer_id)) => {
+                info!("peer {} banned by admin", peer_id);
+                peer_manager.lock().unwrap().ban(&peer_id, ban_duration);
+                HttpResponse::Ok().body("peer banned")
+            }
+            _ => HttpResponse::BadRequest().body("peer id couldn't deserialize"),
+        }
+    }
+
+    pub fn unban_peer_service(
+        peer_manager: &Mutex<PeerManager>,
+        peer_id: web::Bytes,
+    ) -> HttpResponse {
+        match deserialize::<String>(&peer_id).map(|peer_id| peer_id.parse::<PeerId>()) {
+            Ok(Ok(peer_id)) => {
+                if peer_manager.lock().unwrap().unban(&peer_id) {
+                    info!("peer {} unbanned by admin", peer_id);
+                    HttpResponse::Ok().body("peer unbanned")
+                } else {
+                    HttpResponse::NotFound().body("peer isn't banned")
+                }
+            }
+            _ => HttpResponse::BadRequest().body("peer id couldn't deserialize"),
+        }
+    }
+}
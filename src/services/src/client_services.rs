@@ -1,27 +1,56 @@
 use actix_web::{web, HttpResponse};
-use db_service::db_layer::snapshot_db;
 use db_service::db_snapshot_ref::SchemaSnap;
+use db_service::node_context::NodeContext;
 use exonum_crypto::Hash;
-use futures::channel::mpsc::*;
 use message_handler::message_sender::MessageSender;
-use message_handler::messages::MessageTypes;
+use message_handler::queues::GossipSender;
 use schema::signed_transaction::SignedTransaction;
-use schema::transaction_pool::{TxnPool, TxnPoolKeyType, POOL};
-use utils::serializer::{deserialize, serialize};
+use schema::transaction_pool::{TxnPool, TxnPoolKeyType};
+use sdk::traits::AppQuery;
+use utils::configreader::BlockConfig;
+use utils::serializer::{deserialize, deserialize_bounded, serialize};
 
 pub struct ClientServices {}
 
 impl ClientServices {
     pub fn submit_transaction_service(
+        context: &NodeContext,
         transaction: web::Bytes,
-        sender: &mut Sender<Option<MessageTypes>>,
+        sender: &mut GossipSender,
     ) -> HttpResponse {
-        if let Ok(txn) = deserialize::<SignedTransaction>(&transaction) {
+        // the body is within max_message_size, the PayloadConfig of the
+        // controller refuses larger ones before they are read
+        let block_config: &BlockConfig = &context.config.block_config;
+        if let Ok(txn) =
+            deserialize_bounded::<SignedTransaction>(&transaction, block_config.max_message_size)
+        {
             debug!("submit_transaction {:?}", txn);
+            if let Err(error) = txn.check_size(block_config) {
+                return HttpResponse::PayloadTooLarge().body(error);
+            }
+            if !txn.verify() {
+                return HttpResponse::BadRequest().body("txn signature couldn't verify");
+            }
+            let app = context
+                .apps
+                .lock()
+                .unwrap()
+                .appdata
+                .get(&txn.app_name)
+                .cloned();
+            if let Some(app) = app {
+                if let Err(error) = app.lock().unwrap().validate(&txn) {
+                    return HttpResponse::BadRequest().body(error.to_string());
+                }
+            }
             if let Some(string) = txn.header.get(&String::from("timestamp")) {
                 if let Ok(timestamp) = string.parse::<TxnPoolKeyType>() {
-                    POOL.insert_op(&timestamp, &txn);
-                    MessageSender::send_transaction_msg(sender, txn);
+                    // a txn which can't be gossiped isn't pooled, the client retries
+                    if !MessageSender::send_transaction_msg(sender, txn.clone()) {
+                        return HttpResponse::ServiceUnavailable()
+                            .body("node is busy, txn queue is full");
+                    }
+                    context.pool.insert_op(&timestamp, &txn);
                     if let Ok(serialized_string) = serialize(&String::from("txn added in the pool"))
                     {
                         return HttpResponse::Ok().body(serialized_string);
@@ -33,10 +62,13 @@ impl ClientServices {
         HttpResponse::BadRequest().body("txn couldn't deserialize")
     }
 
-    pub fn fetch_pending_transaction_service(transaction_hash: web::Bytes) -> HttpResponse {
+    pub fn fetch_pending_transaction_service(
+        context: &NodeContext,
+        transaction_hash: web::Bytes,
+    ) -> HttpResponse {
         if let Ok(txn_hash) = deserialize::<Hash>(&transaction_hash) {
             debug!("fetch_pending_transaction {:?}", txn_hash);
-            if let Some(transaction) = POOL.get(&txn_hash) {
+            if let Some(transaction) = context.pool.get(&txn_hash) {
                 if let Ok(serialized_transaction) = serialize(&transaction) {
                     return HttpResponse::Ok().body(serialized_transaction);
                 };
@@ -46,10 +78,13 @@ impl ClientServices {
         HttpResponse::BadRequest().body("txn_hash couldn't deserialize")
     }
 
-    pub fn fetch_confirm_transaction_service(transaction_hash: web::Bytes) -> HttpResponse {
+    pub fn fetch_confirm_transaction_service(
+        context: &NodeContext,
+        transaction_hash: web::Bytes,
+    ) -> HttpResponse {
         if let Ok(txn_hash) = deserialize::<Hash>(&transaction_hash) {
             debug!("fetch_confirm_transaction {:?}", txn_hash);
-            let snapshot = snapshot_db();
+            let snapshot = context.db.snapshot();
             let schema = SchemaSnap::new(&snapshot);
             if let Some(transaction) = schema.get_transaction(txn_hash) {
                 if let Ok(serialized_transaction) = serialize(&transaction) {
@@ -61,10 +96,10 @@ impl ClientServices {
         HttpResponse::BadRequest().body("txn_hash couldn't deserialize")
     }
 
-    pub fn fetch_state_service(address: web::Bytes) -> HttpResponse {
+    pub fn fetch_state_service(context: &NodeContext, address: web::Bytes) -> HttpResponse {
         if let Ok(public_address) = deserialize::<String>(&address) {
             debug!("fetch_state {:?}", public_address);
-            let snapshot = snapshot_db();
+            let snapshot = context.db.snapshot();
             let schema = SchemaSnap::new(&snapshot);
             if let Some(state) = schema.get_state(public_address) {
                 if let Ok(serialized_state) = serialize(&state) {
@@ -76,36 +111,58 @@ impl ClientServices {
         HttpResponse::BadRequest().body("string couldn't deserialize")
     }
 
-    pub fn fetch_block_peer_service(address: web::Bytes) -> HttpResponse {
-        if let Ok(block_index) = deserialize::<u64>(&address) {
-            debug!("fetch_block {:?}", block_index);
-            let snapshot = snapshot_db();
+    /// state of key in the namespace of app_name
+    pub fn fetch_app_state_service(
+        context: &NodeContext,
+        app_name: &String,
+        key: web::Bytes,
+    ) -> HttpResponse {
+        if let Ok(state_key) = deserialize::<String>(&key) {
+            debug!("fetch_app_state {:?} {:?}", app_name, state_key);
+            let snapshot = context.db.snapshot();
             let schema = SchemaSnap::new(&snapshot);
-            if let Some(block) = schema.get_block(block_index) {
-                if let Ok(serialized_block) = serialize(&block) {
-                    return HttpResponse::Ok().body(serialized_block);
+            if let Some(state) = schema.get_app_state(app_name, &state_key) {
+                if let Ok(serialized_state) = serialize(&state) {
+                    return HttpResponse::Ok().body(serialized_state);
                 }
             }
             return HttpResponse::BadRequest().body("BadRequest");
         }
-        HttpResponse::BadRequest().body("block index couldn't deserialize")
+        HttpResponse::BadRequest().body("string couldn't deserialize")
     }
 
-    pub fn fetch_latest_block_peer_service() -> HttpResponse {
-        let snapshot = snapshot_db();
-        let schema = SchemaSnap::new(&snapshot);
-        if let Some(block) = schema.get_root_block() {
-            if let Ok(serialized_block) = serialize(&block) {
-                return HttpResponse::Ok().body(serialized_block);
-            }
+    /// run a read only query of app_name on the latest snapshot, the response
+    /// body is whatever the app returns
+    pub fn query_service(
+        context: &NodeContext,
+        app_name: &String,
+        query: web::Bytes,
+    ) -> HttpResponse {
+        if let Ok(app_query) = deserialize::<AppQuery>(&query) {
+            debug!("query {:?} {:?}", app_name, app_query.method);
+            let app = match context.apps.lock().unwrap().appdata.get(app_name) {
+                Some(app) => app.clone(),
+                None => return HttpResponse::NotFound().body("unknown app"),
+            };
+            let snapshot = context.db.snapshot();
+            let schema = SchemaSnap::new(&snapshot);
+            let app_context = schema.app_context(app_name);
+            return match app
+                .lock()
+                .unwrap()
+                .query(&app_query.method, &app_query.args, &app_context)
+            {
+                Ok(result) => HttpResponse::Ok().body(result),
+                Err(error) => HttpResponse::BadRequest().body(error.to_string()),
+            };
         }
-        return HttpResponse::BadRequest().body("BadRequest");
+        HttpResponse::BadRequest().body("query couldn't deserialize")
     }
 
-    pub fn fetch_block_service(address: web::Bytes) -> HttpResponse {
+    pub fn fetch_block_service(context: &NodeContext, address: web::Bytes) -> HttpResponse {
         if let Ok(block_index) = deserialize::<u64>(&address) {
             debug!("fetch_block {:?}", block_index);
-            let snapshot = snapshot_db();
+            let snapshot = context.db.snapshot();
             let schema = SchemaSnap::new(&snapshot);
             if let Some(block) = schema.get_block(block_index) {
                 let block_string: String = block.to_string_format();
@@ -118,8 +175,8 @@ impl ClientServices {
         HttpResponse::BadRequest().body("block index couldn't deserialize")
     }
 
-    pub fn fetch_latest_block_service() -> HttpResponse {
-        let snapshot = snapshot_db();
+    pub fn fetch_latest_block_service(context: &NodeContext) -> HttpResponse {
+        let snapshot = context.db.snapshot();
         let schema = SchemaSnap::new(&snapshot);
         if let Some(block) = schema.get_root_block() {
             let block_string: String = block.to_string_format();
@@ -130,42 +187,12 @@ impl ClientServices {
         return HttpResponse::BadRequest().body("BadRequest");
     }
 
-    pub fn fetch_blockchain_length_peer_service() -> HttpResponse {
-        let snapshot = snapshot_db();
-        let schema = SchemaSnap::new(&snapshot);
-        if let Ok(serialized_length) = serialize(&schema.get_blockchain_length()) {
-            return HttpResponse::Ok().body(serialized_length);
-        }
-        HttpResponse::BadRequest().body("BadRequest")
-    }
-
-    pub fn fetch_blockchain_length_service() -> HttpResponse {
-        let snapshot = snapshot_db();
+    pub fn fetch_blockchain_length_service(context: &NodeContext) -> HttpResponse {
+        let snapshot = context.db.snapshot();
         let schema = SchemaSnap::new(&snapshot);
         if let Ok(serialized_length) = serialize(&schema.get_blockchain_length()) {
             return HttpResponse::Ok().body(serialized_length);
         }
         HttpResponse::BadRequest().body("BadRequest")
     }
-
-    pub fn fetch_transaction_peer_service(transaction_hash: web::Bytes) -> HttpResponse {
-        if let Ok(txn_hash) = deserialize::<Hash>(&transaction_hash) {
-            debug!("fetching transaction for peer {:?}", txn_hash);
-            if let Some(transaction) = POOL.get(&txn_hash) {
-                if let Ok(serialized_transaction) = serialize(&transaction) {
-                    return HttpResponse::Ok().body(serialized_transaction);
-                };
-            } else {
-                let snapshot = snapshot_db();
-                let schema = SchemaSnap::new(&snapshot);
-                if let Some(transaction) = schema.get_transaction(txn_hash) {
-                    if let Ok(serialized_transaction) = serialize(&transaction) {
-                        return HttpResponse::Ok().body(serialized_transaction);
-                    };
-                }
-                return HttpResponse::BadRequest().body("BadRequest");
-            }
-        }
-        HttpResponse::BadRequest().body("txn_hash couldn't deserialize")
-    }
 }
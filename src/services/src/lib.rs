@@ -1,5 +1,6 @@
 #[macro_use]
 extern crate log;
 
+pub mod admin_services;
 pub mod client_services;
 pub mod tests;
@@ -5,39 +5,35 @@ mod test_controller_services {
         dev::{Body, ResponseBody},
         web, HttpResponse,
     };
-    use db_service::{
-        db_fork_ref::SchemaFork,
-        db_layer::{fork_db, patch_db},
-    };
+    use db_service::{db_fork_ref::SchemaFork, db_layer::Db, node_context::NodeContext};
     use exonum_crypto::Hash;
     use exonum_merkledb::ObjectHash;
-    use futures::channel::mpsc::*;
-    use message_handler::messages::MessageTypes;
-    use schema::block::SignedBlock;
+    use message_handler::queues::gossip_channel;
+    use schema::appdata::AppData;
     use schema::signed_transaction::SignedTransaction;
     use schema::state::State;
-    use schema::transaction_pool::TxnPoolKeyType;
-    use sdk::traits::StateContext;
+    use schema::transaction_pool::{TxnPool, TxnPoolKeyType};
+    use sdk::block_context::BlockContext;
+    use sdk::errors::AppError;
+    use sdk::testing::test_keypair;
+    use sdk::traits::{AppHandler, AppQuery, AppStorage, ReadOnlyStateContext, StateContext};
     use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
     use std::time::SystemTime;
     use std::{thread, time::Duration};
-    use utils::configreader::initialize_config;
+    use utils::configreader::Configuration;
     use utils::serializer::{deserialize, serialize};
 
-    fn test_submit_transaction_service() {
-        let (mut sender, mut receiver) = channel::<Option<MessageTypes>>(4194304);
+    fn test_submit_transaction_service(context: &NodeContext) {
+        let (mut sender, mut receiver) = gossip_channel(&context.queue_metrics);
         let mut header = HashMap::default();
         let time_stamp: TxnPoolKeyType = 6565656565;
         header.insert("timestamp".to_string(), time_stamp.to_string());
-        let signed_transaction: SignedTransaction = SignedTransaction {
-            txn: vec![0],
-            app_name: String::from("app_name"),
-            header,
-            signature: vec![0],
-        };
+        let signed_transaction: SignedTransaction =
+            SignedTransaction::create(&test_keypair(), "app_name", header, vec![0]);
         let transaction: web::Bytes = web::Bytes::from(serialize(&signed_transaction).unwrap());
         let mut http_response: HttpResponse =
-            ClientServices::submit_transaction_service(transaction, &mut sender);
+            ClientServices::submit_transaction_service(context, transaction, &mut sender);
         if http_response.status() == 200 {
             let response_body: ResponseBody<Body> = http_response.take_body();
             let body_ref = response_body.as_ref().unwrap();
@@ -58,39 +54,38 @@ mod test_controller_services {
         if let None = data {
             panic!("transaction couldn't able to submit");
         }
+
+        // a txn which can't be queued for gossip, the queue being full, is refused & not pooled
+        let (mut full_sender, _full_receiver) = gossip_channel(&context.queue_metrics);
+        while full_sender.try_send(None).is_ok() {}
+        assert_eq!(full_sender.try_send(None).unwrap_err().is_full(), true);
+        let mut header = HashMap::default();
+        header.insert("timestamp".to_string(), (time_stamp + 1).to_string());
+        let refused_transaction: SignedTransaction =
+            SignedTransaction::create(&test_keypair(), "app_name", header, vec![1]);
+        let transaction: web::Bytes = web::Bytes::from(serialize(&refused_transaction).unwrap());
+        let http_response: HttpResponse =
+            ClientServices::submit_transaction_service(context, transaction, &mut full_sender);
+        assert_eq!(http_response.status(), 503);
+        assert_eq!(
+            context
+                .pool
+                .get(&refused_transaction.object_hash())
+                .is_none(),
+            true
+        );
     }
 
-    fn test_fetch_pending_transaction_service() {
+    fn test_fetch_pending_transaction_service(context: &NodeContext) {
         let time_stamp: TxnPoolKeyType = 6565656565;
         let mut header = HashMap::default();
         header.insert("timestamp".to_string(), time_stamp.to_string());
-        let signed_transaction: SignedTransaction = SignedTransaction {
-            txn: vec![0],
-            app_name: String::from("app_name"),
-            header,
-            signature: vec![0],
-        };
+        let signed_transaction: SignedTransaction =
+            SignedTransaction::create(&test_keypair(), "app_name", header, vec![0]);
         let txn_hash: Hash = signed_transaction.object_hash();
         let transaction_hash: web::Bytes = web::Bytes::from(serialize(&txn_hash).unwrap());
         let mut http_response: HttpResponse =
-            ClientServices::fetch_pending_transaction_service(transaction_hash.clone());
-        if http_response.status() == 200 {
-            let response_body: ResponseBody<Body> = http_response.take_body();
-            let body_ref = response_body.as_ref().unwrap();
-            let body_vec: Vec<u8> = match body_ref {
-                Body::None => panic!("invalid response body type"),
-                Body::Empty => panic!("invalid response body type"),
-                Body::Bytes(ref b) => b.to_vec(),
-                Body::Message(_) => panic!("invalid response body type"),
-            };
-            let output: SignedTransaction = deserialize(&body_vec).unwrap();
-            assert_eq!(txn_hash, output.object_hash());
-        } else {
-            panic!("http_response not equal to 200");
-        }
-
-        let mut http_response: HttpResponse =
-            ClientServices::fetch_transaction_peer_service(transaction_hash);
+            ClientServices::fetch_pending_transaction_service(context, transaction_hash);
         if http_response.status() == 200 {
             let response_body: ResponseBody<Body> = http_response.take_body();
             let body_ref = response_body.as_ref().unwrap();
@@ -107,46 +102,25 @@ mod test_controller_services {
         }
     }
 
-    fn test_fetch_confirm_transaction_service() {
+    fn test_fetch_confirm_transaction_service(context: &NodeContext) {
         let mut header = HashMap::default();
         let time_stamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
             .unwrap()
             .as_micros();
         header.insert("timestamp".to_string(), time_stamp.to_string());
-        let signed_transaction: SignedTransaction = SignedTransaction {
-            txn: vec![0],
-            app_name: String::from("app_name"),
-            header,
-            signature: vec![0],
-        };
+        let signed_transaction: SignedTransaction =
+            SignedTransaction::create(&test_keypair(), "app_name", header, vec![0]);
         let txn_hash: Hash = signed_transaction.object_hash();
-        let fork = fork_db();
+        let fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
             schema.put_txn(&txn_hash, signed_transaction.clone());
         }
-        patch_db(fork);
+        context.db.patch(fork);
         let transaction_hash: web::Bytes = web::Bytes::from(serialize(&txn_hash).unwrap());
         let mut http_response: HttpResponse =
-            ClientServices::fetch_confirm_transaction_service(transaction_hash.clone());
-        if http_response.status() == 200 {
-            let response_body: ResponseBody<Body> = http_response.take_body();
-            let body_ref = response_body.as_ref().unwrap();
-            let body_vec: Vec<u8> = match body_ref {
-                Body::None => panic!("invalid response body type"),
-                Body::Empty => panic!("invalid response body type"),
-                Body::Bytes(ref b) => b.to_vec(),
-                Body::Message(_) => panic!("invalid response body type"),
-            };
-            let output: SignedTransaction = deserialize(&body_vec).unwrap();
-            assert_eq!(txn_hash, output.object_hash())
-        } else {
-            panic!("http_response not equal to 200");
-        }
-
-        let mut http_response: HttpResponse =
-            ClientServices::fetch_transaction_peer_service(transaction_hash);
+            ClientServices::fetch_confirm_transaction_service(context, transaction_hash);
         if http_response.status() == 200 {
             let response_body: ResponseBody<Body> = http_response.take_body();
             let body_ref = response_body.as_ref().unwrap();
@@ -163,17 +137,18 @@ mod test_controller_services {
         }
     }
 
-    fn test_fetch_state_service() {
+    fn test_fetch_state_service(context: &NodeContext) {
         let state_key: String = String::from("dkcnjsdcnosdvnvsfv");
         let state: State = State::new();
-        let fork = fork_db();
+        let fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
             schema.put(&state_key, state.clone());
         }
-        patch_db(fork);
+        context.db.patch(fork);
         let state_key_bytes: web::Bytes = web::Bytes::from(serialize(&state_key).unwrap());
-        let mut http_response: HttpResponse = ClientServices::fetch_state_service(state_key_bytes);
+        let mut http_response: HttpResponse =
+            ClientServices::fetch_state_service(context, state_key_bytes);
         if http_response.status() == 200 {
             let response_body: ResponseBody<Body> = http_response.take_body();
             let body_ref = response_body.as_ref().unwrap();
@@ -190,15 +165,16 @@ mod test_controller_services {
         }
     }
 
-    fn fetch_block_peer_service() {
-        let fork = fork_db();
+    fn test_fetch_block_service(context: &NodeContext) {
+        let fork = context.db.fork();
         {
             let mut schema = SchemaFork::new(&fork);
-            schema.initialize_db(Vec::new(), 0);
+            schema.initialize_db(context, Vec::new(), 0);
         }
-        patch_db(fork);
+        context.db.patch(fork);
         let index_byes: web::Bytes = web::Bytes::from(serialize(&0).unwrap());
-        let mut http_response: HttpResponse = ClientServices::fetch_block_peer_service(index_byes);
+        let mut http_response: HttpResponse =
+            ClientServices::fetch_block_service(context, index_byes);
         if http_response.status() == 200 {
             let response_body: ResponseBody<Body> = http_response.take_body();
             let body_ref = response_body.as_ref().unwrap();
@@ -208,16 +184,14 @@ mod test_controller_services {
                 Body::Bytes(ref b) => b.to_vec(),
                 Body::Message(_) => panic!("invalid response body type"),
             };
-            let output: SignedBlock = deserialize(&body_vec).unwrap();
-            assert_eq!(0, output.block.id);
-            assert_eq!(output.validate(), false);
+            deserialize::<String>(&body_vec).unwrap();
         } else {
             panic!("http_response not equal to 200");
         }
     }
 
-    fn fetch_latest_block_peer_service() {
-        let mut http_response: HttpResponse = ClientServices::fetch_latest_block_peer_service();
+    fn test_fetch_latest_block_servic(context: &NodeContext) {
+        let mut http_response: HttpResponse = ClientServices::fetch_latest_block_service(context);
         if http_response.status() == 200 {
             let response_body: ResponseBody<Body> = http_response.take_body();
             let body_ref = response_body.as_ref().unwrap();
@@ -227,20 +201,15 @@ mod test_controller_services {
                 Body::Bytes(ref b) => b.to_vec(),
                 Body::Message(_) => panic!("invalid response body type"),
             };
-            let output: SignedBlock = deserialize(&body_vec).unwrap();
-            if output.block.id == 0 {
-                assert_eq!(output.validate(), false);
-            } else {
-                assert_eq!(output.validate(), true);
-            }
+            deserialize::<String>(&body_vec).unwrap();
         } else {
             panic!("http_response not equal to 200");
         }
     }
 
-    fn test_fetch_block_service() {
-        let index_byes: web::Bytes = web::Bytes::from(serialize(&0).unwrap());
-        let mut http_response: HttpResponse = ClientServices::fetch_block_service(index_byes);
+    fn test_fetch_blockchain_length_service(context: &NodeContext) {
+        let mut http_response: HttpResponse =
+            ClientServices::fetch_blockchain_length_service(context);
         if http_response.status() == 200 {
             let response_body: ResponseBody<Body> = http_response.take_body();
             let body_ref = response_body.as_ref().unwrap();
@@ -250,76 +219,113 @@ mod test_controller_services {
                 Body::Bytes(ref b) => b.to_vec(),
                 Body::Message(_) => panic!("invalid response body type"),
             };
-            deserialize::<String>(&body_vec).unwrap();
+            deserialize::<u64>(&body_vec).unwrap();
         } else {
             panic!("http_response not equal to 200");
         }
     }
 
-    fn test_fetch_latest_block_servic() {
-        let mut http_response: HttpResponse = ClientServices::fetch_latest_block_service();
-        if http_response.status() == 200 {
-            let response_body: ResponseBody<Body> = http_response.take_body();
-            let body_ref = response_body.as_ref().unwrap();
-            let body_vec: Vec<u8> = match body_ref {
-                Body::None => panic!("invalid response body type"),
-                Body::Empty => panic!("invalid response body type"),
-                Body::Bytes(ref b) => b.to_vec(),
-                Body::Message(_) => panic!("invalid response body type"),
-            };
-            deserialize::<String>(&body_vec).unwrap();
-        } else {
-            panic!("http_response not equal to 200");
+    /// answers a query with the data of the state kept under the method name
+    struct QueryApp {}
+
+    impl AppHandler for QueryApp {
+        fn execute(
+            &self,
+            _txn: &SignedTransaction,
+            _state_context: &mut dyn StateContext,
+            _block_context: &BlockContext,
+        ) -> Result<(), AppError> {
+            Ok(())
         }
-    }
 
-    fn test_fetch_blockchain_length_peer_service() {
-        let mut http_response: HttpResponse =
-            ClientServices::fetch_blockchain_length_peer_service();
-        if http_response.status() == 200 {
-            let response_body: ResponseBody<Body> = http_response.take_body();
-            let body_ref = response_body.as_ref().unwrap();
-            let body_vec: Vec<u8> = match body_ref {
-                Body::None => panic!("invalid response body type"),
-                Body::Empty => panic!("invalid response body type"),
-                Body::Bytes(ref b) => b.to_vec(),
-                Body::Message(_) => panic!("invalid response body type"),
-            };
-            deserialize::<u64>(&body_vec).unwrap();
-        } else {
-            panic!("http_response not equal to 200");
+        fn name(&self) -> String {
+            String::from("query_app")
+        }
+
+        fn query(
+            &self,
+            method: &String,
+            _args: &Vec<u8>,
+            state_context: &dyn ReadOnlyStateContext,
+        ) -> Result<Vec<u8>, AppError> {
+            match state_context.get(method) {
+                Some(state) => Ok(state.get_data().clone()),
+                None => Err(AppError::new(1, "no state")),
+            }
         }
     }
 
-    fn test_fetch_blockchain_length_service() {
-        let mut http_response: HttpResponse = ClientServices::fetch_blockchain_length_service();
-        if http_response.status() == 200 {
-            let response_body: ResponseBody<Body> = http_response.take_body();
-            let body_ref = response_body.as_ref().unwrap();
-            let body_vec: Vec<u8> = match body_ref {
-                Body::None => panic!("invalid response body type"),
-                Body::Empty => panic!("invalid response body type"),
-                Body::Bytes(ref b) => b.to_vec(),
-                Body::Message(_) => panic!("invalid response body type"),
-            };
-            deserialize::<u64>(&body_vec).unwrap();
-        } else {
-            panic!("http_response not equal to 200");
+    fn test_query_service(context: &NodeContext) {
+        let app_name: String = String::from("query_app");
+        let app: Box<dyn AppHandler + Send> = Box::new(QueryApp {});
+        context
+            .apps
+            .lock()
+            .unwrap()
+            .appdata
+            .insert(app_name.clone(), Arc::new(Mutex::new(app)));
+        let mut state: State = State::new();
+        state.set_data(&vec![4, 2]);
+        let fork = context.db.fork();
+        {
+            let mut schema = SchemaFork::new(&fork);
+            schema
+                .app_context(&app_name)
+                .put(&String::from("answer"), state);
         }
+        context.db.patch(fork);
+        // the app answers from the committed state of its namespace
+        let query = AppQuery {
+            method: String::from("answer"),
+            args: vec![],
+        };
+        let query_bytes: web::Bytes = web::Bytes::from(serialize(&query).unwrap());
+        let mut http_response: HttpResponse =
+            ClientServices::query_service(context, &app_name, query_bytes);
+        assert_eq!(http_response.status(), 200);
+        match http_response.take_body().as_ref().unwrap() {
+            Body::Bytes(ref b) => assert_eq!(b.to_vec(), vec![4, 2]),
+            _ => panic!("invalid response body type"),
+        }
+        // an app error is a bad request
+        let query = AppQuery {
+            method: String::from("question"),
+            args: vec![],
+        };
+        let query_bytes: web::Bytes = web::Bytes::from(serialize(&query).unwrap());
+        let http_response: HttpResponse =
+            ClientServices::query_service(context, &app_name, query_bytes);
+        assert_eq!(http_response.status(), 400);
+        context.apps.lock().unwrap().appdata.remove(&app_name);
+
+        let query = AppQuery {
+            method: String::from("balance"),
+            args: vec![],
+        };
+        let query_bytes: web::Bytes = web::Bytes::from(serialize(&query).unwrap());
+        let http_response: HttpResponse =
+            ClientServices::query_service(context, &String::from("unknown_app"), query_bytes);
+        assert_eq!(http_response.status(), 404);
+        let http_response: HttpResponse = ClientServices::query_service(
+            context,
+            &String::from("unknown_app"),
+            web::Bytes::from(vec![0, 1]),
+        );
+        assert_eq!(http_response.status(), 400);
     }
 
     #[test]
     fn test_controller_services() {
-        initialize_config("../../config.toml");
-        test_submit_transaction_service();
-        test_fetch_pending_transaction_service();
-        test_fetch_confirm_transaction_service();
-        test_fetch_state_service();
-        fetch_block_peer_service();
-        fetch_latest_block_peer_service();
-        test_fetch_block_service();
-        test_fetch_latest_block_servic();
-        test_fetch_blockchain_length_peer_service();
-        test_fetch_blockchain_length_service();
+        let config = Configuration::from_file("../../config.toml").unwrap();
+        let db = Db::open(&config.db.dbpath).unwrap();
+        let context = NodeContext::new(Arc::new(config), db, AppData::new());
+        test_submit_transaction_service(&context);
+        test_fetch_pending_transaction_service(&context);
+        test_fetch_confirm_transaction_service(&context);
+        test_fetch_state_service(&context);
+        test_fetch_block_service(&context);
+        test_fetch_latest_block_servic(&context);
+        test_fetch_blockchain_length_service(&context);
+        test_query_service(&context);
     }
 }
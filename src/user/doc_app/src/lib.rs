@@ -7,6 +7,8 @@ extern crate log;
 #[macro_use]
 extern crate serde;
 
+pub mod query;
 pub mod state;
+pub mod store;
 pub mod transaction;
 pub mod user_messages;
@@ -0,0 +1,60 @@
+extern crate utils;
+use crate::state::NFTToken;
+use crate::store::{confirmation_key, pending_view_key, pkg_key, token_key};
+use exonum_crypto::Hash;
+use sdk::errors::AppError;
+use sdk::storage::read_value;
+use sdk::traits::ReadOnlyStateContext;
+use serde::de::DeserializeOwned;
+use utils::serializer::{deserialize, serialize, Serialize};
+
+/// answer a client query. Supported methods, args is the serialized input:
+/// - "pending_review" address => Vec<(pkg_no, Vec<token hash>)> waiting for its review
+/// - "confirmation_list" address => Vec<token hash> transferred to it
+/// - "document" token hash => Option<NFTToken>
+/// - "package" pkg_no => Option<Vec<token hash>>
+/// The legacy registry blob isn't read, it is split into these keys at the
+/// first block after the upgrade.
+pub fn query(
+    method: &String,
+    args: &Vec<u8>,
+    state_context: &dyn ReadOnlyStateContext,
+) -> Result<Vec<u8>, AppError> {
+    if method == "pending_review" {
+        let address: String = parse_args(args)?;
+        let pkg_list: Vec<String> =
+            read_value(state_context, &pending_view_key(&address)).unwrap_or_default();
+        let pending_review: Vec<(String, Vec<Hash>)> = pkg_list
+            .into_iter()
+            .map(|pkg_no| {
+                let doc_list: Vec<Hash> =
+                    read_value(state_context, &pkg_key(&pkg_no)).unwrap_or_default();
+                (pkg_no, doc_list)
+            })
+            .collect();
+        to_bytes(&pending_review)
+    } else if method == "confirmation_list" {
+        let address: String = parse_args(args)?;
+        let doc_list: Vec<Hash> =
+            read_value(state_context, &confirmation_key(&address)).unwrap_or_default();
+        to_bytes(&doc_list)
+    } else if method == "document" {
+        let token_hash: Hash = parse_args(args)?;
+        let token: Option<NFTToken> = read_value(state_context, &token_key(&token_hash));
+        to_bytes(&token)
+    } else if method == "package" {
+        let pkg_no: String = parse_args(args)?;
+        let doc_list: Option<Vec<Hash>> = read_value(state_context, &pkg_key(&pkg_no));
+        to_bytes(&doc_list)
+    } else {
+        Err(AppError::new(2, &format!("unknown query {}", method)))
+    }
+}
+
+fn parse_args<T: DeserializeOwned>(args: &Vec<u8>) -> Result<T, AppError> {
+    deserialize(args.as_slice()).map_err(|_| AppError::new(3, "query args couldn't deserialize"))
+}
+
+fn to_bytes<T: Serialize>(value: &T) -> Result<Vec<u8>, AppError> {
+    serialize(value).map_err(|_| AppError::new(3, "query result couldn't serialize"))
+}
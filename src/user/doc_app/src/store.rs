@@ -0,0 +1,223 @@
+extern crate utils;
+use crate::state::{NFTToken, State as DocState};
+use exonum_crypto::Hash;
+use sdk::state::State;
+use sdk::traits::StateContext;
+use serde::de::DeserializeOwned;
+use utils::serializer::{deserialize, serialize, Serialize};
+
+/// prefix of all document_review keys in the state trie
+pub const DOC_KEY_PREFIX: &str = "document_review";
+
+/// legacy key of the registry blob, taken over by migrate_legacy_state
+pub const STATE_KEY: &str = "34132aec80149c4538bad4a15995ddf6a89d4ed5e39f0060e8466f6ba4dc9ceb";
+
+pub fn token_key(token_hash: &Hash) -> String {
+    format!("{}/token/{}", DOC_KEY_PREFIX, token_hash.to_hex())
+}
+
+pub fn file_hash_key(token_hash: &Hash) -> String {
+    format!("{}/file_hash/{}", DOC_KEY_PREFIX, token_hash.to_hex())
+}
+
+pub fn pkg_key(pkg_no: &String) -> String {
+    format!("{}/pkg/{}", DOC_KEY_PREFIX, pkg_no)
+}
+
+pub fn pending_view_key(address: &String) -> String {
+    format!("{}/pending_view/{}", DOC_KEY_PREFIX, address)
+}
+
+pub fn confirmation_key(address: &String) -> String {
+    format!("{}/confirmation/{}", DOC_KEY_PREFIX, address)
+}
+
+/// DocStore keeps every token, file hash, package and per address list under
+/// its own state key, so a txn only reads & writes the documents it touches.
+pub struct DocStore<'a> {
+    state_context: &'a mut dyn StateContext,
+}
+
+impl<'a> DocStore<'a> {
+    pub fn new(state_context: &'a mut dyn StateContext) -> DocStore<'a> {
+        DocStore { state_context }
+    }
+
+    fn get_value<T: DeserializeOwned>(&self, key: &String) -> Option<T> {
+        match self.state_context.get(key) {
+            Some(state) => match deserialize(state.get_data().as_slice()) {
+                Result::Ok(value) => Some(value),
+                Result::Err(_) => {
+                    error!("document state {:?} couldn't deserialize", key);
+                    None
+                }
+            },
+            None => None,
+        }
+    }
+
+    fn put_value<T: Serialize>(&mut self, key: &String, value: &T) -> bool {
+        let mut state: State = match self.state_context.get(key) {
+            Some(state) => state,
+            None => State::new(),
+        };
+        match serialize(value) {
+            Result::Ok(serialized_value) => state.set_data(&serialized_value),
+            Result::Err(_) => return false,
+        };
+        self.state_context.put(key, state);
+        true
+    }
+
+    pub fn get_nft_token(&self, token_hash: &Hash) -> Option<NFTToken> {
+        self.get_value(&token_key(token_hash))
+    }
+
+    pub fn contains_nft_token(&self, token_hash: &Hash) -> bool {
+        self.state_context.contains(&token_key(token_hash))
+    }
+
+    pub fn put_nft_token(&mut self, token_hash: &Hash, token: &NFTToken) -> bool {
+        self.put_value(&token_key(token_hash), token)
+    }
+
+    pub fn get_file_hash(&self, token_hash: &Hash) -> Option<Hash> {
+        self.get_value(&file_hash_key(token_hash))
+    }
+
+    pub fn put_file_hash(&mut self, token_hash: &Hash, file_hash: &Hash) -> bool {
+        self.put_value(&file_hash_key(token_hash), file_hash)
+    }
+
+    pub fn get_pkg_list(&self, pkg_no: &String) -> Option<Vec<Hash>> {
+        self.get_value(&pkg_key(pkg_no))
+    }
+
+    pub fn put_pkg_list(&mut self, pkg_no: &String, doc_list: &Vec<Hash>) -> bool {
+        self.put_value(&pkg_key(pkg_no), doc_list)
+    }
+
+    /// package numbers waiting for review by address
+    pub fn get_pending_view(&self, address: &String) -> Vec<String> {
+        self.get_value(&pending_view_key(address))
+            .unwrap_or_default()
+    }
+
+    pub fn put_pending_view(&mut self, address: &String, pkg_list: &Vec<String>) -> bool {
+        self.put_value(&pending_view_key(address), pkg_list)
+    }
+
+    /// tokens transferred to address and not yet packaged
+    pub fn get_confirmation_list(&self, address: &String) -> Option<Vec<Hash>> {
+        self.get_value(&confirmation_key(address))
+    }
+
+    pub fn put_confirmation_list(&mut self, address: &String, doc_list: &Vec<Hash>) -> bool {
+        self.put_value(&confirmation_key(address), doc_list)
+    }
+
+    /// split the legacy registry blob, kept under STATE_KEY before app
+    /// namespaces existed, into per item keys
+    pub fn migrate_legacy_state(&mut self, legacy_blob: &State) -> bool {
+        if legacy_blob.get_data().is_empty() {
+            return true;
+        }
+        let legacy_state: DocState = match deserialize(legacy_blob.get_data().as_slice()) {
+            Result::Ok(value) => value,
+            Result::Err(_) => return false,
+        };
+        for (token_hash, token) in legacy_state.tokens.iter() {
+            if !self.put_nft_token(token_hash, token) {
+                return false;
+            }
+        }
+        for (token_hash, file_hash) in legacy_state.file_hash.iter() {
+            if !self.put_file_hash(token_hash, file_hash) {
+                return false;
+            }
+        }
+        for (pkg_no, doc_list) in legacy_state.pkg_no.iter() {
+            if !self.put_pkg_list(pkg_no, doc_list) {
+                return false;
+            }
+        }
+        for (address, pkg_list) in legacy_state.pending_view.iter() {
+            if !self.put_pending_view(address, pkg_list) {
+                return false;
+            }
+        }
+        for (address, doc_list) in legacy_state.confirmation_list.iter() {
+            if !self.put_confirmation_list(address, doc_list) {
+                return false;
+            }
+        }
+        info!(
+            "document registry migrated, {:?} tokens moved to per document keys",
+            legacy_state.tokens.len()
+        );
+        true
+    }
+}
+
+#[cfg(test)]
+mod test_doc_store {
+    use super::*;
+    use crate::state::DocStatus;
+    use exonum_merkledb::ObjectHash;
+    use sdk::testing::MockStateContext;
+
+    #[test]
+    fn test_migrate_legacy_state() {
+        let token = NFTToken {
+            super_owner: String::from("aa"),
+            owner: String::from("aa"),
+            pkg_no: String::from("pkg_1"),
+            status: DocStatus::Submitted,
+        };
+        let token_hash = token.object_hash();
+        let mut legacy_state = DocState::new();
+        legacy_state.tokens.insert(token_hash, token.clone());
+        legacy_state.file_hash.insert(token_hash, Hash::zero());
+        legacy_state
+            .pkg_no
+            .insert(String::from("pkg_1"), vec![token_hash]);
+        legacy_state
+            .pending_view
+            .insert(String::from("bb"), vec![String::from("pkg_1")]);
+        let mut legacy_blob = State::new();
+        legacy_blob.set_data(&serialize(&legacy_state).unwrap());
+
+        let mut state_context = MockStateContext::default();
+        let mut store = DocStore::new(&mut state_context);
+        assert_eq!(store.migrate_legacy_state(&legacy_blob), true);
+        assert_eq!(store.get_nft_token(&token_hash), Some(token));
+        assert_eq!(store.get_file_hash(&token_hash), Some(Hash::zero()));
+        assert_eq!(
+            store.get_pkg_list(&String::from("pkg_1")),
+            Some(vec![token_hash])
+        );
+        assert_eq!(
+            store.get_pending_view(&String::from("bb")),
+            vec![String::from("pkg_1")]
+        );
+        assert_eq!(store.get_confirmation_list(&String::from("bb")), None);
+        // an emptied blob has nothing to migrate, a corrupt one fails
+        assert_eq!(store.migrate_legacy_state(&State::new()), true);
+        let mut corrupt_blob = State::new();
+        corrupt_blob.set_data(&vec![0xff]);
+        assert_eq!(store.migrate_legacy_state(&corrupt_blob), false);
+
+        let args: Vec<u8> = serialize(&String::from("bb")).unwrap();
+        let result: Vec<u8> =
+            crate::query::query(&String::from("pending_review"), &args, &state_context).unwrap();
+        let pending_review: Vec<(String, Vec<Hash>)> = deserialize(&result).unwrap();
+        assert_eq!(
+            pending_review,
+            vec![(String::from("pkg_1"), vec![token_hash])]
+        );
+        assert_eq!(
+            crate::query::query(&String::from("unknown"), &args, &state_context).is_err(),
+            true
+        );
+    }
+}
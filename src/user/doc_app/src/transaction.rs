@@ -1,22 +1,22 @@
 extern crate utils;
-use crate::state::{DocStatus, NFTToken, State as DocState};
+use crate::state::{DocStatus, NFTToken};
+use crate::store::DocStore;
+pub use crate::store::STATE_KEY;
 use crate::user_messages::{CryptoTransaction, DataTypes};
 use exonum_crypto::Hash;
 use exonum_merkledb::ObjectHash;
+use sdk::block_context::BlockContext;
+use sdk::errors::AppError;
 pub use sdk::signed_transaction::SignedTransaction;
-use sdk::state::State;
-use sdk::traits::{AppHandler, StateContext};
+use sdk::traits::{AppHandler, ReadOnlyStateContext, StateContext};
 use std::collections::HashMap;
-use std::convert::AsRef;
 use std::time::SystemTime;
-use utils::keypair::{CryptoKeypair, Keypair, KeypairType, PublicKey, Verify};
+use utils::keypair::{CryptoKeypair, Keypair, KeypairType};
 use utils::logger::logger_init_from_yml;
 use utils::serializer::{deserialize, serialize};
 
 const APPNAME: &str = "Document_Review";
 
-pub const STATE_KEY: &str = "34132aec80149c4538bad4a15995ddf6a89d4ed5e39f0060e8466f6ba4dc9ceb";
-
 trait StateTraits {
     fn execute(&self, state_context: &mut dyn StateContext) -> bool;
 }
@@ -65,15 +65,20 @@ impl TransactionTrait<CryptoTransaction> for CryptoTransaction {
 
 impl TransactionTrait<SignedTransaction> for SignedTransaction {
     fn validate(&self) -> bool {
+        // envelope signature is verified by the node before execution,
+        // only the binding between signer and the txn account is checked here
         let txn: CryptoTransaction = match deserialize(&self.txn) {
             Result::Ok(value) => value,
             Result::Err(_) => return false,
         };
-        PublicKey::verify_from_encoded_pk(&txn.from, &self.txn, &self.signature.as_ref())
+        txn.from == self.signer
     }
 
     fn sign(&self, kp: &KeypairType) -> Vec<u8> {
-        Keypair::sign(&kp, &self.txn)
+        match self.signing_payload() {
+            Result::Ok(payload) => Keypair::sign(&kp, &payload),
+            Result::Err(_) => vec![],
+        }
     }
 
     fn generate(kp: &KeypairType) -> SignedTransaction {
@@ -88,7 +93,6 @@ impl TransactionTrait<SignedTransaction> for SignedTransaction {
             fxn_call: String::from("set_hash"),
             payload,
         };
-        let txn_sign = txn.sign(&kp);
         let mut header = HashMap::default();
         let time_stamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -99,12 +103,7 @@ impl TransactionTrait<SignedTransaction> for SignedTransaction {
             Result::Ok(value) => value,
             Result::Err(_) => vec![0],
         };
-        SignedTransaction {
-            txn: serialized_txn,
-            app_name: String::from(APPNAME),
-            signature: txn_sign,
-            header,
-        }
+        SignedTransaction::create(&kp, APPNAME, header, serialized_txn)
     }
 
     fn get_hash(&self) -> Hash {
@@ -178,34 +177,14 @@ impl ModuleTraits for CryptoTransaction {
             DataTypes::HashVal(value) => value.clone(),
             _ => return false,
         };
-        let mut app_state: State = match state_context.get(&STATE_KEY.to_string()) {
-            Some(state) => state,
-            None => {
-                let doc_state: DocState = DocState::new();
-                let mut state: State = State::new();
-                let serialized_doc_state: Vec<u8> = match serialize(&doc_state) {
-                    Result::Ok(value) => value,
-                    Result::Err(_) => return false,
-                };
-                state.set_data(&serialized_doc_state);
-                state
-            }
-        };
-        let mut state: DocState = match deserialize(app_state.get_data().as_slice()) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        let flag: bool = state.set_hash(token_id, file_hash);
-        if !flag {
+        let mut store: DocStore = DocStore::new(state_context);
+        if store.get_file_hash(&token_id).is_some() {
             info!("operation set_hash failed");
             return false;
         }
-        let serialized_state: Vec<u8> = match serialize(&state) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        app_state.set_data(&serialized_state);
-        state_context.put(&STATE_KEY.to_string(), app_state);
+        if !store.put_file_hash(&token_id, &file_hash) {
+            return false;
+        }
         info!("operation set_hash done");
         true
     }
@@ -233,41 +212,23 @@ impl ModuleTraits for CryptoTransaction {
             DataTypes::VecHashVal(value) => value.clone(),
             _ => return false,
         };
-        let mut app_state: State = match state_context.get(&STATE_KEY.to_string()) {
-            Some(state) => state,
-            None => {
-                let doc_state: DocState = DocState::new();
-                let mut state: State = State::new();
-                let serialized_doc_state: Vec<u8> = match serialize(&doc_state) {
-                    Result::Ok(value) => value,
-                    Result::Err(_) => return false,
-                };
-                state.set_data(&serialized_doc_state);
-                state
+        let mut store: DocStore = DocStore::new(state_context);
+        for (index, each) in token_ids.iter().enumerate() {
+            if store.contains_nft_token(each) || token_ids[..index].contains(each) {
+                return false;
             }
-        };
-        let mut state: DocState = match deserialize(app_state.get_data().as_slice()) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
+        }
+        let token: NFTToken = NFTToken {
+            super_owner: self.from.clone(),
+            owner: self.from.clone(),
+            pkg_no: String::from(""),
+            status: DocStatus::Created,
         };
         for each in token_ids.iter() {
-            let token: NFTToken = NFTToken {
-                super_owner: self.from.clone(),
-                owner: self.from.clone(),
-                pkg_no: String::from(""),
-                status: DocStatus::Created,
-            };
-            let flag: bool = state.add_nft_token(each.clone(), token);
-            if !flag {
+            if !store.put_nft_token(each, &token) {
                 return false;
             }
         }
-        let serialized_state: Vec<u8> = match serialize(&state) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        app_state.set_data(&serialized_state);
-        state_context.put(&STATE_KEY.to_string(), app_state);
         true
     }
 
@@ -296,16 +257,9 @@ impl ModuleTraits for CryptoTransaction {
             DataTypes::StringVal(value) => value.clone(),
             _ => return false,
         };
-        let mut app_state: State = match state_context.get(&STATE_KEY.to_string()) {
-            Some(state) => state,
-            None => return false,
-        };
-        let mut state: DocState = match deserialize(app_state.get_data().as_slice()) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
+        let mut store: DocStore = DocStore::new(state_context);
         for each in token_ids.iter() {
-            match state.get_nft_token(each.clone()) {
+            match store.get_nft_token(each) {
                 Some(token) => {
                     if token.owner != self.from {
                         return false;
@@ -314,13 +268,12 @@ impl ModuleTraits for CryptoTransaction {
                 None => return false,
             }
         }
-        state.add_into_confirmation_list(&to_address, &token_ids);
-        let serialized_state: Vec<u8> = match serialize(&state) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        app_state.set_data(&serialized_state);
-        state_context.put(&STATE_KEY.to_string(), app_state);
+        let mut confirmation_list: Vec<Hash> =
+            store.get_confirmation_list(&to_address).unwrap_or_default();
+        confirmation_list.extend(token_ids.iter().cloned());
+        if !store.put_confirmation_list(&to_address, &confirmation_list) {
+            return false;
+        }
         true
     }
 
@@ -349,16 +302,9 @@ impl ModuleTraits for CryptoTransaction {
             DataTypes::StringVal(value) => value.clone(),
             _ => return false,
         };
-        let mut app_state: State = match state_context.get(&STATE_KEY.to_string()) {
-            Some(state) => state,
-            None => return false,
-        };
-        let mut state: DocState = match deserialize(app_state.get_data().as_slice()) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        let mut waiting_list: Vec<Hash> = match state.get_confirmation_waiting_list(&self.from) {
-            Some(list) => list.clone(),
+        let mut store: DocStore = DocStore::new(state_context);
+        let mut waiting_list: Vec<Hash> = match store.get_confirmation_list(&self.from) {
+            Some(list) => list,
             None => return false,
         };
         let mut token_map: HashMap<Hash, NFTToken> = HashMap::new();
@@ -366,34 +312,33 @@ impl ModuleTraits for CryptoTransaction {
             if !waiting_list.contains(each) {
                 return false;
             }
-            let token: NFTToken = match state.get_nft_token(each.clone()) {
+            let token: NFTToken = match store.get_nft_token(each) {
                 Some(token) => {
                     if token.status != DocStatus::Created {
                         return false;
                     }
-                    token.clone()
+                    token
                 }
                 None => return false,
             };
             token_map.insert(each.clone(), token);
         }
+        if store.get_pkg_list(&pkg_no).is_some() {
+            info!("package {:?} already exists", pkg_no);
+            return false;
+        }
         for (token_hash, token) in token_map.into_iter() {
             let mut token: NFTToken = token.clone();
             token.status = DocStatus::Submitted;
             token.pkg_no = pkg_no.clone();
-            state.replace_nft_token(token_hash.clone(), token);
+            if !store.put_nft_token(&token_hash, &token) {
+                return false;
+            }
             let index = waiting_list.iter().position(|&r| r == token_hash).unwrap();
             waiting_list.remove(index);
         }
-        state.set_pkg_list(&pkg_no, &token_ids);
-        state.update_confirmation_list(&self.from, &waiting_list);
-        let serialized_state: Vec<u8> = match serialize(&state) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        app_state.set_data(&serialized_state);
-        state_context.put(&STATE_KEY.to_string(), app_state);
-        true
+        store.put_pkg_list(&pkg_no, &token_ids)
+            && store.put_confirmation_list(&self.from, &waiting_list)
     }
 
     fn transfer_for_review(&self, state_context: &mut dyn StateContext) -> bool {
@@ -420,20 +365,13 @@ impl ModuleTraits for CryptoTransaction {
             DataTypes::StringVal(value) => value.clone(),
             _ => return false,
         };
-        let mut app_state: State = match state_context.get(&STATE_KEY.to_string()) {
-            Some(state) => state,
-            None => return false,
-        };
-        let mut state: DocState = match deserialize(app_state.get_data().as_slice()) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        let pkg_doc_list: Vec<Hash> = match state.get_pkg_list(&pkg_no) {
-            Some(list) => list.clone(),
+        let mut store: DocStore = DocStore::new(state_context);
+        let pkg_doc_list: Vec<Hash> = match store.get_pkg_list(&pkg_no) {
+            Some(list) => list,
             None => return false,
         };
         for each in pkg_doc_list.iter() {
-            match state.get_nft_token(each.clone()) {
+            match store.get_nft_token(each) {
                 Some(token) => {
                     if token.status != DocStatus::Submitted {
                         return false;
@@ -445,14 +383,9 @@ impl ModuleTraits for CryptoTransaction {
                 None => return false,
             };
         }
-        state.add_pkg_no_for_review(&reviewer_address, &pkg_no);
-        let serialized_state: Vec<u8> = match serialize(&state) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        app_state.set_data(&serialized_state);
-        state_context.put(&STATE_KEY.to_string(), app_state);
-        true
+        let mut pending_view: Vec<String> = store.get_pending_view(&reviewer_address);
+        pending_view.push(pkg_no.clone());
+        store.put_pending_view(&reviewer_address, &pending_view)
     }
 
     fn review_docs(&self, state_context: &mut dyn StateContext) -> bool {
@@ -480,57 +413,41 @@ impl ModuleTraits for CryptoTransaction {
             DataTypes::BoolVal(value) => value.clone(),
             _ => return false,
         };
-        let mut app_state: State = match state_context.get(&STATE_KEY.to_string()) {
-            Some(state) => state,
+        let mut store: DocStore = DocStore::new(state_context);
+        let mut pending_view: Vec<String> = store.get_pending_view(&self.from);
+        let pending_index: usize = match pending_view.iter().position(|r| r == &pkg_no) {
+            Some(index) => index,
             None => return false,
         };
-        let mut state: DocState = match deserialize(app_state.get_data().as_slice()) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        match state.get_pkg_review_pending_list(&self.from) {
-            Some(list) => {
-                if !list.contains(&pkg_no) {
-                    return false;
-                }
-            }
-            None => return false,
-        }
-        let pkg_doc_list: Vec<Hash> = match state.get_pkg_list(&pkg_no) {
-            Some(list) => list.clone(),
+        let pkg_doc_list: Vec<Hash> = match store.get_pkg_list(&pkg_no) {
+            Some(list) => list,
             None => return false,
         };
+        let mut tokens: Vec<NFTToken> = Vec::with_capacity(pkg_doc_list.len());
         for each in pkg_doc_list.iter() {
-            match state.get_nft_token(each.clone()) {
+            match store.get_nft_token(each) {
                 Some(token) => {
                     if token.status != DocStatus::Submitted {
                         return false;
                     }
+                    tokens.push(token);
                 }
                 None => return false,
             };
         }
-        if response_bool {
-            for each in pkg_doc_list.iter() {
-                let mut token: NFTToken = state.get_nft_token(each.clone()).unwrap().clone();
-                token.status = DocStatus::Approved;
-                state.replace_nft_token(each.clone(), token);
-            }
+        let status: DocStatus = if response_bool {
+            DocStatus::Approved
         } else {
-            for each in pkg_doc_list.iter() {
-                let mut token: NFTToken = state.get_nft_token(each.clone()).unwrap().clone();
-                token.status = DocStatus::Rejected;
-                state.replace_nft_token(each.clone(), token);
+            DocStatus::Rejected
+        };
+        for (token_hash, mut token) in pkg_doc_list.iter().zip(tokens.into_iter()) {
+            token.status = status.clone();
+            if !store.put_nft_token(token_hash, &token) {
+                return false;
             }
         }
-        state.remove_pkg_no_from_review_list(&self.from, &pkg_no);
-        let serialized_state: Vec<u8> = match serialize(&state) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        app_state.set_data(&serialized_state);
-        state_context.put(&STATE_KEY.to_string(), app_state);
-        true
+        pending_view.remove(pending_index);
+        store.put_pending_view(&self.from, &pending_view)
     }
 
     fn publish_docs(&self, state_context: &mut dyn StateContext) -> bool {
@@ -552,20 +469,14 @@ impl ModuleTraits for CryptoTransaction {
             DataTypes::StringVal(value) => value.clone(),
             _ => return false,
         };
-        let mut app_state: State = match state_context.get(&STATE_KEY.to_string()) {
-            Some(state) => state,
-            None => return false,
-        };
-        let mut state: DocState = match deserialize(app_state.get_data().as_slice()) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        let pkg_doc_list: Vec<Hash> = match state.get_pkg_list(&pkg_no) {
-            Some(list) => list.clone(),
+        let mut store: DocStore = DocStore::new(state_context);
+        let pkg_doc_list: Vec<Hash> = match store.get_pkg_list(&pkg_no) {
+            Some(list) => list,
             None => return false,
         };
+        let mut tokens: Vec<NFTToken> = Vec::with_capacity(pkg_doc_list.len());
         for each in pkg_doc_list.iter() {
-            match state.get_nft_token(each.clone()) {
+            match store.get_nft_token(each) {
                 Some(token) => {
                     if token.status != DocStatus::Approved {
                         return false;
@@ -573,21 +484,17 @@ impl ModuleTraits for CryptoTransaction {
                     if token.owner != self.from {
                         return false;
                     }
+                    tokens.push(token);
                 }
                 None => return false,
             };
         }
-        for each in pkg_doc_list.iter() {
-            let mut token: NFTToken = state.get_nft_token(each.clone()).unwrap().clone();
+        for (token_hash, mut token) in pkg_doc_list.iter().zip(tokens.into_iter()) {
             token.status = DocStatus::Publish;
-            state.replace_nft_token(each.clone(), token);
+            if !store.put_nft_token(token_hash, &token) {
+                return false;
+            }
         }
-        let serialized_state: Vec<u8> = match serialize(&state) {
-            Result::Ok(value) => value,
-            Result::Err(_) => return false,
-        };
-        app_state.set_data(&serialized_state);
-        state_context.put(&STATE_KEY.to_string(), app_state);
         true
     }
 }
@@ -604,21 +511,72 @@ impl CryptoApp {
 }
 
 impl AppHandler for CryptoApp {
-    fn execute(&self, txn: &SignedTransaction, state_context: &mut dyn StateContext) -> bool {
+    fn execute(
+        &self,
+        txn: &SignedTransaction,
+        state_context: &mut dyn StateContext,
+        _block_context: &BlockContext,
+    ) -> Result<(), AppError> {
         let st = txn as &dyn StateTraits;
-        st.execute(state_context)
+        if st.execute(state_context) {
+            Ok(())
+        } else {
+            Err(AppError::new(1, "document transaction failed"))
+        }
     }
 
     fn name(&self) -> String {
         self.name.clone()
     }
+
+    fn version(&self) -> String {
+        String::from(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn validate(&self, txn: &SignedTransaction) -> Result<(), AppError> {
+        if TransactionTrait::validate(txn) {
+            Ok(())
+        } else {
+            Err(AppError::new(1, "document transaction is invalid"))
+        }
+    }
+
+    /// split the legacy registry blob into per item keys. The node runs this
+    /// once at a block boundary, so no txn pays gas for a large registry.
+    fn migrate_legacy_state(
+        &self,
+        legacy: &mut dyn StateContext,
+        state_context: &mut dyn StateContext,
+    ) -> Result<(), AppError> {
+        let state_key: String = STATE_KEY.to_string();
+        if let Some(legacy_blob) = legacy.get(&state_key) {
+            if !DocStore::new(state_context).migrate_legacy_state(&legacy_blob) {
+                return Err(AppError::new(
+                    1,
+                    "legacy document registry couldn't be migrated",
+                ));
+            }
+            legacy.delete(&state_key);
+        }
+        Ok(())
+    }
+
+    fn query(
+        &self,
+        method: &String,
+        args: &Vec<u8>,
+        state_context: &dyn ReadOnlyStateContext,
+    ) -> Result<Vec<u8>, AppError> {
+        crate::query::query(method, args, state_context)
+    }
 }
 
-#[no_mangle]
 pub fn register_app() -> Box<dyn AppHandler + Send> {
     Box::new(CryptoApp::new(&String::from(APPNAME)))
 }
 
+sdk::export_app!(register_app);
+
 #[cfg(test)]
 mod test_state {
 
@@ -348,7 +348,6 @@ pub fn create_transaction(kp: &KeypairType) -> Option<SignedTransaction> {
             return None;
         }
     }
-    let txn_sign = crypto_transaction.sign(&kp);
     let mut header = HashMap::default();
     let time_stamp = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
@@ -359,10 +358,10 @@ pub fn create_transaction(kp: &KeypairType) -> Option<SignedTransaction> {
         Result::Ok(value) => value,
         Result::Err(_) => vec![0],
     };
-    Some(SignedTransaction {
-        txn: serialized_txn,
-        app_name: String::from(APPNAME),
-        signature: txn_sign,
+    Some(SignedTransaction::create(
+        kp,
+        APPNAME,
         header,
-    })
+        serialized_txn,
+    ))
 }
@@ -2,13 +2,15 @@ extern crate futures;
 
 use crate::cli_config::Configuration;
 use crate::doc_app_types::SignedTransaction;
-use crate::doc_app_types::{CryptoTransaction, DocState};
+use crate::doc_app_types::{CryptoTransaction, NFTToken, APPNAME};
 use awc::Client;
 use bytes::Bytes;
 use exonum_crypto::Hash;
-use sdk::state::State;
+use sdk::traits::AppQuery;
+use serde::de::DeserializeOwned;
+use std::fmt::Debug;
 use utils::crypto::keypair::{CryptoKeypair, Keypair, KeypairType};
-use utils::serializer::{deserialize, serialize};
+use utils::serializer::{deserialize, serialize, Serialize};
 
 pub struct ClientObj {
     client: Client,
@@ -96,11 +98,38 @@ impl ClientObj {
         }
     }
 
-    // request to peer to fetch public_address state
+    // request to peer to fetch the document review lists of public_address
     pub async fn fetch_state(&self, public_address: &String) {
+        info!("packages pending review:");
+        self.query::<Vec<(String, Vec<Hash>)>>("pending_review", public_address)
+            .await;
+        info!("documents waiting for confirmation:");
+        self.query::<Vec<Hash>>("confirmation_list", public_address)
+            .await;
+    }
+
+    // request to peer to fetch a document token
+    pub async fn fetch_document(&self, token_hash: &Hash) {
+        self.query::<Option<NFTToken>>("document", token_hash).await;
+    }
+
+    // request to peer to fetch documents of a package
+    pub async fn fetch_package(&self, pkg_no: &String) {
+        self.query::<Option<Vec<Hash>>>("package", pkg_no).await;
+    }
+
+    // run a read only document_review query on the peer and print its result
+    async fn query<T: DeserializeOwned + Debug>(&self, method: &str, args: &impl Serialize) {
         let mut url: String = self.url.clone();
-        url.extend("client/fetch_state".chars());
-        let serialized_body: Vec<u8> = match serialize(public_address) {
+        url.extend(format!("client/query/{}", APPNAME).chars());
+        let app_query = AppQuery {
+            method: String::from(method),
+            args: match serialize(args) {
+                Result::Ok(value) => value,
+                Result::Err(_) => vec![0],
+            },
+        };
+        let serialized_body: Vec<u8> = match serialize(&app_query) {
             Result::Ok(value) => value,
             Result::Err(_) => vec![0],
         };
@@ -108,31 +137,26 @@ impl ClientObj {
             .client
             .get(url) // <- Create request builder
             .header("User-Agent", "Actix-web")
-            //.send() // <- Send http request
             .send_body(Bytes::from(serialized_body))
             .await
             .map_err(|_| ());
         match result {
             Ok(mut response) => {
                 let resp_body = response.body();
-                info!("fetch_state Status: {:?}", response.status());
-                if response.status() == 200 {
-                    match resp_body.await {
-                        Ok(state) => {
-                            if let Ok(state) = deserialize::<State>(&state) {
-                                if let Ok(doc_state) =
-                                    deserialize::<DocState>(state.get_data().as_slice())
-                                {
-                                    info!("{:#?}", doc_state);
-                                } else {
-                                    info!("document state couldn't deserialize");
-                                }
+                info!("query {:?} Status: {:?}", method, response.status());
+                match resp_body.await {
+                    Ok(body) => {
+                        if response.status() == 200 {
+                            if let Ok(value) = deserialize::<T>(&body) {
+                                info!("{:#?}", value);
                             } else {
-                                info!("state couldn't deserialize");
+                                info!("query result couldn't deserialize");
                             }
+                        } else {
+                            info!("{}", String::from_utf8_lossy(&body));
                         }
-                        Err(e) => error!("Error body: {:?}", e),
                     }
+                    Err(e) => error!("Error body: {:?}", e),
                 }
             }
             Err(e) => error!("Error response: {:?}", e),
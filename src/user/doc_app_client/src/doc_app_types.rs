@@ -2,10 +2,9 @@ extern crate utils;
 use exonum_crypto::Hash;
 use exonum_merkledb::ObjectHash;
 pub use sdk::signed_transaction::SignedTransaction;
-use std::collections::{BTreeMap, HashMap};
-use std::convert::AsRef;
+use std::collections::HashMap;
 use std::time::SystemTime;
-use utils::keypair::{CryptoKeypair, Keypair, KeypairType, PublicKey, Verify};
+use utils::keypair::{CryptoKeypair, Keypair, KeypairType};
 use utils::serializer::{deserialize, serialize, Deserialize, Serialize};
 pub const APPNAME: &str = "Document_Review";
 
@@ -76,11 +75,14 @@ impl TransactionTrait<SignedTransaction> for SignedTransaction {
             Result::Ok(value) => value,
             Result::Err(_) => return false,
         };
-        PublicKey::verify_from_encoded_pk(&txn.from, &self.txn, &self.signature.as_ref())
+        txn.from == self.signer && self.verify()
     }
 
     fn sign(&self, kp: &KeypairType) -> Vec<u8> {
-        Keypair::sign(&kp, &self.txn)
+        match self.signing_payload() {
+            Result::Ok(payload) => Keypair::sign(&kp, &payload),
+            Result::Err(_) => vec![],
+        }
     }
 
     fn generate(kp: &KeypairType) -> SignedTransaction {
@@ -95,7 +97,6 @@ impl TransactionTrait<SignedTransaction> for SignedTransaction {
             fxn_call: String::from("set_hash"),
             payload,
         };
-        let txn_sign = txn.sign(&kp);
         let mut header = HashMap::default();
         let time_stamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -106,12 +107,7 @@ impl TransactionTrait<SignedTransaction> for SignedTransaction {
             Result::Ok(value) => value,
             Result::Err(_) => vec![0],
         };
-        SignedTransaction {
-            txn: serialized_txn,
-            app_name: String::from(APPNAME),
-            signature: txn_sign,
-            header,
-        }
+        SignedTransaction::create(&kp, APPNAME, header, serialized_txn)
     }
 
     fn get_hash(&self) -> Hash {
@@ -143,18 +139,3 @@ pub struct NFTToken {
     pub pkg_no: String,
     pub status: DocStatus,
 }
-
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default, BinaryValue, ObjectHash)]
-#[binary_value(codec = "bincode")]
-pub struct DocState {
-    // NFT_TOKEN_HASH => NFT_TOKEN
-    tokens: BTreeMap<Hash, NFTToken>,
-    // PKG_NO => NFT_TOKEN_HASH_LIST
-    pkg_no: BTreeMap<String, Vec<Hash>>,
-    // NFT_TOKEN => FILE_HASH
-    file_hash: BTreeMap<Hash, Hash>,
-    // PUBLIC_ADDRESS => PKG_NO_LIST
-    pending_view: BTreeMap<String, Vec<String>>,
-    // PUBLIC_ADDRESS => NFT_LIST (NEDDED ONLY FOR BUSINESS LOGIC ALGORITHM)
-    confirmation_list: BTreeMap<String, Vec<Hash>>,
-}
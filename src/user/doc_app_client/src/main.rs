@@ -63,7 +63,9 @@ async fn main() {
         info!("4:) fetch state details");
         info!("5:) fetch block");
         info!("6:) fetch latest block");
-        info!("7:) exit");
+        info!("7:) fetch document");
+        info!("8:) fetch package");
+        info!("9:) exit");
         let mut input = String::new();
         info!("Please select Option:");
         let is_string: bool = get_string_input(&mut input);
@@ -117,6 +119,22 @@ async fn main() {
                 client.fetch_latest_block().await;
                 invalid_opt_count = 0;
             } else if input == String::from("7") {
+                invalid_opt_count = 0;
+                info!("Enter document token Hash");
+                let is_hash: Option<Hash> = get_hash_input(&mut input);
+                match is_hash {
+                    Some(token_hash) => client.fetch_document(&token_hash).await,
+                    None => error!("error: invalid input for token hash"),
+                }
+            } else if input == String::from("8") {
+                invalid_opt_count = 0;
+                info!("Enter package number");
+                if get_string_input(&mut input) {
+                    client.fetch_package(&input).await;
+                } else {
+                    error!("error: invalid package number");
+                }
+            } else if input == String::from("9") {
                 end_flag = true;
             } else {
                 info!("invalid option");
@@ -0,0 +1,62 @@
+use std::fs::File;
+use std::io::prelude::*;
+use toml;
+
+use serde::{Deserialize, Serialize};
+
+pub const CONFIG_FILE_PATH: &str = "wallet_app.toml";
+
+/// wallet_app settings, read from wallet_app.toml in the node working directory.
+/// They are the genesis values of the state, all nodes creating a chain must
+/// use the same file. Later blocks only read the state.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Configuration {
+    /// coins credited to the block author for every block, on top of collected fees
+    pub block_reward: u64,
+    /// keys allowed to change the mint authority set
+    pub admins: Vec<String>,
+    /// keys allowed to mint until an admin changes the set
+    pub mint_authorities: Vec<String>,
+    /// hard cap on total supply, no cap when not set
+    pub supply_cap: Option<u64>,
+}
+
+impl Configuration {
+    pub fn load(file_path: &str) -> Configuration {
+        let mut config_file = match File::open(file_path) {
+            Ok(f) => f,
+            Err(_) => {
+                warn!("{} not found, default wallet_app config is used", file_path);
+                return Configuration::default();
+            }
+        };
+        let mut config_file_str = String::new();
+        config_file
+            .read_to_string(&mut config_file_str)
+            .expect("Error reading wallet_app config");
+        match toml::from_str(&config_file_str) {
+            Ok(conf_data) => conf_data,
+            Err(e) => panic!("Error occurred parsing wallet_app config: Err: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_config() {
+        use super::*;
+        let conf_data: Configuration = toml::from_str("block_reward = 50").unwrap();
+        assert_eq!(conf_data.block_reward, 50);
+        let conf_data: Configuration = toml::from_str("").unwrap();
+        assert_eq!(conf_data.block_reward, 0);
+        assert_eq!(conf_data.supply_cap, None);
+        let conf_data: Configuration =
+            toml::from_str("admins = [\"aa\"]\nmint_authorities = [\"bb\"]\nsupply_cap = 1000")
+                .unwrap();
+        assert_eq!(conf_data.admins, vec![String::from("aa")]);
+        assert_eq!(conf_data.mint_authorities, vec![String::from("bb")]);
+        assert_eq!(conf_data.supply_cap, Some(1000));
+    }
+}
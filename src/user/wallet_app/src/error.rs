@@ -0,0 +1,111 @@
+use sdk::errors::AppError;
+use std::fmt;
+
+/// WalletError lists why a wallet transaction couldn't be applied
+#[derive(Debug, Clone, PartialEq)]
+pub enum WalletError {
+    InvalidTransaction,
+    UnknownFunction(String),
+    Unauthorized,
+    SelfTransfer,
+    AccountNotFound(String),
+    AccountExists(String),
+    NonceMismatch {
+        expected: u64,
+        found: u64,
+    },
+    InsufficientBalance {
+        balance: u64,
+        required: u64,
+    },
+    Overflow,
+    InvalidSigner(String),
+    InvalidThreshold,
+    InsufficientApprovals,
+    Serialization,
+    NotMintAuthority(String),
+    NotAdmin(String),
+    SupplyCapExceeded {
+        cap: u64,
+        total_supply: u64,
+        amount: u64,
+    },
+    InvalidAddress(String),
+}
+
+impl WalletError {
+    /// stable numeric code of the error, reported to the node in AppError
+    pub fn code(&self) -> u32 {
+        match self {
+            WalletError::InvalidTransaction => 1,
+            WalletError::UnknownFunction(_) => 2,
+            WalletError::Unauthorized => 3,
+            WalletError::SelfTransfer => 4,
+            WalletError::AccountNotFound(_) => 5,
+            WalletError::AccountExists(_) => 6,
+            WalletError::NonceMismatch { .. } => 7,
+            WalletError::InsufficientBalance { .. } => 8,
+            WalletError::Overflow => 9,
+            WalletError::InvalidSigner(_) => 10,
+            WalletError::InvalidThreshold => 11,
+            WalletError::InsufficientApprovals => 12,
+            WalletError::Serialization => 13,
+            WalletError::NotMintAuthority(_) => 14,
+            WalletError::NotAdmin(_) => 15,
+            WalletError::SupplyCapExceeded { .. } => 16,
+            WalletError::InvalidAddress(_) => 17,
+        }
+    }
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WalletError::InvalidTransaction => write!(f, "transaction couldn't be decoded"),
+            WalletError::UnknownFunction(fxn_call) => write!(f, "unknown fxn_call {}", fxn_call),
+            WalletError::Unauthorized => write!(f, "signer isn't allowed to use the account"),
+            WalletError::SelfTransfer => write!(f, "self transfer transaction not allowed"),
+            WalletError::AccountNotFound(address) => write!(f, "account {} not found", address),
+            WalletError::AccountExists(address) => write!(f, "account {} already exists", address),
+            WalletError::NonceMismatch { expected, found } => {
+                write!(f, "nonce mismatched, expected {} found {}", expected, found)
+            }
+            WalletError::InsufficientBalance { balance, required } => {
+                write!(f, "insufficient balance {}, required {}", balance, required)
+            }
+            WalletError::Overflow => write!(f, "arithmetic overflow"),
+            WalletError::InvalidSigner(signer) => {
+                write!(f, "multisig signer {} is not a valid public key", signer)
+            }
+            WalletError::InvalidThreshold => {
+                write!(f, "multisig threshold can't be met by the signer set")
+            }
+            WalletError::InsufficientApprovals => {
+                write!(f, "multisig transfer doesn't have enough valid approvals")
+            }
+            WalletError::Serialization => write!(f, "state couldn't be serialized"),
+            WalletError::NotMintAuthority(address) => {
+                write!(f, "{} is not a mint authority", address)
+            }
+            WalletError::NotAdmin(address) => write!(f, "{} is not a wallet admin", address),
+            WalletError::SupplyCapExceeded {
+                cap,
+                total_supply,
+                amount,
+            } => write!(
+                f,
+                "minting {} exceeds supply cap {}, total supply {}",
+                amount, cap, total_supply
+            ),
+            WalletError::InvalidAddress(address) => {
+                write!(f, "{:?} is not an account address", address)
+            }
+        }
+    }
+}
+
+impl From<WalletError> for AppError {
+    fn from(error: WalletError) -> AppError {
+        AppError::new(error.code(), &error.to_string())
+    }
+}
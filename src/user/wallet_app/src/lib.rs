@@ -7,6 +7,8 @@ extern crate log;
 #[macro_use]
 extern crate serde;
 
+pub mod config;
+pub mod error;
 pub mod state;
 pub mod transaction;
 mod user_messages;
@@ -1,4 +1,5 @@
 extern crate utils;
+use crate::error::WalletError;
 use exonum_crypto::Hash;
 use std::convert::AsRef;
 
@@ -41,24 +42,37 @@ impl CryptoState {
         self.code_hash
     }
 
-    pub fn increase_nonce(&mut self) {
-        self.nonce = self.nonce + 1;
+    /// nonce expected in the next transaction of this account
+    pub fn next_nonce(&self) -> Result<u64, WalletError> {
+        self.nonce.checked_add(1).ok_or(WalletError::Overflow)
     }
 
-    pub fn deduct_balance(&mut self, amount: u64) {
-        self.balance = self.balance - amount;
+    pub fn increase_nonce(&mut self) -> Result<(), WalletError> {
+        self.nonce = self.next_nonce()?;
+        Ok(())
     }
 
-    pub fn add_balance(&mut self, amount: u64) -> bool {
-        if self.balance > self.balance + amount {
-            info!("do balance check before creating transaction");
-            false
-        } else {
-            self.balance = self.balance + amount;
-            true
+    pub fn deduct_balance(&mut self, amount: u64) -> Result<(), WalletError> {
+        match self.balance.checked_sub(amount) {
+            Some(balance) => {
+                self.balance = balance;
+                Ok(())
+            }
+            None => Err(WalletError::InsufficientBalance {
+                balance: self.balance,
+                required: amount,
+            }),
         }
     }
 
+    pub fn add_balance(&mut self, amount: u64) -> Result<(), WalletError> {
+        self.balance = self
+            .balance
+            .checked_add(amount)
+            .ok_or(WalletError::Overflow)?;
+        Ok(())
+    }
+
     pub fn set_storage_root(&mut self, new_storage_root: Hash) {
         self.storage_root = new_storage_root;
     }
@@ -68,6 +82,161 @@ impl CryptoState {
     }
 }
 
+/// MultisigState keeps the signer set of an M-of-N account. It is stored
+/// next to the CryptoState of the account under multisig_key(address).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default, BinaryValue, ObjectHash)]
+#[binary_value(codec = "bincode")]
+pub struct MultisigState {
+    signers: Vec<String>,
+    threshold: u32,
+}
+
+impl MultisigState {
+    pub fn new(signers: &Vec<String>, threshold: u32) -> MultisigState {
+        let mut signers = signers.clone();
+        signers.sort();
+        signers.dedup();
+        MultisigState { signers, threshold }
+    }
+
+    pub fn get_signers(&self) -> &Vec<String> {
+        &self.signers
+    }
+
+    pub fn get_threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    pub fn is_signer(&self, public_key: &String) -> bool {
+        self.signers.binary_search(public_key).is_ok()
+    }
+
+    /// threshold must be reachable by the signer set
+    pub fn is_valid(&self) -> bool {
+        self.threshold > 0 && self.threshold as usize <= self.signers.len()
+    }
+}
+
+/// SupplyPolicy controls who may mint coins and how many may exist. It is
+/// written at genesis from wallet_app.toml and kept in state under
+/// SUPPLY_POLICY_KEY, admin transactions change it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default, BinaryValue, ObjectHash)]
+#[binary_value(codec = "bincode")]
+pub struct SupplyPolicy {
+    admins: Vec<String>,
+    mint_authorities: Vec<String>,
+    supply_cap: Option<u64>,
+    total_supply: u64,
+}
+
+impl SupplyPolicy {
+    pub fn new(
+        admins: &Vec<String>,
+        mint_authorities: &Vec<String>,
+        supply_cap: Option<u64>,
+    ) -> SupplyPolicy {
+        let mut policy = SupplyPolicy {
+            admins: admins.clone(),
+            mint_authorities: mint_authorities.clone(),
+            supply_cap,
+            total_supply: 0,
+        };
+        policy.admins.sort();
+        policy.admins.dedup();
+        policy.mint_authorities.sort();
+        policy.mint_authorities.dedup();
+        policy
+    }
+
+    pub fn get_mint_authorities(&self) -> &Vec<String> {
+        &self.mint_authorities
+    }
+
+    pub fn get_supply_cap(&self) -> Option<u64> {
+        self.supply_cap
+    }
+
+    pub fn get_total_supply(&self) -> u64 {
+        self.total_supply
+    }
+
+    pub fn is_admin(&self, address: &String) -> bool {
+        self.admins.binary_search(address).is_ok()
+    }
+
+    pub fn is_mint_authority(&self, address: &String) -> bool {
+        self.mint_authorities.binary_search(address).is_ok()
+    }
+
+    pub fn add_mint_authority(&mut self, address: &String) {
+        if let Err(index) = self.mint_authorities.binary_search(address) {
+            self.mint_authorities.insert(index, address.clone());
+        }
+    }
+
+    pub fn remove_mint_authority(&mut self, address: &String) {
+        if let Ok(index) = self.mint_authorities.binary_search(address) {
+            self.mint_authorities.remove(index);
+        }
+    }
+
+    /// coins which can still be minted before the cap is reached
+    pub fn headroom(&self) -> u64 {
+        match self.supply_cap {
+            Some(cap) => cap.saturating_sub(self.total_supply),
+            None => u64::MAX - self.total_supply,
+        }
+    }
+
+    pub fn mint(&mut self, amount: u64) -> Result<(), WalletError> {
+        if amount > self.headroom() {
+            return match self.supply_cap {
+                Some(cap) => Err(WalletError::SupplyCapExceeded {
+                    cap,
+                    total_supply: self.total_supply,
+                    amount,
+                }),
+                None => Err(WalletError::Overflow),
+            };
+        }
+        self.total_supply += amount;
+        Ok(())
+    }
+
+    pub fn burn(&mut self, amount: u64) {
+        // coins minted before the policy was tracked aren't part of total_supply
+        self.total_supply = self.total_supply.saturating_sub(amount);
+    }
+}
+
+/// separates an account address from the suffix of its other state keys
+pub const KEY_SEPARATOR: char = '/';
+
+/// state key of the SupplyPolicy. Keys of the app itself start with
+/// KEY_SEPARATOR, no account address reaches them.
+pub const SUPPLY_POLICY_KEY: &str = "/supply_policy";
+
+/// account holding the fees of the block in execution, emptied in finalize_block
+pub const FEE_POOL_KEY: &str = "/fee_pool";
+
+/// state key of the coins minted for the block author on every block
+pub const BLOCK_REWARD_KEY: &str = "/block_reward";
+
+/// keys of the SupplyPolicy & the fee pool before they were moved under KEY_SEPARATOR
+pub const LEGACY_SUPPLY_POLICY_KEY: &str = "supply_policy";
+pub const LEGACY_FEE_POOL_KEY: &str = "fee_pool";
+
+/// state key under which multisig signer set of an account is kept
+pub fn multisig_key(address: &String) -> String {
+    format!("{}{}multisig", address, KEY_SEPARATOR)
+}
+
+/// false for keys which can't hold a CryptoState: empty, reserved for the
+/// app or owned by another account like multisig_key(address)
+pub fn is_account_address(address: &String) -> bool {
+    !address.is_empty() && !address.contains(KEY_SEPARATOR)
+}
+
 #[cfg(test)]
 mod test_state {
 
@@ -79,4 +248,83 @@ mod test_state {
         state.balance = 10;
         info!("{:?}", state);
     }
+
+    #[test]
+    pub fn test_checked_balance() {
+        use super::*;
+        let mut state = CryptoState::new();
+        assert_eq!(state.add_balance(u64::MAX), Ok(()));
+        assert_eq!(state.add_balance(1), Err(WalletError::Overflow));
+        assert_eq!(state.get_balance(), u64::MAX);
+        assert_eq!(state.deduct_balance(u64::MAX), Ok(()));
+        assert_eq!(
+            state.deduct_balance(1),
+            Err(WalletError::InsufficientBalance {
+                balance: 0,
+                required: 1
+            })
+        );
+        state.nonce = u64::MAX;
+        assert_eq!(state.increase_nonce(), Err(WalletError::Overflow));
+    }
+
+    #[test]
+    pub fn test_multisig_state() {
+        use super::*;
+        let signers = vec![
+            String::from("cc"),
+            String::from("aa"),
+            String::from("bb"),
+            String::from("aa"),
+        ];
+        let multisig = MultisigState::new(&signers, 2);
+        assert_eq!(multisig.get_signers().len(), 3);
+        assert_eq!(multisig.is_signer(&String::from("bb")), true);
+        assert_eq!(multisig.is_signer(&String::from("dd")), false);
+        assert_eq!(multisig.is_valid(), true);
+        assert_eq!(MultisigState::new(&signers, 4).is_valid(), false);
+        assert_eq!(MultisigState::new(&signers, 0).is_valid(), false);
+    }
+
+    #[test]
+    pub fn test_account_address() {
+        use super::*;
+        let address = String::from("aa");
+        assert_eq!(is_account_address(&address), true);
+        assert_eq!(is_account_address(&multisig_key(&address)), false);
+        assert_eq!(is_account_address(&String::from(SUPPLY_POLICY_KEY)), false);
+        assert_eq!(is_account_address(&String::from(FEE_POOL_KEY)), false);
+        assert_eq!(is_account_address(&String::new()), false);
+    }
+
+    #[test]
+    pub fn test_supply_policy() {
+        use super::*;
+        let admins = vec![String::from("aa")];
+        let mut policy = SupplyPolicy::new(&admins, &vec![String::from("bb")], Some(100));
+        assert_eq!(policy.is_admin(&String::from("aa")), true);
+        assert_eq!(policy.is_mint_authority(&String::from("aa")), false);
+        policy.add_mint_authority(&String::from("aa"));
+        assert_eq!(policy.is_mint_authority(&String::from("aa")), true);
+        policy.remove_mint_authority(&String::from("bb"));
+        assert_eq!(policy.get_mint_authorities(), &vec![String::from("aa")]);
+
+        assert_eq!(policy.mint(60), Ok(()));
+        assert_eq!(
+            policy.mint(50),
+            Err(WalletError::SupplyCapExceeded {
+                cap: 100,
+                total_supply: 60,
+                amount: 50
+            })
+        );
+        policy.burn(20);
+        assert_eq!(policy.mint(50), Ok(()));
+        assert_eq!(policy.get_total_supply(), 90);
+        assert_eq!(policy.headroom(), 10);
+
+        let mut uncapped = SupplyPolicy::new(&admins, &admins, None);
+        assert_eq!(uncapped.mint(u64::MAX), Ok(()));
+        assert_eq!(uncapped.mint(1), Err(WalletError::Overflow));
+    }
 }
@@ -1,12 +1,18 @@
 extern crate utils;
-use super::state::CryptoState;
-pub use crate::user_messages::CryptoTransaction;
-use exonum_crypto::Hash;
+use super::config::{Configuration, CONFIG_FILE_PATH};
+use super::error::WalletError;
+use super::state::{
+    is_account_address, multisig_key, CryptoState, MultisigState, SupplyPolicy, BLOCK_REWARD_KEY,
+    FEE_POOL_KEY, KEY_SEPARATOR, LEGACY_FEE_POOL_KEY, LEGACY_SUPPLY_POLICY_KEY, SUPPLY_POLICY_KEY,
+};
+pub use crate::user_messages::{Approval, CryptoTransaction};
+use exonum_crypto::{hash, Hash};
 use exonum_merkledb::ObjectHash;
+use sdk::block_context::BlockContext;
+use sdk::errors::AppError;
 pub use sdk::signed_transaction::SignedTransaction;
 use sdk::state::State;
-use sdk::traits::{AppHandler, StateContext};
-use std::convert::AsRef;
+use sdk::traits::{AppHandler, ReadOnlyStateContext, StateContext};
 use utils::keypair::{CryptoKeypair, Keypair, KeypairType, PublicKey, Verify};
 use utils::logger::logger_init_from_yml;
 use utils::serializer::{deserialize, serialize};
@@ -14,7 +20,7 @@ use utils::serializer::{deserialize, serialize};
 const APPNAME: &str = "Cryptocurrency";
 
 trait StateTraits {
-    fn execute(&self, state_context: &mut dyn StateContext) -> bool;
+    fn execute(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError>;
 }
 
 pub trait TransactionTrait<T> {
@@ -43,17 +49,71 @@ impl TransactionTrait<CryptoTransaction> for CryptoTransaction {
     }
 }
 
+impl CryptoTransaction {
+    /// bytes signed by each multisig signer, txn without the collected approvals
+    pub fn approval_payload(&self) -> Result<Vec<u8>, String> {
+        let mut unsigned_txn: CryptoTransaction = self.clone();
+        unsigned_txn.approvals.clear();
+        serialize(&unsigned_txn)
+    }
+
+    /// check collected approvals against the signer set of the multisig account
+    fn verify_approvals(&self, multisig: &MultisigState) -> bool {
+        let payload: Vec<u8> = match self.approval_payload() {
+            Result::Ok(value) => value,
+            Result::Err(_) => return false,
+        };
+        let mut approved_by: Vec<&String> = Vec::new();
+        for approval in self.approvals.iter() {
+            if !multisig.is_signer(&approval.public_key)
+                || approved_by.contains(&&approval.public_key)
+            {
+                continue;
+            }
+            if PublicKey::verify_from_encoded_pk(
+                &approval.public_key,
+                &payload,
+                &approval.signature,
+            ) {
+                approved_by.push(&approval.public_key);
+            }
+        }
+        approved_by.len() >= multisig.get_threshold() as usize
+    }
+}
+
+/// address of a multisig account, derived from its creator, creator nonce & signer set
+pub fn multisig_address(creator: &String, nonce: u64, multisig: &MultisigState) -> String {
+    let serialized: Vec<u8> = match serialize(&(creator, nonce, multisig)) {
+        Result::Ok(value) => value,
+        Result::Err(_) => vec![0],
+    };
+    hash(&serialized).to_hex()
+}
+
 impl TransactionTrait<SignedTransaction> for SignedTransaction {
     fn validate(&self) -> bool {
+        // envelope signature is verified by the node before execution,
+        // only the binding between signer and the txn account is checked here.
+        // multisig transactions are authorised by their approvals instead.
         let txn: CryptoTransaction = match deserialize(&self.txn) {
             Result::Ok(value) => value,
             Result::Err(_) => return false,
         };
-        PublicKey::verify_from_encoded_pk(&txn.from, &self.txn, &self.signature.as_ref())
+        // fee header drives pool ordering, it must match the fee actually
+        // charged and only transfers charge one
+        if self.fee() != txn.fee || (txn.fee > 0 && txn.fxn_call != String::from("transfer")) {
+            return false;
+        }
+        txn.from == self.signer
+            || (txn.fxn_call == String::from("transfer") && !txn.approvals.is_empty())
     }
 
     fn sign(&self, kp: &KeypairType) -> Vec<u8> {
-        Keypair::sign(&kp, &self.txn)
+        match self.signing_payload() {
+            Result::Ok(payload) => Keypair::sign(&kp, &payload),
+            Result::Err(_) => vec![],
+        }
     }
 
     fn get_hash(&self) -> Hash {
@@ -62,163 +122,670 @@ impl TransactionTrait<SignedTransaction> for SignedTransaction {
 }
 
 impl StateTraits for SignedTransaction {
-    fn execute(&self, state_context: &mut dyn StateContext) -> bool {
-        let mut flag: bool = false;
-        if self.validate() {
-            let txn: CryptoTransaction = match deserialize(&self.txn) {
-                Result::Ok(value) => value,
-                Result::Err(_) => return false,
-            };
-            let crypto_txn = &txn.clone() as &dyn ModuleTraits;
-            if txn.fxn_call == String::from("transfer") {
-                flag = crypto_txn.transfer(state_context);
-            } else if txn.fxn_call == String::from("mint") {
-                flag = crypto_txn.mint(state_context);
-            } else {
+    fn execute(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError> {
+        let result: Result<(), WalletError> = if self.validate() {
+            match deserialize::<CryptoTransaction>(&self.txn) {
+                Result::Ok(txn) => {
+                    let crypto_txn = &txn as &dyn ModuleTraits;
+                    if txn.fxn_call == String::from("transfer") {
+                        crypto_txn.transfer(state_context)
+                    } else if txn.fxn_call == String::from("mint") {
+                        crypto_txn.mint(state_context)
+                    } else if txn.fxn_call == String::from("burn") {
+                        crypto_txn.burn(state_context)
+                    } else if txn.fxn_call == String::from("create_multisig") {
+                        crypto_txn.create_multisig(state_context)
+                    } else if txn.fxn_call == String::from("add_mint_authority") {
+                        crypto_txn.set_mint_authority(state_context, true)
+                    } else if txn.fxn_call == String::from("remove_mint_authority") {
+                        crypto_txn.set_mint_authority(state_context, false)
+                    } else {
+                        Err(WalletError::UnknownFunction(txn.fxn_call.clone()))
+                    }
+                }
+                Result::Err(_) => Err(WalletError::InvalidTransaction),
             }
-        }
+        } else {
+            Err(WalletError::Unauthorized)
+        };
         state_context.put_txn(&self.get_hash(), self.clone());
-        flag
+        result
+    }
+}
+
+/// CryptoState of address, None if address isn't known yet
+fn get_wallet<C: ReadOnlyStateContext + ?Sized>(
+    state_context: &C,
+    address: &String,
+) -> Result<Option<CryptoState>, WalletError> {
+    match state_context.get(address) {
+        Some(state) => match deserialize(state.get_data().as_slice()) {
+            Result::Ok(value) => Ok(Some(value)),
+            Result::Err(_) => Err(WalletError::Serialization),
+        },
+        None => Ok(None),
+    }
+}
+
+/// CryptoState of address, new empty wallet if address isn't known yet
+fn get_or_create_wallet(
+    state_context: &dyn StateContext,
+    address: &String,
+) -> Result<CryptoState, WalletError> {
+    Ok(get_wallet(state_context, address)?.unwrap_or_else(CryptoState::new))
+}
+
+fn put_wallet(
+    state_context: &mut dyn StateContext,
+    address: &String,
+    wallet: &CryptoState,
+) -> Result<(), WalletError> {
+    let mut state: State = match state_context.get(address) {
+        Some(state) => state,
+        None => State::new(),
+    };
+    let serialized_wallet: Vec<u8> = serialize(wallet).map_err(|_| WalletError::Serialization)?;
+    state.set_data(&serialized_wallet);
+    state_context.put(address, state);
+    Ok(())
+}
+
+/// stored SupplyPolicy, written at genesis. A state without one has no
+/// admin, no mint authority and no cap.
+pub fn get_policy<C: ReadOnlyStateContext + ?Sized>(
+    state_context: &C,
+) -> Result<SupplyPolicy, WalletError> {
+    match state_context.get(&String::from(SUPPLY_POLICY_KEY)) {
+        Some(state) => {
+            deserialize(state.get_data().as_slice()).map_err(|_| WalletError::Serialization)
+        }
+        None => Ok(SupplyPolicy::default()),
+    }
+}
+
+fn put_policy(
+    state_context: &mut dyn StateContext,
+    policy: &SupplyPolicy,
+) -> Result<(), WalletError> {
+    let mut state: State = State::new();
+    let serialized_policy: Vec<u8> = serialize(policy).map_err(|_| WalletError::Serialization)?;
+    state.set_data(&serialized_policy);
+    state_context.put(&String::from(SUPPLY_POLICY_KEY), state);
+    Ok(())
+}
+
+/// stored block reward, written at genesis, 0 when not set
+pub fn get_block_reward<C: ReadOnlyStateContext + ?Sized>(
+    state_context: &C,
+) -> Result<u64, WalletError> {
+    match state_context.get(&String::from(BLOCK_REWARD_KEY)) {
+        Some(state) => {
+            deserialize(state.get_data().as_slice()).map_err(|_| WalletError::Serialization)
+        }
+        None => Ok(0),
+    }
+}
+
+fn put_block_reward(
+    state_context: &mut dyn StateContext,
+    block_reward: u64,
+) -> Result<(), WalletError> {
+    let mut state: State = State::new();
+    let serialized_reward: Vec<u8> =
+        serialize(&block_reward).map_err(|_| WalletError::Serialization)?;
+    state.set_data(&serialized_reward);
+    state_context.put(&String::from(BLOCK_REWARD_KEY), state);
+    Ok(())
+}
+
+/// txn nonce must be the next nonce of the account
+fn check_nonce(wallet: &CryptoState, nonce: u64) -> Result<(), WalletError> {
+    let expected: u64 = wallet.next_nonce()?;
+    if nonce != expected {
+        return Err(WalletError::NonceMismatch {
+            expected,
+            found: nonce,
+        });
     }
+    Ok(())
 }
 
 pub trait ModuleTraits {
-    fn transfer(&self, state_context: &mut dyn StateContext) -> bool;
-    fn mint(&self, state_context: &mut dyn StateContext) -> bool;
+    fn transfer(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError>;
+    fn mint(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError>;
+    fn burn(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError>;
+    fn create_multisig(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError>;
+    fn set_mint_authority(
+        &self,
+        state_context: &mut dyn StateContext,
+        is_authority: bool,
+    ) -> Result<(), WalletError>;
 }
 
 impl ModuleTraits for CryptoTransaction {
-    fn transfer(&self, state_context: &mut dyn StateContext) -> bool {
-        if self.validate() {
-            if self.from == self.to {
-                info!("self transfer transaction not allowed");
-                return false;
-            }
-            let mut from_state: State = match state_context.get(&self.from) {
-                Some(state) => state,
-                None => return false,
-            };
-            let mut from_wallet: CryptoState = match deserialize(from_state.get_data().as_slice()) {
-                Result::Ok(value) => value,
-                Result::Err(_) => return false,
-            };
-            if self.nonce != from_wallet.get_nonce() + 1 {
-                info!(
-                    "transfer txn nonce mismatched {:?} {:?}",
-                    self.nonce,
-                    from_wallet.get_nonce() + 1
-                );
-                return false;
-            }
-            if from_wallet.get_balance() >= self.amount {
-                let mut to_state: State = match state_context.get(&self.to) {
-                    Some(state) => state,
-                    None => {
-                        let crypto_state: CryptoState = CryptoState::new();
-                        let mut state: State = State::new();
-                        let serialized_crypto_state: Vec<u8> = match serialize(&crypto_state) {
-                            Result::Ok(value) => value,
-                            Result::Err(_) => return false,
-                        };
-                        state.set_data(&serialized_crypto_state);
-                        state
-                    }
-                };
-                let mut to_wallet: CryptoState = match deserialize(to_state.get_data().as_slice()) {
-                    Result::Ok(value) => value,
-                    Result::Err(_) => return false,
-                };
-                if !to_wallet.add_balance(self.amount) {
-                    return false;
+    fn transfer(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError> {
+        if !self.validate() {
+            return Err(WalletError::InvalidTransaction);
+        }
+        if !is_account_address(&self.to) {
+            return Err(WalletError::InvalidAddress(self.to.clone()));
+        }
+        if self.from == self.to {
+            return Err(WalletError::SelfTransfer);
+        }
+        match state_context.get(&multisig_key(&self.from)) {
+            Some(multisig_state) => {
+                let multisig: MultisigState = deserialize(multisig_state.get_data().as_slice())
+                    .map_err(|_| WalletError::Serialization)?;
+                if !self.verify_approvals(&multisig) {
+                    return Err(WalletError::InsufficientApprovals);
                 }
-                let serialized_to_wallet: Vec<u8> = match serialize(&to_wallet) {
-                    Result::Ok(value) => value,
-                    Result::Err(_) => return false,
-                };
-                to_state.set_data(&serialized_to_wallet);
-
-                from_wallet.deduct_balance(self.amount);
-                from_wallet.increase_nonce();
-                let serialized_from_wallet: Vec<u8> = match serialize(&from_wallet) {
-                    Result::Ok(value) => value,
-                    Result::Err(_) => return false,
-                };
-                from_state.set_data(&serialized_from_wallet);
-
-                state_context.put(&self.to.clone(), to_state);
-                state_context.put(&self.from.clone(), from_state);
-                return true;
             }
-        }
-        false
-    }
-
-    fn mint(&self, state_context: &mut dyn StateContext) -> bool {
-        if self.validate() {
-            let mut state: State = match state_context.get(&self.from) {
-                Some(state) => state,
-                None => {
-                    let crypto_state: CryptoState = CryptoState::new();
-                    let mut state: State = State::new();
-                    let serialized_crypto_state: Vec<u8> = match serialize(&crypto_state) {
-                        Result::Ok(value) => value,
-                        Result::Err(_) => return false,
-                    };
-                    state.set_data(&serialized_crypto_state);
-                    state
+            None => {
+                if !self.approvals.is_empty() {
+                    return Err(WalletError::Unauthorized);
                 }
-            };
-            let mut wallet: CryptoState = match deserialize(state.get_data().as_slice()) {
-                Result::Ok(value) => value,
-                Result::Err(_) => return false,
-            };
-            if self.nonce != wallet.get_nonce() + 1 {
-                info!(
-                    "transfer txn nonce mismatched {:?} {:?}",
-                    self.nonce,
-                    wallet.get_nonce() + 1
-                );
-                return false;
             }
-            if !wallet.add_balance(self.amount) {
-                return false;
+        }
+        let mut from_wallet: CryptoState = match get_wallet(state_context, &self.from)? {
+            Some(wallet) => wallet,
+            None => return Err(WalletError::AccountNotFound(self.from.clone())),
+        };
+        check_nonce(&from_wallet, self.nonce)?;
+        let total_amount: u64 = self
+            .amount
+            .checked_add(self.fee)
+            .ok_or(WalletError::Overflow)?;
+        from_wallet.deduct_balance(total_amount)?;
+        from_wallet.increase_nonce()?;
+        let mut to_wallet: CryptoState = get_or_create_wallet(state_context, &self.to)?;
+        to_wallet.add_balance(self.amount)?;
+        // fee is held in the fee pool until the block author is paid in finalize_block
+        let fee_pool_key: String = String::from(FEE_POOL_KEY);
+        let mut fee_pool: CryptoState = get_or_create_wallet(state_context, &fee_pool_key)?;
+        fee_pool.add_balance(self.fee)?;
+
+        // all checks are done before the first write, a failed txn leaves no partial state
+        put_wallet(state_context, &self.to, &to_wallet)?;
+        put_wallet(state_context, &self.from, &from_wallet)?;
+        if self.fee > 0 {
+            put_wallet(state_context, &fee_pool_key, &fee_pool)?;
+        }
+        Ok(())
+    }
+
+    fn mint(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError> {
+        if !self.validate() {
+            return Err(WalletError::InvalidTransaction);
+        }
+        let mut policy: SupplyPolicy = get_policy(state_context)?;
+        if !policy.is_mint_authority(&self.from) {
+            return Err(WalletError::NotMintAuthority(self.from.clone()));
+        }
+        let mut wallet: CryptoState = get_or_create_wallet(state_context, &self.from)?;
+        check_nonce(&wallet, self.nonce)?;
+        policy.mint(self.amount)?;
+        wallet.add_balance(self.amount)?;
+        wallet.increase_nonce()?;
+        put_policy(state_context, &policy)?;
+        put_wallet(state_context, &self.from, &wallet)
+    }
+
+    fn burn(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError> {
+        if !self.validate() {
+            return Err(WalletError::InvalidTransaction);
+        }
+        let mut wallet: CryptoState = match get_wallet(state_context, &self.from)? {
+            Some(wallet) => wallet,
+            None => return Err(WalletError::AccountNotFound(self.from.clone())),
+        };
+        check_nonce(&wallet, self.nonce)?;
+        wallet.deduct_balance(self.amount)?;
+        wallet.increase_nonce()?;
+        let mut policy: SupplyPolicy = get_policy(state_context)?;
+        policy.burn(self.amount);
+        put_policy(state_context, &policy)?;
+        put_wallet(state_context, &self.from, &wallet)
+    }
+
+    fn create_multisig(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError> {
+        if !self.validate() {
+            return Err(WalletError::InvalidTransaction);
+        }
+        for signer in self.signers.iter() {
+            if PublicKey::from_string(signer).is_none() {
+                return Err(WalletError::InvalidSigner(signer.clone()));
             }
-            wallet.increase_nonce();
-            let serialized_wallet: Vec<u8> = match serialize(&wallet) {
-                Result::Ok(value) => value,
-                Result::Err(_) => return false,
-            };
-            state.set_data(&serialized_wallet);
-            state_context.put(&self.from.clone(), state);
-            return true;
         }
-        false
+        let multisig: MultisigState = MultisigState::new(&self.signers, self.threshold);
+        if !multisig.is_valid() {
+            return Err(WalletError::InvalidThreshold);
+        }
+        let mut creator_wallet: CryptoState = get_or_create_wallet(state_context, &self.from)?;
+        check_nonce(&creator_wallet, self.nonce)?;
+        creator_wallet.increase_nonce()?;
+        let address: String = multisig_address(&self.from, self.nonce, &multisig);
+        if state_context.contains(&address) {
+            return Err(WalletError::AccountExists(address));
+        }
+        let mut multisig_state: State = State::new();
+        let serialized_multisig: Vec<u8> =
+            serialize(&multisig).map_err(|_| WalletError::Serialization)?;
+        multisig_state.set_data(&serialized_multisig);
+
+        state_context.put(&multisig_key(&address), multisig_state);
+        put_wallet(state_context, &address, &CryptoState::new())?;
+        put_wallet(state_context, &self.from, &creator_wallet)?;
+        info!("multisig account {:?} created", address);
+        Ok(())
+    }
+
+    /// admin txn granting (or revoking) mint rights of the `to` key
+    fn set_mint_authority(
+        &self,
+        state_context: &mut dyn StateContext,
+        is_authority: bool,
+    ) -> Result<(), WalletError> {
+        if !self.validate() {
+            return Err(WalletError::InvalidTransaction);
+        }
+        if PublicKey::from_string(&self.to).is_none() {
+            return Err(WalletError::InvalidSigner(self.to.clone()));
+        }
+        let mut policy: SupplyPolicy = get_policy(state_context)?;
+        if !policy.is_admin(&self.from) {
+            return Err(WalletError::NotAdmin(self.from.clone()));
+        }
+        let mut admin_wallet: CryptoState = get_or_create_wallet(state_context, &self.from)?;
+        check_nonce(&admin_wallet, self.nonce)?;
+        admin_wallet.increase_nonce()?;
+        if is_authority {
+            policy.add_mint_authority(&self.to);
+        } else {
+            policy.remove_mint_authority(&self.to);
+        }
+        put_policy(state_context, &policy)?;
+        put_wallet(state_context, &self.from, &admin_wallet)?;
+        info!(
+            "mint authorities updated {:?}",
+            policy.get_mint_authorities()
+        );
+        Ok(())
     }
 }
 
+/// CryptoApp keeps its settings in state, genesis_config only provides the
+/// values of a new chain
 pub struct CryptoApp {
     name: String,
+    genesis_config: Configuration,
 }
 
 impl CryptoApp {
     pub fn new(s: &String) -> CryptoApp {
         logger_init_from_yml("log.yml");
-        CryptoApp { name: s.clone() }
+        CryptoApp {
+            name: s.clone(),
+            genesis_config: Configuration::load(CONFIG_FILE_PATH),
+        }
+    }
+
+    fn put_genesis_policy(&self, state_context: &mut dyn StateContext) -> Result<(), WalletError> {
+        let config: &Configuration = &self.genesis_config;
+        let policy = SupplyPolicy::new(&config.admins, &config.mint_authorities, config.supply_cap);
+        put_policy(state_context, &policy)
     }
 }
 
 impl AppHandler for CryptoApp {
-    fn execute(&self, txn: &SignedTransaction, state_context: &mut dyn StateContext) -> bool {
+    fn execute(
+        &self,
+        txn: &SignedTransaction,
+        state_context: &mut dyn StateContext,
+        _block_context: &BlockContext,
+    ) -> Result<(), AppError> {
         let st = txn as &dyn StateTraits;
-        st.execute(state_context)
+        st.execute(state_context).map_err(AppError::from)
     }
 
     fn name(&self) -> String {
         self.name.clone()
     }
+
+    fn version(&self) -> String {
+        String::from(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn charges_fee(&self) -> bool {
+        true
+    }
+
+    fn validate(&self, txn: &SignedTransaction) -> Result<(), AppError> {
+        if !TransactionTrait::validate(txn) {
+            return Err(WalletError::Unauthorized.into());
+        }
+        // refused before it reaches the pool, transfer checks it again on execution
+        match deserialize::<CryptoTransaction>(&txn.txn) {
+            Result::Ok(crypto_txn)
+                if crypto_txn.fxn_call == String::from("transfer")
+                    && !is_account_address(&crypto_txn.to) =>
+            {
+                Err(WalletError::InvalidAddress(crypto_txn.to).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// pay collected fees and the block reward to the block author
+    fn finalize_block(
+        &self,
+        author: &String,
+        state_context: &mut dyn StateContext,
+    ) -> Result<(), AppError> {
+        let fee_pool_key: String = String::from(FEE_POOL_KEY);
+        let fee_pool: CryptoState = get_or_create_wallet(state_context, &fee_pool_key)?;
+        // block reward is newly minted, it stops once the supply cap is reached
+        let mut policy: SupplyPolicy = get_policy(state_context)?;
+        let block_reward: u64 = std::cmp::min(get_block_reward(state_context)?, policy.headroom());
+        policy.mint(block_reward)?;
+        let payout: u64 = fee_pool
+            .get_balance()
+            .checked_add(block_reward)
+            .ok_or(WalletError::Overflow)?;
+        if payout == 0 {
+            return Ok(());
+        }
+        let mut author_wallet: CryptoState = get_or_create_wallet(state_context, author)?;
+        author_wallet.add_balance(payout)?;
+        if fee_pool.get_balance() > 0 {
+            put_wallet(state_context, &fee_pool_key, &CryptoState::new())?;
+        }
+        if block_reward > 0 {
+            put_policy(state_context, &policy)?;
+        }
+        put_wallet(state_context, author, &author_wallet)?;
+        Ok(())
+    }
+
+    /// supply policy & block reward of a new chain
+    fn init_genesis(&self, state_context: &mut dyn StateContext) -> Result<(), AppError> {
+        self.put_genesis_policy(state_context)?;
+        put_block_reward(state_context, self.genesis_config.block_reward)?;
+        Ok(())
+    }
+
+    /// take over the accounts, multisig signer sets & supply policy stored
+    /// before app namespaces existed, they are recognised by their encoding.
+    /// The supply policy & fee pool move to their keys under KEY_SEPARATOR.
+    /// Chains created before the settings were kept in state take the
+    /// missing ones from the config once.
+    fn migrate_legacy_state(
+        &self,
+        legacy: &mut dyn StateContext,
+        state_context: &mut dyn StateContext,
+    ) -> Result<(), AppError> {
+        for (key, state) in legacy.range(&String::new(), &String::new(), usize::MAX) {
+            let data: &[u8] = state.get_data().as_slice();
+            let wallet_key: Option<String> = if key == LEGACY_SUPPLY_POLICY_KEY {
+                deserialize::<SupplyPolicy>(data)
+                    .ok()
+                    .map(|_| String::from(SUPPLY_POLICY_KEY))
+            } else if key.ends_with(&format!("{}multisig", KEY_SEPARATOR)) {
+                deserialize::<MultisigState>(data).ok().map(|_| key.clone())
+            } else if key == LEGACY_FEE_POOL_KEY {
+                deserialize::<CryptoState>(data)
+                    .ok()
+                    .map(|_| String::from(FEE_POOL_KEY))
+            } else {
+                deserialize::<CryptoState>(data).ok().map(|_| key.clone())
+            };
+            if let Some(wallet_key) = wallet_key {
+                legacy.delete(&key);
+                state_context.put(&wallet_key, state);
+            }
+        }
+        if !state_context.contains(&String::from(SUPPLY_POLICY_KEY)) {
+            self.put_genesis_policy(state_context)?;
+        }
+        if !state_context.contains(&String::from(BLOCK_REWARD_KEY)) {
+            put_block_reward(state_context, self.genesis_config.block_reward)?;
+        }
+        Ok(())
+    }
+
+    /// "account" returns the CryptoState of the address serialized in args,
+    /// "supply_policy" the SupplyPolicy in effect, "block_reward" the u64
+    /// block reward
+    fn query(
+        &self,
+        method: &String,
+        args: &Vec<u8>,
+        state_context: &dyn ReadOnlyStateContext,
+    ) -> Result<Vec<u8>, AppError> {
+        let result = if method == "account" {
+            let address: String = deserialize(args).map_err(|_| WalletError::Serialization)?;
+            serialize(&get_wallet(state_context, &address)?.unwrap_or_else(CryptoState::new))
+        } else if method == "supply_policy" {
+            serialize(&get_policy(state_context)?)
+        } else if method == "block_reward" {
+            serialize(&get_block_reward(state_context)?)
+        } else {
+            return Err(WalletError::UnknownFunction(method.clone()).into());
+        };
+        result.map_err(|_| AppError::from(WalletError::Serialization))
+    }
 }
 
-#[no_mangle]
 pub fn register_app() -> Box<dyn AppHandler + Send> {
     Box::new(CryptoApp::new(&String::from(APPNAME)))
 }
+
+sdk::export_app!(register_app);
+
+#[cfg(test)]
+mod test_multisig {
+    use super::*;
+
+    fn approve(kp: &KeypairType, txn: &CryptoTransaction) -> Approval {
+        Approval {
+            public_key: hex::encode(kp.public().encode()),
+            signature: Keypair::sign(&kp, &txn.approval_payload().unwrap()),
+        }
+    }
+
+    #[test]
+    fn test_verify_approvals() {
+        let keypairs: Vec<KeypairType> = (0..3).map(|_| Keypair::generate()).collect();
+        let signers: Vec<String> = keypairs
+            .iter()
+            .map(|kp| hex::encode(kp.public().encode()))
+            .collect();
+        let multisig: MultisigState = MultisigState::new(&signers, 2);
+        let mut txn: CryptoTransaction = CryptoTransaction {
+            nonce: 1,
+            from: multisig_address(&signers[0], 1, &multisig),
+            to: signers[2].clone(),
+            fxn_call: String::from("transfer"),
+            amount: 10,
+            ..CryptoTransaction::default()
+        };
+        txn.approvals.push(approve(&keypairs[0], &txn));
+        assert_eq!(txn.verify_approvals(&multisig), false);
+        // same signer approving twice is counted once
+        txn.approvals.push(approve(&keypairs[0], &txn));
+        assert_eq!(txn.verify_approvals(&multisig), false);
+        txn.approvals.push(approve(&keypairs[1], &txn));
+        assert_eq!(txn.verify_approvals(&multisig), true);
+        // approvals don't hold once the txn is changed
+        txn.amount = 20;
+        assert_eq!(txn.verify_approvals(&multisig), false);
+        // outsider approvals are ignored
+        let outsider: KeypairType = Keypair::generate();
+        let mut txn: CryptoTransaction = CryptoTransaction {
+            nonce: 1,
+            amount: 10,
+            ..txn
+        };
+        txn.approvals.clear();
+        txn.approvals.push(approve(&keypairs[0], &txn));
+        txn.approvals.push(approve(&outsider, &txn));
+        assert_eq!(txn.verify_approvals(&multisig), false);
+    }
+}
+
+#[cfg(test)]
+mod test_transfer {
+    use super::*;
+    use sdk::testing::MockStateContext;
+    use std::collections::HashMap;
+
+    fn funded_wallet(state_context: &mut MockStateContext, address: &String, balance: u64) {
+        let mut wallet: CryptoState = CryptoState::new();
+        wallet.add_balance(balance).unwrap();
+        put_wallet(state_context, address, &wallet).unwrap();
+    }
+
+    fn transfer_to(from: &String, to: String) -> CryptoTransaction {
+        CryptoTransaction {
+            nonce: 1,
+            from: from.clone(),
+            to,
+            fxn_call: String::from("transfer"),
+            amount: 0,
+            ..CryptoTransaction::default()
+        }
+    }
+
+    #[test]
+    fn test_transfer_to_internal_key_refused() {
+        let mut state_context = MockStateContext::default();
+        let from: String = String::from("aa");
+        let victim: String = String::from("bb");
+        funded_wallet(&mut state_context, &from, 10);
+        let multisig: MultisigState = MultisigState::new(&vec![from.clone()], 1);
+        let mut multisig_state: State = State::new();
+        multisig_state.set_data(&serialize(&multisig).unwrap());
+        state_context.put(&multisig_key(&victim), multisig_state.clone());
+        let app = CryptoApp {
+            name: String::from(APPNAME),
+            genesis_config: Configuration::default(),
+        };
+
+        for to in vec![
+            multisig_key(&victim),
+            String::from(SUPPLY_POLICY_KEY),
+            String::from(FEE_POOL_KEY),
+            String::new(),
+        ] {
+            let txn: CryptoTransaction = transfer_to(&from, to.clone());
+            assert_eq!(
+                txn.transfer(&mut state_context),
+                Err(WalletError::InvalidAddress(to.clone()))
+            );
+            let signed_txn = SignedTransaction {
+                txn: serialize(&txn).unwrap(),
+                app_name: String::from(APPNAME),
+                header: HashMap::new(),
+                signer: from.clone(),
+                signature: vec![],
+            };
+            assert_eq!(
+                app.validate(&signed_txn),
+                Err(WalletError::InvalidAddress(to).into())
+            );
+        }
+        // victim's signer set and the sender's nonce are untouched
+        assert_eq!(
+            state_context.get(&multisig_key(&victim)),
+            Some(multisig_state)
+        );
+        let sender: CryptoState = get_wallet(&state_context, &from).unwrap().unwrap();
+        assert_eq!(sender.get_nonce(), 0);
+
+        let txn: CryptoTransaction = transfer_to(&from, victim.clone());
+        assert_eq!(txn.transfer(&mut state_context), Ok(()));
+    }
+
+    #[test]
+    fn test_transfer_to_legacy_app_key() {
+        let mut state_context = MockStateContext::default();
+        let from: String = String::from("aa");
+        funded_wallet(&mut state_context, &from, 10);
+        let policy: SupplyPolicy = SupplyPolicy::new(&vec![from.clone()], &vec![], Some(100));
+        put_policy(&mut state_context, &policy).unwrap();
+
+        // the old key names are plain accounts, the policy & the fee pool aren't reached
+        let mut txn: CryptoTransaction = transfer_to(&from, String::from(LEGACY_SUPPLY_POLICY_KEY));
+        txn.amount = 5;
+        txn.fee = 2;
+        assert_eq!(txn.transfer(&mut state_context), Ok(()));
+        let txn: CryptoTransaction = CryptoTransaction {
+            nonce: 2,
+            amount: 1,
+            fee: 0,
+            ..transfer_to(&from, String::from(LEGACY_FEE_POOL_KEY))
+        };
+        assert_eq!(txn.transfer(&mut state_context), Ok(()));
+        assert_eq!(get_policy(&state_context), Ok(policy));
+        let fee_pool: CryptoState = get_wallet(&state_context, &String::from(FEE_POOL_KEY))
+            .unwrap()
+            .unwrap();
+        assert_eq!(fee_pool.get_balance(), 2);
+        let legacy_pool: CryptoState =
+            get_wallet(&state_context, &String::from(LEGACY_FEE_POOL_KEY))
+                .unwrap()
+                .unwrap();
+        assert_eq!(legacy_pool.get_balance(), 1);
+    }
+}
+
+#[cfg(test)]
+mod test_genesis {
+    use super::*;
+    use sdk::testing::MockStateContext;
+
+    fn app(block_reward: u64, supply_cap: Option<u64>) -> CryptoApp {
+        CryptoApp {
+            name: String::from(APPNAME),
+            genesis_config: Configuration {
+                block_reward,
+                supply_cap,
+                ..Configuration::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_settings_from_state() {
+        let mut state_context = MockStateContext::default();
+        app(50, Some(120)).init_genesis(&mut state_context).unwrap();
+        assert_eq!(get_block_reward(&state_context), Ok(50));
+
+        // another wallet_app.toml on a node doesn't change the chain
+        let other_node: CryptoApp = app(1000, None);
+        let author: String = String::from("aa");
+        other_node
+            .finalize_block(&author, &mut state_context)
+            .unwrap();
+        other_node
+            .finalize_block(&author, &mut state_context)
+            .unwrap();
+        other_node
+            .finalize_block(&author, &mut state_context)
+            .unwrap();
+        let wallet: CryptoState = get_wallet(&state_context, &author).unwrap().unwrap();
+        assert_eq!(wallet.get_balance(), 120);
+
+        // the migration only fills in settings the state doesn't have
+        other_node
+            .migrate_legacy_state(&mut MockStateContext::default(), &mut state_context)
+            .unwrap();
+        assert_eq!(get_block_reward(&state_context), Ok(50));
+        let mut legacy_chain = MockStateContext::default();
+        other_node
+            .migrate_legacy_state(&mut MockStateContext::default(), &mut legacy_chain)
+            .unwrap();
+        assert_eq!(get_block_reward(&legacy_chain), Ok(1000));
+        assert_eq!(get_policy(&legacy_chain), Ok(SupplyPolicy::default()));
+    }
+}
@@ -1,6 +1,17 @@
 //// Auto-generated using build.rs and proto files. Don't edit by hand. ////
 #[derive(Clone, PartialEq, Serialize, Deserialize, ::prost::Message, BinaryValue, ObjectHash)]
 #[binary_value(codec = "bincode")]
+#[serde(default)]
+pub struct Approval {
+    #[prost(string, tag = "1")]
+    pub public_key: std::string::String,
+    #[prost(bytes, tag = "2")]
+    pub signature: std::vec::Vec<u8>,
+}
+//// Auto-generated using build.rs and proto files. Don't edit by hand. ////
+#[derive(Clone, PartialEq, Serialize, Deserialize, ::prost::Message, BinaryValue, ObjectHash)]
+#[binary_value(codec = "bincode")]
+#[serde(default)]
 pub struct CryptoTransaction {
     #[prost(uint64, tag = "1")]
     pub nonce: u64,
@@ -12,10 +23,19 @@ pub struct CryptoTransaction {
     pub fxn_call: std::string::String,
     #[prost(uint64, tag = "5")]
     pub amount: u64,
+    #[prost(string, repeated, tag = "6")]
+    pub signers: ::std::vec::Vec<std::string::String>,
+    #[prost(uint32, tag = "7")]
+    pub threshold: u32,
+    #[prost(message, repeated, tag = "8")]
+    pub approvals: ::std::vec::Vec<Approval>,
+    #[prost(uint64, tag = "9")]
+    pub fee: u64,
 }
 //// Auto-generated using build.rs and proto files. Don't edit by hand. ////
 #[derive(Clone, PartialEq, Serialize, Deserialize, ::prost::Message, BinaryValue, ObjectHash)]
 #[binary_value(codec = "bincode")]
+#[serde(default)]
 pub struct SignedTransaction1 {
     #[prost(bytes, tag = "1")]
     pub txn: std::vec::Vec<u8>,
@@ -1,11 +1,15 @@
 extern crate futures;
 
 use crate::cli_config::Configuration;
-use crate::wallet_app_types::{CryptoState, CryptoTransaction, SignedTransaction};
+use crate::wallet_app_types::{
+    CryptoState, CryptoTransaction, SignedTransaction, SupplyPolicy, APPNAME,
+};
 use awc::Client;
 use bytes::Bytes;
 use exonum_crypto::Hash;
 use sdk::state::State;
+use sdk::traits::AppQuery;
+use serde::de::DeserializeOwned;
 use utils::crypto::keypair::{CryptoKeypair, Keypair, KeypairType};
 use utils::serializer::{deserialize, serialize};
 
@@ -98,7 +102,7 @@ impl ClientObj {
     // request to peer to fetch public_address state
     pub async fn fetch_state(&self, public_address: &String) {
         let mut url: String = self.url.clone();
-        url.extend("client/fetch_state".chars());
+        url.extend(format!("client/fetch_app_state/{}", APPNAME).chars());
         let serialized_body: Vec<u8> = match serialize(public_address) {
             Result::Ok(value) => value,
             Result::Err(_) => vec![0],
@@ -138,6 +142,56 @@ impl ClientObj {
         }
     }
 
+    // run a read only wallet_app query on the peer, None if it failed
+    async fn query<T: DeserializeOwned>(&self, method: &str, args: Vec<u8>) -> Option<T> {
+        let mut url: String = self.url.clone();
+        url.extend(format!("client/query/{}", APPNAME).chars());
+        let app_query = AppQuery {
+            method: String::from(method),
+            args,
+        };
+        let serialized_body: Vec<u8> = match serialize(&app_query) {
+            Result::Ok(value) => value,
+            Result::Err(_) => vec![0],
+        };
+        let result = self
+            .client
+            .get(url) // <- Create request builder
+            .header("User-Agent", "Actix-web")
+            .send_body(Bytes::from(serialized_body))
+            .await
+            .map_err(|_| ());
+        match result {
+            Ok(mut response) => {
+                let resp_body = response.body();
+                info!("query {:?} Status: {:?}", method, response.status());
+                match resp_body.await {
+                    Ok(body) => {
+                        if response.status() == 200 {
+                            if let Ok(value) = deserialize::<T>(&body) {
+                                return Some(value);
+                            }
+                            info!("query result couldn't deserialize");
+                        } else {
+                            info!("{}", String::from_utf8_lossy(&body));
+                        }
+                    }
+                    Err(e) => error!("Error body: {:?}", e),
+                }
+            }
+            Err(e) => error!("Error response: {:?}", e),
+        }
+        None
+    }
+
+    // request to peer to fetch supply policy & total supply of wallet_app
+    pub async fn fetch_total_supply(&self) {
+        if let Some(policy) = self.query::<SupplyPolicy>("supply_policy", vec![]).await {
+            info!("total supply {:?}", policy.total_supply);
+            info!("{:#?}", policy);
+        }
+    }
+
     // request to peer to fetch block
     pub async fn fetch_block(&self, block_index: &u64) {
         let mut url: String = self.url.clone();
@@ -254,10 +308,15 @@ impl ClientObj {
     }
 
     pub async fn get_nonce(&self) -> Option<u64> {
-        let public_key: &String = &hex::encode(self.keypair.public().encode());
+        let public_key: String = hex::encode(self.keypair.public().encode());
+        self.get_nonce_of(&public_key).await
+    }
+
+    // next nonce of any account, used for multisig accounts as well
+    pub async fn get_nonce_of(&self, address: &String) -> Option<u64> {
         let mut url: String = self.url.clone();
-        url.extend("client/fetch_state".chars());
-        let serialized_body: Vec<u8> = match serialize(public_key) {
+        url.extend(format!("client/fetch_app_state/{}", APPNAME).chars());
+        let serialized_body: Vec<u8> = match serialize(address) {
             Result::Ok(value) => value,
             Result::Err(_) => vec![0],
         };
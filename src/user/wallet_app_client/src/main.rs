@@ -10,7 +10,10 @@ extern crate log;
 extern crate futures;
 
 mod wallet_app_types;
-use crate::wallet_app_types::{CryptoTransaction, SignedTransaction, TransactionTrait};
+use crate::wallet_app_types::{
+    multisig_address, Approval, CryptoTransaction, MultisigState, SignedTransaction,
+    TransactionTrait,
+};
 use exonum_crypto::{Hash, PublicKey};
 use std::collections::HashMap;
 use std::io;
@@ -20,7 +23,7 @@ use clap::{App, Arg};
 use client::ClientObj;
 use std::time::SystemTime;
 use utils::logger::logger_init_from_yml;
-use utils::serializer::serialize;
+use utils::serializer::{deserialize, serialize};
 
 mod cli_config;
 const APPNAME: &str = "Cryptocurrency";
@@ -103,6 +106,35 @@ pub fn get_public_key_input(input: &mut String) -> bool {
     };
 }
 
+// account address, either a public key or a multisig address
+pub fn get_address_input(input: &mut String) -> bool {
+    input.clear();
+    match io::stdin().read_line(input) {
+        Ok(_) => {
+            remove_trailing_newline(input);
+            match hex::decode(input.as_bytes()) {
+                Ok(slice) => return slice.len() == 32,
+                Err(_) => return false,
+            }
+        }
+        Err(_) => return false,
+    };
+}
+
+pub fn get_hex_input(input: &mut String) -> Option<Vec<u8>> {
+    input.clear();
+    match io::stdin().read_line(input) {
+        Ok(_) => {
+            remove_trailing_newline(input);
+            match hex::decode(input.as_bytes()) {
+                Ok(slice) => return Some(slice),
+                Err(_) => return None,
+            }
+        }
+        Err(_) => return None,
+    };
+}
+
 pub fn get_bool_input(input: &mut String) -> Option<bool> {
     input.clear();
     match io::stdin().read_line(input) {
@@ -132,14 +164,17 @@ pub fn create_transaction(kp: &KeypairType, nonce: u64) -> Option<SignedTransact
     let mut input = String::new();
     info!("1): transfer transaction");
     info!("2): mint transaction");
+    info!("3): burn transaction");
+    info!("4): add mint authority (admin only)");
+    info!("5): remove mint authority (admin only)");
     info!("Please select transaction_type:");
     let is_string: bool = get_string_input(&mut input);
     if is_string {
         if input == String::from("1") {
             crypto_transaction.fxn_call = String::from("transfer");
             info!("Please enter to_address:");
-            let is_public_key: bool = get_public_key_input(&mut input);
-            if is_public_key {
+            let is_address: bool = get_address_input(&mut input);
+            if is_address {
                 crypto_transaction.to = input.clone();
             } else {
                 return None;
@@ -147,6 +182,23 @@ pub fn create_transaction(kp: &KeypairType, nonce: u64) -> Option<SignedTransact
         } else if input == String::from("2") {
             crypto_transaction.fxn_call = String::from("mint");
             crypto_transaction.to = String::default();
+        } else if input == String::from("3") {
+            crypto_transaction.fxn_call = String::from("burn");
+            crypto_transaction.to = String::default();
+        } else if input == String::from("4") || input == String::from("5") {
+            crypto_transaction.fxn_call = if input == String::from("4") {
+                String::from("add_mint_authority")
+            } else {
+                String::from("remove_mint_authority")
+            };
+            info!("Please enter public key of mint authority:");
+            if get_public_key_input(&mut input) {
+                crypto_transaction.to = input.clone();
+            } else {
+                return None;
+            }
+            crypto_transaction.amount = 0;
+            return Some(signed_transaction(kp, &crypto_transaction));
         } else {
             info!("invalid option");
             return None;
@@ -161,23 +213,161 @@ pub fn create_transaction(kp: &KeypairType, nonce: u64) -> Option<SignedTransact
         Some(amount) => crypto_transaction.amount = amount,
         None => return None,
     };
-    let txn_sign = crypto_transaction.sign(&kp);
+    if crypto_transaction.fxn_call == String::from("transfer") {
+        match get_fee_input(&mut input) {
+            Some(fee) => crypto_transaction.fee = fee,
+            None => return None,
+        };
+    }
+    Some(signed_transaction(kp, &crypto_transaction))
+}
+
+// optional transaction fee, higher fee transactions are picked first by validators
+pub fn get_fee_input(input: &mut String) -> Option<u64> {
+    info!("Please enter fee (0 for no fee):");
+    get_integer_input(input)
+}
+
+fn signed_transaction(
+    kp: &KeypairType,
+    crypto_transaction: &CryptoTransaction,
+) -> SignedTransaction {
     let mut header = HashMap::default();
     let time_stamp = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap()
         .as_micros();
     header.insert("timestamp".to_string(), time_stamp.to_string());
-    let serialized_txn: Vec<u8> = match serialize(&crypto_transaction) {
+    if crypto_transaction.fee > 0 {
+        header.insert("fee".to_string(), crypto_transaction.fee.to_string());
+    }
+    let serialized_txn: Vec<u8> = match serialize(crypto_transaction) {
         Result::Ok(value) => value,
         Result::Err(_) => vec![0],
     };
-    Some(SignedTransaction {
-        txn: serialized_txn,
-        app_name: String::from(APPNAME),
-        signature: txn_sign,
-        header,
-    })
+    SignedTransaction::create(kp, APPNAME, header, serialized_txn)
+}
+
+pub fn create_multisig_transaction(kp: &KeypairType, nonce: u64) -> Option<SignedTransaction> {
+    let mut crypto_transaction: CryptoTransaction = CryptoTransaction::generate(kp);
+    crypto_transaction.nonce = nonce;
+    crypto_transaction.fxn_call = String::from("create_multisig");
+    crypto_transaction.to = String::default();
+    crypto_transaction.amount = 0;
+    let mut input = String::new();
+    info!("Please enter number of signers:");
+    let signer_count: u64 = match get_integer_input(&mut input) {
+        Some(count) => count,
+        None => return None,
+    };
+    for index in 0..signer_count {
+        info!("Please enter public key of signer {}:", index + 1);
+        if !get_public_key_input(&mut input) {
+            return None;
+        }
+        crypto_transaction.signers.push(input.clone());
+    }
+    info!("Please enter threshold (number of approvals required):");
+    match get_integer_input(&mut input) {
+        Some(threshold) if threshold > 0 && threshold <= signer_count => {
+            crypto_transaction.threshold = threshold as u32
+        }
+        _ => return None,
+    };
+    let multisig: MultisigState =
+        MultisigState::new(&crypto_transaction.signers, crypto_transaction.threshold);
+    info!(
+        "multisig address {:?}",
+        multisig_address(&crypto_transaction.from, nonce, &multisig)
+    );
+    Some(signed_transaction(kp, &crypto_transaction))
+}
+
+// unsigned multisig transfer, to be passed around the signers for approval
+pub fn create_multisig_transfer(address: &String, nonce: u64) -> Option<String> {
+    let mut input = String::new();
+    info!("Please enter to_address:");
+    if !get_address_input(&mut input) {
+        return None;
+    }
+    let to: String = input.clone();
+    info!("Please enter amount:");
+    let amount: u64 = match get_integer_input(&mut input) {
+        Some(amount) => amount,
+        None => return None,
+    };
+    let fee: u64 = match get_fee_input(&mut input) {
+        Some(fee) => fee,
+        None => return None,
+    };
+    let crypto_transaction: CryptoTransaction = CryptoTransaction {
+        nonce,
+        from: address.clone(),
+        to,
+        fxn_call: String::from("transfer"),
+        amount,
+        fee,
+        ..CryptoTransaction::default()
+    };
+    match serialize(&crypto_transaction) {
+        Result::Ok(value) => Some(hex::encode(value)),
+        Result::Err(_) => None,
+    }
+}
+
+fn get_multisig_transfer_input(input: &mut String) -> Option<CryptoTransaction> {
+    info!("Please enter unsigned multisig transaction:");
+    let serialized_txn: Vec<u8> = match get_hex_input(input) {
+        Some(value) => value,
+        None => return None,
+    };
+    match deserialize::<CryptoTransaction>(&serialized_txn) {
+        Result::Ok(crypto_transaction) => {
+            info!("{:#?}", crypto_transaction);
+            Some(crypto_transaction)
+        }
+        Result::Err(_) => None,
+    }
+}
+
+// partial signature of a multisig transfer with the client keypair
+pub fn sign_multisig_transfer(kp: &KeypairType) -> Option<String> {
+    let mut input = String::new();
+    let crypto_transaction: CryptoTransaction = match get_multisig_transfer_input(&mut input) {
+        Some(value) => value,
+        None => return None,
+    };
+    match serialize(&crypto_transaction.approve(kp)) {
+        Result::Ok(value) => Some(hex::encode(value)),
+        Result::Err(_) => None,
+    }
+}
+
+// attach collected approvals to a multisig transfer
+pub fn combine_multisig_transfer(kp: &KeypairType) -> Option<SignedTransaction> {
+    let mut input = String::new();
+    let mut crypto_transaction: CryptoTransaction = match get_multisig_transfer_input(&mut input) {
+        Some(value) => value,
+        None => return None,
+    };
+    crypto_transaction.approvals.clear();
+    info!("Please enter number of approvals:");
+    let approval_count: u64 = match get_integer_input(&mut input) {
+        Some(count) => count,
+        None => return None,
+    };
+    for index in 0..approval_count {
+        info!("Please enter approval {}:", index + 1);
+        let serialized_approval: Vec<u8> = match get_hex_input(&mut input) {
+            Some(value) => value,
+            None => return None,
+        };
+        match deserialize::<Approval>(&serialized_approval) {
+            Result::Ok(approval) => crypto_transaction.approvals.push(approval),
+            Result::Err(_) => return None,
+        }
+    }
+    Some(signed_transaction(kp, &crypto_transaction))
 }
 
 //this attribute allows main to not need to return anything and still use async calls.
@@ -224,7 +414,12 @@ async fn main() {
         info!("4:) fetch state details");
         info!("5:) fetch block");
         info!("6:) fetch latest block");
-        info!("7:) exit");
+        info!("7:) create multisig account");
+        info!("8:) create multisig transfer");
+        info!("9:) sign multisig transfer");
+        info!("10:) submit multisig transfer");
+        info!("11:) fetch total supply");
+        info!("12:) exit");
         let mut input = String::new();
         info!("Please select Option:");
         let is_string: bool = get_string_input(&mut input);
@@ -285,6 +480,51 @@ async fn main() {
                 client.fetch_latest_block().await;
                 invalid_opt_count = 0;
             } else if input == String::from("7") {
+                invalid_opt_count = 0;
+                match client.get_nonce().await {
+                    Some(nonce) => match create_multisig_transaction(client.get_keypair(), nonce) {
+                        Some(txn) => {
+                            info!("txn_hash {:?}", txn.get_hash().to_hex());
+                            client.submit_transaction(&txn).await;
+                        }
+                        None => error!("error: invalid input for multisig account"),
+                    },
+                    None => error!("SomeThing Wrong happened. Check error"),
+                }
+            } else if input == String::from("8") {
+                invalid_opt_count = 0;
+                info!("Enter multisig address");
+                if get_address_input(&mut input) {
+                    let address: String = input.clone();
+                    match client.get_nonce_of(&address).await {
+                        Some(nonce) => match create_multisig_transfer(&address, nonce) {
+                            Some(unsigned_txn) => info!("unsigned transaction {}", unsigned_txn),
+                            None => error!("error: invalid input for multisig transfer"),
+                        },
+                        None => error!("SomeThing Wrong happened. Check error"),
+                    }
+                } else {
+                    error!("error: invalid multisig address");
+                }
+            } else if input == String::from("9") {
+                invalid_opt_count = 0;
+                match sign_multisig_transfer(client.get_keypair()) {
+                    Some(approval) => info!("approval {}", approval),
+                    None => error!("error: invalid multisig transaction"),
+                }
+            } else if input == String::from("10") {
+                invalid_opt_count = 0;
+                match combine_multisig_transfer(client.get_keypair()) {
+                    Some(txn) => {
+                        info!("txn_hash {:?}", txn.get_hash().to_hex());
+                        client.submit_transaction(&txn).await;
+                    }
+                    None => error!("error: invalid input for multisig transfer"),
+                }
+            } else if input == String::from("11") {
+                invalid_opt_count = 0;
+                client.fetch_total_supply().await;
+            } else if input == String::from("12") {
                 end_flag = true;
             } else {
                 info!("invalid option");
@@ -3,11 +3,10 @@ use exonum_crypto::Hash;
 use exonum_merkledb::ObjectHash;
 pub use sdk::signed_transaction::SignedTransaction;
 use std::collections::HashMap;
-use std::convert::AsRef;
 use std::time::SystemTime;
-use utils::keypair::{CryptoKeypair, Keypair, KeypairType, PublicKey, Verify};
+use utils::keypair::{CryptoKeypair, Keypair, KeypairType};
 use utils::serializer::{deserialize, serialize, Deserialize, Serialize};
-const APPNAME: &str = "Cryptocurrency";
+pub const APPNAME: &str = "Cryptocurrency";
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, BinaryValue, ObjectHash)]
 #[binary_value(codec = "bincode")]
@@ -20,12 +19,80 @@ pub struct CryptoState {
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, BinaryValue, ObjectHash)]
 #[binary_value(codec = "bincode")]
+pub struct MultisigState {
+    pub signers: Vec<String>,
+    pub threshold: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, BinaryValue, ObjectHash)]
+#[binary_value(codec = "bincode")]
+pub struct SupplyPolicy {
+    pub admins: Vec<String>,
+    pub mint_authorities: Vec<String>,
+    pub supply_cap: Option<u64>,
+    pub total_supply: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, BinaryValue, ObjectHash)]
+#[binary_value(codec = "bincode")]
+#[serde(default)]
+pub struct Approval {
+    pub public_key: std::string::String,
+    pub signature: std::vec::Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize, BinaryValue, ObjectHash)]
+#[binary_value(codec = "bincode")]
+#[serde(default)]
 pub struct CryptoTransaction {
     pub nonce: u64,
     pub from: std::string::String,
     pub to: std::string::String,
     pub fxn_call: std::string::String,
     pub amount: u64,
+    pub signers: std::vec::Vec<std::string::String>,
+    pub threshold: u32,
+    pub approvals: std::vec::Vec<Approval>,
+    pub fee: u64,
+}
+
+impl CryptoTransaction {
+    /// bytes signed by each multisig signer, txn without the collected approvals
+    pub fn approval_payload(&self) -> Result<Vec<u8>, String> {
+        let mut unsigned_txn: CryptoTransaction = self.clone();
+        unsigned_txn.approvals.clear();
+        serialize(&unsigned_txn)
+    }
+
+    /// partial signature of a multisig transaction
+    pub fn approve(&self, kp: &KeypairType) -> Approval {
+        let payload: Vec<u8> = match self.approval_payload() {
+            Result::Ok(value) => value,
+            Result::Err(_) => vec![0],
+        };
+        Approval {
+            public_key: hex::encode(kp.public().encode()),
+            signature: Keypair::sign(&kp, &payload),
+        }
+    }
+}
+
+impl MultisigState {
+    pub fn new(signers: &Vec<String>, threshold: u32) -> MultisigState {
+        let mut signers = signers.clone();
+        signers.sort();
+        signers.dedup();
+        MultisigState { signers, threshold }
+    }
+}
+
+/// address of a multisig account, same derivation as wallet_app
+pub fn multisig_address(creator: &String, nonce: u64, multisig: &MultisigState) -> String {
+    let serialized: Vec<u8> = match serialize(&(creator, nonce, multisig)) {
+        Result::Ok(value) => value,
+        Result::Err(_) => vec![0],
+    };
+    exonum_crypto::hash(&serialized).to_hex()
 }
 
 pub trait TransactionTrait<T> {
@@ -61,6 +128,7 @@ impl TransactionTrait<CryptoTransaction> for CryptoTransaction {
             to,
             amount: 32,
             fxn_call: String::from("transfer"),
+            ..CryptoTransaction::default()
         }
     }
 
@@ -75,11 +143,14 @@ impl TransactionTrait<SignedTransaction> for SignedTransaction {
             Result::Ok(value) => value,
             Result::Err(_) => return false,
         };
-        PublicKey::verify_from_encoded_pk(&txn.from, &self.txn, &self.signature.as_ref())
+        txn.from == self.signer && self.verify()
     }
 
     fn sign(&self, kp: &KeypairType) -> Vec<u8> {
-        Keypair::sign(&kp, &self.txn)
+        match self.signing_payload() {
+            Result::Ok(payload) => Keypair::sign(&kp, &payload),
+            Result::Err(_) => vec![],
+        }
     }
 
     fn generate(kp: &KeypairType) -> SignedTransaction {
@@ -92,8 +163,8 @@ impl TransactionTrait<SignedTransaction> for SignedTransaction {
             to,
             amount: 32,
             fxn_call: String::from("transfer"),
+            ..CryptoTransaction::default()
         };
-        let txn_sign = txn.sign(&kp);
         let mut header = HashMap::default();
         let time_stamp = SystemTime::now()
             .duration_since(SystemTime::UNIX_EPOCH)
@@ -104,12 +175,7 @@ impl TransactionTrait<SignedTransaction> for SignedTransaction {
             Result::Ok(value) => value,
             Result::Err(_) => vec![0],
         };
-        SignedTransaction {
-            txn: serialized_txn,
-            app_name: String::from(APPNAME),
-            signature: txn_sign,
-            header,
-        }
+        SignedTransaction::create(&kp, APPNAME, header, serialized_txn)
     }
 
     fn get_hash(&self) -> Hash {
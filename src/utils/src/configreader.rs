@@ -1,36 +1,10 @@
 use super::*;
 use crypto::keypair::{CryptoKeypair, Keypair};
 use serde::{Deserialize, Serialize};
-use std::env;
 use std::fs::File;
 use std::io::Read;
-use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
 use toml;
 
-#[derive(Debug)]
-struct FilePath {
-    path: Arc<std::sync::Mutex<String>>,
-}
-
-impl FilePath {
-    fn new() -> FilePath {
-        FilePath {
-            path: Arc::new(Mutex::new(String::new())),
-        }
-    }
-
-    fn get_file_path(&self) -> String {
-        let locked_path = self.path.lock().unwrap();
-        String::from(locked_path.clone())
-    }
-
-    fn set_file_path(&self, file_path: &String) {
-        let mut locked_path = self.path.lock().unwrap();
-        *locked_path = file_path.clone();
-    }
-}
-
 #[derive(Debug)]
 pub enum NODETYPE {
     FullNode,
@@ -43,20 +17,123 @@ pub struct TomlReaderConfig {
     pub secret: String,
     node_type: String,
     genesis_block: bool,
+    // nodes of other chains are refused on handshake & their messages dropped
+    #[serde(default = "default_chain_id")]
+    chain_id: String,
     //p2p
     p2p_port: u16,
+    // multiaddrs dialed at startup, /ip4/<ip>/tcp/<port>/p2p/<peer id>
+    #[serde(default)]
+    bootstrap_peers: Vec<String>,
+    // discover peers of the local network with mdns
+    #[serde(default = "default_mdns")]
+    mdns: bool,
+    // hex public keys of the only peers allowed to connect, empty for an
+    // open network
+    #[serde(default)]
+    allowed_peers: Vec<String>,
+    // connection limits, by direction
+    #[serde(default = "default_max_peers")]
+    max_inbound_peers: usize,
+    #[serde(default = "default_max_peers")]
+    max_outbound_peers: usize,
+    // seconds a misbehaving peer stays banned
+    #[serde(default = "default_ban_duration")]
+    ban_duration: u64,
+    // file keeping the bans across restarts
+    #[serde(default = "default_banned_peers_path")]
+    banned_peers_path: String,
     //db config
     dbpath: String,
     //client config
     client_port: u32,
     client_host: String,
     client_apps: Vec<String>,
+    // hex public keys allowed to schedule app registry entries, written to
+    // the registry state at genesis
+    #[serde(default)]
+    registry_admins: Vec<String>,
     //block config
     block_creation_time_limit: u64,
     block_transaction_limit: u64,
     transaction_execution_delay_limit: u64,
+    #[serde(default = "default_block_gas_limit")]
+    block_gas_limit: u64,
+    #[serde(default = "default_txn_gas_limit")]
+    txn_gas_limit: u64,
+    #[serde(default = "default_max_txn_size")]
+    max_txn_size: u64,
+    #[serde(default = "default_max_txn_headers")]
+    max_txn_headers: u64,
+    #[serde(default = "default_max_message_size")]
+    max_message_size: u64,
+    // how far in milliseconds a block timestamp may be ahead of the local clock
+    #[serde(default = "default_max_block_time_drift")]
+    max_block_time_drift: u64,
+    // execute every block twice and compare state roots, for testing apps
+    #[serde(default)]
+    determinism_check: bool,
     // consensus name
     consensus_name: String,
+    // wasm runtime config
+    #[serde(default)]
+    wasm_runtime: bool,
+    #[serde(default = "default_wasm_gas_limit")]
+    wasm_gas_limit: u64,
+    #[serde(default = "default_wasm_max_code_size")]
+    wasm_max_code_size: u64,
+}
+
+fn default_chain_id() -> String {
+    String::from("simpleblockchain")
+}
+
+fn default_mdns() -> bool {
+    true
+}
+
+fn default_max_peers() -> usize {
+    25
+}
+
+fn default_ban_duration() -> u64 {
+    3600
+}
+
+fn default_banned_peers_path() -> String {
+    String::from("./banned_peers")
+}
+
+fn default_block_gas_limit() -> u64 {
+    100_000_000
+}
+
+fn default_txn_gas_limit() -> u64 {
+    10_000_000
+}
+
+fn default_max_txn_size() -> u64 {
+    64 * 1024
+}
+
+fn default_max_txn_headers() -> u64 {
+    16
+}
+
+fn default_max_message_size() -> u64 {
+    4 * 1024 * 1024
+}
+
+fn default_max_block_time_drift() -> u64 {
+    15_000
+}
+
+fn default_wasm_gas_limit() -> u64 {
+    10_000_000
+}
+
+fn default_wasm_max_code_size() -> u64 {
+    512 * 1024
 }
 
 #[derive(Debug)]
@@ -70,21 +147,44 @@ pub struct Configuration {
 
     // block creation config
     pub block_config: BlockConfig,
+
+    // wasm app runtime config
+    pub wasm_config: WasmConfig,
+}
+
+impl TomlReaderConfig {
+    pub fn from_toml_str(config: &str) -> Result<TomlReaderConfig, String> {
+        toml::from_str(config).map_err(|error| format!("invalid config: {}", error))
+    }
+
+    pub fn from_file(file_path: &str) -> Result<TomlReaderConfig, String> {
+        let mut config_file_str = String::new();
+        File::open(file_path)
+            .and_then(|mut config_file| config_file.read_to_string(&mut config_file_str))
+            .map_err(|error| format!("config file {} couldn't be read: {}", file_path, error))?;
+        TomlReaderConfig::from_toml_str(&config_file_str)
+    }
+
+    /// keep the chain state in db_path instead of the configured dbpath
+    pub fn set_dbpath(&mut self, db_path: &str) {
+        self.dbpath = String::from(db_path);
+    }
 }
 
 impl Configuration {
-    fn new() -> Self {
-        let tomlreader: TomlReaderConfig = Configuration::init_config();
-        let mut secret = hex::decode(tomlreader.secret).expect("invalid secret");
+    /// Err if the key pair or the node type of tomlreader is invalid
+    pub fn from_toml(tomlreader: TomlReaderConfig) -> Result<Configuration, String> {
+        let mut secret =
+            hex::decode(tomlreader.secret).map_err(|_| String::from("invalid secret"))?;
         let keypair = Keypair::generate_from(secret.as_mut_slice());
         if hex::encode(keypair.public().encode()) != tomlreader.public {
-            panic!("Secret and public key pair is invalid");
+            return Err(String::from("Secret and public key pair is invalid"));
         }
         let mut node_type: NODETYPE = NODETYPE::Validator;
         if tomlreader.node_type.to_ascii_lowercase() == "fullnode" {
             node_type = NODETYPE::FullNode
         } else if tomlreader.node_type.to_ascii_lowercase() != "validator" {
-            panic!("node type not defined properly");
+            return Err(String::from("node type not defined properly"));
         }
         let node_obj: Node = Node {
             public: Keypair::public(&keypair),
@@ -92,10 +192,19 @@ impl Configuration {
             keypair: keypair,
             node_type,
             genesis_block: tomlreader.genesis_block,
+            chain_id: tomlreader.chain_id,
             p2p_port: tomlreader.p2p_port,
+            bootstrap_peers: tomlreader.bootstrap_peers,
+            mdns: tomlreader.mdns,
+            allowed_peers: tomlreader.allowed_peers,
+            max_inbound_peers: tomlreader.max_inbound_peers,
+            max_outbound_peers: tomlreader.max_outbound_peers,
+            ban_duration: tomlreader.ban_duration,
+            banned_peers_path: tomlreader.banned_peers_path,
             client_host: tomlreader.client_host,
             client_port: tomlreader.client_port,
             client_apps: tomlreader.client_apps.to_vec(),
+            registry_admins: tomlreader.registry_admins,
             consensus_name: tomlreader.consensus_name,
         };
         let db_path: Database = Database {
@@ -103,43 +212,36 @@ impl Configuration {
         };
         let delay_in_micros: u128 = 1000 * tomlreader.transaction_execution_delay_limit as u128;
         let time_limit_for_block: u128 = 1000 * tomlreader.block_creation_time_limit as u128;
+        let block_time_drift: u128 = 1000 * tomlreader.max_block_time_drift as u128;
         let block_config: BlockConfig = BlockConfig {
             block_creation_time_limit: time_limit_for_block,
             block_transaction_limit: tomlreader.block_transaction_limit,
             transaction_execution_delay_limit: delay_in_micros,
+            block_gas_limit: tomlreader.block_gas_limit,
+            txn_gas_limit: tomlreader.txn_gas_limit,
+            max_txn_size: tomlreader.max_txn_size,
+            max_txn_headers: tomlreader.max_txn_headers,
+            max_message_size: tomlreader.max_message_size,
+            max_block_time_drift: block_time_drift,
+            determinism_check: tomlreader.determinism_check,
+        };
+        let wasm_config: WasmConfig = WasmConfig {
+            enabled: tomlreader.wasm_runtime,
+            gas_limit: tomlreader.wasm_gas_limit,
+            max_code_size: tomlreader.wasm_max_code_size,
         };
         let conf_obj = Configuration {
             node: node_obj,
             db: db_path,
             block_config,
+            wasm_config,
         };
-        conf_obj
+        Ok(conf_obj)
     }
 
-    pub fn init_config() -> TomlReaderConfig {
-        // get Current Directory
-        let cwd: String = match env::current_dir() {
-            Ok(c) => c.display().to_string(),
-            Err(e) => panic!(
-                "Error processing envirnment variable of current_exe dir - Err: {}!",
-                e
-            ),
-        };
-        let cwd: &Path = Path::new(&cwd);
-        info!(">> Current Working Directory: {}", cwd.to_string_lossy());
-
-        let config_file_path: PathBuf = cwd.join(&FILE_PATH.get_file_path());
-        info!("path = {}", config_file_path.to_string_lossy());
-        let mut config_file = match File::open(config_file_path) {
-            Ok(f) => f,
-            Err(e) => panic!("Error occurred opening config file:  Err: {}", e),
-        };
-        let mut config_file_str = String::new();
-        config_file
-            .read_to_string(&mut config_file_str)
-            .expect("Error reading config");
-        let conf_data: configreader::TomlReaderConfig = toml::from_str(&config_file_str).unwrap();
-        conf_data
+    /// config of the config file at file_path
+    pub fn from_file(file_path: &str) -> Result<Configuration, String> {
+        Configuration::from_toml(TomlReaderConfig::from_file(file_path)?)
     }
 }
 
@@ -151,10 +253,19 @@ pub struct Node {
     pub keypair: crypto::keypair::KeypairType,
     pub node_type: NODETYPE,
     pub genesis_block: bool,
+    pub chain_id: String,
     pub p2p_port: u16,
+    pub bootstrap_peers: Vec<String>,
+    pub mdns: bool,
+    pub allowed_peers: Vec<String>,
+    pub max_inbound_peers: usize,
+    pub max_outbound_peers: usize,
+    pub ban_duration: u64,
+    pub banned_peers_path: String,
     pub client_host: String,
     pub client_port: u32,
     pub client_apps: Vec<String>,
+    pub registry_admins: Vec<String>,
     pub consensus_name: String,
 }
 
@@ -168,19 +279,20 @@ pub struct BlockConfig {
     pub block_creation_time_limit: u128,         // in micro seconds
     pub block_transaction_limit: u64,            // max transaction count in a block
     pub transaction_execution_delay_limit: u128, // in micro seconds
+    pub block_gas_limit: u64,                    // max total gas of the transactions in a block
+    pub txn_gas_limit: u64,                      // max gas of a single transaction
+    pub max_txn_size: u64,                       // max bytes of a txn payload & its headers
+    pub max_txn_headers: u64,                    // max header entries of a transaction
+    pub max_message_size: u64,                   // max bytes of a gossip message or http body
+    pub max_block_time_drift: u128,              // in micro seconds, ahead of the local clock
+    pub determinism_check: bool,                 // re-execute blocks to detect non determinism
 }
 
-pub fn initialize_config(file_path: &str) {
-    &FILE_PATH.set_file_path(&String::from(file_path));
-    lazy_static::initialize(&GLOBAL_CONFIG);
-}
-
-lazy_static! {
-    static ref FILE_PATH: FilePath = FilePath::new();
-}
-
-lazy_static! {
-    pub static ref GLOBAL_CONFIG: Configuration = Configuration::new();
+#[derive(Debug)]
+pub struct WasmConfig {
+    pub enabled: bool,      // register the wasm app runtime
+    pub gas_limit: u64,     // max gas a single wasm call may use
+    pub max_code_size: u64, // max size of a deployed module in bytes
 }
 
 #[cfg(test)]
@@ -188,11 +300,29 @@ mod tests {
     #[test]
     fn test_config() {
         use super::*;
-        initialize_config("../../config.toml");
-        info!("conf data = {:?}", configreader::GLOBAL_CONFIG.node);
+        let config: Configuration = Configuration::from_file("../../config.toml").unwrap();
+        info!("conf data = {:?}", config.node);
         assert_eq!(
-            hex::encode(configreader::GLOBAL_CONFIG.node.keypair.public().encode()),
-            configreader::GLOBAL_CONFIG.node.hex_public
+            hex::encode(config.node.keypair.public().encode()),
+            config.node.hex_public
         );
     }
+
+    #[test]
+    fn test_config_from() {
+        use super::*;
+        let mut config: TomlReaderConfig =
+            TomlReaderConfig::from_file("../../config.toml").unwrap();
+        config.set_dbpath("other_db");
+        assert_eq!(config.dbpath, String::from("other_db"));
+        assert_eq!(TomlReaderConfig::from_toml_str("dbpath = 1").is_err(), true);
+        assert_eq!(TomlReaderConfig::from_file("missing.toml").is_err(), true);
+        let mut other_key: TomlReaderConfig =
+            TomlReaderConfig::from_file("../../config.toml").unwrap();
+        other_key.public = hex::encode(Keypair::generate().public().encode());
+        assert_eq!(Configuration::from_toml(other_key).is_err(), true);
+        // a config per node, independent of the one read from the file
+        let config: Configuration = Configuration::from_toml(config).unwrap();
+        assert_eq!(config.db.dbpath, String::from("other_db"));
+    }
 }
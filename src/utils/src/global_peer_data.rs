@@ -1,7 +1,6 @@
 use libp2p::core::{multiaddr::Protocol, Multiaddr, PeerId};
 use std::collections::hash_map::HashMap;
 use std::net::IpAddr;
-use std::sync::{Arc, Mutex};
 
 #[derive(Debug, Clone)]
 pub struct PeerData {
@@ -41,10 +40,6 @@ impl GlobalData {
     }
 }
 
-lazy_static! {
-    pub static ref GLOBALDATA: Arc<Mutex<GlobalData>> = Arc::new(Mutex::new(GlobalData::new()));
-}
-
 #[cfg(test)]
 mod tests_peer_data {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
@@ -60,11 +55,10 @@ mod tests_peer_data {
         let addr: Multiaddr = Multiaddr::empty();
         let id: PeerId = PeerId::random();
         let peer_data: PeerData = PeerData::new(id, time_stamp, addr);
-        {
-            GLOBALDATA.lock().unwrap().peers.insert(peer_id, peer_data);
-        }
+        let mut global_data = GlobalData::new();
+        global_data.peers.insert(peer_id, peer_data);
         assert_eq!(
-            GLOBALDATA.lock().unwrap().peers.len(),
+            global_data.peers.len(),
             1,
             "insertion error in global peer data"
         );
@@ -79,16 +73,10 @@ mod tests_peer_data {
         let id: PeerId = PeerId::random();
         let peer_data: PeerData = PeerData::new(id, time_stamp, addr);
         thread::sleep(Duration::from_millis(1000));
+        let mut global_data = GlobalData::new();
+        global_data.peers.insert(peer_id.clone(), peer_data);
         {
-            GLOBALDATA
-                .lock()
-                .unwrap()
-                .peers
-                .insert(peer_id.clone(), peer_data);
-        }
-        {
-            let mut lock_global_peer_data = GLOBALDATA.lock().unwrap();
-            let peer_data: &mut PeerData = lock_global_peer_data.peers.get_mut(&peer_id).unwrap();
+            let peer_data: &mut PeerData = global_data.peers.get_mut(&peer_id).unwrap();
             let get_network_addr_result = peer_data.get_network_addr();
             assert!(get_network_addr_result.is_ok());
         }
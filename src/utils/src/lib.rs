@@ -1,6 +1,3 @@
-#[macro_use]
-extern crate lazy_static;
-
 #[macro_use]
 pub extern crate log;
 
@@ -9,4 +6,6 @@ pub mod crypto;
 pub use crypto::keypair;
 pub mod global_peer_data;
 pub mod logger;
+pub mod peer_reports;
+pub mod queue_metrics;
 pub mod serializer;
@@ -0,0 +1,105 @@
+use super::keypair;
+use libp2p::{identity::PublicKey, PeerId};
+use std::collections::{HashMap, VecDeque};
+
+/// transaction origins remembered at most, the oldest are forgotten first
+pub const MAX_ORIGINS: usize = 10_000;
+
+/// misbehaviour of a peer detected outside of the p2p layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Offence {
+    /// a block of the peer failed update_block
+    InvalidBlock,
+    /// a transaction gossiped by the peer was refused by the pool
+    InvalidTransaction,
+}
+
+/// PeerReports collects the offences found by block & transaction
+/// processing, the p2p layer takes them to update peer reputations. Gossiped
+/// transactions are only known by hash there, so the peer which published
+/// each of them is recorded on arrival.
+#[derive(Debug, Default)]
+pub struct PeerReports {
+    // hex txn hash => peer which published the txn
+    origins: HashMap<String, PeerId>,
+    origin_order: VecDeque<String>,
+    reports: Vec<(PeerId, Offence)>,
+}
+
+impl PeerReports {
+    pub fn new() -> PeerReports {
+        PeerReports::default()
+    }
+
+    pub fn record_origin(&mut self, txn_hash: String, peer_id: PeerId) {
+        if self.origins.insert(txn_hash.clone(), peer_id).is_none() {
+            self.origin_order.push_back(txn_hash);
+        }
+        while self.origin_order.len() > MAX_ORIGINS {
+            if let Some(oldest) = self.origin_order.pop_front() {
+                self.origins.remove(&oldest);
+            }
+        }
+    }
+
+    pub fn report(&mut self, peer_id: PeerId, offence: Offence) {
+        self.reports.push((peer_id, offence));
+    }
+
+    /// report the peer which published txn_hash, if it is known
+    pub fn report_origin(&mut self, txn_hash: &String, offence: Offence) {
+        if let Some(peer_id) = self.origins.get(txn_hash).cloned() {
+            self.report(peer_id, offence);
+        }
+    }
+
+    /// report the node owning the hex public_key, e.g. a block author
+    pub fn report_public_key(&mut self, public_key: &String, offence: Offence) {
+        match keypair::PublicKey::from_string(public_key) {
+            Some(public_key) => self.report(PublicKey::Ed25519(public_key).into_peer_id(), offence),
+            None => warn!("offence of invalid public key {} ignored", public_key),
+        }
+    }
+
+    /// reports since the last call
+    pub fn take_reports(&mut self) -> Vec<(PeerId, Offence)> {
+        std::mem::replace(&mut self.reports, Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests_peer_reports {
+    use super::*;
+    use libp2p::identity::ed25519;
+
+    #[test]
+    fn test_peer_reports() {
+        let mut peer_reports = PeerReports::new();
+        let peer_id = PeerId::random();
+        peer_reports.record_origin(String::from("txn"), peer_id.clone());
+        peer_reports.report_origin(&String::from("txn"), Offence::InvalidTransaction);
+        peer_reports.report_origin(&String::from("unknown"), Offence::InvalidTransaction);
+
+        let author = ed25519::Keypair::generate();
+        let author_id: PeerId = PublicKey::Ed25519(author.public()).into_peer_id();
+        peer_reports.report_public_key(
+            &hex::encode(author.public().encode()),
+            Offence::InvalidBlock,
+        );
+        assert_eq!(
+            peer_reports.take_reports(),
+            vec![
+                (peer_id, Offence::InvalidTransaction),
+                (author_id, Offence::InvalidBlock)
+            ]
+        );
+        assert_eq!(peer_reports.take_reports(), Vec::new());
+
+        for index in 0..MAX_ORIGINS {
+            peer_reports.record_origin(index.to_string(), PeerId::random());
+        }
+        // the first origin got forgotten
+        peer_reports.report_origin(&String::from("txn"), Offence::InvalidTransaction);
+        assert_eq!(peer_reports.take_reports(), Vec::new());
+    }
+}
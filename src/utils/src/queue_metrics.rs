@@ -0,0 +1,96 @@
+use super::serializer::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// counters of a bounded queue since the node started, messages refused
+/// because it was full are counted as dropped. enqueued is a total, not the
+/// number of messages waiting in the queue.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QueueStats {
+    pub name: String,
+    pub capacity: usize,
+    pub enqueued: u64,
+    pub dropped: u64,
+}
+
+/// QueueMetrics collects the counters of the node's message queues by name
+#[derive(Debug, Default)]
+pub struct QueueMetrics {
+    queues: BTreeMap<String, QueueStats>,
+}
+
+impl QueueMetrics {
+    pub fn new() -> QueueMetrics {
+        QueueMetrics::default()
+    }
+
+    fn entry(&mut self, name: &str) -> &mut QueueStats {
+        self.queues
+            .entry(String::from(name))
+            .or_insert_with(|| QueueStats {
+                name: String::from(name),
+                ..QueueStats::default()
+            })
+    }
+
+    /// declare the queue name holding up to capacity messages
+    pub fn register(&mut self, name: &str, capacity: usize) {
+        self.entry(name).capacity = capacity;
+    }
+
+    pub fn enqueued(&mut self, name: &str) {
+        self.entry(name).enqueued += 1;
+    }
+
+    pub fn dropped(&mut self, name: &str) {
+        self.entry(name).dropped += 1;
+    }
+
+    /// record the result of a try_send on queue name, true if it was queued
+    pub fn record<E>(&mut self, name: &str, result: &Result<(), E>) -> bool {
+        match result {
+            Result::Ok(()) => {
+                self.enqueued(name);
+                true
+            }
+            Result::Err(_) => {
+                self.dropped(name);
+                false
+            }
+        }
+    }
+
+    pub fn stats(&self) -> Vec<QueueStats> {
+        self.queues.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests_queue_metrics {
+    use super::*;
+
+    #[test]
+    fn test_queue_metrics() {
+        let mut metrics = QueueMetrics::new();
+        metrics.register("node", 2);
+        assert_eq!(metrics.record::<()>("node", &Ok(())), true);
+        assert_eq!(metrics.record("node", &Err(())), false);
+        metrics.dropped("consensus");
+        assert_eq!(
+            metrics.stats(),
+            vec![
+                QueueStats {
+                    name: String::from("consensus"),
+                    capacity: 0,
+                    enqueued: 0,
+                    dropped: 1,
+                },
+                QueueStats {
+                    name: String::from("node"),
+                    capacity: 2,
+                    enqueued: 1,
+                    dropped: 1,
+                },
+            ]
+        );
+    }
+}
@@ -30,6 +30,23 @@ where
     };
 }
 
+/// deserialize like deserialize, slices longer than max_size bytes are
+/// rejected before decoding. The cbor decoder never allocates more than the
+/// input can hold, so a decoded value is bounded by max_size as well.
+pub fn deserialize_bounded<'a, T>(slice: &'a [u8], max_size: u64) -> Result<T, String>
+where
+    T: Deserialize<'a>,
+{
+    if slice.len() as u64 > max_size {
+        return Result::Err(format!(
+            "{} bytes exceed the size limit of {} bytes",
+            slice.len(),
+            max_size
+        ));
+    }
+    deserialize(slice)
+}
+
 #[cfg(test)]
 mod tests_sbserde {
     // Note this useful idiom: importing names from outer (for mod tests) scope.
@@ -68,5 +85,10 @@ mod tests_sbserde {
         assert_eq!(ferris.name, deserobj.name);
         assert_eq!(ferris.year_of_birth, deserobj.year_of_birth);
         assert_eq!(ferris.species, deserobj.species);
+
+        let bounded: Result<Mascot, String> = deserialize_bounded(&serobj, serobj.len() as u64);
+        assert_eq!(bounded.unwrap().name, ferris.name);
+        let bounded: Result<Mascot, String> = deserialize_bounded(&serobj, serobj.len() as u64 - 1);
+        assert_eq!(bounded.is_err(), true);
     }
 }
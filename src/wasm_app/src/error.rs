@@ -0,0 +1,74 @@
+use sdk::errors::AppError;
+use std::fmt;
+
+/// WasmError lists why a wasm transaction or query couldn't be applied
+#[derive(Debug, Clone, PartialEq)]
+pub enum WasmError {
+    InvalidTransaction,
+    UnknownQuery(String),
+    CodeTooLarge { size: u64, max: u64 },
+    InvalidCode(String),
+    CodeNotFound(String),
+    ContractNotFound(String),
+    MethodNotFound(String),
+    OutOfGas { limit: u64 },
+    Trap(String),
+    ContractFailed(i32),
+    StorageLimit,
+    Serialization,
+}
+
+impl WasmError {
+    /// stable numeric code of the error, reported to the node in AppError
+    pub fn code(&self) -> u32 {
+        match self {
+            WasmError::InvalidTransaction => 1,
+            WasmError::UnknownQuery(_) => 2,
+            WasmError::CodeTooLarge { .. } => 3,
+            WasmError::InvalidCode(_) => 4,
+            WasmError::CodeNotFound(_) => 5,
+            WasmError::ContractNotFound(_) => 6,
+            WasmError::MethodNotFound(_) => 7,
+            WasmError::OutOfGas { .. } => 8,
+            WasmError::Trap(_) => 9,
+            WasmError::ContractFailed(_) => 10,
+            WasmError::StorageLimit => 11,
+            WasmError::Serialization => 12,
+        }
+    }
+}
+
+impl fmt::Display for WasmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WasmError::InvalidTransaction => write!(f, "transaction couldn't be decoded"),
+            WasmError::UnknownQuery(method) => write!(f, "unknown query {}", method),
+            WasmError::CodeTooLarge { size, max } => {
+                write!(
+                    f,
+                    "module of {} bytes exceeds the limit of {} bytes",
+                    size, max
+                )
+            }
+            WasmError::InvalidCode(reason) => write!(f, "invalid wasm module: {}", reason),
+            WasmError::CodeNotFound(code_hash) => write!(f, "code {} not found", code_hash),
+            WasmError::ContractNotFound(address) => write!(f, "contract {} not found", address),
+            WasmError::MethodNotFound(method) => {
+                write!(f, "contract doesn't export method {}", method)
+            }
+            WasmError::OutOfGas { limit } => write!(f, "out of gas, limit {}", limit),
+            WasmError::Trap(reason) => write!(f, "contract trapped: {}", reason),
+            WasmError::ContractFailed(status) => {
+                write!(f, "contract returned failure status {}", status)
+            }
+            WasmError::StorageLimit => write!(f, "contract storage has too many keys"),
+            WasmError::Serialization => write!(f, "state couldn't be serialized"),
+        }
+    }
+}
+
+impl From<WasmError> for AppError {
+    fn from(error: WasmError) -> AppError {
+        AppError::new(error.code(), &error.to_string())
+    }
+}
@@ -0,0 +1,9 @@
+#[macro_use]
+extern crate log;
+
+#[macro_use]
+extern crate serde;
+
+pub mod error;
+pub mod runtime;
+pub mod transaction;
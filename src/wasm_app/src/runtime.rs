@@ -0,0 +1,368 @@
+use crate::error::WasmError;
+use exonum_crypto::Hash;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use wasmi::core::{Trap, TrapCode};
+use wasmi::{
+    Caller, Config, Engine, Extern, Linker, Memory, Module, Store, StoreLimits, StoreLimitsBuilder,
+};
+
+/// gas charged by every host function call, on top of the executed instructions
+pub const HOST_CALL_GAS: u64 = 100;
+/// gas charged per byte copied between the contract and the host
+pub const HOST_BYTE_GAS: u64 = 1;
+/// max number of keys a single contract may keep in its storage
+pub const MAX_STORAGE_KEYS: usize = 10_000;
+/// gas charged per key of contract storage loaded for a call
+pub const STORAGE_LOAD_GAS: u64 = 200;
+/// max linear memory of a contract, memory.grow past it returns -1
+pub const MAX_MEMORY_BYTES: usize = 16 * 1024 * 1024;
+/// max number of compiled modules kept between calls
+const MAX_CACHED_MODULES: usize = 64;
+
+/// storage of one contract, contract defined keys and values
+pub type ContractStorage = BTreeMap<Vec<u8>, Vec<u8>>;
+
+/// CallContext is everything a contract method can see while it runs
+pub struct CallContext {
+    pub caller: String,
+    pub input: Vec<u8>,
+    pub storage: ContractStorage,
+    pub read_only: bool,
+}
+
+/// CallOutcome of a successful call, storage is the contract storage after
+/// the call and is only written back to state by the caller of the runtime
+#[derive(Debug)]
+pub struct CallOutcome {
+    pub output: Vec<u8>,
+    pub storage: ContractStorage,
+    pub gas_used: u64,
+}
+
+/// CallFailure of a call, gas_used is the fuel burned before it failed
+#[derive(Debug)]
+pub struct CallFailure {
+    pub error: WasmError,
+    pub gas_used: u64,
+}
+
+impl From<WasmError> for CallFailure {
+    fn from(error: WasmError) -> CallFailure {
+        CallFailure { error, gas_used: 0 }
+    }
+}
+
+struct HostState {
+    context: CallContext,
+    output: Vec<u8>,
+    out_of_gas: bool,
+    storage_limit: bool,
+    limits: StoreLimits,
+}
+
+/// WasmRuntime runs contract methods in the wasmi interpreter. Contracts only
+/// reach the outside through the "env" host functions below and every
+/// instruction costs fuel, so a call can't run longer than its gas limit.
+/// Float instructions are refused as their results may differ between
+/// nodes, and memory is capped at MAX_MEMORY_BYTES.
+///
+/// A contract method is an export of type `() -> i32`, 0 means success and
+/// any other value fails the call without touching storage. Host functions,
+/// pointers & lengths are i32 offsets in the exported "memory":
+/// - input_len() -> i32, input_read(ptr): the call input
+/// - caller_len() -> i32, caller_read(ptr): hex public key of the signer
+/// - storage_read(key_ptr, key_len, value_ptr, value_cap) -> i32: copies at
+///   most value_cap bytes of the value and returns its length, -1 if missing
+/// - storage_write(key_ptr, key_len, value_ptr, value_len)
+/// - storage_remove(key_ptr, key_len)
+/// - output_write(ptr, len): sets the bytes returned by the call
+pub struct WasmRuntime {
+    engine: Engine,
+    gas_limit: u64,
+    max_code_size: u64,
+    // compiled modules by code hash, compiling doesn't depend on the caller
+    modules: Mutex<HashMap<Hash, Arc<Module>>>,
+}
+
+impl WasmRuntime {
+    pub fn new(gas_limit: u64, max_code_size: u64) -> WasmRuntime {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        config.floats(false);
+        WasmRuntime {
+            engine: Engine::new(&config),
+            gas_limit,
+            max_code_size,
+            modules: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// max gas of a single call, a txn can ask for less but never for more
+    pub fn gas_limit(&self) -> u64 {
+        self.gas_limit
+    }
+
+    /// check that code is a valid wasm module within the size limit
+    pub fn check_code(&self, code: &Vec<u8>) -> Result<(), WasmError> {
+        self.compile(code).map(|_| ())
+    }
+
+    fn compile(&self, code: &Vec<u8>) -> Result<Module, WasmError> {
+        if code.len() as u64 > self.max_code_size {
+            return Err(WasmError::CodeTooLarge {
+                size: code.len() as u64,
+                max: self.max_code_size,
+            });
+        }
+        Module::new(&self.engine, code.as_slice())
+            .map_err(|error| WasmError::InvalidCode(error.to_string()))
+    }
+
+    /// module of code, compiled once per code_hash
+    fn module(&self, code_hash: &Hash, code: &Vec<u8>) -> Result<Arc<Module>, WasmError> {
+        if let Some(module) = self.modules.lock().unwrap().get(code_hash) {
+            return Ok(module.clone());
+        }
+        let module: Arc<Module> = Arc::new(self.compile(code)?);
+        let mut modules = self.modules.lock().unwrap();
+        if modules.len() >= MAX_CACHED_MODULES {
+            modules.clear();
+        }
+        modules.insert(*code_hash, module.clone());
+        Ok(module)
+    }
+
+    /// run method of code with at most gas_limit gas, code_hash is the hash of code
+    pub fn call(
+        &self,
+        code_hash: &Hash,
+        code: &Vec<u8>,
+        method: &str,
+        context: CallContext,
+        gas_limit: u64,
+    ) -> Result<CallOutcome, CallFailure> {
+        let gas_limit: u64 = std::cmp::min(gas_limit, self.gas_limit);
+        let module: Arc<Module> = self.module(code_hash, code)?;
+        let mut store: Store<HostState> = Store::new(
+            &self.engine,
+            HostState {
+                context,
+                output: Vec::new(),
+                out_of_gas: false,
+                storage_limit: false,
+                limits: StoreLimitsBuilder::new()
+                    .memory_size(MAX_MEMORY_BYTES)
+                    .build(),
+            },
+        );
+        store.limiter(|host_state| &mut host_state.limits);
+        store.add_fuel(gas_limit).map_err(host_error)?;
+        let linker: Linker<HostState> = host_linker(&self.engine)?;
+        let result = match linker.instantiate(&mut store, &module) {
+            Result::Ok(instance_pre) => match instance_pre.start(&mut store) {
+                Result::Ok(instance) => match instance.get_typed_func::<(), i32>(&store, method) {
+                    Result::Ok(function) => function.call(&mut store, ()),
+                    Result::Err(_) => {
+                        return Err(WasmError::MethodNotFound(String::from(method)).into())
+                    }
+                },
+                Result::Err(error) => Err(error),
+            },
+            Result::Err(error) => return Err(WasmError::InvalidCode(error.to_string()).into()),
+        };
+        let gas_used: u64 = store.fuel_consumed().unwrap_or(0);
+        let host_state: HostState = store.into_data();
+        let error: WasmError = match result {
+            Result::Ok(0) => {
+                return Ok(CallOutcome {
+                    output: host_state.output,
+                    storage: host_state.context.storage,
+                    gas_used,
+                })
+            }
+            Result::Ok(status) => WasmError::ContractFailed(status),
+            Result::Err(error) => {
+                if host_state.out_of_gas || error.as_trap_code() == Some(TrapCode::OutOfFuel) {
+                    WasmError::OutOfGas { limit: gas_limit }
+                } else if host_state.storage_limit {
+                    WasmError::StorageLimit
+                } else {
+                    WasmError::Trap(error.to_string())
+                }
+            }
+        };
+        // fuel of an out of gas call is used up even if the last charge didn't fit
+        let gas_used: u64 = match error {
+            WasmError::OutOfGas { .. } => gas_limit,
+            _ => gas_used,
+        };
+        Err(CallFailure { error, gas_used })
+    }
+}
+
+fn host_error<E: std::fmt::Display>(error: E) -> WasmError {
+    WasmError::Trap(error.to_string())
+}
+
+/// charge the gas of a host call copying bytes bytes
+fn charge(caller: &mut Caller<'_, HostState>, bytes: usize) -> Result<(), Trap> {
+    let gas: u64 = HOST_CALL_GAS.saturating_add(HOST_BYTE_GAS.saturating_mul(bytes as u64));
+    match caller.consume_fuel(gas) {
+        Result::Ok(_) => Ok(()),
+        Result::Err(_) => {
+            caller.data_mut().out_of_gas = true;
+            Err(Trap::new("out of gas"))
+        }
+    }
+}
+
+fn memory(caller: &Caller<'_, HostState>) -> Result<Memory, Trap> {
+    caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| Trap::new("contract doesn't export memory"))
+}
+
+fn read_memory(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Result<Vec<u8>, Trap> {
+    if ptr < 0 || len < 0 {
+        return Err(Trap::new("negative memory offset"));
+    }
+    charge(caller, len as usize)?;
+    let mut buffer: Vec<u8> = vec![0; len as usize];
+    memory(caller)?
+        .read(&*caller, ptr as usize, &mut buffer)
+        .map_err(|error| Trap::new(error.to_string()))?;
+    Ok(buffer)
+}
+
+fn write_memory(caller: &mut Caller<'_, HostState>, ptr: i32, data: &[u8]) -> Result<(), Trap> {
+    if ptr < 0 {
+        return Err(Trap::new("negative memory offset"));
+    }
+    charge(caller, data.len())?;
+    memory(caller)?
+        .write(&mut *caller, ptr as usize, data)
+        .map_err(|error| Trap::new(error.to_string()))
+}
+
+fn check_writable(caller: &Caller<'_, HostState>) -> Result<(), Trap> {
+    if caller.data().context.read_only {
+        return Err(Trap::new("storage can't be changed by a query"));
+    }
+    Ok(())
+}
+
+fn host_linker(engine: &Engine) -> Result<Linker<HostState>, WasmError> {
+    let mut linker: Linker<HostState> = Linker::new(engine);
+    linker
+        .func_wrap(
+            "env",
+            "input_len",
+            |mut caller: Caller<'_, HostState>| -> Result<i32, Trap> {
+                charge(&mut caller, 0)?;
+                Ok(caller.data().context.input.len() as i32)
+            },
+        )
+        .map_err(host_error)?;
+    linker
+        .func_wrap(
+            "env",
+            "input_read",
+            |mut caller: Caller<'_, HostState>, ptr: i32| -> Result<(), Trap> {
+                let input: Vec<u8> = caller.data().context.input.clone();
+                write_memory(&mut caller, ptr, &input)
+            },
+        )
+        .map_err(host_error)?;
+    linker
+        .func_wrap(
+            "env",
+            "caller_len",
+            |mut caller: Caller<'_, HostState>| -> Result<i32, Trap> {
+                charge(&mut caller, 0)?;
+                Ok(caller.data().context.caller.len() as i32)
+            },
+        )
+        .map_err(host_error)?;
+    linker
+        .func_wrap(
+            "env",
+            "caller_read",
+            |mut caller: Caller<'_, HostState>, ptr: i32| -> Result<(), Trap> {
+                let signer: Vec<u8> = caller.data().context.caller.clone().into_bytes();
+                write_memory(&mut caller, ptr, &signer)
+            },
+        )
+        .map_err(host_error)?;
+    linker
+        .func_wrap(
+            "env",
+            "storage_read",
+            |mut caller: Caller<'_, HostState>,
+             key_ptr: i32,
+             key_len: i32,
+             value_ptr: i32,
+             value_cap: i32|
+             -> Result<i32, Trap> {
+                let key: Vec<u8> = read_memory(&mut caller, key_ptr, key_len)?;
+                let value: Vec<u8> = match caller.data().context.storage.get(&key) {
+                    Some(value) => value.clone(),
+                    None => return Ok(-1),
+                };
+                let copied: usize = std::cmp::min(value.len(), value_cap.max(0) as usize);
+                write_memory(&mut caller, value_ptr, &value[..copied])?;
+                Ok(value.len() as i32)
+            },
+        )
+        .map_err(host_error)?;
+    linker
+        .func_wrap(
+            "env",
+            "storage_write",
+            |mut caller: Caller<'_, HostState>,
+             key_ptr: i32,
+             key_len: i32,
+             value_ptr: i32,
+             value_len: i32|
+             -> Result<(), Trap> {
+                check_writable(&caller)?;
+                let key: Vec<u8> = read_memory(&mut caller, key_ptr, key_len)?;
+                let value: Vec<u8> = read_memory(&mut caller, value_ptr, value_len)?;
+                let host_state: &mut HostState = caller.data_mut();
+                if !host_state.context.storage.contains_key(&key)
+                    && host_state.context.storage.len() >= MAX_STORAGE_KEYS
+                {
+                    host_state.storage_limit = true;
+                    return Err(Trap::new("storage limit reached"));
+                }
+                host_state.context.storage.insert(key, value);
+                Ok(())
+            },
+        )
+        .map_err(host_error)?;
+    linker
+        .func_wrap(
+            "env",
+            "storage_remove",
+            |mut caller: Caller<'_, HostState>, key_ptr: i32, key_len: i32| -> Result<(), Trap> {
+                check_writable(&caller)?;
+                let key: Vec<u8> = read_memory(&mut caller, key_ptr, key_len)?;
+                caller.data_mut().context.storage.remove(&key);
+                Ok(())
+            },
+        )
+        .map_err(host_error)?;
+    linker
+        .func_wrap(
+            "env",
+            "output_write",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| -> Result<(), Trap> {
+                let output: Vec<u8> = read_memory(&mut caller, ptr, len)?;
+                caller.data_mut().output = output;
+                Ok(())
+            },
+        )
+        .map_err(host_error)?;
+    Ok(linker)
+}
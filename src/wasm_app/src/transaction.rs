@@ -0,0 +1,598 @@
+extern crate utils;
+use crate::error::WasmError;
+use crate::runtime::{
+    CallContext, CallOutcome, ContractStorage, WasmRuntime, MAX_STORAGE_KEYS, STORAGE_LOAD_GAS,
+};
+use exonum_crypto::{hash, Hash};
+use exonum_merkledb::ObjectHash;
+use sdk::block_context::BlockContext;
+use sdk::errors::AppError;
+use sdk::signed_transaction::SignedTransaction;
+use sdk::state::State;
+use sdk::traits::{AppHandler, ReadOnlyStateContext, StateContext};
+use utils::serializer::{deserialize, serialize};
+
+/// name under which the wasm runtime is registered as an app
+pub const WASM_APP_NAME: &str = "wasm";
+
+/// WasmTransaction is the txn payload of a SignedTransaction sent to the wasm app
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WasmTransaction {
+    /// deploy code as a new contract owned by the signer, the contract address
+    /// is the hex hash of the SignedTransaction
+    Deploy { code: Vec<u8> },
+    /// run method of contract, gas_limit is capped by the node wasm_gas_limit
+    /// and by the gas left to the txn
+    Call {
+        contract: String,
+        method: String,
+        input: Vec<u8>,
+        gas_limit: u64,
+    },
+}
+
+/// ContractInfo is kept under contract_key, code_hash is also set as the
+/// State::code_hash of that key
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContractInfo {
+    pub owner: String,
+    pub code_hash: Hash,
+}
+
+/// WasmQuery is the args of the "call" query, method runs read only
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WasmQuery {
+    pub contract: String,
+    pub method: String,
+    pub input: Vec<u8>,
+}
+
+/// module code, shared by every contract deployed with the same code
+pub fn code_key(code_hash: &Hash) -> String {
+    format!("wasm/code/{}", code_hash.to_hex())
+}
+
+pub fn contract_key(address: &String) -> String {
+    format!("wasm/contract/{}", address)
+}
+
+/// prefix of the storage keys of a contract, followed by the hex encoded key
+pub fn storage_prefix(address: &String) -> String {
+    format!("wasm/storage/{}/", address)
+}
+
+fn get_contract<C: ReadOnlyStateContext + ?Sized>(
+    state_context: &C,
+    address: &String,
+) -> Result<Option<ContractInfo>, WasmError> {
+    match state_context.get(&contract_key(address)) {
+        Some(state) => match deserialize(state.get_data().as_slice()) {
+            Result::Ok(contract) => Ok(Some(contract)),
+            Result::Err(_) => Err(WasmError::Serialization),
+        },
+        None => Ok(None),
+    }
+}
+
+/// code of the contract at address & its hash
+fn get_code<C: ReadOnlyStateContext + ?Sized>(
+    state_context: &C,
+    address: &String,
+) -> Result<(Hash, Vec<u8>), WasmError> {
+    let contract: ContractInfo = match get_contract(state_context, address)? {
+        Some(contract) => contract,
+        None => return Err(WasmError::ContractNotFound(address.clone())),
+    };
+    match state_context.get(&code_key(&contract.code_hash)) {
+        Some(state) => Ok((contract.code_hash, state.get_data().clone())),
+        None => Err(WasmError::CodeNotFound(contract.code_hash.to_hex())),
+    }
+}
+
+fn get_storage<C: ReadOnlyStateContext + ?Sized>(
+    state_context: &C,
+    address: &String,
+) -> Result<ContractStorage, WasmError> {
+    let prefix: String = storage_prefix(address);
+    // '0' is the character following '/', so end is just past the prefix
+    let end: String = format!("{}0", &prefix[..prefix.len() - 1]);
+    let entries: Vec<(String, State)> = state_context.range(&prefix, &end, MAX_STORAGE_KEYS + 1);
+    if entries.len() > MAX_STORAGE_KEYS {
+        return Err(WasmError::StorageLimit);
+    }
+    let mut storage = ContractStorage::new();
+    for (key, state) in entries {
+        match hex::decode(&key[prefix.len()..]) {
+            Result::Ok(storage_key) => storage.insert(storage_key, state.get_data().clone()),
+            Result::Err(_) => return Err(WasmError::Serialization),
+        };
+    }
+    Ok(storage)
+}
+
+/// write the keys changed between before and after back to state
+fn put_storage(
+    state_context: &mut dyn StateContext,
+    address: &String,
+    before: &ContractStorage,
+    after: &ContractStorage,
+) {
+    let prefix: String = storage_prefix(address);
+    for key in before.keys() {
+        if !after.contains_key(key) {
+            state_context.delete(&format!("{}{}", prefix, hex::encode(key)));
+        }
+    }
+    for (key, value) in after.iter() {
+        if before.get(key) != Some(value) {
+            let mut state = State::new();
+            state.set_data(value);
+            state_context.put(&format!("{}{}", prefix, hex::encode(key)), state);
+        }
+    }
+}
+
+/// WasmApp runs contracts deployed by transaction, so business logic can be
+/// added without shipping a native app library to every node
+pub struct WasmApp {
+    name: String,
+    runtime: WasmRuntime,
+}
+
+impl WasmApp {
+    pub fn new(gas_limit: u64, max_code_size: u64) -> WasmApp {
+        WasmApp {
+            name: String::from(WASM_APP_NAME),
+            runtime: WasmRuntime::new(gas_limit, max_code_size),
+        }
+    }
+
+    fn deploy(
+        &self,
+        txn: &SignedTransaction,
+        code: &Vec<u8>,
+        state_context: &mut dyn StateContext,
+    ) -> Result<(), WasmError> {
+        self.runtime.check_code(code)?;
+        let code_hash: Hash = hash(code);
+        let code_key: String = code_key(&code_hash);
+        if !state_context.contains(&code_key) {
+            let mut code_state = State::new();
+            code_state.set_data(code);
+            code_state.set_code_hash(code_hash);
+            state_context.put(&code_key, code_state);
+        }
+        let address: String = txn.object_hash().to_hex();
+        let contract = ContractInfo {
+            owner: txn.signer.clone(),
+            code_hash,
+        };
+        let mut contract_state = State::new();
+        match serialize(&contract) {
+            Result::Ok(data) => contract_state.set_data(&data),
+            Result::Err(_) => return Err(WasmError::Serialization),
+        };
+        contract_state.set_code_hash(code_hash);
+        state_context.put(&contract_key(&address), contract_state);
+        info!(
+            "wasm contract {} deployed with code {}",
+            address,
+            code_hash.to_hex()
+        );
+        Ok(())
+    }
+
+    fn call(
+        &self,
+        txn: &SignedTransaction,
+        contract: &String,
+        method: &String,
+        input: &Vec<u8>,
+        gas_limit: u64,
+        state_context: &mut dyn StateContext,
+    ) -> Result<(), WasmError> {
+        let (code_hash, code) = get_code(state_context, contract)?;
+        let storage: ContractStorage = get_storage(state_context, contract)?;
+        // every loaded key is copied into the call, not only the bytes read
+        let load_gas: u64 = STORAGE_LOAD_GAS.saturating_mul(storage.len() as u64);
+        if state_context.charge_gas(load_gas).is_err() {
+            return Err(WasmError::OutOfGas { limit: gas_limit });
+        }
+        let context = CallContext {
+            caller: txn.signer.clone(),
+            input: input.clone(),
+            storage: storage.clone(),
+            read_only: false,
+        };
+        // the contract can't burn more fuel than the txn has gas left in the block
+        let gas_limit: u64 = std::cmp::min(gas_limit, state_context.remaining_gas());
+        let outcome: CallOutcome = match self
+            .runtime
+            .call(&code_hash, &code, method, context, gas_limit)
+        {
+            Result::Ok(outcome) => outcome,
+            Result::Err(failure) => {
+                // the fuel was burned, the txn pays for it
+                let _ = state_context.charge_gas(failure.gas_used);
+                return Err(failure.error);
+            }
+        };
+        // contract gas also counts against the gas of the txn in the block
+        if state_context.charge_gas(outcome.gas_used).is_err() {
+            return Err(WasmError::OutOfGas { limit: gas_limit });
+        }
+        put_storage(state_context, contract, &storage, &outcome.storage);
+        debug!(
+            "wasm contract {} method {} used {} gas",
+            contract, method, outcome.gas_used
+        );
+        Ok(())
+    }
+}
+
+impl AppHandler for WasmApp {
+    fn execute(
+        &self,
+        txn: &SignedTransaction,
+        state_context: &mut dyn StateContext,
+        _block_context: &BlockContext,
+    ) -> Result<(), AppError> {
+        let result = match deserialize::<WasmTransaction>(txn.txn.as_slice()) {
+            Result::Ok(WasmTransaction::Deploy { code }) => self.deploy(txn, &code, state_context),
+            Result::Ok(WasmTransaction::Call {
+                contract,
+                method,
+                input,
+                gas_limit,
+            }) => self.call(txn, &contract, &method, &input, gas_limit, state_context),
+            Result::Err(_) => Err(WasmError::InvalidTransaction),
+        };
+        state_context.put_txn(&txn.object_hash(), txn.clone());
+        result.map_err(AppError::from)
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn version(&self) -> String {
+        String::from(env!("CARGO_PKG_VERSION"))
+    }
+
+    fn validate(&self, txn: &SignedTransaction) -> Result<(), AppError> {
+        match deserialize::<WasmTransaction>(txn.txn.as_slice()) {
+            Result::Ok(WasmTransaction::Deploy { code }) => {
+                self.runtime.check_code(&code).map_err(AppError::from)
+            }
+            Result::Ok(WasmTransaction::Call { gas_limit, .. }) if gas_limit == 0 => {
+                Err(WasmError::OutOfGas { limit: 0 }.into())
+            }
+            Result::Ok(WasmTransaction::Call { .. }) => Ok(()),
+            Result::Err(_) => Err(WasmError::InvalidTransaction.into()),
+        }
+    }
+
+    /// "call" runs a WasmQuery read only and returns the contract output,
+    /// "contract" returns the Option<ContractInfo> of the address in args
+    fn query(
+        &self,
+        method: &String,
+        args: &Vec<u8>,
+        state_context: &dyn ReadOnlyStateContext,
+    ) -> Result<Vec<u8>, AppError> {
+        if method == "call" {
+            let query: WasmQuery = deserialize(args).map_err(|_| WasmError::Serialization)?;
+            let (code_hash, code) = get_code(state_context, &query.contract)?;
+            let context = CallContext {
+                caller: String::new(),
+                input: query.input,
+                storage: get_storage(state_context, &query.contract)?,
+                read_only: true,
+            };
+            let outcome: CallOutcome = self
+                .runtime
+                .call(
+                    &code_hash,
+                    &code,
+                    &query.method,
+                    context,
+                    self.runtime.gas_limit(),
+                )
+                .map_err(|failure| AppError::from(failure.error))?;
+            Ok(outcome.output)
+        } else if method == "contract" {
+            let address: String = deserialize(args).map_err(|_| WasmError::Serialization)?;
+            serialize(&get_contract(state_context, &address)?)
+                .map_err(|_| AppError::from(WasmError::Serialization))
+        } else {
+            Err(WasmError::UnknownQuery(method.clone()).into())
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_wasm_app {
+    use super::*;
+    use sdk::gas::{GasMeter, TXN_BASE_GAS};
+    use sdk::testing::MockStateContext;
+    use std::collections::HashMap;
+    use utils::keypair::{CryptoKeypair, Keypair, KeypairType};
+
+    const COUNTER: &str = r#"
+        (module
+            (import "env" "storage_read" (func $storage_read (param i32 i32 i32 i32) (result i32)))
+            (import "env" "storage_write" (func $storage_write (param i32 i32 i32 i32)))
+            (import "env" "output_write" (func $output_write (param i32 i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "count")
+            (func (export "increment") (result i32)
+                (drop (call $storage_read (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 4)))
+                (i32.store (i32.const 16) (i32.add (i32.load (i32.const 16)) (i32.const 1)))
+                (call $storage_write (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 4))
+                (i32.const 0))
+            (func (export "get") (result i32)
+                (drop (call $storage_read (i32.const 0) (i32.const 5) (i32.const 16) (i32.const 4)))
+                (call $output_write (i32.const 16) (i32.const 4))
+                (i32.const 0))
+            (func (export "spin") (result i32)
+                (call $storage_write (i32.const 0) (i32.const 5) (i32.const 0) (i32.const 4))
+                (loop $spin (br $spin))
+                (i32.const 0))
+            (func (export "abort") (result i32)
+                unreachable)
+            (func (export "grow") (result i32)
+                (if (i32.eq (memory.grow (i32.const 1024)) (i32.const -1))
+                    (then (return (i32.const 7))))
+                (i32.const 0)))
+    "#;
+
+    const FLOATS: &str = r#"
+        (module
+            (func (export "half") (result i32)
+                (i32.trunc_f32_s (f32.div (f32.const 1) (f32.const 2)))))
+    "#;
+
+    fn signed_txn(kp: &KeypairType, txn: &WasmTransaction) -> SignedTransaction {
+        SignedTransaction::create(kp, WASM_APP_NAME, HashMap::new(), serialize(txn).unwrap())
+    }
+
+    fn call_txn(
+        kp: &KeypairType,
+        contract: &String,
+        method: &str,
+        gas_limit: u64,
+    ) -> SignedTransaction {
+        signed_txn(
+            kp,
+            &WasmTransaction::Call {
+                contract: contract.clone(),
+                method: String::from(method),
+                input: vec![],
+                gas_limit,
+            },
+        )
+    }
+
+    fn query_get(app: &WasmApp, state_context: &MockStateContext, contract: &String) -> Vec<u8> {
+        let query = WasmQuery {
+            contract: contract.clone(),
+            method: String::from("get"),
+            input: vec![],
+        };
+        app.query(
+            &String::from("call"),
+            &serialize(&query).unwrap(),
+            state_context,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_deploy_and_call() {
+        let app = WasmApp::new(1_000_000, 64 * 1024);
+        let kp = Keypair::generate();
+        let mut state_context = MockStateContext::default();
+        let block_context = BlockContext::new(1, 0, &String::new(), &Hash::zero());
+        let code: Vec<u8> = wat::parse_str(COUNTER).unwrap();
+        let deploy_txn = signed_txn(&kp, &WasmTransaction::Deploy { code: code.clone() });
+        assert_eq!(app.validate(&deploy_txn), Ok(()));
+        assert_eq!(
+            app.execute(&deploy_txn, &mut state_context, &block_context),
+            Ok(())
+        );
+        let contract: String = deploy_txn.object_hash().to_hex();
+        let contract_state: State = state_context.get(&contract_key(&contract)).unwrap();
+        assert_eq!(contract_state.get_code_hash(), hash(&code));
+        assert_eq!(state_context.contains(&code_key(&hash(&code))), true);
+
+        for _ in 0..2 {
+            let txn = call_txn(&kp, &contract, "increment", 100_000);
+            assert_eq!(
+                app.execute(&txn, &mut state_context, &block_context),
+                Ok(())
+            );
+        }
+        assert_eq!(query_get(&app, &state_context, &contract), vec![2, 0, 0, 0]);
+
+        // out of gas rolls back the storage write made before the loop
+        let txn = call_txn(&kp, &contract, "spin", 10_000);
+        let error: AppError = app
+            .execute(&txn, &mut state_context, &block_context)
+            .unwrap_err();
+        assert_eq!(error.code, WasmError::OutOfGas { limit: 0 }.code());
+        assert_eq!(state_context.contains_txn(&txn.object_hash()), true);
+        assert_eq!(query_get(&app, &state_context, &contract), vec![2, 0, 0, 0]);
+
+        // queries can't write storage
+        let query = WasmQuery {
+            contract: contract.clone(),
+            method: String::from("increment"),
+            input: vec![],
+        };
+        assert_eq!(
+            app.query(
+                &String::from("call"),
+                &serialize(&query).unwrap(),
+                &state_context
+            )
+            .is_err(),
+            true
+        );
+        let txn = call_txn(&kp, &contract, "missing", 10_000);
+        assert_eq!(
+            app.execute(&txn, &mut state_context, &block_context)
+                .unwrap_err()
+                .code,
+            WasmError::MethodNotFound(String::new()).code()
+        );
+    }
+
+    #[test]
+    fn test_calls_stop_at_block_gas_limit() {
+        let block_gas_limit: u64 = 300_000;
+        let txn_gas_limit: u64 = 100_000;
+        // the node would let a single call run far past the gas of the block
+        let app = WasmApp::new(10 * block_gas_limit, 64 * 1024);
+        let kp = Keypair::generate();
+        let mut state_context = MockStateContext::default();
+        let block_context = BlockContext::new(1, 0, &String::new(), &Hash::zero());
+        let code: Vec<u8> = wat::parse_str(COUNTER).unwrap();
+        let deploy_txn = signed_txn(&kp, &WasmTransaction::Deploy { code });
+        app.execute(&deploy_txn, &mut state_context, &block_context)
+            .unwrap();
+        let contract: String = deploy_txn.object_hash().to_hex();
+
+        // a block of calls asking for all the gas they can get, reserved like the pool does
+        let mut block_gas_used: u64 = 0;
+        let mut block_fuel: u64 = 0;
+        let mut executed: usize = 0;
+        for _ in 0..5 {
+            if block_gas_used + txn_gas_limit > block_gas_limit {
+                break;
+            }
+            let txn = call_txn(&kp, &contract, "spin", u64::MAX);
+            let mut meter = GasMeter::new(&mut state_context, txn_gas_limit);
+            assert_eq!(meter.charge_txn(&txn), true);
+            let result = app.call(
+                &txn,
+                &contract,
+                &String::from("spin"),
+                &vec![],
+                u64::MAX,
+                &mut meter,
+            );
+            match result {
+                Err(WasmError::OutOfGas { limit }) => {
+                    assert!(limit < txn_gas_limit - TXN_BASE_GAS);
+                    block_fuel += limit;
+                }
+                other => panic!("spin returned {:?}", other),
+            }
+            // the burned fuel is charged to the txn
+            assert_eq!(meter.remaining_gas(), 0);
+            block_gas_used += meter.gas_used();
+            executed += 1;
+        }
+        assert_eq!(executed, 3);
+        assert!(block_fuel <= block_gas_used);
+        assert!(block_gas_used <= block_gas_limit);
+    }
+
+    #[test]
+    fn test_call_limits() {
+        let app = WasmApp::new(1_000_000, 64 * 1024);
+        let kp = Keypair::generate();
+        let mut state_context = MockStateContext::default();
+        let block_context = BlockContext::new(1, 0, &String::new(), &Hash::zero());
+        let code: Vec<u8> = wat::parse_str(COUNTER).unwrap();
+        let deploy_txn = signed_txn(&kp, &WasmTransaction::Deploy { code });
+        app.execute(&deploy_txn, &mut state_context, &block_context)
+            .unwrap();
+        let contract: String = deploy_txn.object_hash().to_hex();
+
+        // a trap is a trap even if the call has little gas left
+        let txn = call_txn(&kp, &contract, "abort", 10_000);
+        let mut meter = GasMeter::new(&mut state_context, 100_000);
+        let result = app.call(
+            &txn,
+            &contract,
+            &String::from("abort"),
+            &vec![],
+            10_000,
+            &mut meter,
+        );
+        assert_eq!(
+            result.unwrap_err().code(),
+            WasmError::Trap(String::new()).code()
+        );
+        // memory can't grow past MAX_MEMORY_BYTES (64 MiB asked)
+        let txn = call_txn(&kp, &contract, "grow", 10_000);
+        assert_eq!(
+            app.execute(&txn, &mut state_context, &block_context),
+            Err(WasmError::ContractFailed(7).into())
+        );
+
+        // contract storage loads are charged by key
+        let txn = call_txn(&kp, &contract, "increment", 100_000);
+        let gas_used: u64 = {
+            let mut meter = GasMeter::new(&mut state_context, 100_000);
+            app.call(
+                &txn,
+                &contract,
+                &String::from("increment"),
+                &vec![],
+                100_000,
+                &mut meter,
+            )
+            .unwrap();
+            meter.gas_used()
+        };
+        let txn = call_txn(&kp, &contract, "increment", 100_000);
+        let mut meter = GasMeter::new(&mut state_context, 100_000);
+        app.call(
+            &txn,
+            &contract,
+            &String::from("increment"),
+            &vec![],
+            100_000,
+            &mut meter,
+        )
+        .unwrap();
+        // the first call loaded no key, the second one the counter
+        assert!(meter.gas_used() >= gas_used + STORAGE_LOAD_GAS);
+    }
+
+    #[test]
+    fn test_floats_refused() {
+        let app = WasmApp::new(1_000_000, 64 * 1024);
+        let kp = Keypair::generate();
+        let code: Vec<u8> = wat::parse_str(FLOATS).unwrap();
+        let txn = signed_txn(&kp, &WasmTransaction::Deploy { code });
+        assert_eq!(
+            app.validate(&txn).unwrap_err().code,
+            WasmError::InvalidCode(String::new()).code()
+        );
+    }
+
+    #[test]
+    fn test_invalid_code() {
+        let app = WasmApp::new(1_000_000, 16);
+        let kp = Keypair::generate();
+        let txn = signed_txn(
+            &kp,
+            &WasmTransaction::Deploy {
+                code: vec![1, 2, 3],
+            },
+        );
+        assert_eq!(
+            app.validate(&txn).unwrap_err().code,
+            WasmError::InvalidCode(String::new()).code()
+        );
+        let code: Vec<u8> = wat::parse_str(COUNTER).unwrap();
+        let txn = signed_txn(&kp, &WasmTransaction::Deploy { code });
+        assert_eq!(
+            app.validate(&txn).unwrap_err().code,
+            WasmError::CodeTooLarge { size: 0, max: 0 }.code()
+        );
+    }
+}